@@ -0,0 +1,156 @@
+//! Failures of the commands, each kind exiting with its own code so that scripts can tell them apart
+
+use m10_sdk::error::M10Error;
+use serde_json::json;
+use std::fmt::{Display, Formatter};
+
+/// Exit code of errors of no other kind
+const ERROR_EXIT_CODE: i32 = 1;
+
+/// Exit code of errors of the ledger, whether unreachable or answering with an error status
+const NETWORK_EXIT_CODE: i32 = 8;
+
+/// Exit code of transactions rejected by the ledger, e.g. for lack of funds
+const LEDGER_EXIT_CODE: i32 = 9;
+
+/// Failure expected by scripts, as opposed to unexpected errors of the command
+#[derive(Debug)]
+pub enum Failure {
+    /// Flag missing from the command line & from the defaults, exiting as the errors of the parser do
+    Usage(String),
+    /// No quote or event found for the context
+    NotFound(String),
+    /// Swap in a state the command doesn't apply to, e.g. already executed or cancelled
+    Conflict(String),
+    /// Request, execution or counter-offer rejected by the service
+    Rejected(String),
+    /// Swap which failed or was refunded once executed
+    Failed(String),
+    /// Quote expired or not signed as expected
+    InvalidQuote(String),
+    /// Settlement whose transfers differ from its quote or receipts
+    Discrepancy(String),
+    /// Stream of events which ended before the awaited event of `context_id`
+    Disconnected {
+        context_id: String,
+        awaited: &'static str,
+        /// Whether `execute --resume` picks up the wait
+        resumable: bool,
+    },
+    /// Wait for an event of `context_id` which didn't end within its `--timeout`
+    TimedOut {
+        context_id: String,
+        awaited: &'static str,
+        secs: u64,
+        resumable: bool,
+    },
+}
+
+impl Failure {
+    /// Kind of the failure, as printed in JSON
+    fn kind(&self) -> &'static str {
+        match self {
+            Failure::Usage(_) => "usage",
+            Failure::NotFound(_) => "not_found",
+            Failure::Conflict(_) => "conflict",
+            Failure::Rejected(_) => "rejected",
+            Failure::Failed(_) => "failed",
+            Failure::InvalidQuote(_) => "invalid_quote",
+            Failure::Discrepancy(_) => "discrepancy",
+            Failure::Disconnected { .. } => "network",
+            Failure::TimedOut { .. } => "timed_out",
+        }
+    }
+
+    /// Exit code of the failure, `124` for time outs as the `timeout` utility's
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Usage(_) => 2,
+            Failure::NotFound(_) => 3,
+            Failure::Conflict(_) => 4,
+            Failure::Rejected(_) => 5,
+            Failure::Failed(_) => 6,
+            Failure::InvalidQuote(_) => 7,
+            Failure::Discrepancy(_) => 10,
+            Failure::Disconnected { .. } => NETWORK_EXIT_CODE,
+            Failure::TimedOut { .. } => 124,
+        }
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (context_id, resumable) = match self {
+            Failure::Usage(message)
+            | Failure::NotFound(message)
+            | Failure::Conflict(message)
+            | Failure::Rejected(message)
+            | Failure::Failed(message)
+            | Failure::InvalidQuote(message)
+            | Failure::Discrepancy(message) => return f.write_str(message),
+            Failure::Disconnected {
+                context_id,
+                awaited,
+                resumable,
+            } => {
+                write!(
+                    f,
+                    "Events of {} ended before its {} was received",
+                    context_id, awaited
+                )?;
+                (context_id, resumable)
+            }
+            Failure::TimedOut {
+                context_id,
+                awaited,
+                secs,
+                resumable,
+            } => {
+                write!(
+                    f,
+                    "No {} received for {} within {}s",
+                    awaited, context_id, secs
+                )?;
+                (context_id, resumable)
+            }
+        };
+        if *resumable {
+            write!(
+                f,
+                ", resume waiting with `execute --resume -c {}`",
+                context_id
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Kind & exit code of `err`, from the failure or the error of the ledger it was caused by
+pub fn classify(err: &anyhow::Error) -> (&'static str, i32) {
+    for cause in err.chain() {
+        if let Some(failure) = cause.downcast_ref::<Failure>() {
+            return (failure.kind(), failure.exit_code());
+        }
+        match cause.downcast_ref::<M10Error>() {
+            Some(M10Error::Status(_)) => return ("network", NETWORK_EXIT_CODE),
+            Some(M10Error::Transaction(_)) => return ("ledger", LEDGER_EXIT_CODE),
+            _ => {}
+        }
+    }
+    ("error", ERROR_EXIT_CODE)
+}
+
+/// Details of `err` printed in JSON, as the last line of the standard output
+pub fn to_json(err: &anyhow::Error) -> serde_json::Value {
+    let (kind, exit_code) = classify(err);
+    json!({
+        "error": {
+            "kind": kind,
+            "exit_code": exit_code,
+            "message": err.to_string(),
+            "causes": err.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>(),
+        }
+    })
+}
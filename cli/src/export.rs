@@ -0,0 +1,256 @@
+//! Audit bundle of the `export` command, recording every action & transfer of a swap context with its decoded events
+
+use super::{csv_field, event_step, Output, Step};
+use crate::error::Failure;
+use m10_sdk::client::M10Client;
+use m10_sdk::{ActionsFilter, Ed25519, MetadataExt, Target, TransferFilter, TxId, TxnFilter};
+use ring::digest::{digest, SHA256};
+use serde_json::json;
+use service::event::Envelope;
+use service::{FxSwapMetadata, FX_SWAP_ACTION};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// JSON record of the context, self-contained
+const BUNDLE_FILE: &str = "bundle.json";
+
+/// One row per action & transfer of the context, in order
+const EVENTS_FILE: &str = "events.csv";
+
+/// One row per step of each transfer of the context
+const TRANSFERS_FILE: &str = "transfers.csv";
+
+/// Ledgers the context is read from: the ledger of the source currency, & of the target currency when not the same
+pub struct Ledgers<'a> {
+    pub source: (&'a str, &'a M10Client<Ed25519>),
+    pub target: Option<(&'a str, &'a M10Client<Ed25519>)>,
+}
+
+/// Row of `events.csv`
+struct EventRow {
+    ledger: &'static str,
+    tx_id: TxId,
+    kind: &'static str,
+    /// Stage & details of the event, unless it couldn't be decoded
+    step: Option<Step>,
+    envelope: Option<(String, u64)>,
+    payload_sha256: Option<String>,
+}
+
+/// Hex encoded SHA-256 digest of `payload`
+fn sha256(payload: &[u8]) -> String {
+    hex::encode(digest(&SHA256, payload))
+}
+
+/// Publisher, sequence & event of `envelope`, as exported
+fn envelope_json(envelope: &Envelope) -> serde_json::Value {
+    json!({
+        "version": envelope.version,
+        "created_at": envelope.created_at,
+        "sequence": envelope.sequence,
+        "public_key": hex::encode(&envelope.public_key),
+        "event": envelope.event,
+    })
+}
+
+/// Writes the actions & transfers of `context_id` on the `ledgers` to the directory `out`, which mustn't exist: the raw
+/// payloads with their SHA-256 digests & decoded events in `bundle.json`, along with the digests of the CSV files
+/// listing the events & the transfer steps
+pub async fn run(
+    ledgers: Ledgers<'_>,
+    context_id: Vec<u8>,
+    out: &Path,
+    output: Output,
+) -> anyhow::Result<()> {
+    if out.exists() {
+        return Err(Failure::Conflict(format!("{} already exists", out.display())).into());
+    }
+    let context_hex = hex::encode(&context_id);
+    let mut actions = vec![];
+    let mut transfers = vec![];
+    let mut events = vec![];
+    let mut steps = String::from("ledger,tx_id,status,step,from,to,amount\n");
+    let ledgers = std::iter::once(("source", ledgers.source))
+        .chain(ledgers.target.map(|target| ("target", target)));
+    for (ledger, (url, client)) in ledgers {
+        // FX events are only published as actions on the ledger of the source currency
+        let listed = match ledger {
+            "source" => {
+                client
+                    .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+                        FX_SWAP_ACTION.to_string(),
+                        context_id.clone(),
+                    ))
+                    .await?
+            }
+            _ => vec![],
+        };
+        for action in listed {
+            let envelope = Envelope::decode(&action.payload);
+            if let Err(err) = &envelope {
+                warn!(tx_id = %action.tx_id, %err, "Could not decode action");
+            }
+            let payload_sha256 = sha256(&action.payload);
+            events.push(EventRow {
+                ledger,
+                tx_id: action.tx_id,
+                kind: "action",
+                step: envelope.as_ref().ok().and_then(|envelope| {
+                    event_step(action.tx_id, envelope.created_at, envelope.event.clone())
+                }),
+                envelope: envelope
+                    .as_ref()
+                    .ok()
+                    .map(|envelope| (hex::encode(&envelope.public_key), envelope.sequence)),
+                payload_sha256: Some(payload_sha256.clone()),
+            });
+            actions.push(json!({
+                "ledger": ledger,
+                "url": url,
+                "tx_id": action.tx_id,
+                "name": action.name,
+                "target": match action.target {
+                    Target::Any => None,
+                    Target::Account(account) => Some(account.to_string()),
+                },
+                "payload": hex::encode(&action.payload),
+                "payload_sha256": payload_sha256,
+                "envelope": envelope.as_ref().ok().map(envelope_json),
+                "error": envelope.as_ref().err().map(ToString::to_string),
+            }));
+        }
+
+        let listed = client
+            .list_transfers(TxnFilter::<TransferFilter>::by_context_id(
+                context_id.clone(),
+            ))
+            .await?;
+        for transfer in listed {
+            let at = transfer
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64);
+            let status = format!("{:?}", transfer.status);
+            // Executions are published with the transfer of the source funds
+            let payload = transfer.with_type::<FxSwapMetadata>();
+            let envelope = payload.and_then(|payload| Envelope::decode(payload).ok());
+            let legs = transfer
+                .steps
+                .iter()
+                .map(|step| format!("{} from {} to {}", step.amount, step.from, step.to))
+                .collect::<Vec<_>>()
+                .join(", ");
+            events.push(EventRow {
+                ledger,
+                tx_id: transfer.tx_id,
+                kind: "transfer",
+                step: match &envelope {
+                    Some(envelope) => event_step(transfer.tx_id, at, envelope.event.clone()),
+                    None => Some(Step {
+                        tx_id: transfer.tx_id,
+                        at,
+                        stage: "transferred",
+                        details: format!("{} ({})", legs, status),
+                    }),
+                },
+                envelope: envelope
+                    .as_ref()
+                    .map(|envelope| (hex::encode(&envelope.public_key), envelope.sequence)),
+                payload_sha256: payload.map(sha256),
+            });
+            for (index, step) in transfer.steps.iter().enumerate() {
+                steps.push_str(&format!(
+                    "{},{},{},{},{},{},{}\n",
+                    ledger, transfer.tx_id, status, index, step.from, step.to, step.amount
+                ));
+            }
+            transfers.push(json!({
+                "ledger": ledger,
+                "url": url,
+                "tx_id": transfer.tx_id,
+                "timestamp": at,
+                "status": status,
+                "success": transfer.success,
+                "steps": transfer.steps.iter().map(|step| json!({
+                    "from": step.from.to_string(),
+                    "to": step.to.to_string(),
+                    "amount": step.amount,
+                })).collect::<Vec<_>>(),
+                "payload": payload.map(hex::encode),
+                "payload_sha256": payload.map(sha256),
+                "envelope": envelope.as_ref().map(envelope_json),
+            }));
+        }
+    }
+    if actions.is_empty() && transfers.is_empty() {
+        return Err(Failure::NotFound(format!(
+            "No actions or transfers found for {}",
+            context_hex
+        ))
+        .into());
+    }
+
+    events.sort_by_key(|row| row.tx_id);
+    let mut lifecycle: Vec<&str> = Vec::new();
+    let mut rows =
+        String::from("ledger,tx_id,kind,at,stage,details,public_key,sequence,payload_sha256\n");
+    for row in &events {
+        let (at, stage, details) = match &row.step {
+            Some(step) => (step.at.to_string(), step.stage, step.details.as_str()),
+            None => (String::new(), "", ""),
+        };
+        if !stage.is_empty() && stage != "transferred" && lifecycle.last() != Some(&stage) {
+            lifecycle.push(stage);
+        }
+        let (public_key, sequence) = match &row.envelope {
+            Some((public_key, sequence)) => (public_key.as_str(), sequence.to_string()),
+            None => ("", String::new()),
+        };
+        rows.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            row.ledger,
+            row.tx_id,
+            row.kind,
+            at,
+            stage,
+            csv_field(details),
+            public_key,
+            sequence,
+            row.payload_sha256.as_deref().unwrap_or_default()
+        ));
+    }
+
+    let exported_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let bundle = json!({
+        "context_id": context_hex,
+        "exported_at": exported_at,
+        "lifecycle": lifecycle,
+        "actions": actions,
+        "transfers": transfers,
+        "files": {
+            EVENTS_FILE: sha256(rows.as_bytes()),
+            TRANSFERS_FILE: sha256(steps.as_bytes()),
+        },
+    });
+    let bundle = serde_json::to_string_pretty(&bundle)?;
+    std::fs::create_dir_all(out)?;
+    std::fs::write(out.join(EVENTS_FILE), &rows)?;
+    std::fs::write(out.join(TRANSFERS_FILE), &steps)?;
+    std::fs::write(out.join(BUNDLE_FILE), &bundle)?;
+    info!(
+        path = %out.display(),
+        actions = actions.len(),
+        transfers = transfers.len(),
+        lifecycle = %lifecycle.join(" → "),
+        "Exported context"
+    );
+    output.emit(json!({
+        "context_id": context_hex,
+        "path": out,
+        "actions": actions.len(),
+        "transfers": transfers.len(),
+        "bundle_sha256": sha256(bundle.as_bytes()),
+    }));
+    Ok(())
+}
@@ -0,0 +1,345 @@
+//! Load test of the `loadtest` command, requesting synthetic swaps at a steady rate & measuring how the service keeps up
+
+use super::{
+    auto_execution, execute_swap, initiate_swap, ExecuteQuote, Initiate, LoadTest, Output,
+};
+use crate::error::{self, Failure};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use m10_sdk::account::AccountId;
+use m10_sdk::client::M10Client;
+use m10_sdk::Ed25519;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, info_span, warn, Instrument};
+
+/// Upper bounds of the buckets of the latency histograms, in milliseconds, the last bucket being unbounded
+const BUCKETS_MS: [u64; 10] = [10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// Width of the bar of the fullest bucket of a histogram
+const BAR_WIDTH: usize = 40;
+
+/// Currency pair swaps are requested for, as `from:to`
+#[derive(Debug, Clone)]
+pub struct Pair {
+    from: String,
+    to: String,
+}
+
+impl FromStr for Pair {
+    type Err = anyhow::Error;
+
+    fn from_str(pair: &str) -> Result<Self, Self::Err> {
+        match pair.split_once(':') {
+            Some((from, to)) if !from.is_empty() && !to.is_empty() && from != to => Ok(Pair {
+                from: from.to_lowercase(),
+                to: to.to_lowercase(),
+            }),
+            _ => Err(anyhow::anyhow!(
+                "Expected from:to of two currencies, got {}",
+                pair
+            )),
+        }
+    }
+}
+
+/// Parses a rate of `<n>/s` or `<n>/m`, or `<n>` per second, into swaps per second
+pub fn parse_rate(rate: &str) -> anyhow::Result<f64> {
+    let (count, per) = match rate.split_once('/') {
+        Some((count, "s")) => (count, 1.0),
+        Some((count, "m")) => (count, 60.0),
+        Some(_) => return Err(anyhow::anyhow!("Expected <n>/s or <n>/m, got {}", rate)),
+        None => (rate, 1.0),
+    };
+    match count.parse::<f64>() {
+        Ok(count) if count > 0.0 && count.is_finite() => Ok(count / per),
+        _ => Err(anyhow::anyhow!("Expected a positive rate, got {}", rate)),
+    }
+}
+
+/// Parses a duration of `<n>s`, `<n>m` or `<n>h`, or `<n>` seconds
+pub fn parse_duration(duration: &str) -> anyhow::Result<Duration> {
+    let (count, unit) = match duration.char_indices().last() {
+        Some((index, 's')) => (&duration[..index], 1),
+        Some((index, 'm')) => (&duration[..index], 60),
+        Some((index, 'h')) => (&duration[..index], 3_600),
+        _ => (duration, 1),
+    };
+    match count.parse::<u64>() {
+        Ok(count) if count > 0 => Ok(Duration::from_secs(count * unit)),
+        _ => Err(anyhow::anyhow!(
+            "Expected a positive duration, e.g. 60s, got {}",
+            duration
+        )),
+    }
+}
+
+/// Outcome of a swap of the load test
+struct Outcome {
+    /// Time from the request to its quote
+    quote_latency: Option<Duration>,
+    /// Time from the execution to the completion of the swap
+    completion_latency: Option<Duration>,
+    /// Kind of the error the swap failed with
+    error: Option<&'static str>,
+}
+
+/// Requests swaps of `loadtest.pairs` in turn at `loadtest.rate` for `loadtest.duration`, executing each quote when a
+/// `default_validity` is given, then prints the completion rate & the latency histograms
+pub async fn run(
+    client: &M10Client<Ed25519>,
+    loadtest: LoadTest,
+    accounts: Vec<AccountId>,
+    public_key: &[u8],
+    default_validity: Option<u64>,
+    output: Output,
+) -> anyhow::Result<()> {
+    // The first account of each currency swaps it
+    let mut by_currency = HashMap::new();
+    for account in accounts {
+        let info = client.get_account_info(account).await?;
+        by_currency
+            .entry(info.code.to_lowercase())
+            .or_insert(account);
+    }
+    let account_of = |currency: &str| {
+        by_currency.get(currency).copied().ok_or_else(|| {
+            Failure::Usage(format!("No account in {} among the --accounts", currency))
+        })
+    };
+    let routes = loadtest
+        .pairs
+        .iter()
+        .map(|pair| Ok((account_of(&pair.from)?, account_of(&pair.to)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let template = Initiate {
+        key_pair: loadtest.key_pair.clone(),
+        from: None,
+        to: None,
+        amount: Some(loadtest.amount),
+        receive_amount: None,
+        amount_minor_units: false,
+        memo: None,
+        two_way: false,
+        compliance: None,
+        settle_at: None,
+        auto_execute: false,
+        margin: None,
+        timeout: Some(loadtest.timeout),
+        batch: None,
+        parallelism: 1,
+        results: None,
+    };
+    info!(
+        pairs = routes.len(),
+        rate = %loadtest.rate,
+        duration = ?loadtest.duration,
+        execute = default_validity.is_some(),
+        "Starting load test"
+    );
+
+    let swap = |index: usize| {
+        let (from, to) = routes[index % routes.len()];
+        let initiate = Initiate {
+            from: Some(from),
+            to: Some(to),
+            ..template.clone()
+        };
+        let context_id = fastrand::u64(..).to_be_bytes().to_vec();
+        let context_hex = hex::encode(&context_id);
+        async move {
+            let requested = Instant::now();
+            let execute = auto_execution(&initiate, &context_id);
+            // Only the results of the load test are emitted, rather than the events of every swap
+            let quote = initiate_swap(
+                client,
+                initiate,
+                context_id.clone(),
+                public_key,
+                None,
+                Output::Text,
+            )
+            .await;
+            let mut outcome = Outcome {
+                quote_latency: None,
+                completion_latency: None,
+                error: None,
+            };
+            let result = match (quote, default_validity) {
+                (Ok(quote), Some(default_validity)) => {
+                    outcome.quote_latency = Some(requested.elapsed());
+                    let executed = Instant::now();
+                    let execute = ExecuteQuote {
+                        quote_id: Some(quote.quote_id),
+                        ..execute
+                    };
+                    execute_swap(
+                        client,
+                        execute,
+                        quote,
+                        default_validity,
+                        context_id,
+                        public_key,
+                        Output::Text,
+                    )
+                    .await
+                    .map(|()| outcome.completion_latency = Some(executed.elapsed()))
+                }
+                (Ok(_), None) => {
+                    outcome.quote_latency = Some(requested.elapsed());
+                    Ok(())
+                }
+                (Err(err), _) => Err(err),
+            };
+            if let Err(err) = result {
+                warn!(%err, "Swap failed");
+                outcome.error = Some(error::classify(&err).0);
+            }
+            outcome
+        }
+        .instrument(info_span!("swap", index, context_id = %context_hex))
+    };
+
+    let started = Instant::now();
+    let deadline = started + loadtest.duration;
+    let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / loadtest.rate));
+    let mut swaps = FuturesUnordered::new();
+    let mut outcomes = vec![];
+    let mut requested = 0;
+    loop {
+        tokio::select! {
+            _ = ticks.tick(), if Instant::now() < deadline => {
+                if Instant::now() < deadline {
+                    swaps.push(swap(requested));
+                    requested += 1;
+                }
+            }
+            Some(outcome) = swaps.next(), if !swaps.is_empty() => outcomes.push(outcome),
+            else => break,
+        }
+    }
+    let elapsed = started.elapsed();
+    report(&outcomes, elapsed, default_validity.is_some(), output);
+    Ok(())
+}
+
+/// Prints the counts & latencies of the `outcomes`, completed meaning quoted when the swaps weren't executed
+fn report(outcomes: &[Outcome], elapsed: Duration, executed: bool, output: Output) {
+    let requested = outcomes.len();
+    let quote_latencies = sorted(outcomes.iter().filter_map(|outcome| outcome.quote_latency));
+    let completion_latencies = sorted(
+        outcomes
+            .iter()
+            .filter_map(|outcome| outcome.completion_latency),
+    );
+    let completed = match executed {
+        true => completion_latencies.len(),
+        false => quote_latencies.len(),
+    };
+    let mut failed = BTreeMap::new();
+    for kind in outcomes.iter().filter_map(|outcome| outcome.error) {
+        *failed.entry(kind).or_insert(0usize) += 1;
+    }
+    let completion_rate = match requested {
+        0 => 0.0,
+        requested => completed as f64 / requested as f64,
+    };
+    let throughput = requested as f64 / elapsed.as_secs_f64();
+    info!(
+        requested,
+        quoted = quote_latencies.len(),
+        completed,
+        failed = ?failed,
+        completion_rate = %format!("{:.1}%", completion_rate * 100.0),
+        throughput = %format!("{:.2}/s", throughput),
+        "Load test done"
+    );
+    print_histogram("Quote latency", &quote_latencies);
+    if executed {
+        print_histogram("Completion latency", &completion_latencies);
+    }
+    output.emit(json!({
+        "requested": requested,
+        "quoted": quote_latencies.len(),
+        "completed": completed,
+        "failed": failed,
+        "completion_rate": completion_rate,
+        "throughput": throughput,
+        "elapsed_ms": elapsed.as_millis() as u64,
+        "quote_latency_ms": latency_summary(&quote_latencies),
+        "completion_latency_ms": executed.then(|| latency_summary(&completion_latencies)),
+    }));
+}
+
+/// Latencies in milliseconds, sorted
+fn sorted(latencies: impl Iterator<Item = Duration>) -> Vec<u64> {
+    let mut latencies = latencies
+        .map(|latency| latency.as_millis() as u64)
+        .collect::<Vec<_>>();
+    latencies.sort_unstable();
+    latencies
+}
+
+/// Latency under which a `quantile` of the sorted `latencies` fall
+fn percentile(latencies: &[u64], quantile: f64) -> Option<u64> {
+    let index = ((latencies.len() as f64 - 1.0) * quantile).round() as usize;
+    latencies.get(index).copied()
+}
+
+/// Count of the sorted `latencies` in each bucket, keyed by its upper bound, `None` for the last one
+fn histogram(latencies: &[u64]) -> Vec<(Option<u64>, usize)> {
+    let mut lower = 0;
+    BUCKETS_MS
+        .iter()
+        .map(|&upper| Some(upper))
+        .chain([None])
+        .map(|upper| {
+            let end = match upper {
+                Some(upper) => latencies.partition_point(|&latency| latency <= upper),
+                None => latencies.len(),
+            };
+            let count = end - lower;
+            lower = end;
+            (upper, count)
+        })
+        .collect()
+}
+
+fn latency_summary(latencies: &[u64]) -> serde_json::Value {
+    json!({
+        "p50": percentile(latencies, 0.5),
+        "p90": percentile(latencies, 0.9),
+        "p99": percentile(latencies, 0.99),
+        "max": latencies.last(),
+        "histogram": histogram(latencies)
+            .into_iter()
+            .map(|(upper, count)| json!({ "le": upper, "count": count }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Logs the percentiles of the sorted `latencies` & a bar per bucket of their histogram
+fn print_histogram(name: &str, latencies: &[u64]) {
+    let (p50, p90, p99) = (
+        percentile(latencies, 0.5),
+        percentile(latencies, 0.9),
+        percentile(latencies, 0.99),
+    );
+    info!(p50_ms = ?p50, p90_ms = ?p90, p99_ms = ?p99, max_ms = ?latencies.last(), "{}", name);
+    let histogram = histogram(latencies);
+    let fullest = histogram.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    for (upper, count) in histogram {
+        let bucket = match upper {
+            Some(upper) => format!("<= {}ms", upper),
+            None => format!("> {}ms", BUCKETS_MS[BUCKETS_MS.len() - 1]),
+        };
+        let bar = match fullest {
+            0 => 0,
+            fullest => (count * BAR_WIDTH).div_ceil(fullest),
+        };
+        info!("{:>10} {:>6} {}", bucket, count, "#".repeat(bar));
+    }
+}
@@ -1,28 +1,52 @@
+use crate::error::Failure;
+use crate::profile::Defaults;
 use crate::sdk::rule::Verb;
 use crate::sdk::value::Value;
 use crate::sdk::{Account, Role, RoleBinding, Rule};
-use clap::Parser;
-use futures_util::StreamExt;
+use clap::{CommandFactory, Parser};
+use futures_util::stream::BoxStream;
+use futures_util::{Stream, StreamExt};
 use m10_sdk::account::AccountId;
-use m10_sdk::client::Channel;
 use m10_sdk::client::M10Client;
 use m10_sdk::error::M10Error;
 use m10_sdk::prost::bytes::Bytes;
 use m10_sdk::{
-    sdk, AccountBuilder, AccountFilter, ActionBuilder, ActionsFilter, Collection, DocumentBuilder,
-    Ed25519, Signer, StepBuilder, TransferBuilder, TxId, TxnFilter, WithContext,
+    sdk, AccountBuilder, AccountFilter, AccountInfo, Action, ActionBuilder, ActionsFilter,
+    Collection, DocumentBuilder, DocumentUpdate, Ed25519, MetadataExt, Signer, StepBuilder,
+    TransferBuilder, TransferFilter, TransferStatus, TxId, TxnFilter, WithContext,
 };
-use rust_decimal::prelude::One;
+use rust_decimal::prelude::{One, ToPrimitive};
 use rust_decimal::Decimal;
-use service::config::{Config, LiquidityConfig};
-use service::event::{Event, Execute, Quote, Request};
-use service::{FX_SWAP_ACTION, FX_SWAP_METADATA};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use service::config::{
+    self, Addresses, Config, CurrencyCode, EngineConfig, LiquidityConfig, LogConfig, Rounding,
+    TransportConfig,
+};
+use service::event::{
+    Bounds, Compliance, CounterOffer, Direction, Envelope, Event, Execute, IndicativeQuote, Quote,
+    Request, Side, Strategy, SwapState,
+};
+use service::logging;
+use service::money::{Money, MoneyError};
+use service::secret::SecretSource;
+use service::{FxSwapMetadata, FX_SWAP_ACTION};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tracing::{error, info, info_span, Instrument};
+use tracing::{error, info, info_span, warn, Instrument};
 use uuid::Uuid;
 
+mod error;
+mod export;
+mod loadtest;
+mod profile;
+mod simulate;
+mod tui;
+mod wizard;
+
 const DEFAULT_LEDGER_URL: &str = "https://develop.m10.net";
 
 const TEST_ROOT_KEY: &str = "3053020101300506032b6570042204207cabfa6e59e20cbd271a0c7\
@@ -33,17 +57,79 @@ const TEST_ROOT_KEY: &str = "3053020101300506032b6570042204207cabfa6e59e20cbd271
 #[clap(name = "command")]
 #[clap(bin_name = "command")]
 struct Command {
-    #[clap(short, long, default_value = DEFAULT_LEDGER_URL)]
-    url: String,
+    /// Ledger URL, the one of `~/.m10fx/config` or else https://develop.m10.net when unset
+    #[clap(short, long)]
+    url: Option<String>,
+    /// Config file whose `transport` section tunes the channel to the ledger, the one of `~/.m10fx/config` or else
+    /// `./config.toml` if it exists when unset
+    #[clap(long, env = "APP_CONFIG")]
+    config: Option<PathBuf>,
+    /// Profile of `~/.m10fx/config` & of the config overlaid onto the rest of them, e.g. `prod` for `[profiles.prod]`
+    #[clap(long, env = "APP_PROFILE")]
+    profile: Option<String>,
+    /// Format of the results: `text` logs, or `json` objects on the standard output, one per line, the logs going to the standard error
+    #[clap(long, value_enum, default_value = "text", global = true)]
+    output: Output,
     #[clap(subcommand)]
     command: RPC,
 }
 
+/// Format of the results of a command
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Output {
+    Text,
+    Json,
+}
+
+impl Output {
+    /// Prints `result` on the standard output in JSON, results being only logged in text
+    fn emit(self, result: impl Serialize) {
+        if self == Output::Json {
+            match serde_json::to_string(&result) {
+                Ok(result) => println!("{}", result),
+                Err(err) => error!(%err, "Could not serialize result"),
+            }
+        }
+    }
+}
+
+/// Quote or event of the swap in `context_id`, emitted as is since account IDs don't fit in a JSON value
+#[derive(Serialize)]
+struct SwapResult<'a> {
+    context_id: &'a str,
+    /// Action or transfer publishing the quote or event
+    tx_id: TxId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quote: Option<&'a Quote>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<&'a Event>,
+}
+
 #[derive(clap::Subcommand, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 enum RPC {
     Setup(Setup),
+    Teardown(Teardown),
     Initiate(Initiate),
     Execute(ExecuteQuote),
+    Cancel(CancelQuote),
+    Counter(CounterQuote),
+    Status(SwapStatusQuery),
+    History(SwapHistoryQuery),
+    Accounts(ListAccounts),
+    Balance(AccountBalance),
+    Fund(FundLiquidity),
+    Keys(Keys),
+    Config(Configure),
+    Watch(WatchSwaps),
+    Quote(PreviewQuote),
+    Tui(Dashboard),
+    Verify(VerifySettlement),
+    Export(ExportContext),
+    Simulate(SimulateSwap),
+    Loadtest(LoadTest),
+    Completions(Completions),
+    Man(ManPages),
 }
 
 #[derive(clap::Args, Debug)]
@@ -53,321 +139,3716 @@ struct Setup {
     key_pair: Option<String>,
     #[clap(short, long, multiple = true)]
     currencies: Vec<String>,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 10_000_000,
+        help = "Units funding each new liquidity account"
+    )]
+    fund_liquidity: u64,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 10_000_000,
+        help = "Units funding each new user account whose amount is unset"
+    )]
+    fund_user: u64,
+    #[clap(
+        long = "user",
+        value_parser,
+        multiple_occurrences = true,
+        help = "User account to create as `name:currency[:amount]`, by default `alice` in the first currency & `bob` unfunded in the second"
+    )]
+    users: Vec<SetupUser>,
+}
+
+/// User account created by `setup`, owned by the key pair `./<name>.pkcs8`
+#[derive(Debug, Clone)]
+struct SetupUser {
+    name: String,
+    currency: String,
+    /// Units funding the account, `--fund-user` when unset
+    funding: Option<u64>,
+}
+
+impl FromStr for SetupUser {
+    type Err = anyhow::Error;
+
+    fn from_str(user: &str) -> Result<Self, Self::Err> {
+        let mut parts = user.split(':');
+        let (name, currency) = match (parts.next(), parts.next()) {
+            (Some(name), Some(currency)) if !name.is_empty() && !currency.is_empty() => {
+                (name, currency)
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Expected name:currency[:amount], got {}",
+                    user
+                ))
+            }
+        };
+        if name == "liquidity"
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow::anyhow!("Invalid user name {}", name));
+        }
+        let funding = parts.next().map(str::parse).transpose()?;
+        if parts.next().is_some() {
+            return Err(anyhow::anyhow!(
+                "Expected name:currency[:amount], got {}",
+                user
+            ));
+        }
+        Ok(SetupUser {
+            name: name.to_string(),
+            currency: currency.to_lowercase(),
+            funding,
+        })
+    }
 }
 
 #[derive(clap::Args, Debug)]
 #[clap(author, version, about, long_about = None)]
+struct Teardown {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(
+        long = "user",
+        multiple_occurrences = true,
+        default_values = &DEFAULT_USERS,
+        help = "Name of a user created by setup, whose key file is `./<name>.pkcs8`"
+    )]
+    users: Vec<String>,
+    #[clap(
+        long,
+        help = "Defund the accounts, delete their documents & remove the files, instead of only listing them"
+    )]
+    yes: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+#[clap(author, version, about, long_about = None)]
 struct Initiate {
     #[clap(short, long)]
-    key_pair: String,
+    key_pair: Option<String>,
     #[clap(short, long)]
-    from: AccountId,
-    #[clap(short, long, value_parser)]
-    to: AccountId,
+    from: Option<AccountId>,
     #[clap(short, long, value_parser)]
-    amount: u64,
+    to: Option<AccountId>,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        required_unless_present_any = &["receive-amount", "batch"],
+        help = "Amount to send, in the source currency, e.g. 12.50"
+    )]
+    amount: Option<Decimal>,
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with = "amount",
+        help = "Exact amount to deliver, in the target currency, e.g. 12.50"
+    )]
+    receive_amount: Option<Decimal>,
+    #[clap(
+        long,
+        help = "Read the amounts as integers in the smallest unit of their currency, e.g. 1250 for 12.50"
+    )]
+    amount_minor_units: bool,
+    #[clap(
+        long,
+        value_parser,
+        help = "Reference attached to the settlement transfers, e.g. an invoice number"
+    )]
+    memo: Option<String>,
+    #[clap(long, help = "Request both bid & ask rates")]
+    two_way: bool,
+    #[clap(
+        long,
+        value_parser,
+        help = "JSON file with the originator & beneficiary information"
+    )]
+    compliance: Option<PathBuf>,
+    #[clap(
+        long,
+        value_parser,
+        help = "Epoch time in seconds at which a forward swap settles, at the quoted rate"
+    )]
+    settle_at: Option<u64>,
+    #[clap(
+        long,
+        help = "Execute the quote once received & wait for the swap to complete"
+    )]
+    auto_execute: bool,
+    #[clap(
+        long,
+        value_parser,
+        requires = "auto-execute",
+        help = "Percentage margin on the quoted rate of the limit strategy the quote is executed with, settling immediately when unset"
+    )]
+    margin: Option<Decimal>,
+    #[clap(
+        long,
+        value_parser,
+        help = "Seconds to wait for the quote, & for the swap to complete when executed, indefinitely when unset"
+    )]
+    timeout: Option<u64>,
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with_all = &["from", "to", "amount", "receive-amount"],
+        help = "CSV or JSON file of the (from, to, amount) swaps to request"
+    )]
+    batch: Option<PathBuf>,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 4,
+        requires = "batch",
+        help = "Maximum number of swaps of the batch in flight"
+    )]
+    parallelism: usize,
+    #[clap(
+        long,
+        value_parser,
+        requires = "batch",
+        help = "CSV file the outcome of each swap of the batch is written to, next to the batch by default"
+    )]
+    results: Option<PathBuf>,
 }
 
-#[derive(clap::Args, Debug)]
+#[derive(clap::Args, Clone, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct ExecuteQuote {
     #[clap(short, long)]
-    key_pair: String,
+    key_pair: Option<String>,
     #[clap(short, long, value_parser)]
     context_id: String,
     #[clap(
+        long,
+        value_parser = ["immediate", "limit", "twap"],
+        default_value = "limit",
+        help = "Settlement strategy"
+    )]
+    strategy: String,
+    #[clap(
+        long,
+        conflicts_with = "strategy",
+        help = "Settle right away at the current rate, same as `--strategy immediate`"
+    )]
+    now: bool,
+    #[clap(
+        long,
+        help = "Hold the funds until the destination leg is prepared, both legs being committed together. Settles immediately"
+    )]
+    hold: bool,
+    #[clap(
+        long,
+        value_parser,
+        help = "Percentage margin on the current exchange rate, for the limit strategy"
+    )]
+    margin: Option<Decimal>,
+    #[clap(
+        short,
         long,
         value_parser,
-        help = "Percentage margin on the current exchange rate"
+        help = "Duration in seconds, until the deadline of the limit strategy or over which twap slices are settled"
     )]
-    margin: Decimal,
-    #[clap(short, long, value_parser, help = "Duration in seconds")]
     valid_for: Option<u64>,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 4,
+        help = "Number of slices settled by the twap strategy"
+    )]
+    slices: u32,
+    #[clap(
+        long,
+        value_parser,
+        help = "Hex encoded public key the quote must be signed with"
+    )]
+    liquidity_key: Option<String>,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Quote to execute, defaults to the latest quote"
+    )]
+    quote_id: Option<Uuid>,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Amount to execute at its tier rate, in the currency of the requested amount, e.g. 12.50. Defaults to the requested amount"
+    )]
+    amount: Option<Decimal>,
+    #[clap(
+        long,
+        requires = "amount",
+        help = "Read the amount as an integer in the smallest unit of its currency, e.g. 1250 for 12.50"
+    )]
+    amount_minor_units: bool,
+    #[clap(
+        long,
+        help = "Execute at the ask of a two-way quote, paying from the target account"
+    )]
+    ask: bool,
+    #[clap(
+        long,
+        value_parser,
+        help = "Seconds to wait for the swap to complete, indefinitely when unset"
+    )]
+    timeout: Option<u64>,
+    #[clap(
+        long,
+        conflicts_with_all = &["now", "hold", "margin", "valid-for", "amount"],
+        help = "Wait for the swap already executed in the context to complete, e.g. after a disconnect, instead of executing its quote"
+    )]
+    resume: bool,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Init logging
-    tracing_subscriber::fmt().with_target(false).init();
-
-    let Command { url, command } = Command::parse();
-
-    let channel = Channel::from_shared(url)?
-        .timeout(Duration::from_secs(15))
-        .connect_lazy()?;
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct CancelQuote {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(short, long, value_parser)]
+    context_id: String,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Quote to cancel, defaults to the latest quote"
+    )]
+    quote_id: Option<Uuid>,
+}
 
-    match command {
-        RPC::Setup(setup) => {
-            info!("{:?}", setup);
-            let key_pair = if let Some(key_pair) = setup.key_pair.as_ref() {
-                Ed25519::load_key_pair(key_pair)?
-            } else {
-                root_key()
-            };
-            let client = M10Client::new(key_pair, channel);
-            try_setup(client, setup)
-                .instrument(info_span!("setup"))
-                .await
-        }
-        RPC::Initiate(initiate) => {
-            info!("{:?}", initiate);
-            let key_pair = Ed25519::load_key_pair(&initiate.key_pair)?;
-            let client = M10Client::new(key_pair, channel);
-            try_initiate(client, initiate)
-                .instrument(info_span!("initiate"))
-                .await
-        }
-        RPC::Execute(execute) => {
-            info!("{:?}", execute);
-            let key_pair = Ed25519::load_key_pair(&execute.key_pair)?;
-            let client = M10Client::new(key_pair, channel);
-            let context_id = hex::decode(&execute.context_id)?;
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct CounterQuote {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(short, long, value_parser)]
+    context_id: String,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Quote to negotiate, defaults to the latest quote"
+    )]
+    quote_id: Option<Uuid>,
+    #[clap(short, long, value_parser, help = "Proposed rate, net of the spread")]
+    rate: Decimal,
+}
 
-            // Find the last transaction in the context
-            let actions = client
-                .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
-                    FX_SWAP_ACTION.to_string(),
-                    context_id.clone(),
-                ))
-                .await?;
-            let action = actions
-                .first()
-                .ok_or_else(|| anyhow::anyhow!("No quote found for context"))?;
-            let quote = match serde_json::from_slice(&action.payload)? {
-                Event::Quote(quote) => quote,
-                Event::Request(_) => panic!("Request hasn't been quoted"),
-                Event::Execute(_) | Event::Completed => {
-                    panic!("Already executed");
-                }
-            };
-            let from = quote.request.from;
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct SwapStatusQuery {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(short, long, value_parser)]
+    context_id: String,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value_t = 30,
+        help = "Seconds to wait for the response"
+    )]
+    timeout: u64,
+    /// Prints the lifecycle of the swap recorded on the ledger, instead of asking the service for its state
+    #[clap(long)]
+    history: bool,
+}
 
-            let tx_id = try_execute(&client, execute, quote, context_id.clone())
-                .instrument(info_span!("execute"))
-                .await?;
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct ListAccounts {
+    #[clap(
+        short,
+        long,
+        help = "Key pair reading the accounts, the liquidity key pair of each currency of the config when unset"
+    )]
+    key_pair: Option<String>,
+    #[clap(
+        short,
+        long,
+        multiple = true,
+        help = "Accounts to show, instead of the liquidity accounts of the config"
+    )]
+    accounts: Vec<AccountId>,
+}
 
-            // Wait for confirmation
-            let mut stream = client
-                .observe_actions(
-                    AccountFilter::name(FX_SWAP_ACTION.to_string())
-                        .involves(from)
-                        .starting_from(tx_id + 1),
-                )
-                .await?;
-            info!("Waiting for swap confirmation");
-            while let Some(Ok(actions)) = stream.next().await {
-                for action in actions {
-                    if action.context_id != context_id {
-                        continue;
-                    }
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct AccountBalance {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(short, long)]
+    account: AccountId,
+}
 
-                    let event = serde_json::from_slice(&action.payload);
-                    if let Ok(Event::Completed) = event {
-                        info!("Swap completed");
-                        return Ok(());
-                    } else {
-                        error!("Invalid event: {:?}", event);
-                    }
-                }
-            }
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct WatchSwaps {
+    #[clap(
+        short,
+        long,
+        help = "Key pair observing the accounts, the liquidity key pair of each currency of the config when unset"
+    )]
+    key_pair: Option<String>,
+    #[clap(
+        short,
+        long,
+        multiple = true,
+        help = "Accounts whose swaps are watched, instead of the liquidity accounts of the config"
+    )]
+    accounts: Vec<AccountId>,
+    #[clap(long, help = "Only print the events of this context, in hex")]
+    context_id: Option<String>,
+    #[clap(
+        long,
+        value_parser,
+        help = "Transaction the events are replayed from, only new events being printed when unset"
+    )]
+    from: Option<TxId>,
+}
 
-            Ok(())
-        }
-    }
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Dashboard {
+    #[clap(
+        short,
+        long,
+        help = "Key pair reading the accounts & signing the swaps"
+    )]
+    key_pair: Option<String>,
+    #[clap(
+        short,
+        long,
+        multiple = true,
+        required = true,
+        help = "Accounts whose balances & swaps are shown, the first one funding the swaps initiated"
+    )]
+    accounts: Vec<AccountId>,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 5,
+        help = "Seconds between refreshes of the balances"
+    )]
+    refresh: u64,
 }
 
-async fn try_setup(client: M10Client<Ed25519>, setup: Setup) -> anyhow::Result<()> {
-    let liquidity_key = Ed25519::new_key_pair(Some("./liquidity.pkcs8"))?;
-    let alice_key = Ed25519::new_key_pair(Some("./alice.pkcs8"))?;
-    let bob_key = Ed25519::new_key_pair(Some("./bob.pkcs8"))?;
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Keys {
+    #[clap(subcommand)]
+    command: KeyCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum KeyCommand {
+    Generate(GenerateKey),
+    Show(ShowKey),
+    Rotate(RotateKey),
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Configure {
+    #[clap(subcommand)]
+    command: ConfigCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigCommand {
+    Init(InitConfig),
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct InitConfig {
+    #[clap(
+        short,
+        long,
+        help = "Key pair reading the ledger, the root key pair when unset"
+    )]
+    key_pair: Option<String>,
+    #[clap(
+        short,
+        long,
+        multiple = true,
+        help = "Currencies to provide liquidity for, asked when unset"
+    )]
+    currencies: Vec<String>,
+    #[clap(
+        long,
+        value_parser,
+        help = "Liquidity key pair, as a path or a secret reference, asked when unset"
+    )]
+    liquidity_key: Option<SecretSource>,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value = "config.toml",
+        help = "File the config is written to"
+    )]
+    out: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Take the default answer to every question instead of asking, e.g. for scripts"
+    )]
+    yes: bool,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct GenerateKey {
+    #[clap(short, long, help = "PKCS8 file the key pair is written to")]
+    out: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct ShowKey {
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Key pair, as a path or a secret reference, e.g. env://LIQUIDITY_KEY"
+    )]
+    key_pair: SecretSource,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct RotateKey {
+    #[clap(short, long, default_value = LIQUIDITY_KEY, help = "Key pair rotated")]
+    key_pair: String,
+    #[clap(
+        short,
+        long,
+        required_unless_present = "retire",
+        help = "PKCS8 file the new key pair is written to"
+    )]
+    out: Option<String>,
+    #[clap(
+        long,
+        conflicts_with = "out",
+        help = "Unbinds the rotated key pair, once the service runs with the new one"
+    )]
+    retire: bool,
+    #[clap(
+        long,
+        help = "Key pair updating the role-bindings, the rotated key pair when unset"
+    )]
+    signer: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct FundLiquidity {
+    #[clap(
+        short,
+        long,
+        help = "Key pair transferring the funds, the test root key when unset"
+    )]
+    key_pair: Option<String>,
+    #[clap(short, long, help = "Currency of the liquidity account, as configured")]
+    currency: String,
+    #[clap(short, long, value_parser, help = "Units to transfer")]
+    amount: u64,
+    #[clap(
+        short,
+        long,
+        help = "Account the funds are transferred from, the parent of the liquidity account when unset"
+    )]
+    from: Option<AccountId>,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct PreviewQuote {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(short, long)]
+    from: Option<AccountId>,
+    #[clap(short, long, value_parser)]
+    to: Option<AccountId>,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        required_unless_present = "receive-amount",
+        help = "Amount to send, in the source currency, e.g. 12.50"
+    )]
+    amount: Option<Decimal>,
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with = "amount",
+        help = "Exact amount to deliver, in the target currency, e.g. 12.50"
+    )]
+    receive_amount: Option<Decimal>,
+    #[clap(
+        long,
+        help = "Read the amounts as integers in the smallest unit of their currency, e.g. 1250 for 12.50"
+    )]
+    amount_minor_units: bool,
+    #[clap(
+        long,
+        required_unless_present = "admin",
+        help = "Price the swap from the rates & spreads of the config, without asking the service"
+    )]
+    offline: bool,
+    #[clap(
+        long,
+        value_parser,
+        conflicts_with = "offline",
+        help = "Base URL of the admin API of the service pricing the swap, e.g. http://localhost:9100"
+    )]
+    admin: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct SwapHistoryQuery {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(short, long, help = "Account whose swaps are listed")]
+    account: AccountId,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value_t = 20,
+        help = "Swaps listed per page, most recent first"
+    )]
+    limit: usize,
+    #[clap(
+        long,
+        value_parser,
+        help = "Lists the swaps requested before this transaction, as printed at the end of the previous page"
+    )]
+    before: Option<TxId>,
+    #[clap(
+        long,
+        value_parser,
+        help = "Epoch time in seconds from which swaps were requested"
+    )]
+    since: Option<u64>,
+    #[clap(
+        long,
+        value_parser,
+        help = "Epoch time in seconds until which swaps were requested"
+    )]
+    until: Option<u64>,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct VerifySettlement {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(short, long, value_parser)]
+    context_id: String,
+    #[clap(
+        long,
+        value_parser,
+        help = "Hex encoded public key the quote & receipts must be signed with"
+    )]
+    liquidity_key: Option<String>,
+    #[clap(
+        long,
+        value_parser,
+        help = "URL of the ledger of the target currency, the ledger of the source currency when unset"
+    )]
+    target_url: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct ExportContext {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(short, long, value_parser)]
+    context_id: String,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Directory the bundle is written to, ./audit-<context id> when unset"
+    )]
+    out: Option<PathBuf>,
+    #[clap(
+        long,
+        value_parser,
+        help = "URL of the ledger of the target currency, whose transfers are exported too, when not the same"
+    )]
+    target_url: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct SimulateSwap {
+    #[clap(long, default_value = "USD", help = "Currency the swap is paid in")]
+    from_currency: String,
+    #[clap(long, default_value = "EUR", help = "Currency the swap delivers")]
+    to_currency: String,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value = "100",
+        help = "Amount to send, in the source currency"
+    )]
+    amount: Decimal,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value = "0.9",
+        help = "Rate the mock rate source starts from, in the target currency per unit of the source currency"
+    )]
+    rate: Decimal,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 5,
+        help = "Largest move of the mock rate on each lookup, in basis points"
+    )]
+    volatility_bps: u32,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 25,
+        help = "Spread on the rate, in basis points"
+    )]
+    spread_bps: u32,
+    #[clap(
+        long,
+        value_parser,
+        default_value = "0",
+        help = "Fixed fee deducted from the source amount"
+    )]
+    flat_fee: Decimal,
+    #[clap(
+        long,
+        value_parser,
+        help = "Largest deviation of the settlement rate from the quoted rate, in basis points, unchecked when unset"
+    )]
+    max_slippage_bps: Option<u32>,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 2,
+        help = "Decimals of both currencies"
+    )]
+    decimals: u32,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct LoadTest {
+    #[clap(short, long)]
+    key_pair: Option<String>,
+    #[clap(
+        long,
+        value_parser,
+        multiple = true,
+        required = true,
+        help = "Currency pairs to request swaps of in turn, as `from:to`, e.g. usd:eur"
+    )]
+    pairs: Vec<loadtest::Pair>,
+    #[clap(
+        short,
+        long,
+        multiple = true,
+        help = "Accounts of the key pair to swap between, one per currency, the `from` & `to` of ~/.m10fx/config when unset"
+    )]
+    accounts: Vec<AccountId>,
+    #[clap(
+        long,
+        value_parser = loadtest::parse_rate,
+        default_value = "1/s",
+        help = "Swaps requested per second, or per minute as `<n>/m`"
+    )]
+    rate: f64,
+    #[clap(
+        long,
+        value_parser = loadtest::parse_duration,
+        default_value = "60s",
+        help = "Time swaps are requested for, in seconds, or in minutes or hours as `<n>m` or `<n>h`"
+    )]
+    duration: Duration,
+    #[clap(
+        long,
+        value_parser,
+        default_value = "1",
+        help = "Amount of each swap, in the source currency"
+    )]
+    amount: Decimal,
+    #[clap(
+        long,
+        help = "Only request quotes, rather than executing each quote immediately & waiting for the swap to complete"
+    )]
+    quote_only: bool,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = 30,
+        help = "Seconds to wait for each quote, & for each swap to complete when executed"
+    )]
+    timeout: u64,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Completions {
+    #[clap(value_enum, help = "Shell the completions are generated for")]
+    shell: clap_complete::Shell,
+    #[clap(long, default_value = "cli", help = "Name the binary is invoked by")]
+    bin_name: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct ManPages {
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Directory a page is written to for the binary & each of its commands, only the page of the binary being printed when unset"
+    )]
+    out: Option<PathBuf>,
+    #[clap(long, default_value = "cli", help = "Name the binary is invoked by")]
+    bin_name: String,
+}
+
+#[tokio::main]
+async fn main() {
+    let command = Command::parse();
+    let output = command.output;
+    if let Err(err) = run(command).await {
+        let (_, exit_code) = error::classify(&err);
+        match output {
+            Output::Text => eprintln!("Error: {:?}", err),
+            Output::Json => output.emit(error::to_json(&err)),
+        }
+        std::process::exit(exit_code)
+    }
+}
+
+async fn run(command: Command) -> anyhow::Result<()> {
+    let Command {
+        url,
+        config,
+        profile,
+        output,
+        command,
+    } = command;
+
+    // Flags left unset default to the profile, which the config may not declare
+    let defaults = Defaults::load(profile.as_deref())?;
+    let url = url
+        .or_else(|| defaults.url.clone())
+        .unwrap_or_else(|| DEFAULT_LEDGER_URL.to_string());
+    let config = config.or_else(|| defaults.config.clone());
+    let profile = match profile {
+        Some(name)
+            if defaults.declares() && !config::declares_profile(config.as_deref(), &name)? =>
+        {
+            None
+        }
+        profile => profile,
+    };
+
+    // Init logging
+    let log: LogConfig = config::parse_section(config.as_deref(), profile.as_deref(), "log")?;
+    match output {
+        // The dashboard owns the terminal & generated scripts are printed, logs are only kept when written to files
+        _ if matches!(command, RPC::Tui(_) | RPC::Completions(_) | RPC::Man(_))
+            && log.file.is_none() => {}
+        Output::Text => logging::init(&log, false)?,
+        Output::Json => logging::init_stderr(&log, false)?,
+    }
+
+    let transport = config::parse_section::<TransportConfig>(
+        config.as_deref(),
+        profile.as_deref(),
+        "transport",
+    )?;
+    let channel = transport.endpoint(&url)?.connect_lazy()?;
+
+    match command {
+        RPC::Setup(setup) => {
+            info!("{:?}", setup);
+            let key_pair = if let Some(key_pair) = setup.key_pair.as_ref() {
+                Ed25519::load_key_pair(key_pair)?
+            } else {
+                root_key()
+            };
+            let signer = match setup.key_pair.as_ref() {
+                Some(key_pair) => Ed25519::load_key_pair(key_pair)?,
+                None => root_key(),
+            };
+            let client = M10Client::new(key_pair, channel);
+            try_setup(client, signer, setup, output)
+                .instrument(info_span!("setup"))
+                .await
+        }
+        RPC::Teardown(teardown) => {
+            info!("{:?}", teardown);
+            let load = || match teardown.key_pair.as_ref() {
+                Some(key_pair) => Ed25519::load_key_pair(key_pair),
+                None => Ok(root_key()),
+            };
+            let client = M10Client::new(load()?, channel);
+            try_teardown(client, load()?, teardown, output)
+                .instrument(info_span!("teardown"))
+                .await
+        }
+        RPC::Initiate(mut initiate) => {
+            if initiate.batch.is_none() {
+                initiate.from = initiate.from.or(defaults.from()?);
+                initiate.to = initiate.to.or(defaults.to()?);
+            }
+            info!("{:?}", initiate);
+            let key_pair =
+                Ed25519::load_key_pair(defaults.key_pair(initiate.key_pair.as_deref())?)?;
+            let public_key = key_pair.public_key().to_vec();
+            let client = M10Client::new(key_pair, channel);
+            let default_validity = match initiate.auto_execute {
+                true => {
+                    let engine: EngineConfig =
+                        config::parse_section(config.as_deref(), profile.as_deref(), "engine")?;
+                    Some(engine.default_validity)
+                }
+                false => None,
+            };
+            if let Some(batch) = initiate.batch.clone() {
+                return initiate_batch(
+                    &client,
+                    initiate,
+                    &batch,
+                    &public_key,
+                    default_validity,
+                    output,
+                )
+                .await;
+            }
+            let context_id = fastrand::u64(..).to_be_bytes().to_vec();
+            initiate_swap(
+                &client,
+                initiate,
+                context_id,
+                &public_key,
+                default_validity,
+                output,
+            )
+            .await
+            .map(drop)
+        }
+        RPC::Execute(execute) => {
+            info!("{:?}", execute);
+            let key_pair = Ed25519::load_key_pair(defaults.key_pair(execute.key_pair.as_deref())?)?;
+            let public_key = key_pair.public_key().to_vec();
+            let client = M10Client::new(key_pair, channel);
+            let context_id = hex::decode(&execute.context_id)?;
+            if execute.resume {
+                let (tx_id, quote) =
+                    find_executed_quote(&client, context_id.clone(), execute.quote_id).await?;
+                let from = match execute.ask {
+                    true => quote.request.to,
+                    false => quote.request.from,
+                };
+                // Events published since the quote are replayed, the swap may have completed meanwhile
+                let completion = wait_for_completion(&client, from, tx_id, &context_id, output);
+                return within(execute.timeout, &context_id, "completion", true, completion)
+                    .instrument(info_span!("execute"))
+                    .await;
+            }
+
+            let quote = find_quote(&client, context_id.clone(), execute.quote_id).await?;
+            let engine: EngineConfig =
+                config::parse_section(config.as_deref(), profile.as_deref(), "engine")?;
+            execute_swap(
+                &client,
+                execute,
+                quote,
+                engine.default_validity,
+                context_id,
+                &public_key,
+                output,
+            )
+            .instrument(info_span!("execute"))
+            .await
+        }
+        RPC::Cancel(cancel) => {
+            info!("{:?}", cancel);
+            let key_pair = Ed25519::load_key_pair(defaults.key_pair(cancel.key_pair.as_deref())?)?;
+            let public_key = key_pair.public_key().to_vec();
+            let client = M10Client::new(key_pair, channel);
+            let context_id = hex::decode(&cancel.context_id)?;
+            try_cancel(&client, context_id, cancel.quote_id, &public_key, output)
+                .instrument(info_span!("cancel"))
+                .await
+        }
+        RPC::Status(query) => {
+            info!("{:?}", query);
+            let key_pair = Ed25519::load_key_pair(defaults.key_pair(query.key_pair.as_deref())?)?;
+            let public_key = key_pair.public_key().to_vec();
+            let client = M10Client::new(key_pair, channel);
+            let context_id = hex::decode(&query.context_id)?;
+            if query.history {
+                return print_history(&client, context_id, output)
+                    .instrument(info_span!("status"))
+                    .await;
+            }
+            // The swap may be unknown to the service, which doesn't answer then
+            let status = try_status(&client, context_id.clone(), &public_key, output);
+            within(Some(query.timeout), &context_id, "status", false, status)
+                .instrument(info_span!("status"))
+                .await
+        }
+        RPC::Accounts(list) => {
+            info!("{:?}", list);
+            if !list.accounts.is_empty() {
+                let key_pair = list.key_pair.as_ref().ok_or_else(|| {
+                    Failure::Usage("A --key-pair is required to read explicit accounts".to_string())
+                })?;
+                let client = M10Client::new(Ed25519::load_key_pair(key_pair)?, channel);
+                let accounts = list.accounts.iter().map(|&account| ("-", account));
+                return print_accounts(&client, accounts.collect(), output)
+                    .instrument(info_span!("accounts"))
+                    .await;
+            }
+
+            let address: Option<Addresses> =
+                config::parse_section(config.as_deref(), profile.as_deref(), "address")?;
+            let liquidity: HashMap<CurrencyCode, LiquidityConfig> =
+                config::parse_section(config.as_deref(), profile.as_deref(), "liquidity")?;
+            if liquidity.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "No liquidity configured, pass the --accounts to show"
+                ));
+            }
+            let mut currencies = liquidity.into_iter().collect::<Vec<_>>();
+            currencies.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (currency, liquidity) in currencies {
+                // Each currency is read from its own ledger, with its own key unless one is given
+                let url = ledger_url(&liquidity, address.as_ref(), &url);
+                let key_pair = match &list.key_pair {
+                    Some(key_pair) => Ed25519::load_key_pair(key_pair)?,
+                    None => {
+                        let key_pair: Vec<u8> = liquidity.key_pair.resolve().await?;
+                        Ed25519::from_pkcs8(&key_pair)?
+                    }
+                };
+                let client = M10Client::new(key_pair, transport.endpoint(&url)?.connect_lazy()?);
+                let mut accounts = vec![("primary", liquidity.account)];
+                for &account in &liquidity.accounts {
+                    if account != liquidity.account {
+                        accounts.push(("additional", account));
+                    }
+                }
+                accounts.extend(liquidity.reserve.map(|account| ("reserve", account)));
+                accounts.extend(liquidity.fee_account.map(|account| ("fee", account)));
+                print_accounts(&client, accounts, output)
+                    .instrument(info_span!("accounts", %currency))
+                    .await?;
+            }
+            Ok(())
+        }
+        RPC::Balance(query) => {
+            info!("{:?}", query);
+            let key_pair = Ed25519::load_key_pair(defaults.key_pair(query.key_pair.as_deref())?)?;
+            let client = M10Client::new(key_pair, channel);
+            print_balance(&client, query.account, output)
+                .instrument(info_span!("balance"))
+                .await
+        }
+        RPC::Quote(mut preview) => {
+            preview.from = preview.from.or(defaults.from()?);
+            preview.to = preview.to.or(defaults.to()?);
+            info!("{:?}", preview);
+            let key_pair = Ed25519::load_key_pair(defaults.key_pair(preview.key_pair.as_deref())?)?;
+            let client = M10Client::new(key_pair, channel);
+            let config = match preview.offline {
+                true => Some(config::parse(config.as_deref(), profile.as_deref())?),
+                false => None,
+            };
+            preview_quote(&client, preview, config.as_ref(), output)
+                .instrument(info_span!("quote"))
+                .await
+        }
+        RPC::Fund(fund) => {
+            info!("{:?}", fund);
+            let address: Option<Addresses> =
+                config::parse_section(config.as_deref(), profile.as_deref(), "address")?;
+            let liquidity: HashMap<CurrencyCode, LiquidityConfig> =
+                config::parse_section(config.as_deref(), profile.as_deref(), "liquidity")?;
+            let (currency, liquidity) = liquidity
+                .into_iter()
+                .find(|(currency, _)| currency.eq_ignore_ascii_case(&fund.currency))
+                .ok_or_else(|| anyhow::anyhow!("No liquidity configured for {}", fund.currency))?;
+            let key_pair = match fund.key_pair.as_ref() {
+                Some(key_pair) => Ed25519::load_key_pair(key_pair)?,
+                None => root_key(),
+            };
+            let url = ledger_url(&liquidity, address.as_ref(), &url);
+            let client = M10Client::new(key_pair, transport.endpoint(&url)?.connect_lazy()?);
+            try_fund(&client, liquidity.account, fund, output)
+                .instrument(info_span!("fund", %currency))
+                .await
+        }
+        RPC::Watch(watch) => {
+            info!("{:?}", watch);
+            let context_id = watch.context_id.as_deref().map(hex::decode).transpose()?;
+            let mut streams = Vec::new();
+            if !watch.accounts.is_empty() {
+                let key_pair = watch.key_pair.as_ref().ok_or_else(|| {
+                    Failure::Usage(
+                        "A --key-pair is required to watch explicit accounts".to_string(),
+                    )
+                })?;
+                let client = M10Client::new(Ed25519::load_key_pair(key_pair)?, channel);
+                streams.push(observe_swaps(&client, &watch.accounts, watch.from).await?);
+            } else {
+                let address: Option<Addresses> =
+                    config::parse_section(config.as_deref(), profile.as_deref(), "address")?;
+                let liquidity: HashMap<CurrencyCode, LiquidityConfig> =
+                    config::parse_section(config.as_deref(), profile.as_deref(), "liquidity")?;
+                if liquidity.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "No liquidity configured, pass the --accounts to watch"
+                    ));
+                }
+                // Each currency is observed on its own ledger, with its own key unless one is given
+                for (currency, liquidity) in liquidity {
+                    let url = ledger_url(&liquidity, address.as_ref(), &url);
+                    let key_pair = match &watch.key_pair {
+                        Some(key_pair) => Ed25519::load_key_pair(key_pair)?,
+                        None => {
+                            let key_pair: Vec<u8> = liquidity.key_pair.resolve().await?;
+                            Ed25519::from_pkcs8(&key_pair)?
+                        }
+                    };
+                    let client =
+                        M10Client::new(key_pair, transport.endpoint(&url)?.connect_lazy()?);
+                    let mut accounts = vec![liquidity.account];
+                    accounts.extend(&liquidity.accounts);
+                    info!(%currency, accounts = %accounts.len(), "Watching liquidity accounts");
+                    streams.push(observe_swaps(&client, &accounts, watch.from).await?);
+                }
+            }
+            print_swaps(
+                futures_util::stream::select_all(streams),
+                context_id,
+                output,
+            )
+            .instrument(info_span!("watch"))
+            .await
+        }
+        RPC::Keys(keys) => {
+            info!("{:?}", keys);
+            match keys.command {
+                KeyCommand::Generate(generate) => generate_key(&generate.out, output).map(drop),
+                KeyCommand::Show(show) => {
+                    let key_pair: Vec<u8> = show.key_pair.resolve().await?;
+                    let public_key = hex::encode(Ed25519::from_pkcs8(&key_pair)?.public_key());
+                    info!(key_pair = %show.key_pair, %public_key, "Public key");
+                    output.emit(json!({ "key_pair": show.key_pair, "public_key": public_key }));
+                    Ok(())
+                }
+                KeyCommand::Rotate(rotate) => {
+                    let signer = rotate.signer.as_ref().unwrap_or(&rotate.key_pair);
+                    let client = M10Client::new(Ed25519::load_key_pair(signer)?, channel);
+                    let signer = Ed25519::load_key_pair(signer)?;
+                    try_rotate(&client, &signer, rotate, output)
+                        .instrument(info_span!("rotate"))
+                        .await
+                }
+            }
+        }
+        RPC::Config(configure) => {
+            info!("{:?}", configure);
+            match configure.command {
+                ConfigCommand::Init(init) => {
+                    let load = || match init.key_pair.as_ref() {
+                        Some(key_pair) => Ed25519::load_key_pair(key_pair),
+                        None => Ok(root_key()),
+                    };
+                    let client = M10Client::new(load()?, channel);
+                    wizard::run(&client, &load()?, init, &url, output)
+                        .instrument(info_span!("config"))
+                        .await
+                }
+            }
+        }
+        RPC::History(query) => {
+            info!("{:?}", query);
+            let key_pair = Ed25519::load_key_pair(defaults.key_pair(query.key_pair.as_deref())?)?;
+            let client = M10Client::new(key_pair, channel);
+            list_history(&client, query, output)
+                .instrument(info_span!("history"))
+                .await
+        }
+        RPC::Verify(verify) => {
+            info!("{:?}", verify);
+            let key_pair = defaults.key_pair(verify.key_pair.as_deref())?;
+            let client = M10Client::new(Ed25519::load_key_pair(key_pair)?, channel);
+            let target_client = match &verify.target_url {
+                Some(target_url) => M10Client::new(
+                    Ed25519::load_key_pair(key_pair)?,
+                    transport.endpoint(target_url)?.connect_lazy()?,
+                ),
+                None => client.clone(),
+            };
+            let context_id = hex::decode(&verify.context_id)?;
+            verify_settlement(
+                &client,
+                &target_client,
+                context_id,
+                verify.liquidity_key.as_deref(),
+                output,
+            )
+            .instrument(info_span!("verify"))
+            .await
+        }
+        RPC::Export(export) => {
+            info!("{:?}", export);
+            let key_pair = defaults.key_pair(export.key_pair.as_deref())?;
+            let client = M10Client::new(Ed25519::load_key_pair(key_pair)?, channel);
+            let target_client = match &export.target_url {
+                Some(target_url) => Some(M10Client::new(
+                    Ed25519::load_key_pair(key_pair)?,
+                    transport.endpoint(target_url)?.connect_lazy()?,
+                )),
+                None => None,
+            };
+            let ledgers = export::Ledgers {
+                source: (&url, &client),
+                target: export.target_url.as_deref().zip(target_client.as_ref()),
+            };
+            let context_id = hex::decode(&export.context_id)?;
+            let out = export
+                .out
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(format!("audit-{}", export.context_id)));
+            export::run(ledgers, context_id, &out, output)
+                .instrument(info_span!("export"))
+                .await
+        }
+        RPC::Loadtest(loadtest) => {
+            info!("{:?}", loadtest);
+            let key_pair =
+                Ed25519::load_key_pair(defaults.key_pair(loadtest.key_pair.as_deref())?)?;
+            let public_key = key_pair.public_key().to_vec();
+            let client = M10Client::new(key_pair, channel);
+            let default_validity = match loadtest.quote_only {
+                true => None,
+                false => {
+                    let engine: EngineConfig =
+                        config::parse_section(config.as_deref(), profile.as_deref(), "engine")?;
+                    Some(engine.default_validity)
+                }
+            };
+            let accounts = match loadtest.accounts.is_empty() {
+                true => defaults.from()?.into_iter().chain(defaults.to()?).collect(),
+                false => loadtest.accounts.clone(),
+            };
+            loadtest::run(
+                &client,
+                loadtest,
+                accounts,
+                &public_key,
+                default_validity,
+                output,
+            )
+            .instrument(info_span!("loadtest"))
+            .await
+        }
+        RPC::Simulate(simulate) => {
+            info!("{:?}", simulate);
+            simulate::run(simulate, output)
+                .instrument(info_span!("simulate"))
+                .await
+        }
+        // Generated from the definition of the command line, so that every command is covered
+        RPC::Completions(completions) => {
+            let mut command = <Command as CommandFactory>::command();
+            clap_complete::generate(
+                completions.shell,
+                &mut command,
+                completions.bin_name,
+                &mut std::io::stdout(),
+            );
+            Ok(())
+        }
+        RPC::Man(man) => {
+            let command = <Command as CommandFactory>::command().name(man.bin_name);
+            match man.out {
+                Some(out) => write_man_pages(command, &out),
+                None => Ok(clap_mangen::Man::new(command).render(&mut std::io::stdout())?),
+            }
+        }
+        RPC::Counter(counter) => {
+            info!("{:?}", counter);
+            let key_pair = Ed25519::load_key_pair(defaults.key_pair(counter.key_pair.as_deref())?)?;
+            let public_key = key_pair.public_key().to_vec();
+            let client = M10Client::new(key_pair, channel);
+            let context_id = hex::decode(&counter.context_id)?;
+            try_counter(&client, context_id, counter, &public_key, output)
+                .instrument(info_span!("counter"))
+                .await
+        }
+        RPC::Tui(dashboard) => {
+            let key_pair =
+                Ed25519::load_key_pair(defaults.key_pair(dashboard.key_pair.as_deref())?)?;
+            let public_key = key_pair.public_key().to_vec();
+            let client = M10Client::new(key_pair, channel);
+            let engine: EngineConfig =
+                config::parse_section(config.as_deref(), profile.as_deref(), "engine")?;
+            tui::run(&client, dashboard, &public_key, engine.default_validity).await
+        }
+    }
+}
+
+/// Writes the man page of `command` to `out`, & those of its subcommands as `<command>-<subcommand>`
+fn write_man_pages(command: clap::Command, out: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(out)?;
+    let name = command.get_name().to_string();
+    for subcommand in command.get_subcommands() {
+        let subcommand = subcommand
+            .clone()
+            .name(format!("{}-{}", name, subcommand.get_name()));
+        write_man_pages(subcommand, out)?;
+    }
+    let path = out.join(format!("{}.1", name));
+    let mut file = std::fs::File::create(&path)?;
+    clap_mangen::Man::new(command).render(&mut file)?;
+    info!(path = %path.display(), "Wrote man page");
+    Ok(())
+}
+
+/// Checks that `quote` hasn't expired & is signed, by `liquidity_key` in hex when set
+fn check_quote(quote: &Quote, liquidity_key: Option<&str>) -> anyhow::Result<()> {
+    if quote.is_expired() {
+        return Err(Failure::InvalidQuote(format!(
+            "Quote expired at {}, request a new quote",
+            quote.valid_until
+        ))
+        .into());
+    }
+    let signer = quote.verify()?;
+    if let Some(liquidity_key) = liquidity_key {
+        if hex::decode(liquidity_key)? != signer {
+            return Err(
+                Failure::InvalidQuote("Quote was signed by an unexpected key".to_string()).into(),
+            );
+        }
+    }
+    info!(signer = %hex::encode(signer), "Verified quote signature");
+    Ok(())
+}
+
+/// Executes `quote` as set by `execute`, after checking its signature, & waits for the swap to complete
+async fn execute_swap(
+    client: &M10Client<Ed25519>,
+    execute: ExecuteQuote,
+    quote: Quote,
+    default_validity: u64,
+    context_id: Vec<u8>,
+    public_key: &[u8],
+    output: Output,
+) -> anyhow::Result<()> {
+    check_quote(&quote, execute.liquidity_key.as_deref())?;
+    let from = match execute.ask {
+        true => quote.request.to,
+        false => quote.request.from,
+    };
+    let timeout = execute.timeout;
+
+    let tx_id = try_execute(
+        client,
+        execute,
+        quote,
+        default_validity,
+        context_id.clone(),
+        public_key,
+    )
+    .await?;
+    output.emit(json!({ "context_id": hex::encode(&context_id), "tx_id": tx_id }));
+
+    let completion = wait_for_completion(client, from, tx_id, &context_id, output);
+    within(timeout, &context_id, "completion", true, completion).await
+}
+
+/// Finds the quote executed in `context_id`, `quote_id` or else the latest one, with the transaction publishing it
+async fn find_executed_quote(
+    client: &M10Client<Ed25519>,
+    context_id: Vec<u8>,
+    quote_id: Option<Uuid>,
+) -> anyhow::Result<(TxId, Quote)> {
+    let actions = client
+        .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+            FX_SWAP_ACTION.to_string(),
+            context_id,
+        ))
+        .await?;
+    // Actions are listed from the most recent one
+    let executed = actions
+        .iter()
+        .find_map(|action| match Event::decode(&action.payload) {
+            Ok(Event::Quote(quote)) if quote_id.is_none_or(|id| id == quote.quote_id) => {
+                Some((action.tx_id, quote))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| Failure::NotFound("No quote found for context".to_string()))?;
+    Ok(executed)
+}
+
+/// Waits for the swap in `context_id` paid from `from` to complete, from the events published after the transaction `tx_id`
+async fn wait_for_completion(
+    client: &M10Client<Ed25519>,
+    from: AccountId,
+    tx_id: TxId,
+    context_id: &[u8],
+    output: Output,
+) -> anyhow::Result<()> {
+    let context_hex = hex::encode(context_id);
+    let mut stream = client
+        .observe_actions(
+            AccountFilter::name(FX_SWAP_ACTION.to_string())
+                .involves(from)
+                .starting_from(tx_id + 1),
+        )
+        .await?;
+    info!("Waiting for swap confirmation");
+    let mut sequences = Sequences::default();
+    while let Some(actions) = stream.next().await {
+        for action in actions? {
+            if action.context_id != context_id {
+                continue;
+            }
+
+            let event = match Envelope::decode(&action.payload) {
+                Ok(envelope) if !sequences.observe(&envelope) => continue,
+                Ok(envelope) => Ok(envelope.event),
+                Err(err) => Err(err),
+            };
+            if let Ok(event) = &event {
+                output.emit(SwapResult {
+                    context_id: &context_hex,
+                    tx_id: action.tx_id,
+                    quote: None,
+                    event: Some(event),
+                });
+            }
+            match event {
+                Ok(Event::Completed { paper_trade: false }) => {
+                    info!("Swap completed");
+                    return Ok(());
+                }
+                Ok(Event::Completed { paper_trade: true }) => {
+                    info!("Swap completed as a paper trade, nothing was settled");
+                    return Ok(());
+                }
+                Ok(Event::Receipt(receipt)) if receipt.slices > 0 => {
+                    let signer = receipt.verify()?;
+                    info!(
+                        slices = %receipt.slices,
+                        source_amount = %receipt.source_amount,
+                        target_amount = %receipt.target_amount,
+                        effective_rate = %receipt.effective_rate,
+                        executed_rate = %receipt.executed_rate,
+                        signer = %hex::encode(signer),
+                        "Received TWAP summary receipt"
+                    );
+                }
+                Ok(Event::Receipt(receipt)) => {
+                    let signer = receipt.verify()?;
+                    info!(
+                        source_tx_id = %receipt.source_tx_id,
+                        target_tx_id = %receipt.target_tx_id,
+                        source_amount = %receipt.source_amount,
+                        target_amount = %receipt.target_amount,
+                        effective_rate = %receipt.effective_rate,
+                        quoted_rate = ?receipt.quoted_rate,
+                        executed_rate = %receipt.executed_rate,
+                        signer = %hex::encode(signer),
+                        "Received settlement receipt"
+                    );
+                }
+                Ok(Event::Netted(report)) => {
+                    info!(
+                        tx_id = %report.tx_id,
+                        amount = %report.amount,
+                        net_amount = %report.net_amount,
+                        netted = %report.netted,
+                        "Payout netted into a batched transfer"
+                    );
+                }
+                Ok(Event::PartialFill(fill)) => {
+                    info!(
+                        filled = %fill.filled,
+                        remaining = %fill.remaining,
+                        refunded = %fill.refunded,
+                        "Swap partially filled"
+                    );
+                    return Ok(());
+                }
+                Ok(Event::Reject { reason, .. }) => {
+                    return Err(
+                        Failure::Rejected(format!("Execution was rejected: {}", reason)).into(),
+                    );
+                }
+                Ok(Event::Failed { code, message }) => {
+                    return Err(
+                        Failure::Failed(format!("Swap failed [{}]: {}", code, message)).into(),
+                    );
+                }
+                Ok(Event::Refunded(refund)) => {
+                    info!(
+                        source_tx_id = %refund.source_tx_id,
+                        refund_tx_id = %refund.refund_tx_id,
+                        amount = %refund.amount,
+                        "Source funds refunded"
+                    );
+                    return Err(
+                        Failure::Failed(format!("Swap was refunded: {}", refund.reason)).into(),
+                    );
+                }
+                _ => error!("Invalid event: {:?}", event),
+            }
+        }
+    }
+
+    Err(Failure::Disconnected {
+        context_id: context_hex,
+        awaited: "completion",
+        resumable: true,
+    }
+    .into())
+}
+
+async fn find_quote(
+    client: &M10Client<Ed25519>,
+    context_id: Vec<u8>,
+    quote_id: Option<Uuid>,
+) -> anyhow::Result<Quote> {
+    let actions = client
+        .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+            FX_SWAP_ACTION.to_string(),
+            context_id,
+        ))
+        .await?;
+    let quote_id = match quote_id {
+        Some(quote_id) => quote_id,
+        None => {
+            // Use the last transaction in the context, ignoring status queries
+            let event = actions
+                .iter()
+                .map(|action| Event::decode(&action.payload))
+                .find(|event| !matches!(event, Ok(Event::StatusRequest | Event::StatusResponse(_))))
+                .ok_or_else(|| Failure::NotFound("No quote found for context".to_string()))?;
+            let failure = match event? {
+                Event::Quote(quote) => return Ok(quote),
+                Event::Request(_) => {
+                    Failure::Conflict("Request hasn't been quoted yet".to_string())
+                }
+                Event::CounterOffer(_) => {
+                    Failure::Conflict("Counter-offer hasn't been answered yet".to_string())
+                }
+                Event::Reject { reason, .. } => {
+                    Failure::Rejected(format!("Request was rejected: {}", reason))
+                }
+                Event::Cancel => Failure::Conflict("Quote was cancelled".to_string()),
+                Event::Failed { code, message } => {
+                    Failure::Failed(format!("Swap failed [{}]: {}", code, message))
+                }
+                Event::Refunded(refund) => {
+                    Failure::Failed(format!("Swap was refunded: {}", refund.reason))
+                }
+                Event::StatusRequest | Event::StatusResponse(_) => unreachable!(),
+                Event::Execute(_)
+                | Event::Completed { .. }
+                | Event::PartialFill(_)
+                | Event::Receipt(_)
+                | Event::Netted(_) => Failure::Conflict("Quote was already executed".to_string()),
+            };
+            return Err(failure.into());
+        }
+    };
+
+    // Find the requested quote, unless the context was cancelled since
+    for action in actions {
+        match Event::decode(&action.payload)? {
+            Event::Quote(quote) if quote.quote_id == quote_id => return Ok(quote),
+            Event::Cancel => {
+                return Err(Failure::Conflict("Quote was cancelled".to_string()).into())
+            }
+            _ => {}
+        }
+    }
+    Err(Failure::NotFound(format!("No quote found for id {}", quote_id)).into())
+}
+
+/// Creates the accounts of the liquidity provider, Alice & Bob, reusing the key files & accounts of a previous run
+async fn try_setup(
+    client: M10Client<Ed25519>,
+    signer: Ed25519,
+    setup: Setup,
+    output: Output,
+) -> anyhow::Result<()> {
+    let users = match setup.users.is_empty() {
+        true => default_users(&setup),
+        false => setup.users.clone(),
+    };
+    let liquidity_key = setup_key(LIQUIDITY_KEY, output)?;
+    let mut user_keys = HashMap::new();
+    for user in &users {
+        if !user_keys.contains_key(&user.name) {
+            let key_pair = setup_key(&user_key(&user.name), output)?;
+            user_keys.insert(user.name.clone(), key_pair);
+        }
+    }
+
+    let mut accounts = vec![];
+    for account in ledger_currencies(&client).await? {
+        let currency = account.code.to_lowercase();
+        if !setup.currencies.contains(&currency) {
+            continue;
+        }
+        info!(%account.id, %currency, "Found account");
+        accounts.push(account);
+    }
+
+    // Accounts of a previous run are found from the roles registered for their owner
+    let owners = std::iter::once(&liquidity_key)
+        .chain(user_keys.values())
+        .map(|key| key.public_key().to_vec())
+        .collect::<Vec<_>>();
+    let existing = setup_accounts(&client, &signer, &owners).await?;
+
+    // Create the missing accounts & account docs for all currencies
+    let mut liquidity_accounts = HashMap::new();
+    let mut summary = Vec::new();
+    for account in accounts {
+        let currency = account.code.to_lowercase();
+        let mut holders = vec![(
+            "liquidity",
+            &liquidity_key,
+            "fx-liquidity",
+            setup.fund_liquidity,
+        )];
+        for user in users.iter().filter(|user| user.currency == currency) {
+            let funding = user.funding.unwrap_or(setup.fund_user);
+            holders.push((&user.name, &user_keys[&user.name], &user.name, funding));
+        }
+        for (role, key, name, funding) in holders {
+            let owner = key.public_key();
+            let (account_id, status) = match existing.get(&(owner.to_vec(), currency.clone())) {
+                Some(&account_id) => {
+                    info!(%currency, %role, %account_id, "Reusing account");
+                    (account_id, "reused")
+                }
+                None => {
+                    let account_id =
+                        create_account(&client, account.id, owner, name.to_string(), funding)
+                            .instrument(info_span!("account", %currency, %role))
+                            .await?;
+                    (account_id, "created")
+                }
+            };
+            if role == "liquidity" {
+                liquidity_accounts.insert(currency.clone(), account_id);
+            }
+            summary.push((currency.clone(), role, account_id, status));
+        }
+    }
+
+    for user in &users {
+        if !summary
+            .iter()
+            .any(|(currency, ..)| *currency == user.currency)
+        {
+            warn!(user = %user.name, currency = %user.currency, "No account created, the currency wasn't set up");
+        }
+    }
+    info!(
+        "{:<8} {:<10} {:<32} {:<7}",
+        "CURRENCY", "ROLE", "ACCOUNT", "STATUS"
+    );
+    for (currency, role, account_id, status) in summary {
+        info!(
+            "{:<8} {:<10} {:<32} {:<7}",
+            currency, role, account_id, status
+        );
+        output.emit(json!({
+            "currency": currency,
+            "role": role,
+            "account_id": account_id.to_string(),
+            "status": status,
+        }));
+    }
+
+    if liquidity_accounts.is_empty() {
+        return Ok(());
+    }
+
+    // The config of a previous run is kept, the accounts it lacks being reported
+    if Path::new(SETUP_CONFIG).exists() {
+        let configured: HashMap<CurrencyCode, LiquidityConfig> =
+            config::parse_section(Some(Path::new(SETUP_CONFIG)), None, "liquidity")?;
+        for (currency, account) in &liquidity_accounts {
+            if !configured
+                .values()
+                .any(|liquidity| liquidity.account == *account)
+            {
+                warn!(%currency, %account, path = %SETUP_CONFIG, "Liquidity account missing from the config");
+            }
+        }
+        info!(path = %SETUP_CONFIG, "Kept existing config");
+        output.emit(json!({ "config": SETUP_CONFIG, "status": "kept" }));
+        return Ok(());
+    }
+
+    // Write config
+    let liquidity = liquidity_accounts
+        .into_iter()
+        .map(|(currency, account)| {
+            let base_rate = rate_for(&currency);
+            let key_pair = SecretSource::File(PathBuf::from(LIQUIDITY_KEY));
+            (currency, liquidity_config(account, base_rate, key_pair))
+        })
+        .collect();
+    let toml_string = service_config(DEFAULT_LEDGER_URL, liquidity).to_toml()?;
+    let path = SETUP_CONFIG;
+    std::fs::write(path, toml_string)?;
+    info!(%path, "Wrote config to");
+    output.emit(json!({ "config": path, "status": "written" }));
+
+    Ok(())
+}
 
+/// Root account of each currency of the ledger
+async fn ledger_currencies(client: &M10Client<Ed25519>) -> anyhow::Result<Vec<AccountInfo>> {
     let mut accounts = vec![];
-    // Scan for all currencies
     for i in 0..256 {
         let root_id = AccountId::from_root_account_index(i)?;
         match client.get_account_info(root_id).await {
-            Ok(account) => {
-                let currency = account.code.to_lowercase();
-                if !setup.currencies.contains(&currency) {
-                    continue;
-                }
-                info!(%account.id, %currency, "Found account");
-                accounts.push(account);
-            }
+            Ok(account) => accounts.push(account),
             Err(M10Error::Status(status)) if status.code() as usize == 5 => {
                 // NOT FOUND
                 break;
             }
             Err(err) => {
-                panic!("Could not retrieve account: {:?}", err);
+                return Err(anyhow::Error::from(err).context("Could not retrieve account"));
+            }
+        }
+    }
+    Ok(accounts)
+}
+
+/// Accounts registered by `setup` for the `owners`, by owner & currency
+async fn setup_accounts(
+    client: &M10Client<Ed25519>,
+    signer: &Ed25519,
+    owners: &[Vec<u8>],
+) -> anyhow::Result<HashMap<(Vec<u8>, String), AccountId>> {
+    let mut accounts = HashMap::new();
+    for role in setup_roles(client, signer).await? {
+        let owner = role.owner.to_vec();
+        if !owners.contains(&owner) {
+            continue;
+        }
+        if let Some(id) = role_account(&role) {
+            let currency = client.get_account_info(id).await?.code.to_lowercase();
+            accounts.insert((owner, currency), id);
+        }
+    }
+    Ok(accounts)
+}
+
+/// Config of the service on the ledger at `address`, with the defaults of every section but `liquidity`
+fn service_config(address: &str, liquidity: HashMap<CurrencyCode, LiquidityConfig>) -> Config {
+    Config {
+        address: Addresses(vec![address.to_string()]),
+        requote: Default::default(),
+        negotiation: Default::default(),
+        compliance: Default::default(),
+        refund: Default::default(),
+        shutdown: Default::default(),
+        routing: Default::default(),
+        limits: Default::default(),
+        credit: Default::default(),
+        approval: Default::default(),
+        hours: Vec::new(),
+        netting: None,
+        reload: Default::default(),
+        pairs: Default::default(),
+        transport: Default::default(),
+        log: Default::default(),
+        engine: Default::default(),
+        alerts: Default::default(),
+        rebalance: Default::default(),
+        retry: Default::default(),
+        concurrency: Default::default(),
+        rates: None,
+        rate_stream: None,
+        aggregate: None,
+        breaker: Default::default(),
+        retention: Default::default(),
+        cursors: Some(PathBuf::from("./cursors.json")),
+        database: None,
+        admin: None,
+        admin_token: None,
+        max_slippage_bps: None,
+        dry_run: false,
+        liquidity,
+    }
+}
+
+/// Liquidity of `account` at `base_rate`, signed by `key_pair`, without fees or limits
+fn liquidity_config(
+    account: AccountId,
+    base_rate: Decimal,
+    key_pair: SecretSource,
+) -> LiquidityConfig {
+    LiquidityConfig {
+        account,
+        base_rate,
+        key_pair,
+        address: None,
+        decimals: None,
+        rounding: Default::default(),
+        flat_fee: Decimal::ZERO,
+        spread_bps: 0,
+        ask_spread_bps: None,
+        min_amount: None,
+        max_amount: None,
+        min_balance: None,
+        allocation: None,
+        max_exposure: None,
+        reserve: None,
+        fee_account: None,
+        accounts: Vec::new(),
+        selection: Default::default(),
+        pairs: None,
+        pair_spread_bps: HashMap::new(),
+        quote_ttl: None,
+        tiers: HashMap::new(),
+        skew: None,
+        start_from: None,
+    }
+}
+
+/// Name of the roles & role-bindings registered by `setup` for the accounts it creates
+const SETUP_ROLE: &str = "m10.fx.account";
+
+/// Key file generated by `setup` for all the liquidity accounts
+const LIQUIDITY_KEY: &str = "./liquidity.pkcs8";
+
+/// Users created by `setup` unless set: `alice` in the first currency & `bob` in the second
+const DEFAULT_USERS: [&str; 2] = ["alice", "bob"];
+
+/// Key file generated by `setup` for the user `name`
+fn user_key(name: &str) -> String {
+    format!("./{}.pkcs8", name)
+}
+
+/// Users created by `setup` unless set, only `alice` being funded
+fn default_users(setup: &Setup) -> Vec<SetupUser> {
+    DEFAULT_USERS
+        .iter()
+        .zip(&setup.currencies)
+        .map(|(&name, currency)| SetupUser {
+            name: name.to_string(),
+            currency: currency.to_lowercase(),
+            funding: (name != "alice").then_some(0),
+        })
+        .collect()
+}
+
+/// Config written by `setup`
+const SETUP_CONFIG: &str = "config.toml";
+
+/// Key pair of `setup` read from `path`, generated there if missing
+fn setup_key(path: &str, output: Output) -> anyhow::Result<Ed25519> {
+    let (key_pair, status) = match Path::new(path).exists() {
+        true => (Ed25519::load_key_pair(path)?, "reused"),
+        false => (Ed25519::new_key_pair(Some(path))?, "generated"),
+    };
+    info!(%path, %status, "Key pair");
+    output.emit(json!({ "key_pair": path, "status": status }));
+    Ok(key_pair)
+}
+
+/// Roles listed at once while looking for those of `setup`
+const ROLES_PAGE: u32 = 100;
+
+/// Writes a new key pair to `path`, which mustn't exist
+fn generate_key(path: &str, output: Output) -> anyhow::Result<Ed25519> {
+    if Path::new(path).exists() {
+        return Err(anyhow::anyhow!("{} already exists", path));
+    }
+    let key_pair = Ed25519::new_key_pair(Some(path))?;
+    let public_key = hex::encode(key_pair.public_key());
+    info!(%path, %public_key, "Generated key pair");
+    output.emit(json!({ "key_pair": path, "public_key": public_key }));
+    Ok(key_pair)
+}
+
+/// Binds a new key pair written to `rotate.out` to the role-bindings of `setup` bound to the key pair `rotate.key_pair`,
+/// or retires the latter with `rotate.retire`. The old key stays bound until retired, so the service running with it
+/// keeps working until restarted with the new one
+async fn try_rotate(
+    client: &M10Client<Ed25519>,
+    signer: &Ed25519,
+    rotate: RotateKey,
+    output: Output,
+) -> anyhow::Result<()> {
+    let old_key = Ed25519::load_key_pair(&rotate.key_pair)?
+        .public_key()
+        .to_vec();
+    let bindings = setup_role_bindings(client, signer)
+        .await?
+        .into_iter()
+        .filter(|binding| binding.subjects.iter().any(|subject| *subject == old_key))
+        .collect::<Vec<_>>();
+    if bindings.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No role-binding of setup is bound to {}",
+            rotate.key_pair
+        ));
+    }
+    if rotate.retire {
+        return retire_key(client, bindings, old_key, output).await;
+    }
+    let out = rotate
+        .out
+        .ok_or_else(|| Failure::Usage("Expected --out or --retire".to_string()))?;
+    let new_key = Bytes::from(generate_key(&out, Output::Text)?.public_key().to_vec());
+    let mut documents = DocumentBuilder::default();
+    for binding in &bindings {
+        let subjects = binding
+            .subjects
+            .iter()
+            .filter(|subject| **subject != new_key)
+            .cloned()
+            .chain(std::iter::once(new_key.clone()))
+            .collect::<Vec<_>>();
+        let mut update = DocumentUpdate::<RoleBinding>::new(binding.id.clone());
+        update.subjects(subjects);
+        documents = documents.update(update);
+    }
+    let tx_id = client.documents(documents).await?;
+    info!(%tx_id, role_bindings = %bindings.len(), "Bound the new key pair");
+    // The running service keeps using the old key pair until restarted with the new one
+    warn!(
+        path = %out,
+        "Point the key_pair of the liquidity config to the new key pair, restart the service, then retire the old one with --retire"
+    );
+    output.emit(json!({
+        "key_pair": out,
+        "public_key": hex::encode(&new_key),
+        "role_bindings": bindings.iter().map(|binding| hex::encode(&binding.id)).collect::<Vec<_>>(),
+    }));
+    Ok(())
+}
+
+/// Unbinds `old_key` from its role-bindings of `setup`, each of which must have another key pair bound
+async fn retire_key(
+    client: &M10Client<Ed25519>,
+    bindings: Vec<RoleBinding>,
+    old_key: Vec<u8>,
+    output: Output,
+) -> anyhow::Result<()> {
+    if let Some(binding) = bindings
+        .iter()
+        .find(|binding| binding.subjects.iter().all(|subject| *subject == old_key))
+    {
+        return Err(Failure::Conflict(format!(
+            "Role-binding {} has no other key pair bound, rotate the key pair first",
+            hex::encode(&binding.id)
+        ))
+        .into());
+    }
+    let mut documents = DocumentBuilder::default();
+    for binding in &bindings {
+        let subjects = binding
+            .subjects
+            .iter()
+            .filter(|subject| **subject != old_key)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut update = DocumentUpdate::<RoleBinding>::new(binding.id.clone());
+        update.subjects(subjects);
+        documents = documents.update(update);
+    }
+    let tx_id = client.documents(documents).await?;
+    info!(%tx_id, role_bindings = %bindings.len(), "Retired the old key pair");
+    output.emit(json!({
+        "retired": hex::encode(&old_key),
+        "role_bindings": bindings.iter().map(|binding| hex::encode(&binding.id)).collect::<Vec<_>>(),
+    }));
+    Ok(())
+}
+
+/// Role-bindings registered by `setup`, whoever owns them, listed a page at a time
+async fn setup_role_bindings(
+    client: &M10Client<Ed25519>,
+    signer: &Ed25519,
+) -> anyhow::Result<Vec<RoleBinding>> {
+    let mut bindings = Vec::new();
+    let mut last_id = Vec::new();
+    loop {
+        let request = sdk::ListRoleBindingsRequest {
+            page: Some(sdk::Page {
+                limit: ROLES_PAGE,
+                last_id: last_id.clone(),
+            }),
+            filter: Some(sdk::list_role_bindings_request::Filter::Name(
+                SETUP_ROLE.to_string(),
+            )),
+        };
+        let page = client
+            .client
+            .clone()
+            .list_role_bindings(signer.sign_request(request).await?)
+            .await?
+            .role_bindings;
+        let exhausted = page.len() < ROLES_PAGE as usize;
+        if let Some(last) = page.last() {
+            last_id = last.id.to_vec();
+        }
+        bindings.extend(page);
+        if exhausted {
+            return Ok(bindings);
+        }
+    }
+}
+
+/// Account created by `setup`, with the role & role-binding granting it to its owner
+struct SetupAccount {
+    id: AccountId,
+    parent_id: AccountId,
+    /// Balance, in units of the ledger
+    balance: u64,
+    decimals: u32,
+    /// ID shared by the role & its role-binding
+    role_id: Vec<u8>,
+}
+
+/// Undoes `setup` for the keys it generated: balances are returned to the parent accounts, the account, role & role-binding
+/// documents deleted & the key files removed, along with the config unless it holds other accounts. Ledger accounts can't be
+/// deleted, they are left empty. Only lists what would be torn down without `--yes`
+async fn try_teardown(
+    client: M10Client<Ed25519>,
+    signer: Ed25519,
+    teardown: Teardown,
+    output: Output,
+) -> anyhow::Result<()> {
+    let mut owners = Vec::new();
+    let mut files = Vec::new();
+    let paths = std::iter::once(LIQUIDITY_KEY.to_string())
+        .chain(teardown.users.iter().map(|name| user_key(name)));
+    for path in paths {
+        if !Path::new(&path).exists() {
+            continue;
+        }
+        owners.push(Ed25519::load_key_pair(&path)?.public_key().to_vec());
+        files.push(path);
+    }
+
+    let mut accounts = Vec::new();
+    for role in setup_roles(&client, &signer).await? {
+        if !owners.contains(&role.owner.to_vec()) {
+            continue;
+        }
+        let id = match role_account(&role) {
+            Some(id) => id,
+            None => {
+                warn!(role_id = %hex::encode(&role.id), "Role grants no account");
+                continue;
+            }
+        };
+        let info = client.get_account_info(id).await?;
+        let balance = client.get_account(id).await?.balance;
+        info!(account = %id, parent = %info.parent_id, name = %info.public_name, %balance, "Found setup account");
+        accounts.push(SetupAccount {
+            id,
+            parent_id: info.parent_id,
+            balance,
+            decimals: info.decimals,
+            role_id: role.id.to_vec(),
+        });
+    }
+
+    if Path::new(SETUP_CONFIG).exists() {
+        let liquidity: HashMap<CurrencyCode, LiquidityConfig> =
+            config::parse_section(Some(Path::new(SETUP_CONFIG)), None, "liquidity")?;
+        let created = |id: AccountId| accounts.iter().any(|account| account.id == id);
+        match liquidity
+            .values()
+            .all(|liquidity| created(liquidity.account))
+        {
+            true => files.push(SETUP_CONFIG.to_string()),
+            false => {
+                warn!(path = %SETUP_CONFIG, "Keeping the config, it holds accounts not created by setup")
+            }
+        }
+    }
+
+    if !teardown.yes {
+        for path in &files {
+            info!(%path, "Found setup file");
+        }
+        warn!(
+            accounts = %accounts.len(),
+            files = %files.len(),
+            "Nothing was torn down, pass --yes to proceed"
+        );
+        return Ok(());
+    }
+
+    for account in accounts {
+        if account.balance > 0 {
+            let tx_id = client
+                .transfer(TransferBuilder::new().step(StepBuilder::new(
+                    account.id,
+                    account.parent_id,
+                    account.balance,
+                )))
+                .await?;
+            info!(account = %account.id, balance = %account.balance, %tx_id, "Returned balance to parent");
+        }
+        client
+            .documents(
+                DocumentBuilder::default()
+                    .delete_custom(Collection::Accounts, account.id.to_vec())
+                    .delete_custom(Collection::Roles, account.role_id.clone())
+                    .delete_custom(Collection::RoleBindings, account.role_id.clone()),
+            )
+            .await?;
+        info!(account = %account.id, "Deleted account, role & role-binding documents");
+        output.emit(json!({
+            "account_id": account.id.to_string(),
+            "role_id": hex::encode(&account.role_id),
+            "defunded": Money::new(account.decimals, Rounding::Down)?.from_units(account.balance),
+        }));
+    }
+    for path in files {
+        std::fs::remove_file(&path)?;
+        info!(%path, "Removed setup file");
+        output.emit(json!({ "removed": path }));
+    }
+    Ok(())
+}
+
+/// Roles registered by `setup`, whoever owns them, listed a page at a time
+async fn setup_roles(
+    client: &M10Client<Ed25519>,
+    signer: &Ed25519,
+) -> anyhow::Result<Vec<sdk::Role>> {
+    // The page builder of the client can't filter by name, the request is built here
+    let mut roles = Vec::new();
+    let mut last_id = Vec::new();
+    loop {
+        let request = sdk::ListRolesRequest {
+            page: Some(sdk::Page {
+                limit: ROLES_PAGE,
+                last_id: last_id.clone(),
+            }),
+            filter: Some(sdk::list_roles_request::Filter::Name(
+                SETUP_ROLE.to_string(),
+            )),
+        };
+        let page = client
+            .client
+            .clone()
+            .list_roles(signer.sign_request(request).await?)
+            .await?
+            .roles;
+        let exhausted = page.len() < ROLES_PAGE as usize;
+        if let Some(last) = page.last() {
+            last_id = last.id.to_vec();
+        }
+        roles.extend(page);
+        if exhausted {
+            return Ok(roles);
+        }
+    }
+}
+
+/// Account granted by a role of `setup`
+fn role_account(role: &sdk::Role) -> Option<AccountId> {
+    role.rules
+        .iter()
+        .filter(|rule| rule.collection == Collection::Accounts.to_string())
+        .flat_map(|rule| &rule.instance_keys)
+        .find_map(|key| match &key.value {
+            Some(Value::BytesValue(bytes)) => AccountId::try_from_be_slice(bytes).ok(),
+            _ => None,
+        })
+}
+
+/// Execution of the quote of `initiate` in `context_id`, once received
+fn auto_execution(initiate: &Initiate, context_id: &[u8]) -> ExecuteQuote {
+    ExecuteQuote {
+        key_pair: initiate.key_pair.clone(),
+        context_id: hex::encode(context_id),
+        // Settles right away unless a margin bounds the rate
+        strategy: match initiate.margin {
+            Some(_) => "limit".to_string(),
+            None => "immediate".to_string(),
+        },
+        now: false,
+        hold: false,
+        margin: initiate.margin,
+        valid_for: None,
+        slices: 4,
+        liquidity_key: None,
+        quote_id: None,
+        amount: None,
+        amount_minor_units: false,
+        ask: false,
+        timeout: initiate.timeout,
+        resume: false,
+    }
+}
+
+/// Requests a quote, returning it along with the context of the swap
+/// Requests a swap in `context_id`, executing its quote once received when a `default_validity` is given
+async fn initiate_swap(
+    client: &M10Client<Ed25519>,
+    initiate: Initiate,
+    context_id: Vec<u8>,
+    public_key: &[u8],
+    default_validity: Option<u64>,
+    output: Output,
+) -> anyhow::Result<Quote> {
+    let execute = auto_execution(&initiate, &context_id);
+    let quote = try_initiate(
+        client,
+        initiate,
+        context_id.clone(),
+        public_key.to_vec(),
+        output,
+    )
+    .instrument(info_span!("initiate"))
+    .await?;
+    let default_validity = match default_validity {
+        Some(default_validity) => default_validity,
+        None => return Ok(quote),
+    };
+    let execute = ExecuteQuote {
+        quote_id: Some(quote.quote_id),
+        ..execute
+    };
+    execute_swap(
+        client,
+        execute,
+        quote.clone(),
+        default_validity,
+        context_id,
+        public_key,
+        output,
+    )
+    .instrument(info_span!("execute"))
+    .await?;
+    Ok(quote)
+}
+
+async fn try_initiate(
+    client: &M10Client<Ed25519>,
+    initiate: Initiate,
+    context_id: Vec<u8>,
+    public_key: Vec<u8>,
+    output: Output,
+) -> anyhow::Result<Quote> {
+    let from = initiate
+        .from
+        .ok_or_else(|| Failure::Usage("Missing source account".to_string()))?;
+    let to = initiate
+        .to
+        .ok_or_else(|| Failure::Usage("Missing target account".to_string()))?;
+    let from_account = client.get_account_info(from).await?;
+    let context_hex = hex::encode(&context_id);
+    let compliance = initiate
+        .compliance
+        .map(|path| -> anyhow::Result<Compliance> {
+            Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+        })
+        .transpose()?;
+    let request = match (initiate.amount, initiate.receive_amount) {
+        (_, Some(receive_amount)) => {
+            let to_account = client.get_account_info(to).await?;
+            Request {
+                from: from_account.id,
+                to,
+                amount: parse_amount(
+                    receive_amount,
+                    &to_account.code,
+                    to_account.decimals,
+                    initiate.amount_minor_units,
+                )?,
+                side: Side::BuyExact,
+                memo: initiate.memo,
+                two_way: initiate.two_way,
+                compliance,
+                settle_at: initiate.settle_at,
+            }
+        }
+        (Some(amount), None) => Request {
+            from: from_account.id,
+            to,
+            amount: parse_amount(
+                amount,
+                &from_account.code,
+                from_account.decimals,
+                initiate.amount_minor_units,
+            )?,
+            side: Side::SellExact,
+            memo: initiate.memo,
+            two_way: initiate.two_way,
+            compliance,
+            settle_at: initiate.settle_at,
+        },
+        (None, None) => return Err(Failure::Usage("Missing amount".to_string()).into()),
+    };
+    // A new context, the request is the first event published in it
+    let envelope = Envelope::new(Event::Request(request), 1, public_key);
+
+    // Submit request
+    let tx_id = client
+        .action(
+            ActionBuilder::for_all(FX_SWAP_ACTION.to_string(), from_account.id)
+                .payload(envelope.encode()),
+            context_id.clone(),
+        )
+        .await?;
+    info!(%tx_id, context_id=%context_hex, "Submitted transaction");
+    output.emit(json!({ "context_id": context_hex, "tx_id": tx_id }));
+
+    let quote = wait_for_quote(client, from_account.id, tx_id, &context_id, output);
+    within(initiate.timeout, &context_id, "quote", false, quote).await
+}
+
+/// Parses an `amount` of `currency` given on the command line, which must be a whole number of its smallest unit,
+/// itself when read in `minor_units`
+fn parse_amount(
+    amount: Decimal,
+    currency: &str,
+    decimals: u32,
+    minor_units: bool,
+) -> anyhow::Result<Decimal> {
+    let money = Money::new(decimals, Rounding::Exact)?;
+    let units = match minor_units {
+        true => amount
+            .to_u64()
+            .filter(|_| amount.fract().is_zero())
+            .ok_or_else(|| {
+                Failure::Usage(format!(
+                    "Amount {} is not a whole number of minor units of {}",
+                    amount, currency
+                ))
+            })?,
+        false => money.to_units(amount).map_err(|err| match err {
+            MoneyError::SubUnit { .. } => Failure::Usage(format!(
+                "Amount {} has more than the {} decimals of {}",
+                amount, decimals, currency
+            )),
+            err => Failure::Usage(err.to_string()),
+        })?,
+    };
+    if units == 0 {
+        return Err(Failure::Usage(format!("Amount {} is not positive", amount)).into());
+    }
+    Ok(money.from_units(units))
+}
+
+/// Swap requested by a row of a batch file, its amount being in the source currency
+#[derive(Deserialize, Debug)]
+struct BatchRow {
+    from: String,
+    to: String,
+    amount: Decimal,
+}
+
+/// Outcome of a swap of a batch, written as a row of the results file
+#[derive(Serialize, Debug)]
+struct BatchResult {
+    row: usize,
+    from: String,
+    to: String,
+    amount: Decimal,
+    context_id: String,
+    /// `quoted` or `completed` when executed, `failed` otherwise
+    outcome: &'static str,
+    rate: Option<Decimal>,
+    error: Option<String>,
+}
+
+/// Reads the rows of a batch, as a JSON array if the file ends in `.json`, or as CSV with an optional header otherwise
+fn read_batch(path: &Path) -> anyhow::Result<Vec<BatchRow>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Could not read {}: {}", path.display(), err))?;
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        return Ok(serde_json::from_str(&contents)?);
+    }
+    let mut rows = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (index == 0 && line.starts_with("from")) {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let row = match fields.as_slice() {
+            [from, to, amount] => BatchRow {
+                from: from.to_string(),
+                to: to.to_string(),
+                amount: amount.parse().map_err(|err| {
+                    anyhow::anyhow!("Invalid amount on line {}: {}", index + 1, err)
+                })?,
+            },
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Line {} of {} is not from,to,amount",
+                    index + 1,
+                    path.display()
+                ))
+            }
+        };
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Quotes a CSV field containing a separator or a quote
+fn csv_field(field: &str) -> String {
+    match field.contains(&[',', '"', '\n'][..]) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Requests the swaps of a batch, at most `parallelism` at once, writing the outcome of each to the results file
+async fn initiate_batch(
+    client: &M10Client<Ed25519>,
+    initiate: Initiate,
+    batch: &Path,
+    public_key: &[u8],
+    default_validity: Option<u64>,
+    output: Output,
+) -> anyhow::Result<()> {
+    let rows = read_batch(batch)?;
+    let results_path = initiate
+        .results
+        .clone()
+        .unwrap_or_else(|| batch.with_extension("results.csv"));
+    info!(rows = rows.len(), parallelism = %initiate.parallelism, "Initiating batch");
+
+    let swaps = rows.into_iter().enumerate().map(|(index, row)| {
+        let initiate = initiate.clone();
+        async move {
+            let context_id = fastrand::u64(..).to_be_bytes().to_vec();
+            let context_hex = hex::encode(&context_id);
+            let swap = async {
+                let initiate = Initiate {
+                    from: Some(AccountId::from_str(&row.from)?),
+                    to: Some(AccountId::from_str(&row.to)?),
+                    amount: Some(row.amount),
+                    batch: None,
+                    ..initiate
+                };
+                // Results of the batch are emitted per row, rather than per event
+                initiate_swap(
+                    client,
+                    initiate,
+                    context_id,
+                    public_key,
+                    default_validity,
+                    Output::Text,
+                )
+                .await
+            };
+            let result = swap
+                .instrument(info_span!("swap", row = index + 1, context_id = %context_hex))
+                .await;
+            let (outcome, rate, error) = match result {
+                Ok(quote) if default_validity.is_some() => {
+                    ("completed", Some(quote.effective_rate()), None)
+                }
+                Ok(quote) => ("quoted", Some(quote.effective_rate()), None),
+                Err(err) => {
+                    warn!(row = index + 1, %err, "Swap failed");
+                    ("failed", None, Some(err.to_string()))
+                }
+            };
+            BatchResult {
+                row: index + 1,
+                from: row.from,
+                to: row.to,
+                amount: row.amount,
+                context_id: context_hex,
+                outcome,
+                rate,
+                error,
             }
         }
+    });
+    let mut results: Vec<BatchResult> = futures_util::stream::iter(swaps)
+        .buffer_unordered(initiate.parallelism.max(1))
+        .inspect(|result| output.emit(result))
+        .collect()
+        .await;
+    results.sort_by_key(|result| result.row);
+
+    let mut contents = "row,from,to,amount,context_id,outcome,rate,error\n".to_string();
+    for result in &results {
+        contents.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            result.row,
+            csv_field(&result.from),
+            csv_field(&result.to),
+            result.amount,
+            result.context_id,
+            result.outcome,
+            result.rate.map(|rate| rate.to_string()).unwrap_or_default(),
+            csv_field(result.error.as_deref().unwrap_or_default()),
+        ));
     }
+    std::fs::write(&results_path, contents)
+        .map_err(|err| anyhow::anyhow!("Could not write {}: {}", results_path.display(), err))?;
+    let failed = results
+        .iter()
+        .filter(|result| result.outcome == "failed")
+        .count();
+    info!(
+        swaps = results.len(),
+        failed,
+        results = %results_path.display(),
+        "Batch done"
+    );
+    Ok(())
+}
 
-    // Create accounts & account docs for all currencies
-    let mut liquidity_accounts = HashMap::new();
-    for account in accounts {
-        let currency = account.code.to_lowercase();
-        async {
-            let account_id = create_account(
-                &client,
-                account.id,
-                liquidity_key.public_key(),
-                "fx-liquidity".to_string(),
-                10_000_000,
-            )
-            .instrument(info_span!("liquidity"))
-            .await?;
+/// Waits for `wait` for up to `timeout` seconds, indefinitely when unset
+async fn within<T>(
+    timeout: Option<u64>,
+    context_id: &[u8],
+    awaited: &'static str,
+    resumable: bool,
+    wait: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let secs = match timeout {
+        Some(secs) => secs,
+        None => return wait.await,
+    };
+    tokio::time::timeout(Duration::from_secs(secs), wait)
+        .await
+        .map_err(|_| Failure::TimedOut {
+            context_id: hex::encode(context_id),
+            awaited,
+            secs,
+            resumable,
+        })?
+}
 
-            liquidity_accounts.insert(currency.clone(), account_id);
+/// Waits for the quote published in response to the action `tx_id`
+async fn wait_for_quote(
+    client: &M10Client<Ed25519>,
+    account: AccountId,
+    tx_id: TxId,
+    context_id: &[u8],
+    output: Output,
+) -> anyhow::Result<Quote> {
+    let context_hex = hex::encode(context_id);
+    let mut actions = client
+        .observe_actions(
+            AccountFilter::name(FX_SWAP_ACTION.to_string())
+                .starting_from(tx_id + 1)
+                .involves(account),
+        )
+        .await?;
 
-            if currency == setup.currencies[0].to_lowercase() {
-                // Create an account for alice
-                let account_id = create_account(
-                    &client,
-                    account.id,
-                    alice_key.public_key(),
-                    "alice".to_string(),
-                    10_000_000,
-                )
-                .instrument(info_span!("Alice"))
-                .await?;
-                info!(%account_id, "Created Alice's account");
+    info!("Waiting for the proposed quote");
+    let mut sequences = Sequences::default();
+    while let Some(actions) = actions.next().await {
+        for action in actions? {
+            if action.context_id != context_id {
+                continue;
             }
 
-            if currency == setup.currencies[1].to_lowercase() {
-                let account_id = create_account(
-                    &client,
-                    account.id,
-                    bob_key.public_key(),
-                    "bob".to_string(),
-                    0,
-                )
-                .instrument(info_span!("Bob"))
-                .await?;
-                info!(%account_id, "Created Bob's account");
+            let envelope = Envelope::decode(&action.payload)?;
+            if !sequences.observe(&envelope) {
+                continue;
             }
+            let event = envelope.event;
 
-            Result::<(), anyhow::Error>::Ok(())
+            match event {
+                Event::Quote(quote) => {
+                    info!(context_id=%context_hex, "Received quote {}", quote);
+                    if quote.indicative {
+                        warn!("Quote is indicative, the liquidity provider may only partially fill it");
+                    }
+                    if let Some(ask) = &quote.ask {
+                        info!(bid = %quote.fees.net_rate(quote.rate), ask = %ask.rate, "Two-way rates");
+                    }
+                    for tier in &quote.tiers {
+                        info!(min_amount = %tier.min_amount, rate = %tier.rate, spread_bps = %tier.spread_bps, "Tier");
+                    }
+                    info!(
+                        effective_rate = %quote.effective_rate(),
+                        flat_fee = %quote.fees.flat_fee,
+                        spread_bps = %quote.fees.spread_bps,
+                        fee_currency = %quote.fees.currency,
+                        "Fees"
+                    );
+                    output.emit(SwapResult {
+                        context_id: &context_hex,
+                        tx_id: action.tx_id,
+                        quote: Some(&quote),
+                        event: None,
+                    });
+                    return Ok(quote);
+                }
+                Event::Reject {
+                    reason,
+                    allowed,
+                    reopens_at,
+                } => {
+                    let allowed = allowed.map(|allowed| allowed.to_string());
+                    error!(context_id=%context_hex, %reason, ?allowed, ?reopens_at, "Request rejected");
+                    output.emit(json!({
+                        "context_id": context_hex,
+                        "rejected": { "reason": reason, "allowed": allowed, "reopens_at": reopens_at },
+                    }));
+                    return Err(
+                        Failure::Rejected(format!("Request was rejected: {}", reason)).into(),
+                    );
+                }
+                event => {
+                    return Err(anyhow::anyhow!(
+                        "Unexpected event while waiting for the quote: {:?}",
+                        event
+                    ))
+                }
+            }
         }
-        .instrument(info_span!("account", %currency))
-        .await?;
     }
-
-    if liquidity_accounts.is_empty() {
-        return Ok(());
+    Err(Failure::Disconnected {
+        context_id: context_hex,
+        awaited: "quote",
+        resumable: false,
     }
+    .into())
+}
 
-    // Write config
-    let toml_string = toml::to_string(&Config {
-        address: DEFAULT_LEDGER_URL.to_string(),
-        liquidity: liquidity_accounts
-            .into_iter()
-            .map(|(currency, account)| {
-                let base_rate = rate_for(&currency);
-                (
-                    currency,
-                    LiquidityConfig {
-                        account: account.to_string(),
-                        base_rate,
-                        key_pair: PathBuf::from("./liquidity.pkcs8"),
-                    },
-                )
-            })
-            .collect(),
-    })?;
-    let path = "config.toml";
-    std::fs::write(path, toml_string)?;
-    info!(%path, "Wrote config to");
+async fn try_execute(
+    client: &M10Client<Ed25519>,
+    execute: ExecuteQuote,
+    quote: Quote,
+    default_validity: u64,
+    context_id: Vec<u8>,
+    public_key: &[u8],
+) -> anyhow::Result<TxId> {
+    let request = match execute.amount {
+        Some(_) if quote.tiers.is_empty() => {
+            return Err(anyhow::anyhow!(
+                "Quote is not tiered, the amount can't be changed"
+            ))
+        }
+        Some(amount) => {
+            // The requested amount is in the source currency, unless the target amount is exact
+            let account = match quote.request.side {
+                Side::BuyExact => quote.request.to,
+                Side::SellExact => quote.request.from,
+            };
+            let info = client.get_account_info(account).await?;
+            Request {
+                amount: parse_amount(
+                    amount,
+                    &info.code,
+                    info.decimals,
+                    execute.amount_minor_units,
+                )?,
+                ..quote.request.clone()
+            }
+        }
+        None => quote.request.clone(),
+    };
+    let (direction, intermediary, rate) = match (execute.ask, &quote.ask) {
+        (true, None) => return Err(anyhow::anyhow!("Quote has no ask side")),
+        (true, Some(ask)) => (Direction::Ask, ask.intermediary, Decimal::ONE / quote.rate),
+        (false, _) => (Direction::Bid, quote.intermediary, quote.rate),
+    };
+    let swap = Execute {
+        request,
+        quote_id: quote.quote_id,
+        quote: Some(quote.clone()),
+        // Forward swaps settle at their settlement date
+        strategy: match quote.request.settle_at {
+            Some(_) => Strategy::Immediate,
+            None => strategy(&execute, rate, default_validity)?,
+        },
+        direction,
+    };
+    let settlement = swap.settlement_request();
+    match (direction, quote.tier(swap.request.amount)) {
+        (Direction::Bid, None) => {}
+        (Direction::Bid, Some(tier)) => {
+            info!(min_amount = %tier.min_amount, rate = %tier.rate, "Executing at tier");
+        }
+        (Direction::Ask, _) => {
+            info!(rate = %quote.ask.as_ref().map(|ask| ask.rate).unwrap_or_default(), "Executing at the ask");
+        }
+    }
+    let amount = swap.source_amount()?;
+    // Rounded up, so the transfer covers the quoted amount
+    let decimals = client.get_account_info(settlement.from).await?.decimals;
+    let units = Money::new(decimals, Rounding::Up)?.to_units(amount)?;
+    info!("Transferring from {} -> {}", settlement.from, intermediary);
+    let sequence = next_sequence(client, &context_id, public_key).await?;
+    let envelope = Envelope::new(Event::Execute(swap), sequence, public_key);
+    let mut step = StepBuilder::new(settlement.from, intermediary, units)
+        .metadata(FxSwapMetadata::from(&envelope));
+    if let Some(memo) = settlement.memo_metadata() {
+        step = step.metadata(memo);
+    }
+    if let Some(compliance) = settlement.compliance_metadata() {
+        step = step.metadata(compliance);
+    }
+    let transfer = TransferBuilder::new()
+        .step(step)
+        .context_id(context_id.clone());
+    let tx_id = match execute.hold {
+        true => client.initiate_transfer(transfer).await?,
+        false => client.transfer(transfer).await?,
+    };
+    info!(%tx_id, held = execute.hold, "Transfer success");
+    Ok(tx_id)
+}
 
+async fn try_cancel(
+    client: &M10Client<Ed25519>,
+    context_id: Vec<u8>,
+    quote_id: Option<Uuid>,
+    public_key: &[u8],
+    output: Output,
+) -> anyhow::Result<()> {
+    let quote = find_quote(client, context_id.clone(), quote_id).await?;
+    let sequence = next_sequence(client, &context_id, public_key).await?;
+    let tx_id = client
+        .action(
+            ActionBuilder::for_account(
+                FX_SWAP_ACTION.to_string(),
+                quote.request.from,
+                quote.intermediary,
+            )
+            .payload(Envelope::new(Event::Cancel, sequence, public_key).encode()),
+            context_id.clone(),
+        )
+        .await?;
+    info!(%tx_id, "Cancelled quote");
+    output.emit(json!({
+        "context_id": hex::encode(&context_id),
+        "tx_id": tx_id,
+        "cancelled": quote.quote_id,
+    }));
     Ok(())
 }
 
-async fn try_initiate(client: M10Client<Ed25519>, initiate: Initiate) -> anyhow::Result<()> {
-    let from_account = client.get_account_info(initiate.from).await?;
-    let context_id = fastrand::u64(..).to_be_bytes().to_vec();
-    let context_hex = hex::encode(&context_id);
-    let event = Event::Request(Request {
-        from: from_account.id,
-        to: initiate.to,
-        amount: Decimal::new(initiate.amount as i64, from_account.decimals),
-    });
+/// Settlement strategy selected by the `execute` options, around `rate`, lasting `default_validity` seconds unless set
+fn strategy(
+    execute: &ExecuteQuote,
+    rate: Decimal,
+    default_validity: u64,
+) -> anyhow::Result<Strategy> {
+    let valid_for = execute.valid_for.unwrap_or(default_validity);
+    if execute.now || execute.hold {
+        return Ok(Strategy::Immediate);
+    }
+    Ok(match execute.strategy.as_str() {
+        "immediate" => Strategy::Immediate,
+        "twap" => Strategy::Twap {
+            window: valid_for,
+            slices: execute.slices,
+        },
+        _ => {
+            let margin = execute
+                .margin
+                .ok_or_else(|| anyhow::anyhow!("The limit strategy requires a margin"))?;
+            Strategy::Limit {
+                bounds: Bounds {
+                    lower: (Decimal::one() - margin) * rate,
+                    upper: (Decimal::one() + margin) * rate,
+                },
+                deadline: (SystemTime::now() + Duration::from_secs(valid_for))
+                    .duration_since(UNIX_EPOCH)?
+                    .as_secs(),
+            }
+        }
+    })
+}
 
-    // Submit request
+/// Sequence number of the next event published in the context with `public_key`
+async fn next_sequence(
+    client: &M10Client<Ed25519>,
+    context_id: &[u8],
+    public_key: &[u8],
+) -> anyhow::Result<u64> {
+    let actions = client
+        .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+            FX_SWAP_ACTION.to_string(),
+            context_id.to_vec(),
+        ))
+        .await?;
+    let transfers = client
+        .list_transfers(TxnFilter::<TransferFilter>::by_context_id(
+            context_id.to_vec(),
+        ))
+        .await?;
+    let last = actions
+        .iter()
+        .map(|action| action.payload.as_slice())
+        .chain(
+            transfers
+                .iter()
+                .filter_map(|transfer| transfer.with_type::<FxSwapMetadata>()),
+        )
+        .filter_map(|payload| Envelope::decode(payload).ok())
+        .filter(|envelope| envelope.public_key == public_key)
+        .map(|envelope| envelope.sequence)
+        .max()
+        .unwrap_or_default();
+    Ok(last + 1)
+}
+
+/// Last sequence number observed per publisher, to detect replayed or out of order events
+#[derive(Default)]
+struct Sequences(HashMap<Vec<u8>, u64>);
+
+impl Sequences {
+    /// Records the sequence number of an event, returns `false` if it must be ignored
+    fn observe(&mut self, envelope: &Envelope) -> bool {
+        if envelope.sequence == 0 {
+            return true;
+        }
+        let last = self.0.entry(envelope.public_key.clone()).or_default();
+        if envelope.sequence <= *last {
+            warn!(
+                sequence = envelope.sequence,
+                public_key = %hex::encode(&envelope.public_key),
+                "Ignoring replayed or out of order event"
+            );
+            return false;
+        }
+        *last = envelope.sequence;
+        true
+    }
+}
+
+async fn try_status(
+    client: &M10Client<Ed25519>,
+    context_id: Vec<u8>,
+    public_key: &[u8],
+    output: Output,
+) -> anyhow::Result<()> {
+    let actions = client
+        .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+            FX_SWAP_ACTION.to_string(),
+            context_id.clone(),
+        ))
+        .await?;
+    let quote = actions
+        .iter()
+        .find_map(|action| match Event::decode(&action.payload) {
+            Ok(Event::Quote(quote)) => Some(quote),
+            _ => None,
+        })
+        .ok_or_else(|| Failure::NotFound("No quote found for context".to_string()))?;
+    let from = quote.request.from;
+    let sequence = next_sequence(client, &context_id, public_key).await?;
     let tx_id = client
         .action(
-            ActionBuilder::for_all(FX_SWAP_ACTION.to_string(), from_account.id)
-                .payload(serde_json::to_vec(&event)?),
+            ActionBuilder::for_account(FX_SWAP_ACTION.to_string(), from, quote.intermediary)
+                .payload(Envelope::new(Event::StatusRequest, sequence, public_key).encode()),
             context_id.clone(),
         )
         .await?;
-    info!(%tx_id, context_id=%context_hex, "Submitted transaction");
+    info!(%tx_id, "Requested swap status");
 
-    // Wait for the quote
-    let mut actions = client
+    let mut stream = client
         .observe_actions(
             AccountFilter::name(FX_SWAP_ACTION.to_string())
                 .starting_from(tx_id + 1)
-                .involves(from_account.id),
+                .involves(from),
         )
         .await?;
-
-    info!("Waiting for the proposed quote");
-    while let Some(Ok(actions)) = actions.next().await {
+    while let Some(Ok(actions)) = stream.next().await {
         for action in actions {
             if action.context_id != context_id {
                 continue;
             }
+            if let Ok(Event::StatusResponse(status)) = Event::decode(&action.payload) {
+                info!(
+                    state = %status.state,
+                    quote_id = ?status.quote_id,
+                    valid_until = %status.valid_until,
+                    created_at = %status.created_at,
+                    updated_at = %status.updated_at,
+                    "Swap status"
+                );
+                output.emit(json!({ "context_id": hex::encode(&context_id), "status": status }));
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
 
-            let event =
-                serde_json::from_slice::<Event>(&action.payload).expect("invalid Event data");
+/// Transaction of a swap context, as printed by `status --history`
+struct Step {
+    tx_id: TxId,
+    /// [EPOCH TIME] Milliseconds at which the event was published, or the transfer processed
+    at: u64,
+    stage: &'static str,
+    details: String,
+}
 
-            if let Event::Quote(quote) = event {
-                info!(context_id=%context_hex, "Received quote {}", quote);
-                return Ok(());
-            } else {
-                panic!("Invalid Event type");
+/// Prints the lifecycle of the swap in `context_id` from the FX events of its actions & transfers
+async fn print_history(
+    client: &M10Client<Ed25519>,
+    context_id: Vec<u8>,
+    output: Output,
+) -> anyhow::Result<()> {
+    let context_hex = hex::encode(&context_id);
+    let actions = client
+        .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+            FX_SWAP_ACTION.to_string(),
+            context_id.clone(),
+        ))
+        .await?;
+    let transfers = client
+        .list_transfers(TxnFilter::<TransferFilter>::by_context_id(context_id))
+        .await?;
+
+    let mut steps = Vec::new();
+    for action in actions {
+        match Envelope::decode(&action.payload) {
+            Ok(envelope) => steps.extend(event_step(
+                action.tx_id,
+                envelope.created_at,
+                envelope.event,
+            )),
+            Err(err) => warn!(tx_id = %action.tx_id, %err, "Could not decode action"),
+        }
+    }
+    for transfer in transfers {
+        let at = transfer
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        // Executions are published with the transfer of the source funds
+        let envelope = transfer
+            .with_type::<FxSwapMetadata>()
+            .and_then(|payload| Envelope::decode(payload).ok());
+        if let Some(envelope) = envelope {
+            steps.extend(event_step(transfer.tx_id, at, envelope.event));
+            continue;
+        }
+        let legs = transfer
+            .steps
+            .iter()
+            .map(|step| format!("{} from {} to {}", step.amount, step.from, step.to))
+            .collect::<Vec<_>>()
+            .join(", ");
+        steps.push(Step {
+            tx_id: transfer.tx_id,
+            at,
+            stage: "transferred",
+            details: format!("{} ({:?})", legs, transfer.status),
+        });
+    }
+    if steps.is_empty() {
+        return Err(Failure::NotFound("No FX events found for context".to_string()).into());
+    }
+
+    steps.sort_by_key(|step| step.tx_id);
+    let mut stages: Vec<&str> = Vec::new();
+    for step in &steps {
+        info!(tx_id = %step.tx_id, at = %step.at, "{}: {}", step.stage, step.details);
+        if stages.last() != Some(&step.stage) && step.stage != "transferred" {
+            stages.push(step.stage);
+        }
+    }
+    info!("Lifecycle: {}", stages.join(" → "));
+    let steps = steps
+        .iter()
+        .map(|step| {
+            json!({
+                "tx_id": step.tx_id,
+                "at": step.at,
+                "stage": step.stage,
+                "details": step.details,
+            })
+        })
+        .collect::<Vec<_>>();
+    output.emit(json!({ "context_id": context_hex, "steps": steps, "lifecycle": stages }));
+    Ok(())
+}
+
+/// Checks of `verify`, each discrepancy being reported as found
+#[derive(Default)]
+struct Checks(Vec<serde_json::Value>);
+
+impl Checks {
+    fn check(&mut self, check: &'static str, ok: bool, details: String) {
+        match ok {
+            true => info!(%check, "OK: {}", details),
+            false => warn!(%check, "Discrepancy: {}", details),
+        }
+        self.0
+            .push(json!({ "check": check, "ok": ok, "details": details }));
+    }
+
+    /// Checks `signature` verified, by `liquidity_key` in hex when set
+    fn signature(
+        &mut self,
+        check: &'static str,
+        signer: anyhow::Result<&[u8]>,
+        liquidity_key: Option<&str>,
+    ) {
+        match signer {
+            Ok(signer) if liquidity_key.is_some_and(|key| key != hex::encode(signer)) => {
+                self.check(check, false, format!("signed by {}", hex::encode(signer)))
+            }
+            Ok(signer) => self.check(check, true, format!("signed by {}", hex::encode(signer))),
+            Err(err) => self.check(check, false, err.to_string()),
+        }
+    }
+
+    fn discrepancies(&self) -> usize {
+        self.0.iter().filter(|check| check["ok"] == false).count()
+    }
+}
+
+/// Cross-checks the quote executed in `context_id` & its receipts against the transfers recorded on the ledgers,
+/// the target leg being looked up on the ledger of `target_client`
+async fn verify_settlement(
+    client: &M10Client<Ed25519>,
+    target_client: &M10Client<Ed25519>,
+    context_id: Vec<u8>,
+    liquidity_key: Option<&str>,
+    output: Output,
+) -> anyhow::Result<()> {
+    let context_hex = hex::encode(&context_id);
+    let events = client
+        .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+            FX_SWAP_ACTION.to_string(),
+            context_id.clone(),
+        ))
+        .await?
+        .into_iter()
+        .filter_map(|action| Event::decode(&action.payload).ok())
+        .collect::<Vec<_>>();
+    let transfers = client
+        .list_transfers(TxnFilter::<TransferFilter>::by_context_id(context_id))
+        .await?;
+
+    // Executions are published with the transfer of the source funds, the latest one being verified
+    let (execution, execute) = transfers
+        .iter()
+        .find_map(|transfer| {
+            let envelope = transfer
+                .with_type::<FxSwapMetadata>()
+                .and_then(|payload| Envelope::decode(payload).ok())?;
+            match envelope.event {
+                Event::Execute(execute) => Some((transfer, execute)),
+                _ => None,
             }
+        })
+        .ok_or_else(|| Failure::NotFound("No execution found for context".to_string()))?;
+    let quote = events
+        .iter()
+        .find_map(|event| match event {
+            Event::Quote(quote) if quote.quote_id == execute.quote_id => Some(quote.clone()),
+            _ => None,
+        })
+        .or_else(|| execute.quote.clone())
+        .ok_or_else(|| Failure::NotFound(format!("No quote found for id {}", execute.quote_id)))?;
+    let settlement = execute.settlement_request();
+    let (intermediary, rate) = match (execute.direction, &quote.ask) {
+        (Direction::Ask, Some(ask)) => (ask.intermediary, Decimal::ONE / quote.rate),
+        _ => (quote.intermediary, quote.rate),
+    };
+    let source = Money::new(
+        client.get_account_info(settlement.from).await?.decimals,
+        Rounding::Up,
+    )?;
+    let target = Money::new(
+        target_client
+            .get_account_info(settlement.to)
+            .await?
+            .decimals,
+        Rounding::Down,
+    )?;
+
+    let mut checks = Checks::default();
+    checks.signature("quote signature", quote.verify(), liquidity_key);
+
+    // Source leg, from the initiator to the liquidity provider
+    checks.check(
+        "execution status",
+        matches!(execution.status, TransferStatus::Accepted),
+        format!("transfer {} is {:?}", execution.tx_id, execution.status),
+    );
+    let paid = execution
+        .steps
+        .iter()
+        .filter(|step| step.from == settlement.from && step.to == intermediary)
+        .map(|step| step.amount)
+        .sum::<u64>();
+    checks.check(
+        "execution direction",
+        paid > 0,
+        format!(
+            "transfer {} pays {} from {} to {}",
+            execution.tx_id, paid, settlement.from, intermediary
+        ),
+    );
+    let expected = source.to_units(execute.source_amount()?)?;
+    checks.check(
+        "execution amount",
+        paid == expected,
+        format!("{} paid for {} quoted", paid, expected),
+    );
+
+    // Target legs, one per receipt of a settlement
+    let netted = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Netted(report) => Some((report.tx_id, report.net_amount)),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+    let mut settled = 0;
+    for receipt in events.iter().filter_map(|event| match event {
+        Event::Receipt(receipt) if receipt.slices == 0 => Some(receipt),
+        _ => None,
+    }) {
+        settled += receipt.source_amount;
+        checks.signature("receipt signature", receipt.verify(), liquidity_key);
+        checks.check(
+            "receipt quote",
+            receipt.quote_id == execute.quote_id && receipt.source_tx_id == execution.tx_id,
+            format!(
+                "receipt of quote {} settles transfer {}",
+                receipt.quote_id, receipt.source_tx_id
+            ),
+        );
+        if let Some(quoted_rate) = receipt.quoted_rate {
+            checks.check(
+                "receipt quoted rate",
+                quoted_rate == rate,
+                format!("{} receipted, {} quoted", quoted_rate, rate),
+            );
+        }
+        let effective_rate = match receipt.source_amount {
+            0 => Decimal::ZERO,
+            amount => target.from_units(receipt.target_amount) / source.from_units(amount),
+        };
+        checks.check(
+            "receipt effective rate",
+            effective_rate == receipt.effective_rate,
+            format!(
+                "{} receipted, {} settled",
+                receipt.effective_rate, effective_rate
+            ),
+        );
+        // Paper trades transfer nothing to the recipient
+        if receipt.target_tx_id == 0 {
+            continue;
+        }
+        let payout = target_client.get_transfer(receipt.target_tx_id).await?;
+        let received = payout
+            .steps
+            .iter()
+            .filter(|step| step.to == settlement.to)
+            .map(|step| step.amount)
+            .sum::<u64>();
+        checks.check(
+            "payout status",
+            matches!(payout.status, TransferStatus::Accepted),
+            format!("transfer {} is {:?}", payout.tx_id, payout.status),
+        );
+        // Netted payouts are transferred together with the other payouts to the recipient
+        let expected = netted
+            .get(&receipt.target_tx_id)
+            .copied()
+            .unwrap_or(receipt.target_amount);
+        checks.check(
+            "payout amount",
+            received == expected,
+            format!(
+                "transfer {} pays {} to {} for {} receipted",
+                payout.tx_id, received, settlement.to, expected
+            ),
+        );
+    }
+
+    // Swaps completed in full settle all the funds paid
+    let completed = events
+        .iter()
+        .any(|event| matches!(event, Event::Completed { .. }));
+    let partial = events
+        .iter()
+        .any(|event| matches!(event, Event::PartialFill(_) | Event::Refunded(_)));
+    if completed && !partial {
+        checks.check(
+            "settled amount",
+            settled == paid,
+            format!("{} settled of {} paid", settled, paid),
+        );
+    }
+
+    let discrepancies = checks.discrepancies();
+    output.emit(json!({
+        "context_id": context_hex,
+        "checks": checks.0,
+        "discrepancies": discrepancies,
+    }));
+    match discrepancies {
+        0 => {
+            info!("Settlement verified");
+            Ok(())
+        }
+        discrepancies => Err(Failure::Discrepancy(format!(
+            "{} discrepancies found for {}",
+            discrepancies, context_hex
+        ))
+        .into()),
+    }
+}
+
+/// Step of the lifecycle recorded by `event`, status queries being left out
+fn event_step(tx_id: TxId, at: u64, event: Event) -> Option<Step> {
+    let (stage, details) = match event {
+        Event::Request(request) => (
+            "requested",
+            format!("{} from {} to {}", request.amount, request.from, request.to),
+        ),
+        Event::Quote(quote) => (
+            "quoted",
+            format!(
+                "quote {} at {}, valid until {}",
+                quote.quote_id, quote.rate, quote.valid_until
+            ),
+        ),
+        Event::CounterOffer(offer) => ("countered", format!("offered {}", offer.rate)),
+        Event::Execute(execute) => (
+            "executing",
+            format!("quote {} with {:?}", execute.quote_id, execute.strategy),
+        ),
+        Event::Receipt(receipt) => (
+            "settled",
+            format!(
+                "{} for {} at {}, paid out by {}",
+                receipt.source_amount,
+                receipt.target_amount,
+                receipt.effective_rate,
+                receipt.target_tx_id
+            ),
+        ),
+        Event::Netted(report) => (
+            "netted",
+            format!("{} in batched transfer {}", report.amount, report.tx_id),
+        ),
+        Event::PartialFill(fill) => (
+            "partially filled",
+            format!("{} filled, {} refunded", fill.filled, fill.refunded),
+        ),
+        Event::Completed { paper_trade: false } => ("completed", String::new()),
+        Event::Completed { paper_trade: true } => {
+            ("completed", "paper trade, nothing was settled".to_string())
         }
+        Event::Reject { reason, .. } => ("rejected", reason),
+        Event::Cancel => ("cancelled", String::new()),
+        Event::Failed { code, message } => ("failed", format!("[{}] {}", code, message)),
+        Event::Refunded(refund) => (
+            "refunded",
+            format!(
+                "{} by {}: {}",
+                refund.amount, refund.refund_tx_id, refund.reason
+            ),
+        ),
+        Event::StatusRequest | Event::StatusResponse(_) => return None,
+    };
+    Some(Step {
+        tx_id,
+        at,
+        stage,
+        details,
+    })
+}
+
+/// Prints the currency & balance of each account, along with its role for the liquidity provider
+async fn print_accounts(
+    client: &M10Client<Ed25519>,
+    accounts: Vec<(&str, AccountId)>,
+    output: Output,
+) -> anyhow::Result<()> {
+    info!(
+        "{:<32} {:<10} {:<8} {:>20} {:<6}",
+        "ACCOUNT", "ROLE", "CURRENCY", "BALANCE", "FROZEN"
+    );
+    for (role, id) in accounts {
+        let account = match client.get_account(id).await {
+            Ok(account) => account,
+            Err(err) => {
+                error!(account = %id, %err, "Could not read account");
+                continue;
+            }
+        };
+        let balance = Money::new(account.decimals, Rounding::Down)?.from_units(account.balance);
+        info!(
+            "{:<32} {:<10} {:<8} {:>20} {:<6}",
+            id, role, account.code, balance, account.frozen
+        );
+        output.emit(json!({
+            "account_id": id.to_string(),
+            "role": role,
+            "currency": account.code,
+            "balance": balance,
+            "frozen": account.frozen,
+        }));
     }
     Ok(())
 }
 
-async fn try_execute(
+/// URL of the ledger holding the currency of `liquidity`, the top-level `address` or else `default` when it sets none
+fn ledger_url(liquidity: &LiquidityConfig, address: Option<&Addresses>, default: &str) -> String {
+    match liquidity.address.as_ref().or(address) {
+        Some(address) => address.urls()[0].clone(),
+        None => default.to_string(),
+    }
+}
+
+/// Tops up the liquidity account `account` with `fund.amount` units, from its parent account unless set
+async fn try_fund(
     client: &M10Client<Ed25519>,
-    execute: ExecuteQuote,
-    quote: Quote,
+    account: AccountId,
+    fund: FundLiquidity,
+    output: Output,
+) -> anyhow::Result<()> {
+    let info = client.get_account_info(account).await?;
+    let from = fund.from.unwrap_or(info.parent_id);
+    let tx_id = client
+        .transfer(TransferBuilder::new().step(StepBuilder::new(from, account, fund.amount)))
+        .await?;
+    let balance = client.get_account(account).await?.balance;
+    let money = Money::new(info.decimals, Rounding::Down)?;
+    info!(
+        %tx_id,
+        %from,
+        to = %account,
+        amount = %money.from_units(fund.amount),
+        balance = %money.from_units(balance),
+        "Funded liquidity account"
+    );
+    output.emit(json!({
+        "tx_id": tx_id,
+        "from": from.to_string(),
+        "account_id": account.to_string(),
+        "amount": money.from_units(fund.amount),
+        "balance": money.from_units(balance),
+    }));
+    Ok(())
+}
+
+/// Stream of the FX actions involving `accounts`, from the transaction `from` or else from the next one
+async fn observe_swaps(
+    client: &M10Client<Ed25519>,
+    accounts: &[AccountId],
+    from: Option<TxId>,
+) -> anyhow::Result<BoxStream<'static, Result<Vec<Action>, M10Error>>> {
+    let mut filter = AccountFilter::name(FX_SWAP_ACTION.to_string());
+    for &account in accounts {
+        filter = filter.involves(account);
+    }
+    if let Some(from) = from {
+        filter = filter.starting_from(from);
+    }
+    Ok(client.observe_actions(filter).await?.boxed())
+}
+
+/// Prints the FX events of the actions as they are published, only those of `context_id` if set
+async fn print_swaps(
+    mut actions: impl Stream<Item = Result<Vec<Action>, M10Error>> + Unpin,
+    context_id: Option<Vec<u8>>,
+    output: Output,
+) -> anyhow::Result<()> {
+    while let Some(actions) = actions.next().await {
+        for action in actions? {
+            if context_id
+                .as_ref()
+                .is_some_and(|context_id| *context_id != action.context_id)
+            {
+                continue;
+            }
+            let envelope = match Envelope::decode(&action.payload) {
+                Ok(envelope) => envelope,
+                Err(err) => {
+                    warn!(tx_id = %action.tx_id, %err, "Could not decode action");
+                    continue;
+                }
+            };
+            let context_hex = hex::encode(&action.context_id);
+            output.emit(SwapResult {
+                context_id: &context_hex,
+                tx_id: action.tx_id,
+                quote: None,
+                event: Some(&envelope.event),
+            });
+            if let Some(step) = event_step(action.tx_id, envelope.created_at, envelope.event) {
+                info!(
+                    tx_id = %step.tx_id,
+                    at = %step.at,
+                    context_id = %context_hex,
+                    "{}: {}",
+                    step.stage,
+                    step.details
+                );
+            }
+        }
+    }
+    warn!("Observation ended");
+    Ok(())
+}
+
+/// Latest transfers of an account scanned for the funds it holds in pending transfers
+const HELD_SCAN: u64 = 100;
+
+/// Prints the currency, balance & frozen state of `id`, along with the funds held by its pending transfers
+async fn print_balance(
+    client: &M10Client<Ed25519>,
+    id: AccountId,
+    output: Output,
+) -> anyhow::Result<()> {
+    let account = client.get_account(id).await?;
+    let transfers = client
+        .list_transfers(TxnFilter::<TransferFilter>::by_account(id).limit(HELD_SCAN))
+        .await?;
+    let held: u64 = transfers
+        .iter()
+        .filter(|transfer| matches!(transfer.status, TransferStatus::Pending))
+        .flat_map(|transfer| &transfer.steps)
+        .filter(|step| step.from == id)
+        .map(|step| step.amount)
+        .sum();
+    let money = Money::new(account.decimals, Rounding::Down)?;
+    info!(
+        account = %id,
+        currency = %account.code,
+        decimals = %account.decimals,
+        balance = %money.from_units(account.balance),
+        held = %money.from_units(held),
+        frozen = %account.frozen,
+        "Balance"
+    );
+    output.emit(json!({
+        "account_id": id.to_string(),
+        "currency": account.code,
+        "decimals": account.decimals,
+        "balance": money.from_units(account.balance),
+        "held": money.from_units(held),
+        "frozen": account.frozen,
+    }));
+    Ok(())
+}
+
+/// Prices a swap without publishing any action, from `config` when given or else from the admin API of the service
+async fn preview_quote(
+    client: &M10Client<Ed25519>,
+    preview: PreviewQuote,
+    config: Option<&Config>,
+    output: Output,
+) -> anyhow::Result<()> {
+    let from = preview
+        .from
+        .ok_or_else(|| Failure::Usage("Missing source account".to_string()))?;
+    let to = preview
+        .to
+        .ok_or_else(|| Failure::Usage("Missing target account".to_string()))?;
+    let from = client.get_account(from).await?;
+    let to = client.get_account(to).await?;
+    let (side, amount) = match (preview.amount, preview.receive_amount) {
+        (_, Some(receive_amount)) => (
+            Side::BuyExact,
+            parse_amount(
+                receive_amount,
+                &to.code,
+                to.decimals,
+                preview.amount_minor_units,
+            )?,
+        ),
+        (Some(amount), None) => (
+            Side::SellExact,
+            parse_amount(
+                amount,
+                &from.code,
+                from.decimals,
+                preview.amount_minor_units,
+            )?,
+        ),
+        (None, None) => return Err(Failure::Usage("Missing amount".to_string()).into()),
+    };
+    let quote = match (config, preview.admin) {
+        (Some(config), _) => config.indicative_quote(&from.code, &to.code, side, amount)?,
+        (None, Some(admin)) => {
+            let side = match side {
+                Side::SellExact => "sell",
+                Side::BuyExact => "buy",
+            };
+            let url = format!(
+                "{}/quote?from={}&to={}&amount={}&side={}",
+                admin.trim_end_matches('/'),
+                from.code,
+                to.code,
+                amount,
+                side
+            );
+            reqwest::get(&url)
+                .await?
+                .error_for_status()?
+                .json::<IndicativeQuote>()
+                .await?
+        }
+        (None, None) => {
+            return Err(Failure::Usage("Pass --offline or the --admin URL".to_string()).into())
+        }
+    };
+    info!(
+        route = %quote.route.join(">"),
+        rate = %quote.rate,
+        spread_bps = %quote.fees.spread_bps,
+        flat_fee = %quote.fees.flat_fee,
+        net_rate = %quote.fees.net_rate(quote.rate),
+        source_amount = %quote.source_amount,
+        target_amount = %quote.target_amount,
+        "Indicative quote, not binding"
+    );
+    output.emit(json!({ "quote": quote }));
+    Ok(())
+}
+
+/// Actions scanned at once while looking for the swaps of an account
+const HISTORY_SCAN: u64 = 100;
+
+/// Swap listed by the `history` command
+struct SwapSummary {
     context_id: Vec<u8>,
-) -> anyhow::Result<TxId> {
+    /// Action publishing the request
+    tx_id: TxId,
+    /// [EPOCH TIME] Milliseconds at which the swap was requested
+    requested_at: u64,
+    request: Request,
+}
+
+/// Lists the swaps requested by or from `query.account`, most recent first, a page at a time
+async fn list_history(
+    client: &M10Client<Ed25519>,
+    query: SwapHistoryQuery,
+    output: Output,
+) -> anyhow::Result<()> {
+    let since = query.since.map(|since| since * 1000);
+    let until = query.until.map(|until| until * 1000);
+    let mut swaps = Vec::new();
+    let mut max = query
+        .before
+        .map_or(u64::MAX, |before| before.saturating_sub(1));
+    let mut exhausted = false;
+    // Swaps are found from their requests, each context being listed once
+    'scan: while swaps.len() < query.limit {
+        let mut actions = client
+            .list_actions(
+                TxnFilter::<ActionsFilter>::by_account(FX_SWAP_ACTION.to_string(), query.account)
+                    .max(max)
+                    .limit(HISTORY_SCAN),
+            )
+            .await?;
+        if actions.is_empty() {
+            exhausted = true;
+            break;
+        }
+        actions.sort_by_key(|action| std::cmp::Reverse(action.tx_id));
+        for action in actions {
+            max = action.tx_id.saturating_sub(1);
+            let envelope = match Envelope::decode(&action.payload) {
+                Ok(envelope) => envelope,
+                Err(_) => continue,
+            };
+            let request = match envelope.event {
+                Event::Request(request) => request,
+                _ => continue,
+            };
+            // Envelopes predating their timestamps are never filtered out
+            let requested_at = envelope.created_at;
+            if requested_at > 0 && until.is_some_and(|until| requested_at > until) {
+                continue;
+            }
+            if requested_at > 0 && since.is_some_and(|since| requested_at < since) {
+                exhausted = true;
+                break 'scan;
+            }
+            swaps.push(SwapSummary {
+                context_id: action.context_id,
+                tx_id: action.tx_id,
+                requested_at,
+                request,
+            });
+            if swaps.len() == query.limit {
+                break 'scan;
+            }
+        }
+        if max == 0 {
+            exhausted = true;
+        }
+    }
+
     info!(
-        "Transferring from {} -> {}",
-        quote.request.from, quote.intermediary
+        "{:<16} {:<9} {:>14} {:>14} {:<15} {:>13}",
+        "CONTEXT", "PAIR", "AMOUNT", "RATE", "STATE", "REQUESTED AT"
     );
-    let amount = quote.rate * quote.request.amount;
+    for swap in &swaps {
+        let (pair, rate, state) = swap_outcome(client, &swap.context_id).await?;
+        let state = state.map_or("Requested".to_string(), |state| state.to_string());
+        info!(
+            "{:<16} {:<9} {:>14} {:>14} {:<15} {:>13}",
+            hex::encode(&swap.context_id),
+            pair,
+            swap.request.amount,
+            rate.map(|rate| rate.to_string()).unwrap_or_default(),
+            state,
+            swap.requested_at
+        );
+        output.emit(json!({
+            "context_id": hex::encode(&swap.context_id),
+            "tx_id": swap.tx_id,
+            "pair": pair,
+            "amount": swap.request.amount,
+            "rate": rate,
+            "state": state,
+            "requested_at": swap.requested_at,
+        }));
+    }
+    match swaps.last() {
+        Some(last) if !exhausted => info!(before = %last.tx_id, "More swaps with --before"),
+        _ => info!(swaps = %swaps.len(), "No more swaps"),
+    }
+    Ok(())
+}
+
+/// Pair, latest rate & state of the swap in `context_id`, from its actions & transfers
+async fn swap_outcome(
+    client: &M10Client<Ed25519>,
+    context_id: &[u8],
+) -> anyhow::Result<(String, Option<Decimal>, Option<SwapState>)> {
+    let actions = client
+        .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+            FX_SWAP_ACTION.to_string(),
+            context_id.to_vec(),
+        ))
+        .await?;
+    let transfers = client
+        .list_transfers(TxnFilter::<TransferFilter>::by_context_id(
+            context_id.to_vec(),
+        ))
+        .await?;
+    let mut events =
+        actions
+            .iter()
+            .map(|action| (action.tx_id, action.payload.as_slice()))
+            .chain(transfers.iter().filter_map(|transfer| {
+                Some((transfer.tx_id, transfer.with_type::<FxSwapMetadata>()?))
+            }))
+            .filter_map(|(tx_id, payload)| Some((tx_id, Envelope::decode(payload).ok()?.event)))
+            .collect::<Vec<_>>();
+    events.sort_by_key(|(tx_id, _)| *tx_id);
+
+    let (mut pair, mut rate, mut state) = ("?".to_string(), None, None);
+    for (_, event) in events {
+        state = match event {
+            Event::Quote(quote) => {
+                if let (Some(from), Some(to)) = (quote.route.first(), quote.route.last()) {
+                    pair = format!("{}/{}", from, to).to_uppercase();
+                }
+                rate = Some(quote.rate);
+                Some(SwapState::Quoted)
+            }
+            Event::Receipt(receipt) => {
+                rate = Some(receipt.effective_rate);
+                continue;
+            }
+            Event::Execute(_) => Some(SwapState::Executing),
+            Event::Completed { paper_trade: false } => Some(SwapState::Settled),
+            Event::Completed { paper_trade: true } => Some(SwapState::PaperTraded),
+            Event::PartialFill(_) => Some(SwapState::PartiallyFilled),
+            Event::Reject { .. } => Some(SwapState::Rejected),
+            Event::Cancel => Some(SwapState::Cancelled),
+            Event::Failed { .. } => Some(SwapState::Failed),
+            Event::Refunded(_) => Some(SwapState::Refunded),
+            Event::Request(_)
+            | Event::CounterOffer(_)
+            | Event::Netted(_)
+            | Event::StatusRequest
+            | Event::StatusResponse(_) => continue,
+        };
+    }
+    Ok((pair, rate, state))
+}
+
+async fn try_counter(
+    client: &M10Client<Ed25519>,
+    context_id: Vec<u8>,
+    counter: CounterQuote,
+    public_key: &[u8],
+    output: Output,
+) -> anyhow::Result<()> {
+    let quote = find_quote(client, context_id.clone(), counter.quote_id).await?;
+    info!(quoted = %quote.fees.net_rate(quote.rate), offered = %counter.rate, "Proposing rate");
+    let from = quote.request.from;
+    let intermediary = quote.intermediary;
+    let sequence = next_sequence(client, &context_id, public_key).await?;
+    let offer = Event::CounterOffer(CounterOffer {
+        quote,
+        rate: counter.rate,
+    });
     let tx_id = client
-        .transfer(
-            TransferBuilder::new()
-                .step(
-                    StepBuilder::new(quote.request.from, quote.intermediary, amount.try_into()?)
-                        .custom_metadata(
-                            FX_SWAP_METADATA,
-                            serde_json::to_vec(&Event::Execute(Execute {
-                                request: quote.request,
-                                valid_until: (SystemTime::now()
-                                    + Duration::from_secs(execute.valid_for.unwrap_or(300)))
-                                .duration_since(UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs(),
-                                upper_limit: (Decimal::one() + execute.margin) * quote.rate,
-                                lower_limits: (Decimal::one() - execute.margin) * quote.rate,
-                            }))?,
-                        ),
-                )
-                .context_id(context_id.clone()),
+        .action(
+            ActionBuilder::for_account(FX_SWAP_ACTION.to_string(), from, intermediary)
+                .payload(Envelope::new(offer, sequence, public_key).encode()),
+            context_id.clone(),
         )
         .await?;
-    info!(%tx_id, "Transfer success");
-    Ok(tx_id)
+    info!(%tx_id, "Submitted counter-offer");
+    output.emit(json!({ "context_id": hex::encode(&context_id), "tx_id": tx_id }));
+    wait_for_quote(client, from, tx_id, &context_id, output)
+        .await
+        .map(drop)
 }
 
 fn root_key() -> Ed25519 {
@@ -405,7 +3886,7 @@ async fn create_account(
                 .insert(Role {
                     id: Bytes::copy_from_slice(&role_id.into_bytes()),
                     owner: Bytes::copy_from_slice(owner),
-                    name: "m10.fx.account".to_string(),
+                    name: SETUP_ROLE.to_string(),
                     rules: vec![
                         can_read_and_transact_accounts(account_id),
                         can_read_and_transact_ledger_accounts(account_id),
@@ -414,7 +3895,7 @@ async fn create_account(
                 .insert(RoleBinding {
                     id: Bytes::copy_from_slice(&role_id.into_bytes()),
                     owner: Bytes::copy_from_slice(owner),
-                    name: "m10.fx.account".to_string(),
+                    name: SETUP_ROLE.to_string(),
                     role: Bytes::copy_from_slice(role_id.as_bytes()),
                     subjects: vec![Bytes::copy_from_slice(owner)],
                     expressions: vec![],
@@ -0,0 +1,121 @@
+//! Defaults of the command line read from `~/.m10fx/config`, so that the signer & accounts needn't be repeated on every command
+
+use crate::error::Failure;
+use m10_sdk::account::AccountId;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// File of the defaults, relative to the home directory
+const DEFAULTS_PATH: &str = ".m10fx/config";
+
+/// Settings of `~/.m10fx/config`, overlaid with its `profiles.<profile>` table when a profile is selected,
+/// flags given on the command line taking precedence over them
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Defaults {
+    /// Ledger URL
+    pub url: Option<String>,
+    /// Config file of the service
+    pub config: Option<PathBuf>,
+    /// Key pair signing the commands
+    pub key_pair: Option<String>,
+    /// Account swaps are requested from, in hex
+    from: Option<String>,
+    /// Account swaps are requested to, in hex
+    to: Option<String>,
+    /// Whether the selected profile is one of the file
+    #[serde(skip)]
+    declared: bool,
+}
+
+impl Defaults {
+    /// Loads the defaults, which are all unset when there's no `~/.m10fx/config`
+    pub fn load(profile: Option<&str>) -> anyhow::Result<Defaults> {
+        let home = match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home),
+            None => return Ok(Defaults::default()),
+        };
+        let path = home.join(DEFAULTS_PATH);
+        if !path.exists() {
+            return Ok(Defaults::default());
+        }
+        let mut file: toml::value::Table = toml::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(|err| anyhow::anyhow!("Invalid {}: {}", path.display(), err))?;
+        let mut profiles = match file.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => {
+                return Err(anyhow::anyhow!(
+                    "Invalid {}: profiles must be tables",
+                    path.display()
+                ))
+            }
+            None => toml::value::Table::new(),
+        };
+        let overlay = profile.and_then(|name| profiles.remove(name));
+        let declared = overlay.is_some();
+        match overlay {
+            Some(toml::Value::Table(overlay)) => file.extend(overlay),
+            Some(_) => {
+                return Err(anyhow::anyhow!(
+                    "Invalid {}: profiles must be tables",
+                    path.display()
+                ))
+            }
+            None => {}
+        }
+        let mut defaults: Defaults = toml::Value::Table(file)
+            .try_into()
+            .map_err(|err| anyhow::anyhow!("Invalid {}: {}", path.display(), err))?;
+        defaults.declared = declared;
+        defaults.key_pair = defaults
+            .key_pair
+            .map(|key_pair| expand_home(&key_pair, &home));
+        defaults.config = defaults
+            .config
+            .map(|config| PathBuf::from(expand_home(&config.to_string_lossy(), &home)));
+        Ok(defaults)
+    }
+
+    /// Whether `~/.m10fx/config` has a `profiles.<profile>` table
+    pub fn declares(&self) -> bool {
+        self.declared
+    }
+
+    /// `key_pair` when given, or else the key pair of the defaults
+    pub fn key_pair<'a>(&'a self, key_pair: Option<&'a str>) -> anyhow::Result<&'a str> {
+        key_pair.or(self.key_pair.as_deref()).ok_or_else(|| {
+            Failure::Usage(format!(
+                "A --key-pair is required, none being set in ~/{}",
+                DEFAULTS_PATH
+            ))
+            .into()
+        })
+    }
+
+    /// Default source account
+    pub fn from(&self) -> anyhow::Result<Option<AccountId>> {
+        self.from
+            .as_deref()
+            .map(AccountId::from_str)
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Default target account
+    pub fn to(&self) -> anyhow::Result<Option<AccountId>> {
+        self.to
+            .as_deref()
+            .map(AccountId::from_str)
+            .transpose()
+            .map_err(Into::into)
+    }
+}
+
+/// Resolves a leading `~/` of `path` against the home directory
+fn expand_home(path: &str, home: &Path) -> String {
+    match path.strip_prefix("~/") {
+        Some(relative) => home.join(relative).to_string_lossy().into_owned(),
+        None => path.to_string(),
+    }
+}
@@ -0,0 +1,243 @@
+//! Local swap of the `simulate` command, running the request → quote → execute → completed flow without a ledger
+
+use super::{check_quote, event_step, Output, SimulateSwap, SwapResult};
+use crate::error::Failure;
+use async_trait::async_trait;
+use m10_sdk::account::AccountId;
+use m10_sdk::{Ed25519, Signer, TxId};
+use rust_decimal::Decimal;
+use service::config::Rounding;
+use service::event::{
+    Direction, Envelope, Event, Execute, FailureCode, Fees, Receipt, Refund, Request, Side,
+    Signature, Strategy,
+};
+use service::money::Money;
+use service::pricing::{check_slippage, effective_rate, slippage_bps, Payout, Price, RateProvider};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::info;
+
+/// Account of the initiator, in the source currency
+const INITIATOR_ACCOUNT: &str = "00000000004a00000000000000000001";
+
+/// Account of the recipient, in the target currency
+const RECIPIENT_ACCOUNT: &str = "04000000003200000000000000000001";
+
+/// Liquidity account the initiator pays, in the source currency
+const LIQUIDITY_ACCOUNT: &str = "00000000004900000000000000000001";
+
+/// Validity of the simulated quotes
+const QUOTE_TTL: Duration = Duration::from_secs(60);
+
+/// Transaction IDs are spaced as on the ledger
+const TX_ID_STEP: TxId = 10_000;
+
+/// Rate source of the simulation, moving by up to `volatility_bps` on every lookup
+struct MockRates {
+    /// Rate of the simulated pair
+    rate: Mutex<Decimal>,
+    volatility_bps: u32,
+}
+
+#[async_trait]
+impl RateProvider for MockRates {
+    async fn rate(&self, _base: &str, _quote: &str) -> anyhow::Result<Decimal> {
+        let volatility = self.volatility_bps as i64;
+        let drift = Decimal::new(fastrand::i64(-volatility..=volatility), 4);
+        let mut rate = self.rate.lock().unwrap();
+        *rate = (*rate * (Decimal::ONE + drift)).round_dp(6);
+        Ok(*rate)
+    }
+}
+
+/// Party publishing an event of the simulation
+#[derive(Clone, Copy)]
+enum Party {
+    Initiator,
+    Liquidity,
+}
+
+/// Events of the simulated context, each published by one of the parties
+struct Simulation {
+    context_hex: String,
+    initiator: Ed25519,
+    liquidity: Ed25519,
+    /// Last sequence number of the initiator & of the liquidity provider
+    sequences: [u64; 2],
+    tx_id: TxId,
+    output: Output,
+}
+
+impl Simulation {
+    fn next_tx_id(&mut self) -> TxId {
+        self.tx_id += TX_ID_STEP;
+        self.tx_id
+    }
+
+    /// Publishes `event` as `party`, printing it as decoded from its wire format like `watch` does
+    fn publish(&mut self, party: Party, event: Event) -> anyhow::Result<(TxId, Event)> {
+        let (sequence, public_key) = match party {
+            Party::Initiator => (&mut self.sequences[0], self.initiator.public_key()),
+            Party::Liquidity => (&mut self.sequences[1], self.liquidity.public_key()),
+        };
+        *sequence += 1;
+        let payload = Envelope::new(event, *sequence, public_key).encode();
+        let envelope = Envelope::decode(&payload)?;
+        let tx_id = self.next_tx_id();
+        self.output.emit(SwapResult {
+            context_id: &self.context_hex,
+            tx_id,
+            quote: None,
+            event: Some(&envelope.event),
+        });
+        if let Some(step) = event_step(tx_id, envelope.created_at, envelope.event.clone()) {
+            info!(
+                tx_id = %step.tx_id,
+                at = %step.at,
+                context_id = %self.context_hex,
+                "{}: {}",
+                step.stage,
+                step.details
+            );
+        }
+        Ok((tx_id, envelope.event))
+    }
+
+    async fn sign(&self, payload: &[u8]) -> anyhow::Result<Signature> {
+        Signature::sign(&self.liquidity, payload).await
+    }
+}
+
+/// Runs a swap between in-memory parties, the liquidity provider quoting & settling it from a mock rate source with
+/// the pricing of the service
+pub async fn run(simulate: SimulateSwap, output: Output) -> anyhow::Result<()> {
+    let rates = MockRates {
+        rate: Mutex::new(simulate.rate),
+        volatility_bps: simulate.volatility_bps,
+    };
+    let mut simulation = Simulation {
+        context_hex: hex::encode(fastrand::u64(..).to_be_bytes()),
+        initiator: Ed25519::new_key_pair(None)?,
+        liquidity: Ed25519::new_key_pair(None)?,
+        sequences: [0; 2],
+        tx_id: 6_003_550_000,
+        output,
+    };
+    let (source, target) = (
+        Money::new(simulate.decimals, Rounding::Up)?,
+        Money::new(simulate.decimals, Rounding::Down)?,
+    );
+    let from_currency = simulate.from_currency.to_lowercase();
+    let to_currency = simulate.to_currency.to_lowercase();
+    info!(context_id = %simulation.context_hex, from = %from_currency, to = %to_currency, "Simulating swap");
+
+    // The initiator requests a quote
+    let request = Request {
+        from: AccountId::from_str(INITIATOR_ACCOUNT)?,
+        to: AccountId::from_str(RECIPIENT_ACCOUNT)?,
+        amount: simulate.amount,
+        side: Side::SellExact,
+        memo: None,
+        two_way: false,
+        compliance: None,
+        settle_at: None,
+    };
+    request.validate()?;
+    simulation.publish(Party::Initiator, Event::Request(request.clone()))?;
+
+    // The liquidity provider prices it from the current rate & signs the quote
+    let rate = rates.rate(&from_currency, &to_currency).await?;
+    let fees = Fees {
+        flat_fee: simulate.flat_fee,
+        spread_bps: simulate.spread_bps,
+        currency: from_currency.to_uppercase(),
+    };
+    let price = Price::new(
+        rate,
+        vec![from_currency.clone(), to_currency.clone()],
+        fees,
+        [],
+        request.amount,
+    );
+    let mut quote = price.quote(
+        request.clone(),
+        AccountId::from_str(LIQUIDITY_ACCOUNT)?,
+        QUOTE_TTL,
+    )?;
+    quote.validate()?;
+    quote.signature = Some(simulation.sign(&quote.signing_payload()).await?);
+    let quote = match simulation.publish(Party::Liquidity, Event::Quote(quote))? {
+        (_, Event::Quote(quote)) => quote,
+        _ => unreachable!(),
+    };
+
+    // The initiator checks the quote was signed by its liquidity provider & executes it right away
+    check_quote(
+        &quote,
+        Some(&hex::encode(simulation.liquidity.public_key())),
+    )?;
+    let execute = Execute {
+        request: request.clone(),
+        quote_id: quote.quote_id,
+        quote: Some(quote.clone()),
+        strategy: Strategy::Immediate,
+        direction: Direction::Bid,
+    };
+    execute.validate()?;
+    let paid = source.to_units(quote.source_amount)?;
+    let (source_tx_id, _) =
+        simulation.publish(Party::Initiator, Event::Execute(execute.clone()))?;
+
+    // The liquidity provider settles at the rate looked up again, unless it slipped too far
+    let executed_rate = rates.rate(&from_currency, &to_currency).await?;
+    let slippage_bps = slippage_bps(executed_rate, rate).round_dp(2);
+    info!(quoted_rate = %rate, %executed_rate, %slippage_bps, "Settling swap");
+    let slipped = simulate
+        .max_slippage_bps
+        .map(|max_bps| check_slippage(executed_rate, rate, max_bps));
+    if let Some(Err(slipped)) = slipped {
+        let reason = slipped.to_string();
+        simulation.publish(
+            Party::Liquidity,
+            Event::Failed {
+                code: FailureCode::Slippage,
+                message: reason.clone(),
+            },
+        )?;
+        let refund_tx_id = simulation.next_tx_id();
+        simulation.publish(
+            Party::Liquidity,
+            Event::Refunded(Refund {
+                source_tx_id,
+                refund_tx_id,
+                amount: paid,
+                reason: reason.clone(),
+            }),
+        )?;
+        return Err(Failure::Failed(format!("Swap was refunded: {}", reason)).into());
+    }
+    let received = Payout::new(&request, &quote.fees, target, executed_rate, paid)?.share(paid);
+    let target_tx_id = simulation.next_tx_id();
+    let mut receipt = Receipt {
+        quote_id: quote.quote_id,
+        source_tx_id,
+        target_tx_id,
+        source_amount: paid,
+        target_amount: received,
+        effective_rate: effective_rate(source, target, paid, received),
+        quoted_rate: execute.quoted_rate(),
+        executed_rate,
+        slices: 0,
+        signature: None,
+    };
+    receipt.signature = Some(simulation.sign(&receipt.signing_payload()).await?);
+    if let (_, Event::Receipt(receipt)) =
+        simulation.publish(Party::Liquidity, Event::Receipt(receipt))?
+    {
+        receipt.verify()?;
+    }
+    simulation.publish(Party::Liquidity, Event::Completed { paper_trade: false })?;
+    info!("Simulated swap completed");
+    Ok(())
+}
@@ -0,0 +1,436 @@
+//! Console dashboard of the `tui` command, showing the balances & swaps of accounts as they change
+
+use super::{
+    check_quote, event_step, observe_swaps, try_cancel, try_execute, try_initiate, Dashboard,
+    ExecuteQuote, Initiate, Output,
+};
+use crossterm::event::{Event as TermEvent, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures_util::future::LocalBoxFuture;
+use futures_util::stream::{BoxStream, FuturesUnordered};
+use futures_util::{FutureExt, StreamExt};
+use m10_sdk::account::AccountId;
+use m10_sdk::client::M10Client;
+use m10_sdk::error::M10Error;
+use m10_sdk::{Action, Ed25519};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use rust_decimal::Decimal;
+use service::config::Rounding;
+use service::event::{Envelope, Event, Quote};
+use service::money::Money;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Stages of a swap whose quote can still be executed or cancelled
+const OPEN_STAGES: [&str; 2] = ["quoted", "countered"];
+
+/// Stages of a swap being settled
+const IN_FLIGHT_STAGES: [&str; 4] = ["executing", "settled", "netted", "partially filled"];
+
+/// Runs the dashboard until `q` is pressed, restoring the terminal on exit
+pub async fn run(
+    client: &M10Client<Ed25519>,
+    dashboard: Dashboard,
+    public_key: &[u8],
+    default_validity: u64,
+) -> anyhow::Result<()> {
+    let swaps = observe_swaps(client, &dashboard.accounts, None).await?;
+    let mut app = App {
+        client,
+        public_key,
+        key_pair: dashboard.key_pair.as_deref(),
+        default_validity,
+        accounts: &dashboard.accounts,
+        balances: vec![],
+        swaps: vec![],
+        selected: TableState::default(),
+        input: None,
+        status: "Waiting for swaps".to_string(),
+    };
+    let mut terminal = ratatui::init();
+    let result = app
+        .run(
+            &mut terminal,
+            swaps,
+            Duration::from_secs(dashboard.refresh.max(1)),
+        )
+        .await;
+    ratatui::restore();
+    result
+}
+
+/// Balance of a watched account, or the error reading it
+struct BalanceRow {
+    account: AccountId,
+    currency: String,
+    balance: String,
+}
+
+/// Latest stage of a swap observed in a context
+struct SwapRow {
+    context_id: Vec<u8>,
+    stage: &'static str,
+    details: String,
+    /// Latest quote of the swap, executed or cancelled from the dashboard
+    quote: Option<Quote>,
+}
+
+struct App<'a> {
+    client: &'a M10Client<Ed25519>,
+    public_key: &'a [u8],
+    key_pair: Option<&'a str>,
+    default_validity: u64,
+    accounts: &'a [AccountId],
+    balances: Vec<BalanceRow>,
+    /// Swaps in the order they were first observed
+    swaps: Vec<SwapRow>,
+    selected: TableState,
+    /// `<to> <amount>` of a swap being typed, when initiating one
+    input: Option<String>,
+    status: String,
+}
+
+impl<'a> App<'a> {
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        mut swaps: BoxStream<'static, Result<Vec<Action>, M10Error>>,
+        refresh: Duration,
+    ) -> anyhow::Result<()> {
+        let mut keys = EventStream::new();
+        let mut ticks = tokio::time::interval(refresh);
+        let mut tasks: FuturesUnordered<LocalBoxFuture<'a, String>> = FuturesUnordered::new();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            tokio::select! {
+                _ = ticks.tick() => self.refresh_balances().await,
+                actions = swaps.next() => match actions {
+                    Some(Ok(actions)) => self.observe(actions),
+                    Some(Err(err)) => self.status = format!("Could not observe swaps: {}", err),
+                    None => {
+                        self.status = "Swaps are no longer observed".to_string();
+                        swaps = futures_util::stream::pending().boxed();
+                    }
+                },
+                Some(status) = tasks.next(), if !tasks.is_empty() => self.status = status,
+                event = keys.next() => match event {
+                    Some(Ok(TermEvent::Key(key))) if key.kind == KeyEventKind::Press => {
+                        let quit = key.code == KeyCode::Char('c')
+                            && key.modifiers.contains(KeyModifiers::CONTROL);
+                        if quit {
+                            return Ok(());
+                        }
+                        match self.input.as_mut() {
+                            Some(input) => match key.code {
+                                KeyCode::Char(c) => input.push(c),
+                                KeyCode::Backspace => drop(input.pop()),
+                                KeyCode::Esc => self.input = None,
+                                KeyCode::Enter => {
+                                    let input = self.input.take().unwrap_or_default();
+                                    tasks.extend(self.initiate(&input));
+                                }
+                                _ => {}
+                            },
+                            None => match key.code {
+                                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                                KeyCode::Up | KeyCode::Char('k') => self.selected.select_previous(),
+                                KeyCode::Down | KeyCode::Char('j') => self.selected.select_next(),
+                                KeyCode::Char('i') => self.input = Some(String::new()),
+                                KeyCode::Char('e') => tasks.extend(self.execute()),
+                                KeyCode::Char('c') => tasks.extend(self.cancel()),
+                                KeyCode::Char('r') => self.refresh_balances().await,
+                                _ => {}
+                            },
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err.into()),
+                    None => return Ok(()),
+                },
+            }
+        }
+    }
+
+    async fn refresh_balances(&mut self) {
+        let mut balances = Vec::with_capacity(self.accounts.len());
+        for &account in self.accounts {
+            let row = match self.client.get_account(account).await {
+                Ok(info) => BalanceRow {
+                    account,
+                    currency: info.code,
+                    balance: match Money::new(info.decimals, Rounding::Down) {
+                        Ok(money) => money.from_units(info.balance).to_string(),
+                        Err(err) => format!("unavailable: {}", err),
+                    },
+                },
+                Err(err) => BalanceRow {
+                    account,
+                    currency: "-".to_string(),
+                    balance: format!("unavailable: {}", err),
+                },
+            };
+            balances.push(row);
+        }
+        self.balances = balances;
+    }
+
+    /// Updates the stage of the swaps from their FX events
+    fn observe(&mut self, actions: Vec<Action>) {
+        for action in actions {
+            let envelope = match Envelope::decode(&action.payload) {
+                Ok(envelope) => envelope,
+                Err(_) => continue,
+            };
+            let quote = match &envelope.event {
+                Event::Quote(quote) => Some(quote.clone()),
+                _ => None,
+            };
+            let step = match event_step(action.tx_id, envelope.created_at, envelope.event) {
+                Some(step) => step,
+                None => continue,
+            };
+            let position = self
+                .swaps
+                .iter()
+                .position(|swap| swap.context_id == action.context_id);
+            let swap = match position {
+                Some(position) => &mut self.swaps[position],
+                None => {
+                    self.swaps.push(SwapRow {
+                        context_id: action.context_id,
+                        stage: step.stage,
+                        details: String::new(),
+                        quote: None,
+                    });
+                    if self.selected.selected().is_none() {
+                        self.selected.select(Some(0));
+                    }
+                    self.swaps.last_mut().expect("swap was just added")
+                }
+            };
+            swap.stage = step.stage;
+            swap.details = step.details;
+            if quote.is_some() {
+                swap.quote = quote;
+            }
+        }
+    }
+
+    /// Requests a swap of `<amount>` of the currency of the first account to `<to>`, as typed
+    fn initiate(&mut self, input: &str) -> Option<LocalBoxFuture<'a, String>> {
+        let parsed = match input.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [to, amount] => AccountId::from_str(to)
+                .ok()
+                .zip(amount.parse::<Decimal>().ok()),
+            _ => None,
+        };
+        let (to, amount) = match parsed {
+            Some(parsed) => parsed,
+            None => {
+                self.status = "Expected <to account> <amount>".to_string();
+                return None;
+            }
+        };
+        let initiate = Initiate {
+            key_pair: self.key_pair.map(str::to_string),
+            from: self.accounts.first().copied(),
+            to: Some(to),
+            amount: Some(amount),
+            receive_amount: None,
+            amount_minor_units: false,
+            memo: None,
+            two_way: false,
+            compliance: None,
+            settle_at: None,
+            auto_execute: false,
+            margin: None,
+            batch: None,
+            timeout: None,
+            parallelism: 1,
+            results: None,
+        };
+        let context_id = fastrand::u64(..).to_be_bytes().to_vec();
+        let context_hex = hex::encode(&context_id);
+        self.status = format!("Requesting a quote in {}", context_hex);
+        let (client, public_key) = (self.client, self.public_key.to_vec());
+        Some(
+            async move {
+                match try_initiate(client, initiate, context_id, public_key, Output::Text).await {
+                    Ok(quote) => format!("Quoted {} at {}", context_hex, quote.rate),
+                    Err(err) => format!("Could not initiate {}: {}", context_hex, err),
+                }
+            }
+            .boxed_local(),
+        )
+    }
+
+    /// Executes the quote of the selected swap immediately
+    fn execute(&mut self) -> Option<LocalBoxFuture<'a, String>> {
+        let (context_id, quote) = self.open_quote()?;
+        if let Err(err) = check_quote(&quote, None) {
+            self.status = format!("Could not execute {}: {}", hex::encode(&context_id), err);
+            return None;
+        }
+        let context_hex = hex::encode(&context_id);
+        let execute = ExecuteQuote {
+            key_pair: self.key_pair.map(str::to_string),
+            context_id: context_hex.clone(),
+            strategy: "immediate".to_string(),
+            now: false,
+            hold: false,
+            margin: None,
+            valid_for: None,
+            slices: 4,
+            liquidity_key: None,
+            quote_id: Some(quote.quote_id),
+            amount: None,
+            amount_minor_units: false,
+            ask: false,
+            timeout: None,
+            resume: false,
+        };
+        self.status = format!("Executing {}", context_hex);
+        let (client, public_key, default_validity) =
+            (self.client, self.public_key, self.default_validity);
+        Some(
+            async move {
+                let executed = try_execute(
+                    client,
+                    execute,
+                    quote,
+                    default_validity,
+                    context_id,
+                    public_key,
+                )
+                .await;
+                match executed {
+                    Ok(tx_id) => format!("Executed {} in {}", context_hex, tx_id),
+                    Err(err) => format!("Could not execute {}: {}", context_hex, err),
+                }
+            }
+            .boxed_local(),
+        )
+    }
+
+    /// Cancels the quote of the selected swap
+    fn cancel(&mut self) -> Option<LocalBoxFuture<'a, String>> {
+        let (context_id, quote) = self.open_quote()?;
+        let context_hex = hex::encode(&context_id);
+        self.status = format!("Cancelling {}", context_hex);
+        let (client, public_key) = (self.client, self.public_key);
+        Some(
+            async move {
+                let quote_id = Some(quote.quote_id);
+                match try_cancel(client, context_id, quote_id, public_key, Output::Text).await {
+                    Ok(()) => format!("Cancelled {}", context_hex),
+                    Err(err) => format!("Could not cancel {}: {}", context_hex, err),
+                }
+            }
+            .boxed_local(),
+        )
+    }
+
+    /// Context & quote of the selected swap, if its quote is still open
+    fn open_quote(&mut self) -> Option<(Vec<u8>, Quote)> {
+        let swap = self
+            .selected
+            .selected()
+            .and_then(|selected| self.swaps.get(selected));
+        match swap {
+            Some(swap) if OPEN_STAGES.contains(&swap.stage) => swap
+                .quote
+                .clone()
+                .map(|quote| (swap.context_id.clone(), quote)),
+            Some(swap) => {
+                self.status = format!("{} is {}", hex::encode(&swap.context_id), swap.stage);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [balances_area, swaps_area, footer_area] = Layout::vertical([
+            Constraint::Length(self.accounts.len() as u16 + 3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+
+        let balances = self.balances.iter().map(|row| {
+            Row::new(vec![
+                row.account.to_string(),
+                row.currency.clone(),
+                row.balance.clone(),
+            ])
+        });
+        let balances = Table::new(
+            balances,
+            [
+                Constraint::Length(34),
+                Constraint::Length(10),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(bold_row(["Account", "Currency", "Balance"]))
+        .block(Block::bordered().title(" Balances "));
+        frame.render_widget(balances, balances_area);
+
+        let open = self.count(&OPEN_STAGES);
+        let in_flight = self.count(&IN_FLIGHT_STAGES);
+        let swaps = self.swaps.iter().map(|swap| {
+            let color = match swap.stage {
+                stage if OPEN_STAGES.contains(&stage) => Color::Yellow,
+                stage if IN_FLIGHT_STAGES.contains(&stage) => Color::Cyan,
+                "completed" => Color::Green,
+                _ => Color::DarkGray,
+            };
+            Row::new(vec![
+                hex::encode(&swap.context_id),
+                swap.stage.to_string(),
+                swap.details.clone(),
+            ])
+            .style(Style::default().fg(color))
+        });
+        let swaps = Table::new(
+            swaps,
+            [
+                Constraint::Length(18),
+                Constraint::Length(18),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(bold_row(["Context", "Stage", "Details"]))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title(format!(
+            " Swaps: {} open quotes, {} in flight ",
+            open, in_flight
+        )));
+        frame.render_stateful_widget(swaps, swaps_area, &mut self.selected);
+
+        let footer = match &self.input {
+            Some(input) => Paragraph::new(format!("{}_", input)).block(
+                Block::bordered()
+                    .title(" Initiate: <to account> <amount>, Enter to request, Esc to abort "),
+            ),
+            None => Paragraph::new(self.status.as_str()).block(
+                Block::bordered()
+                    .title(" i initiate, e execute, c cancel, r refresh, ↑↓ select, q quit "),
+            ),
+        };
+        frame.render_widget(footer, footer_area);
+    }
+
+    fn count(&self, stages: &[&str]) -> usize {
+        self.swaps
+            .iter()
+            .filter(|swap| stages.contains(&swap.stage))
+            .count()
+    }
+}
+
+fn bold_row<'a>(cells: [&'a str; 3]) -> Row<'a> {
+    Row::new(cells).style(Style::default().add_modifier(Modifier::BOLD))
+}
@@ -0,0 +1,207 @@
+//! Guided `config init`, writing the config of the service from the currencies of the ledger & the accounts of `setup`
+
+use super::{
+    generate_key, ledger_currencies, liquidity_config, rate_for, service_config, setup_accounts,
+    InitConfig, Output, LIQUIDITY_KEY,
+};
+use crate::error::Failure;
+use m10_sdk::account::AccountId;
+use m10_sdk::client::M10Client;
+use m10_sdk::{Ed25519, Signer};
+use rust_decimal::Decimal;
+use serde_json::json;
+use service::config::{self, CurrencyCode, LiquidityConfig};
+use service::secret::SecretSource;
+use std::collections::HashMap;
+use std::io::Write;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// Questions of the wizard, asked on the standard error so that the standard output stays parseable
+struct Prompt {
+    /// Whether every question takes its default answer without being asked
+    yes: bool,
+}
+
+impl Prompt {
+    /// Answer to `question`, `default` when left empty
+    fn ask(&self, question: &str, default: &str) -> anyhow::Result<String> {
+        if self.yes {
+            return Ok(default.to_string());
+        }
+        match default.is_empty() {
+            true => eprint!("{}: ", question),
+            false => eprint!("{} [{}]: ", question, default),
+        }
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        match answer.trim() {
+            "" => Ok(default.to_string()),
+            answer => Ok(answer.to_string()),
+        }
+    }
+
+    /// Answer to `question`, asked again until `parse` accepts it
+    fn ask_with<T>(
+        &self,
+        question: &str,
+        default: &str,
+        parse: impl Fn(&str) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        loop {
+            let answer = self.ask(question, default)?;
+            match parse(&answer) {
+                Ok(value) => return Ok(value),
+                Err(err) if self.yes => return Err(err),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+    }
+
+    /// Yes or no answer to `question`
+    fn confirm(&self, question: &str, default: bool) -> anyhow::Result<bool> {
+        let default = match default {
+            true => "y",
+            false => "n",
+        };
+        self.ask_with(question, default, |answer| {
+            match answer.to_lowercase().as_str() {
+                "y" | "yes" => Ok(true),
+                "n" | "no" => Ok(false),
+                _ => Err(anyhow::anyhow!("Expected y or n, got {}", answer)),
+            }
+        })
+    }
+}
+
+/// Asks for the currencies to provide liquidity for among those of the ledger, the liquidity key pair, generated if
+/// missing, its account & the base rate of each currency, then writes the config to `init.out` once validated
+pub async fn run(
+    client: &M10Client<Ed25519>,
+    signer: &Ed25519,
+    init: InitConfig,
+    url: &str,
+    output: Output,
+) -> anyhow::Result<()> {
+    let prompt = Prompt { yes: init.yes };
+    if init.out.exists() && !prompt.confirm(&format!("Overwrite {}?", init.out.display()), false)? {
+        return Err(Failure::Conflict(format!("{} already exists", init.out.display())).into());
+    }
+
+    // Currencies are discovered from the root accounts of the ledger
+    let available = ledger_currencies(client)
+        .await?
+        .into_iter()
+        .map(|account| account.code.to_lowercase())
+        .collect::<Vec<_>>();
+    if available.is_empty() {
+        return Err(Failure::NotFound(format!("No currencies found on {}", url)).into());
+    }
+    info!(currencies = %available.join(","), %url, "Found currencies");
+    let select = |currencies: &str| -> anyhow::Result<Vec<String>> {
+        let selected = currencies
+            .split(',')
+            .map(|currency| currency.trim().to_lowercase())
+            .filter(|currency| !currency.is_empty())
+            .collect::<Vec<_>>();
+        match selected
+            .iter()
+            .find(|currency| !available.contains(currency))
+        {
+            Some(currency) => Err(Failure::Usage(format!(
+                "Currency {} is not on the ledger, expected some of {}",
+                currency,
+                available.join(",")
+            ))
+            .into()),
+            None if selected.is_empty() => {
+                Err(Failure::Usage("Expected at least one currency".to_string()).into())
+            }
+            None => Ok(selected),
+        }
+    };
+    let currencies = match init.currencies.is_empty() {
+        true => prompt.ask_with(
+            "Currencies to provide liquidity for",
+            &available.join(","),
+            select,
+        )?,
+        false => select(&init.currencies.join(","))?,
+    };
+
+    // The liquidity key pair is located, or else generated
+    let key_pair = match init.liquidity_key {
+        Some(key_pair) => key_pair,
+        None => prompt.ask_with("Liquidity key pair", LIQUIDITY_KEY, SecretSource::from_str)?,
+    };
+    let liquidity_key = match &key_pair {
+        SecretSource::File(path) if !path.exists() => {
+            let question = format!("Generate a new key pair at {}?", path.display());
+            if !prompt.confirm(&question, true)? {
+                return Err(Failure::NotFound(format!("No key pair at {}", path.display())).into());
+            }
+            generate_key(&path.to_string_lossy(), output)?
+        }
+        key_pair => {
+            let liquidity_key = Ed25519::from_pkcs8(&key_pair.resolve().await?)?;
+            info!(%key_pair, public_key = %hex::encode(liquidity_key.public_key()), "Found key pair");
+            liquidity_key
+        }
+    };
+
+    // Accounts created by `setup` for the key pair are found from their roles, the others are asked for
+    let owner = liquidity_key.public_key().to_vec();
+    let existing = setup_accounts(client, signer, std::slice::from_ref(&owner)).await?;
+    let mut liquidity: HashMap<CurrencyCode, LiquidityConfig> = HashMap::new();
+    for currency in currencies {
+        let name = currency.to_uppercase();
+        let account = match existing.get(&(owner.clone(), currency.clone())) {
+            Some(&account) => {
+                info!(currency = %name, %account, "Found liquidity account");
+                account
+            }
+            None => {
+                let question = format!("Liquidity account in {}, empty to skip", name);
+                let account = prompt.ask_with(&question, "", |account| match account {
+                    "" => Ok(None),
+                    account => Ok(Some(AccountId::from_str(account)?)),
+                })?;
+                match account {
+                    Some(account) => account,
+                    None => {
+                        warn!(currency = %name, "No liquidity account, create one with `setup`");
+                        continue;
+                    }
+                }
+            }
+        };
+        let base_rate = prompt.ask_with(
+            &format!("Base rate of {}", name),
+            &rate_for(&currency).to_string(),
+            |rate| Ok(Decimal::from_str(rate)?),
+        )?;
+        liquidity.insert(name, liquidity_config(account, base_rate, key_pair.clone()));
+    }
+    if liquidity.is_empty() {
+        return Err(Failure::NotFound("No liquidity account to configure".to_string()).into());
+    }
+
+    // Validated as by the service on startup, & loaded back once written
+    let currencies = {
+        let mut currencies = liquidity.keys().cloned().collect::<Vec<_>>();
+        currencies.sort();
+        currencies
+    };
+    let new_config = service_config(url, liquidity);
+    new_config.validate()?;
+    std::fs::write(&init.out, new_config.to_toml()?)?;
+    config::parse(Some(&init.out), None)?;
+    info!(path = %init.out.display(), currencies = %currencies.join(","), "Wrote config");
+    output.emit(json!({
+        "config": init.out,
+        "status": "written",
+        "currencies": currencies,
+    }));
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use crate::approvals::Approvals;
+use crate::pool::SwapPool;
+use crate::rates::RateProvider;
+use crate::store::SwapStore;
+use crate::LedgerDB;
+use hyper::header::AUTHORIZATION;
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use ring::constant_time::verify_slices_are_equal;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use service::event::Side;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Inventory & open exposure of a currency
+#[derive(Serialize)]
+struct Position {
+    /// Settled inventory held in the liquidity account
+    inventory: u64,
+    /// Net amount of the swaps quoted or being settled, to receive when positive & to deliver when negative
+    open_exposure: Decimal,
+    max_exposure: Option<Decimal>,
+}
+
+/// Serves the positions of the service, as JSON on `/positions` & in the Prometheus format on `/metrics`
+/// along with the swaps being settled & queued & the health of the rate sources.
+/// Requests pending approval are listed on `/approvals` & decided on `/approvals/{context_id}/approve` or `/reject`,
+/// with the bearer `token` if set, or else from a loopback address.
+/// `/quote?from=<currency>&to=<currency>&amount=<amount>&side=<sell|buy>` prices a swap without publishing a request
+pub async fn serve(
+    address: SocketAddr,
+    db: LedgerDB,
+    store: SwapStore,
+    pool: SwapPool,
+    rates: Arc<dyn RateProvider>,
+    approvals: Approvals,
+    token: Option<String>,
+) -> anyhow::Result<()> {
+    let token = Arc::new(token);
+    let service = make_service_fn(move |conn: &AddrStream| {
+        let (db, store, pool, rates) = (db.clone(), store.clone(), pool.clone(), rates.clone());
+        let (approvals, token) = (approvals.clone(), token.clone());
+        let remote = conn.remote_addr();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let authorized = authorized(&request, remote, token.as_deref());
+                handle(
+                    request,
+                    authorized,
+                    db.clone(),
+                    store.clone(),
+                    pool.clone(),
+                    rates.clone(),
+                    approvals.clone(),
+                )
+            }))
+        }
+    });
+    info!(%address, "Serving admin API");
+    Server::try_bind(&address)?.serve(service).await?;
+    Ok(())
+}
+
+/// Whether `request` from `remote` may decide on requests pending approval
+fn authorized(request: &Request<Body>, remote: SocketAddr, token: Option<&str>) -> bool {
+    let token = match token {
+        Some(token) => token,
+        None => return remote.ip().is_loopback(),
+    };
+    request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .is_some_and(|bearer| verify_slices_are_equal(bearer.as_bytes(), token.as_bytes()).is_ok())
+}
+
+async fn handle(
+    request: Request<Body>,
+    authorized: bool,
+    db: LedgerDB,
+    store: SwapStore,
+    pool: SwapPool,
+    rates: Arc<dyn RateProvider>,
+    approvals: Approvals,
+) -> Result<Response<Body>, Infallible> {
+    let body = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/positions") => positions(&db, &store)
+            .await
+            .and_then(|positions| Ok(serde_json::to_string(&positions)?)),
+        (&Method::GET, "/metrics") => positions(&db, &store)
+            .await
+            .map(|positions| metrics(&positions, &store, &pool, rates.as_ref(), &approvals)),
+        // Pending requests carry the compliance information of their parties
+        (_, path) if path.starts_with("/approvals") && !authorized => {
+            info!(%path, "Unauthorized approvals request");
+            return Ok(status(StatusCode::UNAUTHORIZED));
+        }
+        (&Method::GET, "/approvals") => {
+            serde_json::to_string(&approvals.pending()).map_err(anyhow::Error::from)
+        }
+        (&Method::GET, "/quote") => {
+            let (from, to, side, amount) = match request.uri().query().and_then(quote_params) {
+                Some(params) if db.contains_key(&params.0) && db.contains_key(&params.1) => params,
+                _ => return Ok(status(StatusCode::BAD_REQUEST)),
+            };
+            match db[&from].indicative_quote(&db, &to, side, amount).await {
+                Ok(quote) => serde_json::to_string(&quote).map_err(anyhow::Error::from),
+                Err(err) => {
+                    info!(%from, %to, %err, "Could not price indicative quote");
+                    return Ok(status(StatusCode::BAD_REQUEST));
+                }
+            }
+        }
+        (&Method::POST, _) if !authorized => {
+            info!(path = %request.uri().path(), "Unauthorized decision");
+            return Ok(status(StatusCode::UNAUTHORIZED));
+        }
+        (&Method::POST, path) => match decision(path) {
+            Some((context_id, approve)) if approvals.decide(&context_id, approve) => {
+                info!(context_id = %hex::encode(&context_id), approve, "Request decided");
+                Ok(String::new())
+            }
+            _ => return Ok(status(StatusCode::NOT_FOUND)),
+        },
+        _ => return Ok(status(StatusCode::NOT_FOUND)),
+    };
+    Ok(match body {
+        Ok(body) => Response::new(Body::from(body)),
+        Err(err) => {
+            error!(%err, "Could not serve admin request");
+            status(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    })
+}
+
+/// Context & decision of an `/approvals/{context_id}/approve` or `/approvals/{context_id}/reject` path
+fn decision(path: &str) -> Option<(Vec<u8>, bool)> {
+    let (context_id, decision) = path.strip_prefix("/approvals/")?.split_once('/')?;
+    let approve = match decision {
+        "approve" => true,
+        "reject" => false,
+        _ => return None,
+    };
+    Some((hex::decode(context_id).ok()?, approve))
+}
+
+/// Currencies, side & amount of a `/quote` query, selling `amount` unless `side=buy`
+fn quote_params(query: &str) -> Option<(String, String, Side, Decimal)> {
+    let params = query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .collect::<BTreeMap<_, _>>();
+    let side = match params.get("side").copied().unwrap_or("sell") {
+        "sell" => Side::SellExact,
+        "buy" => Side::BuyExact,
+        _ => return None,
+    };
+    Some((
+        params.get("from")?.to_lowercase(),
+        params.get("to")?.to_lowercase(),
+        side,
+        params.get("amount")?.parse().ok()?,
+    ))
+}
+
+fn status(code: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = code;
+    response
+}
+
+async fn positions(db: &LedgerDB, store: &SwapStore) -> anyhow::Result<BTreeMap<String, Position>> {
+    let exposure = store.exposure(&[]);
+    let mut positions = BTreeMap::new();
+    for (currency, ledger) in db.iter() {
+        positions.insert(
+            currency.clone(),
+            Position {
+                inventory: ledger.balance().await?,
+                open_exposure: exposure.get(currency).copied().unwrap_or_default(),
+                max_exposure: ledger.max_exposure(),
+            },
+        );
+    }
+    Ok(positions)
+}
+
+fn metrics(
+    positions: &BTreeMap<String, Position>,
+    store: &SwapStore,
+    pool: &SwapPool,
+    rates: &dyn RateProvider,
+    approvals: &Approvals,
+) -> String {
+    let mut metrics = String::new();
+    let _ = writeln!(metrics, "fx_contexts_tracked {}", store.tracked());
+    let _ = writeln!(
+        metrics,
+        "fx_approvals_pending {}",
+        approvals.pending().len()
+    );
+    let _ = writeln!(metrics, "fx_swaps_running {}", pool.running());
+    let _ = writeln!(metrics, "fx_swaps_queued {}", pool.queued());
+    for (source, health) in rates.health() {
+        let _ = writeln!(
+            metrics,
+            "fx_rate_source_up{{source=\"{}\"}} {}",
+            source, health.up as u8
+        );
+        let _ = writeln!(
+            metrics,
+            "fx_rate_source_errors{{source=\"{}\"}} {}",
+            source, health.errors
+        );
+        let _ = writeln!(
+            metrics,
+            "fx_rate_source_outliers{{source=\"{}\"}} {}",
+            source, health.outliers
+        );
+    }
+    for (currency, position) in positions {
+        let _ = writeln!(
+            metrics,
+            "fx_inventory{{currency=\"{}\"}} {}",
+            currency, position.inventory
+        );
+        let _ = writeln!(
+            metrics,
+            "fx_open_exposure{{currency=\"{}\"}} {}",
+            currency, position.open_exposure
+        );
+    }
+    metrics
+}
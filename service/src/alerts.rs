@@ -0,0 +1,58 @@
+use serde::Serialize;
+use service::config::AlertConfig;
+use tracing::{error, info, warn};
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// The liquidity balance dropped below its minimum
+    LowLiquidity,
+    /// The liquidity balance was topped up above its minimum
+    LiquidityRestored,
+}
+
+/// Alert posted to the webhook
+#[derive(Serialize, Debug)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub currency: String,
+    pub account: String,
+    pub balance: u64,
+    pub min_balance: String,
+}
+
+/// Notifies operators of liquidity running low
+#[derive(Clone)]
+pub struct Alerter {
+    webhook: Option<String>,
+    client: reqwest::Client,
+}
+
+impl Alerter {
+    pub fn new(config: &AlertConfig) -> Self {
+        Self {
+            webhook: config.webhook.clone(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Logs `alert` & posts it to the webhook, if configured
+    pub async fn fire(&self, alert: Alert) {
+        match alert.kind {
+            AlertKind::LowLiquidity => warn!(?alert, "Liquidity below minimum balance"),
+            AlertKind::LiquidityRestored => info!(?alert, "Liquidity restored"),
+        }
+        if let Some(webhook) = &self.webhook {
+            let result = self
+                .client
+                .post(webhook)
+                .json(&alert)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(err) = result {
+                error!(%err, "Could not post alert");
+            }
+        }
+    }
+}
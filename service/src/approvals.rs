@@ -0,0 +1,68 @@
+use crate::store::ContextId;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use service::event::Request;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Request parked until an operator approves it
+#[derive(Serialize, Debug, Clone)]
+pub struct PendingApproval {
+    pub context_id: String,
+    pub currency: String,
+    pub request: Request,
+    /// Notional of the request, in base amount
+    pub notional: Decimal,
+    /// Why the request needs approval
+    pub reason: String,
+    /// [EPOCH TIME] The request is rejected after this time
+    pub expires_at: u64,
+}
+
+/// Parked request & the sender of the operator's decision on it
+type Parked = (PendingApproval, oneshot::Sender<bool>);
+
+/// Requests waiting for an operator's decision
+#[derive(Clone, Default)]
+pub struct Approvals {
+    pending: Arc<Mutex<HashMap<ContextId, Parked>>>,
+}
+
+impl Approvals {
+    /// Parks a request, returning the receiver of the operator's decision
+    pub fn park(&self, context_id: &[u8], approval: PendingApproval) -> oneshot::Receiver<bool> {
+        let (decide, decision) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(context_id.to_vec(), (approval, decide));
+        decision
+    }
+
+    /// Requests waiting for a decision, oldest expiry first
+    pub fn pending(&self) -> Vec<PendingApproval> {
+        let mut pending = self
+            .pending
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(approval, _)| approval.clone())
+            .collect::<Vec<_>>();
+        pending.sort_by_key(|approval| approval.expires_at);
+        pending
+    }
+
+    /// Approves or rejects a parked request, returns `false` if none is parked for `context_id`
+    pub fn decide(&self, context_id: &[u8], approve: bool) -> bool {
+        match self.pending.lock().unwrap().remove(context_id) {
+            Some((_, decide)) => decide.send(approve).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Drops a parked request, e.g. once it timed out
+    pub fn remove(&self, context_id: &[u8]) {
+        self.pending.lock().unwrap().remove(context_id);
+    }
+}
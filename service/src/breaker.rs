@@ -0,0 +1,81 @@
+use service::config::{BreakerConfig, CurrencyCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// State of the circuit breaker of a pair
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// The rate is fresh, the pair is quoted & settled
+    Closed,
+    /// The rate is stale, the pair is neither quoted nor settled
+    Open,
+    /// The rate is fresh again since `since`, the pair resuming once it stays fresh for the cooldown
+    HalfOpen { since: Instant },
+}
+
+/// Pauses quoting & settling the pairs whose rates went stale
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    states: Arc<Mutex<HashMap<(CurrencyCode, CurrencyCode), State>>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            states: Default::default(),
+        }
+    }
+
+    /// Updates the breaker of `from`/`to` with the `age` of its rate, failing unless the pair is quoted & settled
+    pub fn check(&self, from: &str, to: &str, age: Option<Duration>) -> anyhow::Result<()> {
+        let max_age = match self.config.max_age {
+            Some(max_age) => Duration::from_secs(max_age),
+            None => return Ok(()),
+        };
+        let stale = age.is_some_and(|age| age > max_age);
+        let mut states = self.states.lock().unwrap();
+        let state = states
+            .entry((from.to_string(), to.to_string()))
+            .or_insert(State::Closed);
+        let next = match (*state, stale) {
+            (_, true) => State::Open,
+            (State::Open, false) => State::HalfOpen {
+                since: Instant::now(),
+            },
+            (State::HalfOpen { since }, false)
+                if since.elapsed() >= Duration::from_secs(self.config.cooldown) =>
+            {
+                State::Closed
+            }
+            (state, false) => state,
+        };
+        match (*state, next) {
+            (State::Open, State::Open) => {}
+            (_, State::Open) => warn!(%from, %to, ?age, "Rate is stale, pausing pair"),
+            (State::Open, State::HalfOpen { .. }) => {
+                info!(%from, %to, cooldown = %self.config.cooldown, "Rate is fresh again, resuming pair after cooldown")
+            }
+            (State::HalfOpen { .. }, State::Closed) => info!(%from, %to, "Resumed pair"),
+            _ => {}
+        }
+        *state = next;
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        match next {
+            State::Closed => Ok(()),
+            State::Open => Err(anyhow::anyhow!(
+                "Rate of {}/{} is stale, the pair is paused",
+                from,
+                to
+            )),
+            State::HalfOpen { .. } => Err(anyhow::anyhow!(
+                "Rate of {}/{} recovered, the pair resumes shortly",
+                from,
+                to
+            )),
+        }
+    }
+}
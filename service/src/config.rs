@@ -1,38 +1,1860 @@
-use config::Environment;
+use crate::event::{Fees, IndicativeQuote, Side};
+use crate::money::MAX_DECIMALS;
+use crate::secret::{self, SecretSource};
+use config::{ConfigError, Environment, Map, Source, Value, ValueKind};
+use hyper::Uri;
+use m10_sdk::account::AccountId;
+use m10_sdk::Ed25519;
 use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Identity};
 
 pub type CurrencyCode = String;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
-    /// Ledger address, e.g. https://develop.m10.net
+    /// Ledger address, e.g. https://develop.m10.net, or a list of them failed over in order
     #[serde(default = "default_address")]
-    pub address: String,
+    pub address: Addresses,
+    /// File persisting the last transaction observed on each ledger, observations start from the latest one when unset
+    #[serde(default)]
+    pub cursors: Option<PathBuf>,
+    /// Database recording the lifecycle of swaps, e.g. `sqlite://swaps.db?mode=rwc` or `postgres://fx@localhost/fx`.
+    /// Swaps being settled are lost on restart when unset
+    #[serde(default)]
+    pub database: Option<String>,
+    /// Address the admin API listens on, e.g. `127.0.0.1:9100`, disabled when unset
+    #[serde(default)]
+    pub admin: Option<String>,
+    /// Bearer token required to list, approve or reject requests on the admin API, e.g. `file:///etc/fx/admin.token`.
+    /// Approvals are only served to loopback addresses when unset
+    #[serde(default)]
+    pub admin_token: Option<SecretSource>,
+    /// Largest deviation of the settlement rate from the quoted rate, in basis points, unchecked when unset
+    #[serde(default)]
+    pub max_slippage_bps: Option<u32>,
+    /// Quotes normally but only simulates settlements, publishing them as paper trades
+    #[serde(default)]
+    pub dry_run: bool,
     /// Liquidity config
     pub liquidity: HashMap<CurrencyCode, LiquidityConfig>,
+    /// Re-quoting of unaccepted quotes
+    #[serde(default)]
+    pub requote: RequoteConfig,
+    /// Negotiation of quoted rates through counter-offers
+    #[serde(default)]
+    pub negotiation: NegotiationConfig,
+    /// Travel-rule requirements on requests
+    #[serde(default)]
+    pub compliance: ComplianceConfig,
+    /// Refunds of source funds left unsettled
+    #[serde(default)]
+    pub refund: RefundConfig,
+    /// Draining of the swaps being settled on shutdown
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    /// Routing of pairs which aren't quoted directly
+    #[serde(default)]
+    pub routing: RoutingConfig,
+    /// Tuning of the gRPC channels to the ledgers
+    #[serde(default)]
+    pub transport: TransportConfig,
+    /// Levels, format & destination of the logs
+    #[serde(default)]
+    pub log: LogConfig,
+    /// Timing of the quotes & settlements
+    #[serde(default)]
+    pub engine: EngineConfig,
+    /// Quoting terms of each pair, keyed by `from/to` currencies, overriding those of the source currency
+    #[serde(default)]
+    pub pairs: HashMap<String, PairQuoteConfig>,
+    /// Caps on the volume settled by each account
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// Caps on the unsettled exposure to each account
+    #[serde(default)]
+    pub credit: CreditConfig,
+    /// Manual approval of large requests
+    #[serde(default)]
+    pub approval: ApprovalConfig,
+    /// Hours during which pairs are quoted & settled, at all times for pairs not listed
+    #[serde(default)]
+    pub hours: Vec<HoursConfig>,
+    /// Batched settlement of the payouts owed to the same accounts, each paid out on its own when unset
+    #[serde(default)]
+    pub netting: Option<NettingConfig>,
+    /// Reloading of the quoting parameters when the config changes
+    #[serde(default)]
+    pub reload: ReloadConfig,
+    /// Alerts on liquidity running low
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    /// Rebalancing of the liquidity inventory to its target allocation
+    #[serde(default)]
+    pub rebalance: RebalanceConfig,
+    /// Retries of ledger RPCs failing transiently
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// Bounds on the swaps settled concurrently
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+    /// HTTP feed the rates are polled from, the base rates being used when unset
+    #[serde(default)]
+    pub rates: Option<RateFeedConfig>,
+    /// WebSocket stream the rates are received from, exclusive with `rates`
+    #[serde(default)]
+    pub rate_stream: Option<RateStreamConfig>,
+    /// Rate sources aggregated into a median rate, exclusive with `rates` & `rate_stream`
+    #[serde(default)]
+    pub aggregate: Option<AggregateConfig>,
+    /// Pausing of pairs whose rates went stale
+    #[serde(default)]
+    pub breaker: BreakerConfig,
+    /// Bounds on the swap contexts tracked in memory
+    #[serde(default)]
+    pub retention: RetentionConfig,
 }
 
-fn default_address() -> String {
-    "https://develop.m10.net".to_string()
+fn default_address() -> Addresses {
+    Addresses(vec!["https://develop.m10.net".to_string()])
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransportConfig {
+    /// Seconds allowed to connect to a ledger, unbounded when unset
+    #[serde(default)]
+    pub connect_timeout: Option<u64>,
+    /// Seconds allowed for each request to a ledger
+    #[serde(default = "default_request_timeout")]
+    pub timeout: u64,
+    /// Interval in seconds of the HTTP/2 keep-alive pings, also sent while idle
+    #[serde(default = "default_keep_alive_interval")]
+    pub keep_alive_interval: u64,
+    /// Requests in flight on each channel, unbounded when unset
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    /// TLS settings of the `https` ledgers, the system roots being trusted when unset
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+/// Trust & client identity of the TLS connections, e.g. to a private ledger requiring mutual TLS
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TlsConfig {
+    /// PEM file of a CA certificate trusted alongside the system roots
+    #[serde(default)]
+    pub ca_certificate: Option<PathBuf>,
+    /// PEM file of the client certificate presented for mutual TLS, along with `key`
+    #[serde(default)]
+    pub certificate: Option<PathBuf>,
+    /// PEM file of the private key of the client certificate
+    #[serde(default)]
+    pub key: Option<PathBuf>,
+    /// Name the ledger certificates are verified against, the host of each address when unset
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+impl TlsConfig {
+    /// Client TLS settings, reading the PEM files
+    pub fn client_config(&self) -> anyhow::Result<ClientTlsConfig> {
+        let read = |path: &PathBuf| {
+            std::fs::read(path)
+                .map_err(|err| anyhow::anyhow!("Could not read {}: {}", path.display(), err))
+        };
+        let mut config = ClientTlsConfig::new();
+        if let Some(ca_certificate) = &self.ca_certificate {
+            config = config.ca_certificate(Certificate::from_pem(read(ca_certificate)?));
+        }
+        match (&self.certificate, &self.key) {
+            (Some(certificate), Some(key)) => {
+                config = config.identity(Identity::from_pem(read(certificate)?, read(key)?));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Both a client certificate & its key are required for mutual TLS"
+                ))
+            }
+        }
+        if let Some(domain) = &self.domain {
+            config = config.domain_name(domain);
+        }
+        Ok(config)
+    }
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            timeout: default_request_timeout(),
+            keep_alive_interval: default_keep_alive_interval(),
+            concurrency: None,
+            tls: None,
+        }
+    }
+}
+
+impl TransportConfig {
+    /// Endpoint of the ledger at `address`, tuned by this config
+    pub fn endpoint(&self, address: &str) -> anyhow::Result<Endpoint> {
+        let mut endpoint = Endpoint::from_shared(address.to_string())?
+            .timeout(Duration::from_secs(self.timeout))
+            .http2_keep_alive_interval(Duration::from_secs(self.keep_alive_interval))
+            .keep_alive_while_idle(true);
+        if let Some(connect_timeout) = self.connect_timeout {
+            endpoint = endpoint.connect_timeout(Duration::from_secs(connect_timeout));
+        }
+        if let Some(concurrency) = self.concurrency {
+            endpoint = endpoint.concurrency_limit(concurrency);
+        }
+        if let Some(tls) = self
+            .tls
+            .as_ref()
+            .filter(|_| endpoint.uri().scheme_str() == Some("https"))
+        {
+            endpoint = endpoint.tls_config(tls.client_config()?)?;
+        }
+        Ok(endpoint)
+    }
+}
+
+fn default_request_timeout() -> u64 {
+    30
+}
+
+fn default_keep_alive_interval() -> u64 {
+    30
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogConfig {
+    /// Level of the events logged, e.g. `info`
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Level per module, overriding `level`, e.g. `"service::ledger" = "debug"`
+    #[serde(default)]
+    pub modules: HashMap<String, String>,
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Files the logs are written to instead of the standard output
+    #[serde(default)]
+    pub file: Option<LogFileConfig>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            modules: HashMap::new(),
+            format: LogFormat::default(),
+            file: None,
+        }
+    }
+}
+
+impl LogConfig {
+    /// Filter directives of the levels, e.g. `info,service::ledger=debug`
+    pub fn directives(&self) -> String {
+        let mut modules = self.modules.iter().collect::<Vec<_>>();
+        modules.sort();
+        std::iter::once(self.level.clone())
+            .chain(
+                modules
+                    .into_iter()
+                    .map(|(module, level)| format!("{}={}", module, level)),
+            )
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// One line per event
+    #[default]
+    Text,
+    /// Multiple lines per event, for development
+    Pretty,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogFileConfig {
+    /// Directory the log files are written to
+    pub directory: PathBuf,
+    /// Prefix of the log file names, followed by the date when rotated
+    #[serde(default = "default_log_prefix")]
+    pub prefix: String,
+    /// How often a new log file is started
+    #[serde(default)]
+    pub rotation: LogRotation,
+}
+
+fn default_log_prefix() -> String {
+    "fx.log".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+/// Endpoints of a ledger in order of preference, written as a single URL, a list, or comma-separated URLs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Addresses(pub Vec<String>);
+
+impl Addresses {
+    pub fn urls(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl Display for Addresses {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join(", "))
+    }
+}
+
+impl Serialize for Addresses {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [address] => serializer.serialize_str(address),
+            addresses => addresses.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Addresses {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let addresses: Vec<String> = match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(addresses) => addresses.split(',').map(str::to_string).collect(),
+            OneOrMany::Many(addresses) => addresses,
+        };
+        let addresses: Vec<String> = addresses
+            .iter()
+            .map(|address| address.trim().to_string())
+            .filter(|address| !address.is_empty())
+            .collect();
+        if addresses.is_empty() {
+            return Err(serde::de::Error::custom("at least one address is required"));
+        }
+        Ok(Addresses(addresses))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RequoteConfig {
+    /// Interval in seconds at which pending quotes are re-evaluated
+    #[serde(default = "default_requote_interval")]
+    pub interval: u64,
+    /// Maximum number of re-quotes per request, disabled when 0
+    #[serde(default)]
+    pub max_requotes: u32,
+    /// Rate movement in basis points which triggers a re-quote before expiry
+    #[serde(default = "default_rate_tolerance_bps")]
+    pub rate_tolerance_bps: u32,
+}
+
+impl Default for RequoteConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_requote_interval(),
+            max_requotes: 0,
+            rate_tolerance_bps: default_rate_tolerance_bps(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct NegotiationConfig {
+    /// Improvement over the quoted rate accepted from a counter-offer, in basis points
+    #[serde(default)]
+    pub tolerance_bps: u32,
+    /// Maximum number of counter-offers per request, rejected beyond
+    #[serde(default = "default_max_counter_offers")]
+    pub max_counter_offers: u32,
+}
+
+impl Default for NegotiationConfig {
+    fn default() -> Self {
+        Self {
+            tolerance_bps: 0,
+            max_counter_offers: default_max_counter_offers(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct ComplianceConfig {
+    /// Notional in base amount above which requests must carry originator & beneficiary information
+    #[serde(default)]
+    pub threshold: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RefundConfig {
+    /// Interval in seconds at which unsettled executions are checked
+    #[serde(default = "default_refund_interval")]
+    pub interval: u64,
+    /// Seconds after its settlement deadline at which an unsettled execution is refunded
+    #[serde(default = "default_refund_grace")]
+    pub grace: u64,
+    /// Retries of a failed payout to the recipient before the source leg is refunded
+    #[serde(default = "default_payout_retries")]
+    pub payout_retries: u32,
+    /// Seconds before the first payout retry, doubled on each retry
+    #[serde(default = "default_payout_backoff")]
+    pub payout_backoff: u64,
+}
+
+impl Default for RefundConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_refund_interval(),
+            grace: default_refund_grace(),
+            payout_retries: default_payout_retries(),
+            payout_backoff: default_payout_backoff(),
+        }
+    }
+}
+
+fn default_payout_retries() -> u32 {
+    3
+}
+
+fn default_payout_backoff() -> u64 {
+    2
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RoutingConfig {
+    /// Currency through which pairs which aren't quoted directly are converted, e.g. USD
+    #[serde(default)]
+    pub pivot: Option<CurrencyCode>,
+    /// Pairs which are quoted, all of them when empty
+    #[serde(default)]
+    pub allowed: Vec<PairConfig>,
+}
+
+/// Currency pair quoted in one or both directions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PairConfig {
+    pub base: CurrencyCode,
+    pub quote: CurrencyCode,
+    #[serde(default)]
+    pub direction: PairDirection,
+}
+
+impl PairConfig {
+    /// Whether swaps from `from` to `to` are allowed by this pair
+    pub fn allows(&self, from: &str, to: &str) -> bool {
+        let forward = self.base.eq_ignore_ascii_case(from) && self.quote.eq_ignore_ascii_case(to);
+        let reverse = self.quote.eq_ignore_ascii_case(from) && self.base.eq_ignore_ascii_case(to);
+        match self.direction {
+            PairDirection::Both => forward || reverse,
+            PairDirection::BaseToQuote => forward,
+            PairDirection::QuoteToBase => reverse,
+        }
+    }
+}
+
+/// Quoting terms of swaps from one currency to another, each falling back to those of the source currency when unset
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PairQuoteConfig {
+    /// Whether the pair is quoted
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Spread charged on swaps of the pair, in basis points
+    #[serde(default)]
+    pub spread_bps: Option<u32>,
+    /// Smallest swap served, in the source currency
+    #[serde(default)]
+    pub min_amount: Option<Decimal>,
+    /// Largest swap served, in the source currency
+    #[serde(default)]
+    pub max_amount: Option<Decimal>,
+    /// Seconds for which quotes of the pair are valid
+    #[serde(default)]
+    pub quote_ttl: Option<u64>,
+}
+
+impl PairQuoteConfig {
+    /// Source & target currencies of a `from/to` pair key
+    pub fn currencies(pair: &str) -> Option<(CurrencyCode, CurrencyCode)> {
+        let (from, to) = pair.split_once('/')?;
+        let (from, to) = (from.trim(), to.trim());
+        if from.is_empty() || to.is_empty() {
+            return None;
+        }
+        Some((from.to_lowercase(), to.to_lowercase()))
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PairDirection {
+    #[default]
+    Both,
+    /// Swaps from the base currency to the quote currency only
+    BaseToQuote,
+    /// Swaps from the quote currency to the base currency only
+    QuoteToBase,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ShutdownConfig {
+    /// Seconds to wait for the swaps being settled before exiting
+    #[serde(default = "default_shutdown_deadline")]
+    pub deadline: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            deadline: default_shutdown_deadline(),
+        }
+    }
+}
+
+fn default_shutdown_deadline() -> u64 {
+    30
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LimitsConfig {
+    /// Notional each account can settle within the window, in base amount, uncapped when unset
+    #[serde(default)]
+    pub cap: Option<Decimal>,
+    /// Rolling window in seconds over which the settled volume is capped
+    #[serde(default = "default_limits_window")]
+    pub window: u64,
+    /// File persisting the settled volumes, kept in memory only when unset
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Caps overriding `cap`, per account ID
+    #[serde(default)]
+    pub accounts: HashMap<String, Decimal>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            cap: None,
+            window: default_limits_window(),
+            path: None,
+            accounts: HashMap::new(),
+        }
+    }
+}
+
+fn default_limits_window() -> u64 {
+    24 * 60 * 60
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CreditConfig {
+    /// Unsettled notional each account can be exposed to, in base amount, unlimited when unset
+    #[serde(default)]
+    pub limit: Option<Decimal>,
+    /// Limits overriding `limit`, per account ID
+    #[serde(default)]
+    pub accounts: HashMap<String, Decimal>,
+    /// Handling of requests which would exceed the limit
+    #[serde(default)]
+    pub on_breach: CreditBreach,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CreditBreach {
+    /// The request is rejected
+    #[default]
+    Reject,
+    /// The request awaits an operator's approval before being quoted
+    Flag,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ApprovalConfig {
+    /// Notional from which requests await an operator's approval, in base amount, none do when unset
+    #[serde(default)]
+    pub threshold: Option<Decimal>,
+    /// Seconds after which requests not approved are rejected
+    #[serde(default = "default_approval_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            threshold: None,
+            timeout: default_approval_timeout(),
+        }
+    }
+}
+
+fn default_approval_timeout() -> u64 {
+    15 * 60
+}
+
+/// Hours during which a pair is quoted & settled, in both directions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HoursConfig {
+    pub base: CurrencyCode,
+    pub quote: CurrencyCode,
+    /// IANA timezone of the hours, e.g. `America/New_York`
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Days on which the pair opens, e.g. `["mon", "tue"]`, weekdays by default
+    #[serde(default = "default_days")]
+    pub days: Vec<String>,
+    /// Local time at which the pair opens for quotes, e.g. `08:00`
+    pub open: String,
+    /// Local time at which the pair closes for quotes, the next day when not after `open`
+    pub close: String,
+    /// Window during which executions are settled, the same as quotes when unset
+    #[serde(default)]
+    pub settlement: Option<WindowConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WindowConfig {
+    /// Local time at which the window opens, e.g. `09:00`
+    pub open: String,
+    /// Local time at which the window closes, the next day when not after `open`
+    pub close: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct NettingConfig {
+    /// Seconds over which payouts are accumulated before being settled together
+    #[serde(default = "default_netting_window")]
+    pub window: u64,
+}
+
+fn default_netting_window() -> u64 {
+    60
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ReloadConfig {
+    /// Interval in seconds at which the config file is checked for changes
+    #[serde(default = "default_reload_interval")]
+    pub interval: u64,
+}
+
+impl Default for ReloadConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_reload_interval(),
+        }
+    }
+}
+
+fn default_reload_interval() -> u64 {
+    10
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct EngineConfig {
+    /// Interval in seconds at which the rates watched by limit executions are looked up, when not pushed
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: u64,
+    /// Seconds until the deadline of limit executions or over which TWAP slices are settled, unless set by the initiator
+    #[serde(default = "default_validity")]
+    pub default_validity: u64,
+    /// Seconds for which quotes are valid, unless set for their currency or pair
+    #[serde(default = "default_quote_ttl")]
+    pub quote_ttl: u64,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: default_poll_interval(),
+            default_validity: default_validity(),
+            quote_ttl: default_quote_ttl(),
+        }
+    }
+}
+
+fn default_poll_interval() -> u64 {
+    10
+}
+
+fn default_validity() -> u64 {
+    300
+}
+
+fn default_quote_ttl() -> u64 {
+    60
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_days() -> Vec<String> {
+    ["mon", "tue", "wed", "thu", "fri"]
+        .iter()
+        .map(|day| day.to_string())
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertConfig {
+    /// Interval in seconds at which the liquidity balances are checked
+    #[serde(default = "default_alert_interval")]
+    pub interval: u64,
+    /// URL alerts are posted to as JSON, only logged when unset
+    #[serde(default)]
+    pub webhook: Option<String>,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_alert_interval(),
+            webhook: None,
+        }
+    }
+}
+
+fn default_alert_interval() -> u64 {
+    60
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RebalanceConfig {
+    /// Interval in seconds at which the inventory is rebalanced
+    #[serde(default = "default_rebalance_interval")]
+    pub interval: u64,
+    /// Percentage points a currency can drift from its target allocation before being rebalanced
+    #[serde(default = "default_rebalance_tolerance")]
+    pub tolerance: Decimal,
+    /// Only reports the transfers rebalancing would make
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl Default for RebalanceConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_rebalance_interval(),
+            tolerance: default_rebalance_tolerance(),
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RateFeedConfig {
+    /// JSON endpoint polled, e.g. https://api.exchangerate.host/latest?base=USD
+    pub url: String,
+    /// Name of the source in logs & metrics, its URL by default
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Symbol the rates of the feed are quoted against, e.g. USD
+    pub base: String,
+    /// JSON pointer to the object of rates by symbol
+    #[serde(default = "default_rates_pointer")]
+    pub pointer: String,
+    /// Interval in seconds at which the feed is polled
+    #[serde(default = "default_rates_interval")]
+    pub interval: u64,
+    /// Seconds after which rates which couldn't be refreshed are no longer served, served regardless when unset
+    #[serde(default)]
+    pub max_age: Option<u64>,
+    /// Symbol of each currency in the feed, its code in uppercase by default
+    #[serde(default)]
+    pub symbols: HashMap<CurrencyCode, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RateStreamConfig {
+    /// WebSocket endpoint streaming rates, e.g. wss://stream.example.com/rates
+    pub url: String,
+    /// Name of the source in logs & metrics, its URL by default
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Symbol the streamed rates are quoted against, e.g. USD
+    pub base: String,
+    /// Message sent once connected, e.g. to subscribe to the symbols streamed
+    #[serde(default)]
+    pub subscribe: Option<String>,
+    /// JSON pointer to the object of rates by symbol in each message
+    #[serde(default = "default_rates_pointer")]
+    pub pointer: String,
+    /// Seconds after which rates which weren't updated are no longer served, served regardless when unset
+    #[serde(default)]
+    pub max_age: Option<u64>,
+    /// Symbol of each currency in the stream, its code in uppercase by default
+    #[serde(default)]
+    pub symbols: HashMap<CurrencyCode, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct BreakerConfig {
+    /// Seconds after which the rate of a pair which wasn't updated is stale, pairs never being paused when unset
+    #[serde(default)]
+    pub max_age: Option<u64>,
+    /// Seconds the rate of a paused pair must stay fresh before it is resumed
+    #[serde(default = "default_breaker_cooldown")]
+    pub cooldown: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            max_age: None,
+            cooldown: default_breaker_cooldown(),
+        }
+    }
+}
+
+fn default_breaker_cooldown() -> u64 {
+    30
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AggregateConfig {
+    /// Basis points a source can deviate from the median of all sources before being discarded
+    #[serde(default = "default_outlier_bps")]
+    pub tolerance_bps: u32,
+    /// Sources left once outliers are discarded required to serve a rate
+    #[serde(default = "default_min_sources")]
+    pub min_sources: usize,
+    /// Includes the base rates of the liquidity providers as a source
+    #[serde(default)]
+    pub base_rates: bool,
+    /// HTTP feeds polled
+    #[serde(default)]
+    pub feeds: Vec<RateFeedConfig>,
+    /// WebSocket streams received
+    #[serde(default)]
+    pub streams: Vec<RateStreamConfig>,
+}
+
+fn default_outlier_bps() -> u32 {
+    100
+}
+
+fn default_min_sources() -> usize {
+    1
+}
+
+fn default_rates_pointer() -> String {
+    "/rates".to_string()
+}
+
+fn default_rates_interval() -> u64 {
+    60
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ConcurrencyConfig {
+    /// Swaps settled at once
+    #[serde(default = "default_max_swaps")]
+    pub max_swaps: usize,
+    /// Accepted executions waiting for a swap to complete, further executions being rejected
+    #[serde(default = "default_max_queued")]
+    pub max_queued: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_swaps: default_max_swaps(),
+            max_queued: default_max_queued(),
+        }
+    }
+}
+
+fn default_max_swaps() -> usize {
+    64
+}
+
+fn default_max_queued() -> usize {
+    256
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RetentionConfig {
+    /// Interval in seconds at which stale contexts are collected
+    #[serde(default = "default_retention_interval")]
+    pub interval: u64,
+    /// Seconds after their last activity at which contexts no longer settling are forgotten
+    #[serde(default = "default_retention_ttl")]
+    pub ttl: u64,
+    /// Contexts tracked at most, the least recently active being forgotten beyond it
+    #[serde(default = "default_max_contexts")]
+    pub max_contexts: usize,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_retention_interval(),
+            ttl: default_retention_ttl(),
+            max_contexts: default_max_contexts(),
+        }
+    }
+}
+
+fn default_retention_interval() -> u64 {
+    300
+}
+
+fn default_retention_ttl() -> u64 {
+    3600
+}
+
+fn default_max_contexts() -> usize {
+    100_000
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryConfig {
+    /// Attempts made of each transfer, action & account lookup, retries being disabled when 1
+    #[serde(default = "default_retry_attempts")]
+    pub max_attempts: u32,
+    /// Milliseconds before the first retry, doubled on each retry
+    #[serde(default = "default_retry_backoff")]
+    pub backoff: u64,
+    /// gRPC status codes retried, in snake case
+    #[serde(default = "default_retry_codes")]
+    pub codes: Vec<String>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_attempts(),
+            backoff: default_retry_backoff(),
+            codes: default_retry_codes(),
+        }
+    }
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+fn default_retry_backoff() -> u64 {
+    500
+}
+
+fn default_retry_codes() -> Vec<String> {
+    [
+        "unavailable",
+        "deadline_exceeded",
+        "resource_exhausted",
+        "aborted",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+fn default_rebalance_interval() -> u64 {
+    60 * 60
+}
+
+fn default_rebalance_tolerance() -> Decimal {
+    Decimal::from(5)
+}
+
+fn default_refund_interval() -> u64 {
+    60
+}
+
+fn default_refund_grace() -> u64 {
+    300
+}
+
+fn default_max_counter_offers() -> u32 {
+    3
+}
+
+fn default_requote_interval() -> u64 {
+    15
+}
+
+fn default_rate_tolerance_bps() -> u32 {
+    50
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LiquidityConfig {
     /// Account ID of the liquidity provider for that currency
-    pub account: String,
+    #[serde(with = "account_id")]
+    pub account: AccountId,
     /// Currency value in base amount (~ USD)
     pub base_rate: Decimal,
-    /// Liquidity key pair
-    pub key_pair: PathBuf,
+    /// Liquidity key pair, as a PKCS8 document referenced by a path, `env://` or `vault://` URI
+    pub key_pair: SecretSource,
+    /// Address of the ledger holding this currency, or a list of them, defaults to the top-level `address`
+    #[serde(default)]
+    pub address: Option<Addresses>,
+    /// Digits after the decimal point of the ledger amounts of this currency, looked up on the ledger when unset
+    #[serde(default)]
+    pub decimals: Option<u32>,
+    /// Rounding of amounts finer than the smallest unit of this currency
+    #[serde(default)]
+    pub rounding: Rounding,
+    /// Flat fee charged per swap from this currency
+    #[serde(default)]
+    pub flat_fee: Decimal,
+    /// Spread charged on swaps from this currency, in basis points
+    #[serde(default)]
+    pub spread_bps: u32,
+    /// Spread charged at the ask of two-way quotes from this currency, defaults to `spread_bps`
+    #[serde(default)]
+    pub ask_spread_bps: Option<u32>,
+    /// Smallest swap served from this currency, in that currency
+    #[serde(default)]
+    pub min_amount: Option<Decimal>,
+    /// Largest swap served from this currency, in that currency
+    #[serde(default)]
+    pub max_amount: Option<Decimal>,
+    /// Balance of the liquidity account below which alerts are fired, in that currency
+    #[serde(default)]
+    pub min_balance: Option<Decimal>,
+    /// Target share of the liquidity inventory value held in this currency, relative to the other allocations
+    #[serde(default)]
+    pub allocation: Option<Decimal>,
+    /// Account ID funding & absorbing the rebalancing of this currency, required with `allocation`
+    #[serde(default, with = "account_id::option")]
+    pub reserve: Option<AccountId>,
+    /// Net open exposure allowed in this currency, in that currency, uncapped when unset
+    #[serde(default)]
+    pub max_exposure: Option<Decimal>,
+    /// Account ID collecting the fees charged on swaps from this currency, kept in the liquidity account when unset
+    #[serde(default, with = "account_id::option")]
+    pub fee_account: Option<AccountId>,
+    /// Further account IDs sharding the liquidity inventory of this currency alongside `account`
+    #[serde(default, with = "account_id::vec")]
+    pub accounts: Vec<AccountId>,
+    /// How the account receiving a swap is selected among the liquidity accounts
+    #[serde(default)]
+    pub selection: Selection,
+    /// Target currencies quoted directly from this currency, all of them when unset
+    #[serde(default)]
+    pub pairs: Option<Vec<CurrencyCode>>,
+    /// Spread charged on swaps from this currency to a given target currency, in basis points, overriding `spread_bps`
+    #[serde(default)]
+    pub pair_spread_bps: HashMap<CurrencyCode, u32>,
+    /// Seconds for which quotes from this currency are valid, the `engine` quote TTL when unset
+    #[serde(default)]
+    pub quote_ttl: Option<u64>,
+    /// Spread tiers by notional amount, per target currency
+    #[serde(default)]
+    pub tiers: HashMap<CurrencyCode, Vec<TierConfig>>,
+    /// Spread adjustment from the inventory of this currency
+    #[serde(default)]
+    pub skew: Option<SkewConfig>,
+    /// Transaction the observations of this currency start from on startup, instead of their persisted cursors
+    #[serde(default)]
+    pub start_from: Option<Checkpoint>,
+}
+
+/// Position of the ledger an observation starts from: `earliest`, `latest` or a transaction ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Checkpoint {
+    /// First transaction of the ledger, replaying its whole history
+    Earliest,
+    /// Next transaction of the ledger, skipping everything before it
+    Latest,
+    TxId(u64),
+}
+
+impl FromStr for Checkpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(checkpoint: &str) -> Result<Self, Self::Err> {
+        match checkpoint {
+            "earliest" => Ok(Checkpoint::Earliest),
+            "latest" => Ok(Checkpoint::Latest),
+            tx_id => tx_id.parse().map(Checkpoint::TxId).map_err(|_| {
+                anyhow::anyhow!(
+                    "Invalid checkpoint {}, expected earliest, latest or a transaction ID",
+                    checkpoint
+                )
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for Checkpoint {
+    type Error = anyhow::Error;
+
+    fn try_from(checkpoint: String) -> Result<Self, Self::Error> {
+        checkpoint.parse()
+    }
+}
+
+impl From<Checkpoint> for String {
+    fn from(checkpoint: Checkpoint) -> Self {
+        checkpoint.to_string()
+    }
+}
+
+impl Display for Checkpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Checkpoint::Earliest => write!(f, "earliest"),
+            Checkpoint::Latest => write!(f, "latest"),
+            Checkpoint::TxId(tx_id) => write!(f, "{}", tx_id),
+        }
+    }
+}
+
+impl Config {
+    /// Prices a swap from the base rates & quoting terms of the config alone, without the rate sources,
+    /// pivot currencies & inventory skew the service also quotes with
+    pub fn indicative_quote(
+        &self,
+        from: &str,
+        to: &str,
+        side: Side,
+        amount: Decimal,
+    ) -> anyhow::Result<IndicativeQuote> {
+        let liquidity = |currency: &str| {
+            self.liquidity
+                .iter()
+                .find(|(code, _)| code.eq_ignore_ascii_case(currency))
+                .map(|(_, liquidity)| liquidity)
+                .ok_or_else(|| anyhow::anyhow!("{} is not configured", currency.to_uppercase()))
+        };
+        let (source, target) = (liquidity(from)?, liquidity(to)?);
+        let (from, to) = (from.to_lowercase(), to.to_lowercase());
+        let terms = self
+            .pairs
+            .iter()
+            .find(|(pair, _)| PairQuoteConfig::currencies(pair) == Some((from.clone(), to.clone())))
+            .map(|(_, terms)| terms);
+        if terms.is_some_and(|terms| !terms.enabled) {
+            return Err(anyhow::anyhow!("Pair {}/{} is disabled", from, to));
+        }
+        let spread_bps = source
+            .pair_spread_bps
+            .iter()
+            .find(|(currency, _)| currency.eq_ignore_ascii_case(&to))
+            .map(|(_, spread_bps)| *spread_bps);
+        let tier = source
+            .tiers
+            .iter()
+            .filter(|(currency, _)| currency.eq_ignore_ascii_case(&to))
+            .flat_map(|(_, tiers)| tiers)
+            .filter(|tier| tier.min_amount <= amount)
+            .max_by_key(|tier| tier.min_amount);
+        let fees = Fees {
+            flat_fee: source.flat_fee,
+            // The tier of the amount overrides the spread of the pair
+            spread_bps: match tier {
+                Some(tier) => tier.spread_bps,
+                None => terms
+                    .and_then(|terms| terms.spread_bps)
+                    .or(spread_bps)
+                    .unwrap_or(source.spread_bps),
+            },
+            currency: from.to_uppercase(),
+        };
+        let rate = target.base_rate / source.base_rate;
+        let (source_amount, target_amount) = fees.convert(side, amount, rate)?;
+        Ok(IndicativeQuote {
+            route: vec![from, to],
+            rate,
+            fees,
+            source_amount,
+            target_amount,
+        })
+    }
+}
+
+/// Rounding of an amount to the smallest unit of its currency
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// Towards zero, in favour of the liquidity provider
+    #[default]
+    Down,
+    /// Away from zero
+    Up,
+    /// To the nearest unit, halves away from zero
+    HalfUp,
+    /// To the nearest unit, halves to the even unit
+    HalfEven,
+    /// No rounding, amounts finer than the smallest unit are rejected
+    Exact,
+}
+
+/// Selection of the liquidity account receiving a swap, the largest balance always paying out
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    /// Each account in turn
+    #[default]
+    RoundRobin,
+    /// The account holding the largest balance
+    LargestBalance,
+    /// The account first selected for each counterparty
+    Sticky,
+}
+
+/// Quotes tighter to sell a currency the liquidity provider is long, & wider to buy more of it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct SkewConfig {
+    /// Inventory at which no skew applies, in that currency
+    pub target: Decimal,
+    /// Spread adjustment in basis points when the inventory is empty or twice the target, proportional in between
+    pub max_bps: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TierConfig {
+    /// Requested amount from which the tier applies, up to the next tier
+    pub min_amount: Decimal,
+    /// Spread charged within the tier, in basis points
+    pub spread_bps: u32,
 }
 
-pub fn parse() -> Result<Config, config::ConfigError> {
-    let config = config::Config::builder()
-        .add_source(config::File::from(Path::new("./config.toml")))
-        .add_source(Environment::with_prefix("APP"))
-        .build()?;
-    config.try_deserialize()
+/// Account IDs written as hex strings, also read from their integer representation
+pub mod account_id {
+    use m10_sdk::account::AccountId;
+    use serde::de::{self, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::fmt::Formatter;
+
+    pub fn serialize<S: Serializer>(account: &AccountId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(account.to_be_bytes()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AccountId, D::Error> {
+        deserializer.deserialize_any(AccountIdVisitor)
+    }
+
+    struct AccountIdVisitor;
+
+    impl<'de> Visitor<'de> for AccountIdVisitor {
+        type Value = AccountId;
+
+        fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "an account ID as a hex string or an integer")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            // Hex strings are 32 characters long, other strings of digits being the integer representation
+            if value.len() != 32 && value.bytes().all(|byte| byte.is_ascii_digit()) {
+                let integer = value
+                    .parse::<u128>()
+                    .map_err(|err| E::custom(format!("invalid account ID {}: {}", value, err)))?;
+                return self.visit_u128(integer);
+            }
+            let bytes = hex::decode(value)
+                .map_err(|err| E::custom(format!("account ID {} is not hex: {}", value, err)))?;
+            AccountId::try_from_be_slice(&bytes)
+                .map_err(|err| E::custom(format!("invalid account ID {}: {}", value, err)))
+        }
+
+        fn visit_u128<E: de::Error>(self, value: u128) -> Result<Self::Value, E> {
+            AccountId::try_from_be_slice(&value.to_be_bytes())
+                .map_err(|err| E::custom(format!("invalid account ID {}: {}", value, err)))
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+            self.visit_u128(value.into())
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+            let value = u64::try_from(value)
+                .map_err(|_| E::custom(format!("invalid account ID {}", value)))?;
+            self.visit_u64(value)
+        }
+    }
+
+    /// Account ID nested in a collection
+    #[derive(Serialize, Deserialize)]
+    #[serde(transparent)]
+    struct Hex(#[serde(with = "super::account_id")] AccountId);
+
+    pub mod option {
+        use super::Hex;
+        use m10_sdk::account::AccountId;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            account: &Option<AccountId>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            account.map(Hex).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<AccountId>, D::Error> {
+            Ok(Option::<Hex>::deserialize(deserializer)?.map(|Hex(account)| account))
+        }
+    }
+
+    pub mod vec {
+        use super::Hex;
+        use m10_sdk::account::AccountId;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            accounts: &[AccountId],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(accounts.iter().copied().map(Hex))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<AccountId>, D::Error> {
+            Ok(Vec::<Hex>::deserialize(deserializer)?
+                .into_iter()
+                .map(|Hex(account)| account)
+                .collect())
+        }
+    }
+}
+
+/// File the config is loaded from when no other is given, if it exists
+pub const CONFIG_PATH: &str = "./config.toml";
+
+/// Problems found in a config, all reported at once
+#[derive(Debug, Clone)]
+pub struct InvalidConfig {
+    pub problems: Vec<String>,
+}
+
+impl Display for InvalidConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid config, {} problem(s) found:",
+            self.problems.len()
+        )?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
+
+impl Config {
+    /// TOML of the config, as read back by `parse`. Plain values are written before the tables they'd otherwise
+    /// follow, which TOML doesn't allow
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(&toml::Value::try_from(self)?)
+    }
+
+    /// Checks the ledger addresses, liquidity keys, accounts & base rates before anything connects
+    pub fn validate(&self) -> Result<(), InvalidConfig> {
+        let mut problems = Vec::new();
+        check_address("address", &self.address, &mut problems);
+        if let Some(Err(err)) = self.transport.tls.as_ref().map(TlsConfig::client_config) {
+            problems.push(format!("transport.tls is invalid: {}", err));
+        }
+        let mut currencies = HashSet::new();
+        for (currency, liquidity) in &self.liquidity {
+            let name = currency.to_uppercase();
+            if !currencies.insert(currency.to_lowercase()) {
+                problems.push(format!("Currency {} is configured more than once", name));
+            }
+            if let Some(address) = &liquidity.address {
+                check_address(
+                    &format!("liquidity.{}.address", name),
+                    address,
+                    &mut problems,
+                );
+            }
+            if liquidity.base_rate <= Decimal::ZERO {
+                problems.push(format!(
+                    "liquidity.{}.base_rate must be positive, got {}",
+                    name, liquidity.base_rate
+                ));
+            }
+            if let Some(decimals) = liquidity
+                .decimals
+                .filter(|&decimals| decimals > MAX_DECIMALS)
+            {
+                problems.push(format!(
+                    "liquidity.{}.decimals must be at most {}, got {}",
+                    name, MAX_DECIMALS, decimals
+                ));
+            }
+            // Without the recorded lifecycles, replayed executions would be refunded although settled
+            if let Some(checkpoint @ (Checkpoint::Earliest | Checkpoint::TxId(_))) =
+                liquidity.start_from
+            {
+                if self.database.is_none() {
+                    problems.push(format!(
+                        "liquidity.{}.start_from {} replays past executions, which requires a database",
+                        name, checkpoint
+                    ));
+                }
+            }
+            // Remote secrets are only fetched when the service starts
+            let key_pair = match liquidity.key_pair.is_remote() {
+                true => secret::vault_env().map(drop),
+                false => liquidity
+                    .key_pair
+                    .read()
+                    .and_then(|pkcs8| Ok(Ed25519::from_pkcs8(&pkcs8).map(drop)?)),
+            };
+            if let Err(err) = key_pair {
+                problems.push(format!(
+                    "liquidity.{}.key_pair {} could not be loaded: {}",
+                    name, liquidity.key_pair, err
+                ));
+            }
+        }
+        if let Some(token) = &self.admin_token {
+            let read = match token.is_remote() {
+                true => secret::vault_env().map(drop),
+                false => token.read().map(drop),
+            };
+            if let Err(err) = read {
+                problems.push(format!(
+                    "admin_token {} could not be loaded: {}",
+                    token, err
+                ));
+            }
+        }
+        // Each ticks a timer, which can't tick without a period
+        let feeds = self
+            .rates
+            .iter()
+            .map(|feed| ("rates.interval", feed.interval))
+            .chain(self.aggregate.iter().flat_map(|aggregate| {
+                aggregate
+                    .feeds
+                    .iter()
+                    .map(|feed| ("aggregate.feeds.interval", feed.interval))
+            }));
+        let intervals = [
+            ("requote.interval", self.requote.interval),
+            ("refund.interval", self.refund.interval),
+            ("reload.interval", self.reload.interval),
+            ("engine.poll_interval", self.engine.poll_interval),
+            ("alerts.interval", self.alerts.interval),
+            ("rebalance.interval", self.rebalance.interval),
+            ("retention.interval", self.retention.interval),
+        ]
+        .into_iter()
+        .chain(
+            self.netting
+                .map(|netting| ("netting.window", netting.window)),
+        )
+        .chain(feeds);
+        for (field, interval) in intervals {
+            if interval == 0 {
+                problems.push(format!("{} must be at least a second", field));
+            }
+        }
+        for account in self.limits.accounts.keys() {
+            check_account("limits.accounts", account, &mut problems);
+        }
+        for account in self.credit.accounts.keys() {
+            check_account("credit.accounts", account, &mut problems);
+        }
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(InvalidConfig { problems }),
+        }
+    }
+}
+
+fn check_address(field: &str, addresses: &Addresses, problems: &mut Vec<String>) {
+    for address in addresses.urls() {
+        match address.parse::<Uri>() {
+            Ok(uri)
+                if matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some() => {}
+            Ok(_) => problems.push(format!(
+                "{} {} must be an http(s) URL with a host",
+                field, address
+            )),
+            Err(err) => problems.push(format!("{} {} is not a valid URL: {}", field, address, err)),
+        }
+    }
+}
+
+fn check_account(field: &str, account: &str, problems: &mut Vec<String>) {
+    let decoded = hex::decode(account)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| Ok(AccountId::try_from_be_slice(&bytes)?));
+    if let Err(err) = decoded {
+        problems.push(format!(
+            "{} {} is not a valid account ID: {}",
+            field, account, err
+        ));
+    }
+}
+
+/// File the config is loaded from, `path` or else `./config.toml`
+pub fn file(path: Option<&Path>) -> &Path {
+    path.unwrap_or_else(|| Path::new(CONFIG_PATH))
+}
+
+/// Loads the config from the file at `path`, which must exist, or else from `./config.toml` if any,
+/// overlaid with its `profiles.<profile>` table when a profile is selected.
+/// `APP_` environment variables take precedence over the file, nested keys being separated by `__`,
+/// e.g. `APP_LIQUIDITY__USD__ACCOUNT`, so that the service can run from the environment alone
+pub fn parse(path: Option<&Path>, profile: Option<&str>) -> Result<Config, ConfigError> {
+    load(path, profile)?.try_deserialize()
+}
+
+/// Loads a single section of the config, e.g. `transport`, defaulting when there's none
+pub fn parse_section<T: DeserializeOwned + Default>(
+    path: Option<&Path>,
+    profile: Option<&str>,
+    name: &str,
+) -> Result<T, ConfigError> {
+    match load(path, profile)?.get(name) {
+        Err(ConfigError::NotFound(_)) => Ok(T::default()),
+        section => section,
+    }
+}
+
+/// Whether the config file at `path`, or else `./config.toml` if any, has a `profiles.<profile>` table
+pub fn declares_profile(path: Option<&Path>, profile: &str) -> Result<bool, ConfigError> {
+    let file = config::File::from(file(path)).required(path.is_some());
+    match config::Config::builder()
+        .add_source(file)
+        .build()?
+        .get_table("profiles")
+    {
+        Ok(profiles) => Ok(profiles.contains_key(profile)),
+        Err(ConfigError::NotFound(_)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+fn load(path: Option<&Path>, profile: Option<&str>) -> Result<config::Config, ConfigError> {
+    let file = config::File::from(file(path)).required(path.is_some());
+    config::Config::builder()
+        .add_source(Profile {
+            file: config::Config::builder().add_source(file).build()?,
+            name: profile.map(str::to_string),
+        })
+        .add_source(
+            Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__"),
+        )
+        .build()
+}
+
+/// Config file overlaid with one of its `profiles` when selected, e.g. `[profiles.prod]`, its keys lowercased as those
+/// of the environment variables are, so that `APP_LIQUIDITY__USD__ACCOUNT` overrides `[liquidity.USD]`
+#[derive(Debug, Clone)]
+struct Profile {
+    file: config::Config,
+    name: Option<String>,
+}
+
+impl Source for Profile {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+        let mut config = self.file.collect()?;
+        let mut profiles = match config.remove("profiles") {
+            Some(profiles) => profiles.into_table()?,
+            None => Map::new(),
+        };
+        let mut config = lowercase(config);
+        if let Some(name) = &self.name {
+            let overlay = profiles
+                .remove(name)
+                .ok_or_else(|| ConfigError::Message(format!("Unknown profile {}", name)))?;
+            merge(&mut config, lowercase(overlay.into_table()?));
+        }
+        Ok(config)
+    }
+}
+
+/// `table` with the keys of its nested tables lowercased
+fn lowercase(table: Map<String, Value>) -> Map<String, Value> {
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let kind = match value.kind {
+                ValueKind::Table(table) => ValueKind::Table(lowercase(table)),
+                kind => kind,
+            };
+            (key.to_lowercase(), Value::new(None, kind))
+        })
+        .collect()
+}
+
+/// Overlays `overlay` onto `base`, merging the tables present in both & replacing other values
+fn merge(base: &mut Map<String, Value>, overlay: Map<String, Value>) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value.kind) {
+            (
+                Some(Value {
+                    kind: ValueKind::Table(table),
+                    ..
+                }),
+                ValueKind::Table(overlay),
+            ) => merge(table, overlay),
+            (_, kind) => {
+                base.insert(key, Value::new(None, kind));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Config as written by `setup`, with a liquidity account per currency
+    const SETUP: &str = r#"
+address = "https://develop.m10.net"
+cursors = "./cursors.json"
+
+[liquidity.USD]
+account = "00000000004a00000000000000000001"
+base_rate = "1"
+key_pair = "./liquidity.pkcs8"
+
+[liquidity.EUR]
+account = "04000000003200000000000000000001"
+base_rate = "1.1"
+key_pair = "./liquidity.pkcs8"
+"#;
+
+    /// Serializes the tests reading `APP_` environment variables
+    static ENV: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// Writes `contents` to a config file unique to the test `name`
+    fn config_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("fx-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn account(id: &str) -> AccountId {
+        AccountId::try_from_be_slice(&hex::decode(id).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn setup_config_round_trips() {
+        let config: Config = toml::from_str(SETUP).unwrap();
+        let written = config.to_toml().unwrap();
+        assert!(written.contains(r#"account = "00000000004a00000000000000000001""#));
+
+        let parsed: Config = toml::from_str(&written).unwrap();
+        assert_eq!(parsed.address.urls(), config.address.urls());
+        assert_eq!(parsed.liquidity.len(), 2);
+        for (currency, id) in [
+            ("USD", "00000000004a00000000000000000001"),
+            ("EUR", "04000000003200000000000000000001"),
+        ] {
+            let liquidity = &parsed.liquidity[currency];
+            assert_eq!(liquidity.account, account(id));
+            assert_eq!(liquidity.base_rate, config.liquidity[currency].base_rate);
+            assert_eq!(liquidity.key_pair, config.liquidity[currency].key_pair);
+        }
+    }
+
+    #[test]
+    fn account_ids_parse_from_integers() {
+        let id = account("00000000004a00000000000000000001");
+        let integer = u128::from_be_bytes(id.to_be_bytes()).to_string();
+        // Too large for a TOML integer, so written as a string of digits
+        let legacy = SETUP.replace(
+            r#""00000000004a00000000000000000001""#,
+            &format!(r#""{}""#, integer),
+        );
+        let config: Config = toml::from_str(&legacy).unwrap();
+        assert_eq!(config.liquidity["USD"].account, id);
+
+        let written = config.to_toml().unwrap();
+        assert!(written.contains(r#"account = "00000000004a00000000000000000001""#));
+    }
+
+    #[test]
+    fn invalid_account_ids_are_rejected() {
+        for invalid in [r#""not hex""#, r#""00004a00000000000000000001""#, "-1"] {
+            let config = SETUP.replace(r#""00000000004a00000000000000000001""#, invalid);
+            assert!(toml::from_str::<Config>(&config).is_err(), "{}", invalid);
+        }
+    }
+
+    /// Problems found validating `config`
+    fn problems(config: &Config) -> Vec<String> {
+        config
+            .validate()
+            .err()
+            .map(|invalid| invalid.problems)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn replaying_requires_a_database() {
+        let mut config: Config = toml::from_str(SETUP).unwrap();
+        let replays = |config: &Config| {
+            problems(config)
+                .iter()
+                .any(|problem| problem.contains("requires a database"))
+        };
+        for checkpoint in [Checkpoint::Earliest, Checkpoint::TxId(1024)] {
+            config.liquidity.get_mut("USD").unwrap().start_from = Some(checkpoint);
+            assert!(replays(&config), "{}", checkpoint);
+        }
+        config.liquidity.get_mut("USD").unwrap().start_from = Some(Checkpoint::Latest);
+        assert!(!replays(&config));
+
+        config.liquidity.get_mut("USD").unwrap().start_from = Some(Checkpoint::Earliest);
+        config.database = Some("sqlite://fx.db".to_string());
+        assert!(!replays(&config));
+    }
+
+    #[test]
+    fn zero_intervals_are_rejected() {
+        let mut config: Config = toml::from_str(SETUP).unwrap();
+        config.refund.interval = 0;
+        config.requote.interval = 0;
+        config.reload.interval = 0;
+        config.engine.poll_interval = 0;
+        config.retention.interval = 0;
+        config.netting = Some(NettingConfig { window: 0 });
+        let problems = problems(&config);
+        for field in [
+            "refund.interval",
+            "requote.interval",
+            "reload.interval",
+            "engine.poll_interval",
+            "retention.interval",
+            "netting.window",
+        ] {
+            let problem = format!("{} must be at least a second", field);
+            assert!(problems.contains(&problem), "{}", field);
+        }
+    }
+
+    #[test]
+    fn parses_file_only() {
+        let _env = ENV.lock().unwrap_or_else(|err| err.into_inner());
+        let path = config_file("file-only", SETUP);
+        let config = parse(Some(&path), None).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.address.urls(), vec!["https://develop.m10.net"]);
+        assert_eq!(config.cursors, Some(PathBuf::from("./cursors.json")));
+        assert_eq!(
+            config.liquidity["usd"].account,
+            account("00000000004a00000000000000000001")
+        );
+        assert_eq!(config.liquidity["eur"].base_rate, Decimal::new(11, 1));
+    }
+
+    #[test]
+    fn parses_env_only() {
+        let _env = ENV.lock().unwrap_or_else(|err| err.into_inner());
+        let vars = [
+            ("APP_ADDRESS", "https://play.m10.net"),
+            (
+                "APP_LIQUIDITY__USD__ACCOUNT",
+                "00000000004a00000000000000000001",
+            ),
+            ("APP_LIQUIDITY__USD__BASE_RATE", "1"),
+            ("APP_LIQUIDITY__USD__KEY_PAIR", "env://LIQUIDITY_KEY"),
+        ];
+        for (var, value) in vars {
+            std::env::set_var(var, value);
+        }
+        let config = parse(None, None);
+        for (var, _) in vars {
+            std::env::remove_var(var);
+        }
+        let config = config.unwrap();
+
+        assert_eq!(config.address.urls(), vec!["https://play.m10.net"]);
+        assert_eq!(config.liquidity.len(), 1);
+        let liquidity = &config.liquidity["usd"];
+        assert_eq!(
+            liquidity.account,
+            account("00000000004a00000000000000000001")
+        );
+        assert_eq!(liquidity.base_rate, Decimal::ONE);
+        assert_eq!(
+            liquidity.key_pair,
+            SecretSource::Env("LIQUIDITY_KEY".to_string())
+        );
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        let _env = ENV.lock().unwrap_or_else(|err| err.into_inner());
+        let path = config_file("env-overrides-file", SETUP);
+        let vars = [
+            (
+                "APP_LIQUIDITY__USD__ACCOUNT",
+                "00000000004900000000000000000001",
+            ),
+            ("APP_LIQUIDITY__EUR__BASE_RATE", "1.2"),
+        ];
+        for (var, value) in vars {
+            std::env::set_var(var, value);
+        }
+        let config = parse(Some(&path), None);
+        for (var, _) in vars {
+            std::env::remove_var(var);
+        }
+        std::fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+
+        // Overridden keys are replaced, the rest of their table being kept from the file
+        assert_eq!(config.liquidity.len(), 2);
+        let usd = &config.liquidity["usd"];
+        assert_eq!(usd.account, account("00000000004900000000000000000001"));
+        assert_eq!(usd.base_rate, Decimal::ONE);
+        assert_eq!(
+            usd.key_pair,
+            SecretSource::File(PathBuf::from("./liquidity.pkcs8"))
+        );
+        let eur = &config.liquidity["eur"];
+        assert_eq!(eur.account, account("04000000003200000000000000000001"));
+        assert_eq!(eur.base_rate, Decimal::new(12, 1));
+    }
 }
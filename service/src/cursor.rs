@@ -0,0 +1,71 @@
+use m10_sdk::TxId;
+use service::config::Checkpoint;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Last transaction processed by each observation, persisted to resume from after a restart
+#[derive(Clone, Default)]
+pub struct Cursors {
+    /// File the cursors are persisted to, kept in memory only when unset
+    path: Option<PathBuf>,
+    positions: Arc<Mutex<HashMap<String, TxId>>>,
+}
+
+impl Cursors {
+    /// Loads the cursors persisted at `path`, if any
+    pub fn load(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let positions = match &path {
+            Some(path) if path.exists() => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+            _ => HashMap::new(),
+        };
+        Ok(Self {
+            path,
+            positions: Arc::new(Mutex::new(positions)),
+        })
+    }
+
+    /// Transaction from which `stream` resumes, if it was observed before
+    pub fn resume_from(&self, stream: &str) -> Option<TxId> {
+        self.positions
+            .lock()
+            .unwrap()
+            .get(stream)
+            .map(|tx_id| tx_id + 1)
+    }
+
+    /// Records `tx_id` as processed by `stream`
+    pub fn advance(&self, stream: &str, tx_id: TxId) -> anyhow::Result<()> {
+        let mut positions = self.positions.lock().unwrap();
+        let position = positions.entry(stream.to_string()).or_default();
+        if tx_id <= *position {
+            return Ok(());
+        }
+        *position = tx_id;
+        self.persist(&positions)
+    }
+
+    /// Moves `stream` to `checkpoint`, wherever it was before
+    pub fn rewind(&self, stream: &str, checkpoint: Checkpoint) -> anyhow::Result<()> {
+        let mut positions = self.positions.lock().unwrap();
+        match checkpoint {
+            Checkpoint::Earliest => positions.insert(stream.to_string(), 0),
+            // Observations without a cursor start from the next transaction
+            Checkpoint::Latest => positions.remove(stream),
+            Checkpoint::TxId(tx_id) => {
+                positions.insert(stream.to_string(), tx_id.saturating_sub(1))
+            }
+        };
+        self.persist(&positions)
+    }
+
+    fn persist(&self, positions: &HashMap<String, TxId>) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            // Replace the file atomically, so a crash never leaves it truncated
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec(positions)?)?;
+            std::fs::rename(tmp, path)?;
+        }
+        Ok(())
+    }
+}
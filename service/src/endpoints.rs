@@ -0,0 +1,127 @@
+use m10_sdk::account::AccountId;
+use m10_sdk::client::Channel;
+use m10_sdk::error::M10Error;
+use m10_sdk::{Ed25519, M10Client};
+use service::config::TransportConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Mutex;
+use tonic::transport::Endpoint;
+use tonic::Code;
+use tower::discover::Change;
+use tracing::{info, warn};
+
+/// Time within which an endpoint must answer its health check
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Endpoints of a ledger, requests going to the active one only & failing over to the next reachable one
+#[derive(Clone)]
+pub struct Endpoints {
+    /// Each endpoint & a client checking its health
+    endpoints: Arc<Vec<(Endpoint, M10Client<Ed25519>)>>,
+    /// Index of the active endpoint, locked while failing over
+    active: Arc<Mutex<usize>>,
+    /// Changes to the endpoints the ledger channel is balanced over
+    changes: Sender<Change<usize, Endpoint>>,
+}
+
+impl Endpoints {
+    /// Returns the endpoints & the channel sending requests to the active one, the first one until connected
+    pub fn new(
+        addresses: &[String],
+        transport: &TransportConfig,
+        key_pair: &[u8],
+    ) -> anyhow::Result<(Self, Channel)> {
+        let mut endpoints = Vec::new();
+        for address in addresses {
+            let endpoint = transport.endpoint(address)?;
+            let probe = M10Client::new(Ed25519::from_pkcs8(key_pair)?, endpoint.connect_lazy()?);
+            endpoints.push((endpoint, probe));
+        }
+        let first = endpoints
+            .first()
+            .map(|(endpoint, _)| endpoint.clone())
+            .ok_or_else(|| anyhow::anyhow!("A ledger needs at least one address"))?;
+        let (channel, changes) = Channel::balance_channel(endpoints.len() * 2);
+        changes
+            .try_send(Change::Insert(0, first))
+            .map_err(|_| anyhow::anyhow!("Could not activate the ledger endpoint"))?;
+        Ok((
+            Self {
+                endpoints: Arc::new(endpoints),
+                active: Default::default(),
+                changes,
+            },
+            channel,
+        ))
+    }
+
+    /// Activates the first reachable endpoint, in order, keeping the first one if none is
+    pub async fn connect(&self, liquidity: AccountId) {
+        if self.endpoints.len() < 2 {
+            return;
+        }
+        let mut active = self.active.lock().await;
+        for index in 0..self.endpoints.len() {
+            if self.is_reachable(index, liquidity).await {
+                self.activate(&mut active, index).await;
+                return;
+            }
+            warn!(address = %self.address(index), "Ledger endpoint unreachable");
+        }
+    }
+
+    /// Fails over to the next reachable endpoint, in order, if the active one is unreachable
+    pub async fn fail_over(&self, liquidity: AccountId) {
+        if self.endpoints.len() < 2 {
+            return;
+        }
+        let mut active = self.active.lock().await;
+        if self.is_reachable(*active, liquidity).await {
+            return;
+        }
+        warn!(address = %self.address(*active), "Active ledger endpoint unreachable");
+        for offset in 1..self.endpoints.len() {
+            let index = (*active + offset) % self.endpoints.len();
+            if self.is_reachable(index, liquidity).await {
+                self.activate(&mut active, index).await;
+                return;
+            }
+        }
+        warn!("No reachable ledger endpoint to fail over to");
+    }
+
+    async fn activate(&self, active: &mut usize, index: usize) {
+        if *active == index {
+            return;
+        }
+        let endpoint = self.endpoints[index].0.clone();
+        let changes = [Change::Remove(*active), Change::Insert(index, endpoint)];
+        for change in changes {
+            if self.changes.send(change).await.is_err() {
+                warn!("Ledger channel closed, could not switch endpoint");
+                return;
+            }
+        }
+        info!(from = %self.address(*active), to = %self.address(index), "Failed over to ledger endpoint");
+        *active = index;
+    }
+
+    /// Whether the endpoint answers at all, even with an error
+    async fn is_reachable(&self, index: usize, liquidity: AccountId) -> bool {
+        let probe = &self.endpoints[index].1;
+        match tokio::time::timeout(PROBE_TIMEOUT, probe.get_account_info(liquidity)).await {
+            Ok(Err(M10Error::Status(status))) => !matches!(
+                status.code(),
+                Code::Unavailable | Code::DeadlineExceeded | Code::Unknown
+            ),
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+
+    fn address(&self, index: usize) -> String {
+        self.endpoints[index].0.uri().to_string()
+    }
+}
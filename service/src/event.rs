@@ -1,47 +1,960 @@
 use m10_sdk::account::AccountId;
+use m10_sdk::sdk::metadata::Memo;
+use m10_sdk::Signer;
+use prost::Message;
+use ring::signature::{UnparsedPublicKey, ED25519};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
 
+mod proto;
+mod v1;
+
+pub use proto::{ComplianceMetadata, FxSwapMetadata};
+
+/// Current version of the event schema
+pub const EVENT_VERSION: u32 = 2;
+
+/// Largest event payload accepted from other participants
+pub const MAX_PAYLOAD_SIZE: usize = 16 * 1024;
+
+/// Longest memo accepted on a request
+pub const MAX_MEMO_LEN: usize = 256;
+
+/// Largest amount accepted in a request (1,000,000,000,000,000), so that pricing it can't overflow
+pub const MAX_AMOUNT: Decimal = Decimal::from_parts(2_764_472_320, 232_830, 0, false, 0);
+
+/// Largest number of slices of a TWAP execution
+pub const MAX_TWAP_SLICES: u32 = 100;
+
+/// Lowest FX rate accepted in a quote (0.000001)
+pub const MIN_RATE: Decimal = Decimal::from_parts(1, 0, 0, false, 6);
+
+/// Highest FX rate accepted in a quote
+pub const MAX_RATE: Decimal = Decimal::from_parts(1_000_000, 0, 0, false, 0);
+
+/// Versioned wrapper of a published event, identifying its publisher & ordering
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Envelope {
+    pub version: u32,
+    /// [EPOCH TIME] Milliseconds at which the event was published
+    #[serde(default)]
+    pub created_at: u64,
+    /// Per-context sequence number of the publishing party, starting at 1
+    #[serde(default)]
+    pub sequence: u64,
+    /// Public key of the publishing party
+    #[serde(default)]
+    pub public_key: Vec<u8>,
+    pub event: Event,
+}
+
+impl Envelope {
+    pub fn new(event: Event, sequence: u64, public_key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            version: EVENT_VERSION,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            sequence,
+            public_key: public_key.into(),
+            event,
+        }
+    }
+
+    /// Serializes the envelope as protobuf in the current schema version
+    pub fn encode(&self) -> Vec<u8> {
+        FxSwapMetadata::from(self).encode_to_vec()
+    }
+
+    /// Deserializes an envelope published in any supported schema version
+    pub fn decode(payload: &[u8]) -> anyhow::Result<Self> {
+        if payload.len() > MAX_PAYLOAD_SIZE {
+            return Err(anyhow::anyhow!(
+                "Payload of {} bytes exceeds the maximum of {} bytes",
+                payload.len(),
+                MAX_PAYLOAD_SIZE
+            ));
+        }
+        if payload.starts_with(b"{") {
+            // Contexts created before the protobuf encoding
+            return Ok(Self::decode_json(payload)?);
+        }
+        proto::decode(payload)
+    }
+
+    fn decode_json(payload: &[u8]) -> serde_json::Result<Self> {
+        let value = serde_json::from_slice::<serde_json::Value>(payload)?;
+        let version = match value.get("version") {
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| serde::de::Error::custom("invalid event version"))?,
+            // Unversioned payloads predate the envelope
+            None => 1,
+        };
+        match version {
+            1 => Ok(Self {
+                version: 1,
+                created_at: 0,
+                sequence: 0,
+                public_key: Vec::new(),
+                event: serde_json::from_value::<v1::Event>(value)?.into(),
+            }),
+            2 => serde_json::from_value::<Envelope>(value),
+            version => Err(serde::de::Error::custom(format!(
+                "unsupported event version {}",
+                version
+            ))),
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Event {
     Request(Request),
     Quote(Quote),
     Execute(Execute),
-    Completed,
+    /// The swap was settled, or only simulated by a paper trade
+    Completed {
+        #[serde(default)]
+        paper_trade: bool,
+    },
+    /// Only part of the swap could be settled, the remainder was refunded
+    PartialFill(PartialFill),
+    /// The liquidity provider declined to serve the request
+    Reject {
+        reason: String,
+        /// Amounts served, when the requested amount was outside of them
+        #[serde(default)]
+        allowed: Option<AmountRange>,
+        /// [EPOCH TIME] Time at which the pair is next quoted, when requested outside of its hours
+        #[serde(default)]
+        reopens_at: Option<u64>,
+    },
+    /// The initiator aborted the swap, any later execution is ignored
+    Cancel,
+    /// Signed summary of a settled swap
+    Receipt(Receipt),
+    /// The swap could not be settled
+    Failed {
+        code: FailureCode,
+        message: String,
+    },
+    /// The initiator proposes a different rate for a quote
+    CounterOffer(CounterOffer),
+    /// Asks the liquidity provider for the current state of the swap
+    StatusRequest,
+    /// Current state of the swap, in response to a `StatusRequest`
+    StatusResponse(SwapStatus),
+    /// Source funds were returned to the initiator without being settled
+    Refunded(Refund),
+    /// The payout was settled by a batched transfer, netted with the others owed to the recipient
+    Netted(NettingReport),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl Event {
+    /// Deserializes the event of an envelope, discarding its header
+    pub fn decode(payload: &[u8]) -> anyhow::Result<Self> {
+        Ok(Envelope::decode(payload)?.event)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureCode {
+    /// No FX rate could be determined for the currency pair
+    RateUnavailable,
+    /// The liquidity provider can't cover any part of the swap
+    InsufficientLiquidity,
+    /// The quote expired before it was executed
+    Expired,
+    /// A settlement transfer was rejected by the ledger
+    TransferError,
+    /// The rate at settlement moved too far from the quoted rate
+    Slippage,
+}
+
+impl Display for FailureCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+/// Travel-rule information on the parties to a cross-currency transfer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Compliance {
+    pub originator: Party,
+    pub beneficiary: Party,
+}
+
+impl Compliance {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.originator.validate("Originator")?;
+        self.beneficiary.validate("Beneficiary")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Party {
+    /// Full name of the natural or legal person
+    pub name: String,
+    /// Postal address
+    #[serde(default)]
+    pub address: Option<String>,
+    /// ISO 3166 country code
+    #[serde(default)]
+    pub country: Option<String>,
+    /// Identifier of the person, e.g. a national ID or LEI
+    #[serde(default)]
+    pub identifier: Option<String>,
+}
+
+impl Party {
+    fn validate(&self, role: &str) -> anyhow::Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("{} name is required", role));
+        }
+        let fields = [&self.address, &self.country, &self.identifier];
+        if self.name.len() > MAX_MEMO_LEN
+            || fields
+                .iter()
+                .any(|field| field.as_ref().map_or(0, String::len) > MAX_MEMO_LEN)
+        {
+            return Err(anyhow::anyhow!(
+                "{} information exceeds the maximum of {} bytes per field",
+                role,
+                MAX_MEMO_LEN
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Request {
     pub from: AccountId,
     pub to: AccountId,
+    /// Amount in the source currency when selling, or the target currency when buying
     pub amount: Decimal,
+    #[serde(default)]
+    pub side: Side,
+    /// Free-text reference, e.g. an invoice number, attached to the settlement transfers
+    #[serde(default)]
+    pub memo: Option<String>,
+    /// Requests both bid & ask rates, the direction being chosen at execution
+    #[serde(default)]
+    pub two_way: bool,
+    /// Originator & beneficiary information, attached to the settlement transfers
+    #[serde(default)]
+    pub compliance: Option<Compliance>,
+    /// [EPOCH TIME] Settlement of a forward swap is deferred to this time, at the quoted rate
+    #[serde(default)]
+    pub settle_at: Option<u64>,
+}
+
+impl Request {
+    /// Checks the request is well-formed, independently of the ledger state
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.amount <= Decimal::ZERO {
+            return Err(anyhow::anyhow!("Amount must be positive"));
+        }
+        if self.amount > MAX_AMOUNT {
+            return Err(anyhow::anyhow!(
+                "Amount exceeds the maximum of {}",
+                MAX_AMOUNT
+            ));
+        }
+        if self.from == self.to {
+            return Err(anyhow::anyhow!("Source and target accounts must differ"));
+        }
+        if self.memo.as_ref().map_or(0, String::len) > MAX_MEMO_LEN {
+            return Err(anyhow::anyhow!(
+                "Memo exceeds the maximum of {} bytes",
+                MAX_MEMO_LEN
+            ));
+        }
+        if let Some(compliance) = &self.compliance {
+            compliance.validate()?;
+        }
+        if let Some(settle_at) = self.settle_at {
+            if UNIX_EPOCH + Duration::from_secs(settle_at) <= SystemTime::now() {
+                return Err(anyhow::anyhow!("Settlement date must be in the future"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Transfer metadata carrying the memo, if any
+    pub fn memo_metadata(&self) -> Option<Memo> {
+        self.memo.clone().map(|plaintext| Memo { plaintext })
+    }
+
+    /// Transfer metadata carrying the originator & beneficiary information, if any
+    pub fn compliance_metadata(&self) -> Option<ComplianceMetadata> {
+        self.compliance.as_ref().map(ComplianceMetadata::from)
+    }
+
+    /// Same request in the opposite direction, from the target to the source account
+    pub fn reversed(&self) -> Self {
+        Self {
+            from: self.to,
+            to: self.from,
+            side: match self.side {
+                Side::SellExact => Side::BuyExact,
+                Side::BuyExact => Side::SellExact,
+            },
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// Sell the source currency for the target currency at the bid
+    #[default]
+    Bid,
+    /// Buy the source currency with the target currency at the ask
+    Ask,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Side {
+    /// Sell exactly `amount` of the source currency
+    #[default]
+    SellExact,
+    /// Buy exactly `amount` of the target currency
+    BuyExact,
+}
+
+/// Pricing of a swap previewed without publishing a request or a quote
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndicativeQuote {
+    /// Currencies the swap would convert through
+    pub route: Vec<String>,
+    /// Rate before fees
+    pub rate: Decimal,
+    pub fees: Fees,
+    pub source_amount: Decimal,
+    pub target_amount: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Quote {
+    /// Identifies this quote among all quotes published for the context
+    #[serde(default)]
+    pub quote_id: Uuid,
     pub request: Request,
     pub rate: Decimal,
+    /// Amount to be transferred by the initiator, in the source currency
+    #[serde(default)]
+    pub source_amount: Decimal,
+    /// Amount to be delivered to the recipient, in the target currency
+    #[serde(default)]
+    pub target_amount: Decimal,
+    pub intermediary: AccountId,
+    /// Fees charged by the liquidity provider, included in the quoted amounts
+    #[serde(default)]
+    pub fees: Fees,
+    /// Set when this quote supersedes an earlier quote for the same request
+    #[serde(default)]
+    pub requoted: Option<Requoted>,
+    /// Rates by notional amount, any amount within a tier can be executed
+    #[serde(default)]
+    pub tiers: Vec<RateTier>,
+    /// Ask side of a two-way quote, `rate` & `fees` being the bid side
+    #[serde(default)]
+    pub ask: Option<Ask>,
+    /// Currencies the swap is converted through, from the source to the target currency
+    #[serde(default)]
+    pub route: Vec<String>,
+    /// Set when the target liquidity didn't cover the quoted amount, the swap may then only be partially filled
+    #[serde(default)]
+    pub indicative: bool,
+    /// [EPOCH TIME] The quote can be executed until this time
+    pub valid_until: u64,
+    /// Liquidity provider's signature over the quote
+    #[serde(default)]
+    pub signature: Option<Signature>,
+}
+
+impl Quote {
+    /// Checks the quoted rate & amounts are within sane bounds
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.request.validate()?;
+        if !(MIN_RATE..=MAX_RATE).contains(&self.rate) {
+            return Err(anyhow::anyhow!("Rate {} is out of bounds", self.rate));
+        }
+        if self.source_amount <= Decimal::ZERO || self.target_amount <= Decimal::ZERO {
+            return Err(anyhow::anyhow!("Quoted amounts must be positive"));
+        }
+        if let Some(tier) = self
+            .tiers
+            .iter()
+            .find(|tier| !(MIN_RATE..=MAX_RATE).contains(&tier.rate))
+        {
+            return Err(anyhow::anyhow!("Tier rate {} is out of bounds", tier.rate));
+        }
+        if let Some(ask) = &self.ask {
+            if !(MIN_RATE..=MAX_RATE).contains(&ask.rate) {
+                return Err(anyhow::anyhow!("Ask rate {} is out of bounds", ask.rate));
+            }
+        }
+        Ok(())
+    }
+
+    /// Tier applicable to `amount`, if the quote is tiered
+    pub fn tier(&self, amount: Decimal) -> Option<&RateTier> {
+        self.tiers
+            .iter()
+            .filter(|tier| tier.min_amount <= amount)
+            .max_by_key(|tier| tier.min_amount)
+    }
+
+    /// Fees applicable to `amount`, with the spread of its tier if any
+    pub fn fees_for(&self, amount: Decimal) -> Fees {
+        Fees {
+            spread_bps: self
+                .tier(amount)
+                .map_or(self.fees.spread_bps, |tier| tier.spread_bps),
+            ..self.fees.clone()
+        }
+    }
+
+    /// Checks `request` only differs from the quoted request by an amount allowed by the tiers
+    pub fn covers(&self, request: &Request) -> bool {
+        if self.tiers.is_empty() {
+            return &self.request == request;
+        }
+        let request = Request {
+            amount: self.request.amount,
+            ..request.clone()
+        };
+        self.request == request
+    }
+
+    pub fn is_expired(&self) -> bool {
+        SystemTime::now() > UNIX_EPOCH + Duration::from_secs(self.valid_until)
+    }
+
+    /// All-in rate received by the initiator, including fees
+    pub fn effective_rate(&self) -> Decimal {
+        if self.source_amount.is_zero() {
+            return Decimal::ZERO;
+        }
+        self.target_amount / self.source_amount
+    }
+
+    /// Canonical encoding of the quote covered by its signature
+    pub fn signing_payload(&self) -> Vec<u8> {
+        proto::Quote::from(&Quote {
+            signature: None,
+            ..self.clone()
+        })
+        .encode_to_vec()
+    }
+
+    /// Verifies the quote signature, returning the public key of the signer
+    pub fn verify(&self) -> anyhow::Result<&[u8]> {
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Quote is not signed"))?;
+        signature.verify(&self.signing_payload())?;
+        Ok(&signature.public_key)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Requoted {
+    /// The superseded quote, which can no longer be executed
+    pub previous_quote_id: Uuid,
+    /// Number of re-quotes published for the request
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Ask {
+    /// Target currency paid per unit of the source currency, including the spread
+    pub rate: Decimal,
+    /// Fees charged when executing at the ask, in the target currency
+    pub fees: Fees,
+    /// Liquidity account receiving the target currency when executing at the ask
     pub intermediary: AccountId,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RateTier {
+    /// Requested amount from which the tier applies, up to the next tier
+    pub min_amount: Decimal,
+    /// Spread charged within the tier, in basis points
+    pub spread_bps: u32,
+    /// FX rate within the tier, net of the spread
+    pub rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Fees {
+    /// Fixed fee per swap, deducted from the source amount
+    pub flat_fee: Decimal,
+    /// Spread on the FX rate, in basis points
+    pub spread_bps: u32,
+    /// Currency in which the fees are charged
+    pub currency: String,
+}
+
+impl Fees {
+    /// FX rate after deducting the spread
+    pub fn net_rate(&self, rate: Decimal) -> Decimal {
+        rate * (Decimal::ONE - Decimal::from(self.spread_bps) / Decimal::from(10_000))
+    }
+
+    /// Source & target amounts of `request` at `rate`, net of fees
+    pub fn amounts(&self, request: &Request, rate: Decimal) -> anyhow::Result<(Decimal, Decimal)> {
+        self.convert(request.side, request.amount, rate)
+    }
+
+    /// Source & target amounts when selling or buying exactly `amount` at `rate`, net of fees
+    pub fn convert(
+        &self,
+        side: Side,
+        amount: Decimal,
+        rate: Decimal,
+    ) -> anyhow::Result<(Decimal, Decimal)> {
+        let net_rate = self.net_rate(rate);
+        if net_rate <= Decimal::ZERO {
+            return Err(anyhow::anyhow!("Invalid rate {}", net_rate));
+        }
+        let overflow = || anyhow::anyhow!("Amount {} overflows at rate {}", amount, net_rate);
+        let (source, target) = match side {
+            Side::SellExact => (
+                amount,
+                amount
+                    .checked_sub(self.flat_fee)
+                    .and_then(|net| net.checked_mul(net_rate))
+                    .ok_or_else(overflow)?,
+            ),
+            Side::BuyExact => (
+                amount
+                    .checked_div(net_rate)
+                    .and_then(|gross| gross.checked_add(self.flat_fee))
+                    .ok_or_else(overflow)?,
+                amount,
+            ),
+        };
+        if target <= Decimal::ZERO {
+            return Err(anyhow::anyhow!("Amount does not cover the fees"));
+        }
+        Ok((source, target))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Signature {
+    /// Ed25519 public key of the liquidity provider
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl Signature {
+    /// Signature of `payload` by `signer`
+    pub async fn sign(signer: &impl Signer, payload: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self {
+            public_key: signer.public_key().to_vec(),
+            signature: signer.sign(payload).await?,
+        })
+    }
+
+    pub fn verify(&self, payload: &[u8]) -> anyhow::Result<()> {
+        UnparsedPublicKey::new(&ED25519, &self.public_key)
+            .verify(payload, &self.signature)
+            .map_err(|_| anyhow::anyhow!("Invalid signature"))
+    }
+}
+
+/// Proof of a settled swap, signed by the liquidity provider
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Receipt {
+    pub quote_id: Uuid,
+    /// Transfer from the initiator to the liquidity provider
+    pub source_tx_id: u64,
+    /// Transfer from the liquidity provider to the recipient, 0 for a paper trade
+    pub target_tx_id: u64,
+    /// Settled amount in the source currency
+    pub source_amount: u64,
+    /// Settled amount in the target currency
+    pub target_amount: u64,
+    /// Settled amounts ratio, including fees
+    pub effective_rate: Decimal,
+    /// Rate quoted for the settlement, before fees
+    #[serde(default)]
+    pub quoted_rate: Option<Decimal>,
+    /// Rate looked up right before settling, before fees
+    #[serde(default)]
+    pub executed_rate: Decimal,
+    /// Slices summarized by the final receipt of a TWAP execution, at their volume-weighted rate.
+    /// 0 for the receipt of a single settlement
+    #[serde(default)]
+    pub slices: u32,
+    pub signature: Option<Signature>,
+}
+
+impl Receipt {
+    /// Canonical encoding of the receipt covered by its signature
+    pub fn signing_payload(&self) -> Vec<u8> {
+        proto::Receipt::from(&Receipt {
+            signature: None,
+            ..self.clone()
+        })
+        .encode_to_vec()
+    }
+
+    /// Verifies the receipt signature, returning the public key of the signer
+    pub fn verify(&self) -> anyhow::Result<&[u8]> {
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Receipt is not signed"))?;
+        signature.verify(&self.signing_payload())?;
+        Ok(&signature.public_key)
+    }
+}
+
 impl Display for Quote {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "from={} to={} amount={} rate={} intermediary={}",
-            self.request.from, self.request.to, self.request.amount, self.rate, self.intermediary
-        )
+            "quote_id={} from={} to={} source_amount={} target_amount={} rate={} intermediary={} valid_until={}",
+            self.quote_id,
+            self.request.from,
+            self.request.to,
+            self.source_amount,
+            self.target_amount,
+            self.rate,
+            self.intermediary,
+            self.valid_until
+        )?;
+        if self.route.len() > 2 {
+            write!(f, " route={}", self.route.join(">"))?;
+        }
+        if let Some(memo) = &self.request.memo {
+            write!(f, " memo={:?}", memo)?;
+        }
+        if self.indicative {
+            write!(f, " indicative")?;
+        }
+        Ok(())
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Execute {
     pub request: Request,
-    /// [EPOCH TIME] Execution will run until this time
+    /// The quote being executed
+    #[serde(default)]
+    pub quote_id: Uuid,
+    /// The signed quote being executed
+    #[serde(default)]
+    pub quote: Option<Quote>,
+    /// How & when the swap is settled
+    pub strategy: Strategy,
+    /// Side of a two-way quote being executed
+    #[serde(default)]
+    pub direction: Direction,
+}
+
+impl Execute {
+    /// Checks the execution request & its strategy are well-formed
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.request.validate()?;
+        self.strategy.validate()?;
+        if self.request.settle_at.is_some() && !matches!(self.strategy, Strategy::Immediate) {
+            return Err(anyhow::anyhow!(
+                "Forward swaps settle immediately at their settlement date"
+            ));
+        }
+        if let Some(quote) = &self.quote {
+            quote.validate()?;
+            if self.direction == Direction::Ask && quote.ask.is_none() {
+                return Err(anyhow::anyhow!("Quote has no ask side"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Request settled by the execution, reversed when executing at the ask
+    pub fn settlement_request(&self) -> Request {
+        match self.direction {
+            Direction::Bid => self.request.clone(),
+            Direction::Ask => self.request.reversed(),
+        }
+    }
+
+    /// Rate quoted for the settlement, before fees
+    pub fn quoted_rate(&self) -> Option<Decimal> {
+        let rate = self.quote.as_ref()?.rate;
+        match self.direction {
+            Direction::Bid => Some(rate),
+            Direction::Ask => Decimal::ONE.checked_div(rate),
+        }
+    }
+
+    /// [EPOCH TIME] Time by which the execution settles, when accepted at `now`
+    pub fn settles_by(&self, now: u64) -> u64 {
+        self.strategy
+            .settles_by(now.max(self.request.settle_at.unwrap_or_default()))
+    }
+
+    /// Fees charged on the settlement
+    pub fn fees(&self) -> Fees {
+        match (&self.quote, self.direction) {
+            (Some(quote), Direction::Bid) => quote.fees_for(self.request.amount),
+            (Some(quote), Direction::Ask) => quote
+                .ask
+                .as_ref()
+                .map(|ask| ask.fees.clone())
+                .unwrap_or_default(),
+            (None, _) => Fees::default(),
+        }
+    }
+
+    /// Amount of the source currency of the settlement paid by the initiator to execute the quote
+    pub fn source_amount(&self) -> anyhow::Result<Decimal> {
+        let quote = self
+            .quote
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Missing quote"))?;
+        match (self.direction, quote.tier(self.request.amount)) {
+            (Direction::Bid, None) => Ok(quote.source_amount),
+            _ => {
+                let rate = self
+                    .quoted_rate()
+                    .ok_or_else(|| anyhow::anyhow!("Invalid quoted rate {}", quote.rate))?;
+                Ok(self.fees().amounts(&self.settlement_request(), rate)?.0)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Strategy {
+    /// Settle at the current rate as soon as the execution is accepted
+    Immediate,
+    /// Settle once the rate leaves `bounds`, or at `deadline` at the latest
+    Limit {
+        bounds: Bounds,
+        /// [EPOCH TIME] Settles at the current rate at this time
+        deadline: u64,
+    },
+    /// Settle in `slices` equal parts at regular intervals over `window` seconds
+    Twap { window: u64, slices: u32 },
+}
+
+impl Strategy {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Strategy::Immediate => {}
+            Strategy::Limit { bounds, .. } if bounds.upper < bounds.lower => {
+                return Err(anyhow::anyhow!(
+                    "Invalid rate bounds {}..{}",
+                    bounds.lower,
+                    bounds.upper
+                ));
+            }
+            Strategy::Limit { .. } => {}
+            Strategy::Twap { slices, .. } if !(1..=MAX_TWAP_SLICES).contains(slices) => {
+                return Err(anyhow::anyhow!(
+                    "Number of slices must be between 1 and {}",
+                    MAX_TWAP_SLICES
+                ));
+            }
+            Strategy::Twap { window: 0, .. } => {
+                return Err(anyhow::anyhow!("TWAP window must be at least a second"));
+            }
+            Strategy::Twap { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// [EPOCH TIME] Time by which the strategy settles, when started at `now`
+    pub fn settles_by(&self, now: u64) -> u64 {
+        match self {
+            Strategy::Immediate => now,
+            Strategy::Limit { deadline, .. } => *deadline,
+            Strategy::Twap { window, .. } => now + window,
+        }
+    }
+}
+
+/// FX rate range, exclusive of `upper`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bounds {
+    pub lower: Decimal,
+    pub upper: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapState {
+    /// A quote was published & can be executed
+    Quoted,
+    /// The request or its execution was rejected
+    Rejected,
+    /// The initiator cancelled the swap
+    Cancelled,
+    /// An execution was accepted & is being settled
+    Executing,
+    /// The swap was fully settled
+    Settled,
+    /// Part of the swap was settled, the remainder refunded
+    PartiallyFilled,
+    /// The swap could not be settled
+    Failed,
+    /// The source funds were returned to the initiator
+    Refunded,
+    /// The request awaits an operator's approval before being quoted
+    PendingApproval,
+    /// The swap was only simulated by a dry run, settling nothing
+    PaperTraded,
+}
+
+impl SwapState {
+    /// Whether the swap reached a final state, settling nothing more
+    pub fn is_final(&self) -> bool {
+        !matches!(
+            self,
+            SwapState::Quoted | SwapState::Executing | SwapState::PendingApproval
+        )
+    }
+}
+
+impl Display for SwapState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SwapStatus {
+    pub state: SwapState,
+    /// Latest quote published for the swap
+    pub quote_id: Option<Uuid>,
+    /// [EPOCH TIME] The latest quote can be executed until this time
     pub valid_until: u64,
-    /// Fx rate limits. If exceeded will exchange for this rate immediately
-    pub upper_limit: Decimal,
-    /// Fx rate limits. If exceeded will exchange for this rate immediately
-    pub lower_limits: Decimal,
+    /// [EPOCH TIME] Milliseconds at which the swap was first quoted or rejected
+    pub created_at: u64,
+    /// [EPOCH TIME] Milliseconds at which the state last changed
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CounterOffer {
+    /// The signed quote being negotiated
+    pub quote: Quote,
+    /// Proposed rate, net of the spread
+    pub rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Refund {
+    /// Transfer from the initiator to the liquidity provider
+    pub source_tx_id: u64,
+    /// Transfer returning the funds to the initiator
+    pub refund_tx_id: u64,
+    /// Refunded amount in the source currency
+    pub amount: u64,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NettingReport {
+    /// Batched transfer settling the payouts of the netting window
+    pub tx_id: u64,
+    /// Payout of this swap, in the target currency
+    pub amount: u64,
+    /// Net amount transferred to the recipient, in the target currency
+    pub net_amount: u64,
+    /// Payouts to the recipient netted together, including this one
+    pub netted: u32,
+}
+
+/// Bounds of the swap sizes served by a liquidity provider, in its currency
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct AmountRange {
+    pub min: Option<Decimal>,
+    pub max: Option<Decimal>,
+}
+
+impl AmountRange {
+    pub fn is_bounded(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+
+    pub fn contains(&self, amount: Decimal) -> bool {
+        self.min.is_none_or(|min| amount >= min) && self.max.is_none_or(|max| amount <= max)
+    }
+}
+
+impl Display for AmountRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(min) = self.min {
+            write!(f, "{}", min)?;
+        }
+        write!(f, "..")?;
+        if let Some(max) = self.max {
+            write!(f, "{}", max)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PartialFill {
+    /// Amount delivered to the recipient, in the target currency
+    pub filled: u64,
+    /// Amount which could not be delivered, in the target currency
+    pub remaining: u64,
+    /// Amount returned to the initiator, in the source currency
+    pub refunded: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn request(amount: Decimal) -> Request {
+        Request {
+            from: AccountId::from_str("00000000004a00000000000000000001").unwrap(),
+            to: AccountId::from_str("04000000003200000000000000000001").unwrap(),
+            amount,
+            side: Side::SellExact,
+            memo: None,
+            two_way: false,
+            compliance: None,
+            settle_at: None,
+        }
+    }
+
+    #[test]
+    fn oversized_amounts_are_rejected() {
+        assert_eq!(MAX_AMOUNT, Decimal::from(1_000_000_000_000_000u64));
+        assert!(request(MAX_AMOUNT).validate().is_ok());
+        assert!(request(MAX_AMOUNT + Decimal::ONE).validate().is_err());
+        assert!(request(Decimal::MAX).validate().is_err());
+    }
+
+    #[test]
+    fn overflowing_conversions_fail() {
+        let fees = Fees::default();
+        assert!(fees
+            .convert(Side::SellExact, Decimal::MAX, MAX_RATE)
+            .is_err());
+        assert!(fees
+            .convert(Side::BuyExact, Decimal::MAX, MIN_RATE)
+            .is_err());
+        // The largest request is priced at the extreme rates
+        assert!(fees.convert(Side::SellExact, MAX_AMOUNT, MAX_RATE).is_ok());
+        assert!(fees.convert(Side::BuyExact, MAX_AMOUNT, MIN_RATE).is_ok());
+    }
 }
@@ -0,0 +1,961 @@
+//! Protobuf encoding of the event schema, published as `FxSwapMetadata`
+
+use m10_sdk::account::AccountId;
+use m10_sdk::MetadataType;
+use prost::{Enumeration, Message};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FxSwapMetadata {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(
+        oneof = "fx_swap_metadata::Event",
+        tags = "2, 3, 4, 5, 6, 7, 8, 9, 10, 14, 15, 16, 17"
+    )]
+    pub event: Option<fx_swap_metadata::Event>,
+    #[prost(uint64, tag = "11")]
+    pub created_at: u64,
+    #[prost(uint64, tag = "12")]
+    pub sequence: u64,
+    #[prost(bytes = "vec", tag = "13")]
+    pub public_key: Vec<u8>,
+}
+
+impl MetadataType for FxSwapMetadata {
+    const TYPE_URL: &'static str = crate::FX_SWAP_METADATA;
+}
+
+pub mod fx_swap_metadata {
+    use prost::Oneof;
+
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Clone, PartialEq, Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "2")]
+        Request(super::Request),
+        #[prost(message, tag = "3")]
+        Quote(super::Quote),
+        #[prost(message, tag = "4")]
+        Execute(super::Execute),
+        #[prost(message, tag = "5")]
+        Completed(super::Completed),
+        #[prost(message, tag = "6")]
+        PartialFill(super::PartialFill),
+        #[prost(message, tag = "7")]
+        Reject(super::Reject),
+        #[prost(message, tag = "8")]
+        Cancel(super::Cancel),
+        #[prost(message, tag = "9")]
+        Failed(super::Failed),
+        #[prost(message, tag = "10")]
+        Receipt(super::Receipt),
+        #[prost(message, tag = "14")]
+        CounterOffer(super::CounterOffer),
+        #[prost(message, tag = "15")]
+        StatusRequest(super::StatusRequest),
+        #[prost(message, tag = "16")]
+        StatusResponse(super::StatusResponse),
+        #[prost(message, tag = "17")]
+        Refunded(super::Refunded),
+        #[prost(message, tag = "18")]
+        Netted(super::Netted),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Request {
+    #[prost(bytes = "vec", tag = "1")]
+    pub from: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub to: Vec<u8>,
+    #[prost(string, tag = "3")]
+    pub amount: String,
+    #[prost(enumeration = "Side", tag = "4")]
+    pub side: i32,
+    #[prost(string, tag = "5")]
+    pub memo: String,
+    #[prost(bool, tag = "6")]
+    pub two_way: bool,
+    #[prost(message, optional, tag = "7")]
+    pub compliance: Option<ComplianceMetadata>,
+    /// Spot when zero
+    #[prost(uint64, tag = "8")]
+    pub settle_at: u64,
+}
+
+/// Travel-rule information, also attached as metadata to the settlement transfers
+#[derive(Clone, PartialEq, Message)]
+pub struct ComplianceMetadata {
+    #[prost(message, optional, tag = "1")]
+    pub originator: Option<Party>,
+    #[prost(message, optional, tag = "2")]
+    pub beneficiary: Option<Party>,
+}
+
+impl MetadataType for ComplianceMetadata {
+    const TYPE_URL: &'static str = crate::COMPLIANCE_METADATA;
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Party {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub address: String,
+    #[prost(string, tag = "3")]
+    pub country: String,
+    #[prost(string, tag = "4")]
+    pub identifier: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum Direction {
+    Bid = 0,
+    Ask = 1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum Side {
+    SellExact = 0,
+    BuyExact = 1,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Quote {
+    #[prost(message, optional, tag = "1")]
+    pub request: Option<Request>,
+    #[prost(string, tag = "2")]
+    pub rate: String,
+    #[prost(bytes = "vec", tag = "3")]
+    pub intermediary: Vec<u8>,
+    #[prost(uint64, tag = "4")]
+    pub valid_until: u64,
+    #[prost(message, optional, tag = "5")]
+    pub signature: Option<Signature>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub quote_id: Vec<u8>,
+    #[prost(string, tag = "7")]
+    pub source_amount: String,
+    #[prost(string, tag = "8")]
+    pub target_amount: String,
+    #[prost(message, optional, tag = "9")]
+    pub fees: Option<Fees>,
+    #[prost(message, optional, tag = "10")]
+    pub requoted: Option<Requoted>,
+    #[prost(message, repeated, tag = "11")]
+    pub tiers: Vec<RateTier>,
+    #[prost(message, optional, tag = "12")]
+    pub ask: Option<Ask>,
+    #[prost(string, repeated, tag = "13")]
+    pub route: Vec<String>,
+    #[prost(bool, tag = "14")]
+    pub indicative: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Ask {
+    #[prost(string, tag = "1")]
+    pub rate: String,
+    #[prost(message, optional, tag = "2")]
+    pub fees: Option<Fees>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub intermediary: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RateTier {
+    #[prost(string, tag = "1")]
+    pub min_amount: String,
+    #[prost(uint32, tag = "2")]
+    pub spread_bps: u32,
+    #[prost(string, tag = "3")]
+    pub rate: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Requoted {
+    #[prost(bytes = "vec", tag = "1")]
+    pub previous_quote_id: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub count: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Fees {
+    #[prost(string, tag = "1")]
+    pub flat_fee: String,
+    #[prost(uint32, tag = "2")]
+    pub spread_bps: u32,
+    #[prost(string, tag = "3")]
+    pub currency: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Signature {
+    #[prost(bytes = "vec", tag = "1")]
+    pub public_key: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Receipt {
+    #[prost(bytes = "vec", tag = "1")]
+    pub quote_id: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub source_tx_id: u64,
+    #[prost(uint64, tag = "3")]
+    pub target_tx_id: u64,
+    #[prost(uint64, tag = "4")]
+    pub source_amount: u64,
+    #[prost(uint64, tag = "5")]
+    pub target_amount: u64,
+    #[prost(string, tag = "6")]
+    pub effective_rate: String,
+    #[prost(message, optional, tag = "7")]
+    pub signature: Option<Signature>,
+    #[prost(uint32, tag = "8")]
+    pub slices: u32,
+    #[prost(string, tag = "9")]
+    pub quoted_rate: String,
+    #[prost(string, tag = "10")]
+    pub executed_rate: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Execute {
+    #[prost(message, optional, tag = "1")]
+    pub request: Option<Request>,
+    #[prost(uint64, tag = "2")]
+    pub valid_until: u64,
+    #[prost(string, tag = "3")]
+    pub upper_limit: String,
+    #[prost(string, tag = "4")]
+    pub lower_limits: String,
+    #[prost(message, optional, tag = "5")]
+    pub quote: Option<Quote>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub quote_id: Vec<u8>,
+    #[prost(enumeration = "Direction", tag = "7")]
+    pub direction: i32,
+    #[prost(oneof = "execute::Strategy", tags = "8, 9, 10")]
+    pub strategy: Option<execute::Strategy>,
+}
+
+pub mod execute {
+    use prost::Oneof;
+
+    #[derive(Clone, PartialEq, Oneof)]
+    pub enum Strategy {
+        #[prost(message, tag = "8")]
+        Immediate(super::Immediate),
+        #[prost(message, tag = "9")]
+        Limit(super::Limit),
+        #[prost(message, tag = "10")]
+        Twap(super::Twap),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Immediate {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Limit {
+    #[prost(string, tag = "1")]
+    pub lower: String,
+    #[prost(string, tag = "2")]
+    pub upper: String,
+    #[prost(uint64, tag = "3")]
+    pub deadline: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Twap {
+    #[prost(uint64, tag = "1")]
+    pub window: u64,
+    #[prost(uint32, tag = "2")]
+    pub slices: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StatusRequest {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StatusResponse {
+    #[prost(enumeration = "SwapState", tag = "1")]
+    pub state: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub quote_id: Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub valid_until: u64,
+    #[prost(uint64, tag = "4")]
+    pub created_at: u64,
+    #[prost(uint64, tag = "5")]
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum SwapState {
+    Quoted = 0,
+    Rejected = 1,
+    Cancelled = 2,
+    Executing = 3,
+    Settled = 4,
+    PartiallyFilled = 5,
+    Failed = 6,
+    Refunded = 7,
+    PendingApproval = 8,
+    PaperTraded = 9,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Refunded {
+    #[prost(uint64, tag = "1")]
+    pub source_tx_id: u64,
+    #[prost(uint64, tag = "2")]
+    pub refund_tx_id: u64,
+    #[prost(uint64, tag = "3")]
+    pub amount: u64,
+    #[prost(string, tag = "4")]
+    pub reason: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Netted {
+    #[prost(uint64, tag = "1")]
+    pub tx_id: u64,
+    #[prost(uint64, tag = "2")]
+    pub amount: u64,
+    #[prost(uint64, tag = "3")]
+    pub net_amount: u64,
+    #[prost(uint32, tag = "4")]
+    pub netted: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct CounterOffer {
+    #[prost(message, optional, tag = "1")]
+    pub quote: Option<Quote>,
+    #[prost(string, tag = "2")]
+    pub rate: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Completed {
+    #[prost(bool, tag = "1")]
+    pub paper_trade: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct PartialFill {
+    #[prost(uint64, tag = "1")]
+    pub filled: u64,
+    #[prost(uint64, tag = "2")]
+    pub remaining: u64,
+    #[prost(uint64, tag = "3")]
+    pub refunded: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Reject {
+    #[prost(string, tag = "1")]
+    pub reason: String,
+    #[prost(message, optional, tag = "2")]
+    pub allowed: Option<AmountRange>,
+    /// Unset when zero
+    #[prost(uint64, tag = "3")]
+    pub reopens_at: u64,
+}
+
+/// Unbounded sides are empty
+#[derive(Clone, PartialEq, Message)]
+pub struct AmountRange {
+    #[prost(string, tag = "1")]
+    pub min: String,
+    #[prost(string, tag = "2")]
+    pub max: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Cancel {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Failed {
+    #[prost(enumeration = "FailureCode", tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum FailureCode {
+    RateUnavailable = 0,
+    InsufficientLiquidity = 1,
+    Expired = 2,
+    TransferError = 3,
+    Slippage = 4,
+}
+
+impl From<&super::Envelope> for FxSwapMetadata {
+    fn from(envelope: &super::Envelope) -> Self {
+        use fx_swap_metadata::Event;
+        let event = match &envelope.event {
+            super::Event::Request(request) => Event::Request(request.into()),
+            super::Event::Quote(quote) => Event::Quote(quote.into()),
+            super::Event::Execute(execute) => Event::Execute(Execute {
+                request: Some((&execute.request).into()),
+                // Superseded by the strategy, kept empty
+                valid_until: 0,
+                upper_limit: String::new(),
+                lower_limits: String::new(),
+                strategy: Some((&execute.strategy).into()),
+                quote: execute.quote.as_ref().map(Quote::from),
+                quote_id: execute.quote_id.as_bytes().to_vec(),
+                direction: match execute.direction {
+                    super::Direction::Bid => Direction::Bid,
+                    super::Direction::Ask => Direction::Ask,
+                } as i32,
+            }),
+            super::Event::Completed { paper_trade } => Event::Completed(Completed {
+                paper_trade: *paper_trade,
+            }),
+            super::Event::PartialFill(fill) => Event::PartialFill(PartialFill {
+                filled: fill.filled,
+                remaining: fill.remaining,
+                refunded: fill.refunded,
+            }),
+            super::Event::Reject {
+                reason,
+                allowed,
+                reopens_at,
+            } => Event::Reject(Reject {
+                reason: reason.clone(),
+                allowed: allowed.map(|allowed| AmountRange {
+                    min: allowed.min.map(|min| min.to_string()).unwrap_or_default(),
+                    max: allowed.max.map(|max| max.to_string()).unwrap_or_default(),
+                }),
+                reopens_at: reopens_at.unwrap_or_default(),
+            }),
+            super::Event::Cancel => Event::Cancel(Cancel {}),
+            super::Event::Receipt(receipt) => Event::Receipt(receipt.into()),
+            super::Event::StatusRequest => Event::StatusRequest(StatusRequest {}),
+            super::Event::StatusResponse(status) => Event::StatusResponse(status.into()),
+            super::Event::Refunded(refund) => Event::Refunded(Refunded {
+                source_tx_id: refund.source_tx_id,
+                refund_tx_id: refund.refund_tx_id,
+                amount: refund.amount,
+                reason: refund.reason.clone(),
+            }),
+            super::Event::Netted(report) => Event::Netted(Netted {
+                tx_id: report.tx_id,
+                amount: report.amount,
+                net_amount: report.net_amount,
+                netted: report.netted,
+            }),
+            super::Event::CounterOffer(offer) => Event::CounterOffer(CounterOffer {
+                quote: Some((&offer.quote).into()),
+                rate: offer.rate.to_string(),
+            }),
+            super::Event::Failed { code, message } => Event::Failed(Failed {
+                code: FailureCode::from(*code) as i32,
+                message: message.clone(),
+            }),
+        };
+        Self {
+            version: envelope.version,
+            event: Some(event),
+            created_at: envelope.created_at,
+            sequence: envelope.sequence,
+            public_key: envelope.public_key.clone(),
+        }
+    }
+}
+
+impl TryFrom<FxSwapMetadata> for super::Envelope {
+    type Error = anyhow::Error;
+
+    fn try_from(metadata: FxSwapMetadata) -> Result<Self, Self::Error> {
+        use fx_swap_metadata::Event;
+        let event = metadata
+            .event
+            .ok_or_else(|| anyhow::anyhow!("missing event"))?;
+        let event = match event {
+            Event::Request(request) => super::Event::Request(request.try_into()?),
+            Event::Quote(quote) => super::Event::Quote(quote.try_into()?),
+            Event::Execute(execute) => super::Event::Execute(super::Execute {
+                request: required(execute.request)?.try_into()?,
+                quote_id: Uuid::from_slice(&execute.quote_id)?,
+                quote: execute.quote.map(TryInto::try_into).transpose()?,
+                strategy: match execute.strategy {
+                    Some(strategy) => strategy.try_into()?,
+                    // Executions published before strategies were introduced
+                    None => super::Strategy::Limit {
+                        bounds: super::Bounds {
+                            lower: Decimal::from_str(&execute.lower_limits)?,
+                            upper: Decimal::from_str(&execute.upper_limit)?,
+                        },
+                        deadline: execute.valid_until,
+                    },
+                },
+                direction: match Direction::from_i32(execute.direction) {
+                    Some(Direction::Bid) => super::Direction::Bid,
+                    Some(Direction::Ask) => super::Direction::Ask,
+                    None => return Err(anyhow::anyhow!("invalid direction {}", execute.direction)),
+                },
+            }),
+            Event::Completed(completed) => super::Event::Completed {
+                paper_trade: completed.paper_trade,
+            },
+            Event::PartialFill(fill) => super::Event::PartialFill(super::PartialFill {
+                filled: fill.filled,
+                remaining: fill.remaining,
+                refunded: fill.refunded,
+            }),
+            Event::Reject(reject) => super::Event::Reject {
+                reason: reject.reason,
+                allowed: match reject.allowed {
+                    Some(allowed) => Some(super::AmountRange {
+                        min: bound(&allowed.min)?,
+                        max: bound(&allowed.max)?,
+                    }),
+                    None => None,
+                },
+                reopens_at: Some(reject.reopens_at).filter(|reopens_at| *reopens_at > 0),
+            },
+            Event::Cancel(_) => super::Event::Cancel,
+            Event::Receipt(receipt) => super::Event::Receipt(receipt.try_into()?),
+            Event::StatusRequest(_) => super::Event::StatusRequest,
+            Event::StatusResponse(status) => super::Event::StatusResponse(status.try_into()?),
+            Event::Refunded(refund) => super::Event::Refunded(super::Refund {
+                source_tx_id: refund.source_tx_id,
+                refund_tx_id: refund.refund_tx_id,
+                amount: refund.amount,
+                reason: refund.reason,
+            }),
+            Event::Netted(report) => super::Event::Netted(super::NettingReport {
+                tx_id: report.tx_id,
+                amount: report.amount,
+                net_amount: report.net_amount,
+                netted: report.netted,
+            }),
+            Event::CounterOffer(offer) => super::Event::CounterOffer(super::CounterOffer {
+                quote: required(offer.quote)?.try_into()?,
+                rate: Decimal::from_str(&offer.rate)?,
+            }),
+            Event::Failed(failed) => super::Event::Failed {
+                code: FailureCode::from_i32(failed.code)
+                    .ok_or_else(|| anyhow::anyhow!("invalid failure code {}", failed.code))?
+                    .into(),
+                message: failed.message,
+            },
+        };
+        Ok(Self {
+            version: metadata.version,
+            created_at: metadata.created_at,
+            sequence: metadata.sequence,
+            public_key: metadata.public_key,
+            event,
+        })
+    }
+}
+
+impl From<&super::Quote> for Quote {
+    fn from(quote: &super::Quote) -> Self {
+        Self {
+            request: Some((&quote.request).into()),
+            rate: quote.rate.to_string(),
+            source_amount: quote.source_amount.to_string(),
+            target_amount: quote.target_amount.to_string(),
+            intermediary: quote.intermediary.to_vec(),
+            fees: Some((&quote.fees).into()),
+            requoted: quote.requoted.as_ref().map(|requoted| Requoted {
+                previous_quote_id: requoted.previous_quote_id.as_bytes().to_vec(),
+                count: requoted.count,
+            }),
+            tiers: quote
+                .tiers
+                .iter()
+                .map(|tier| RateTier {
+                    min_amount: tier.min_amount.to_string(),
+                    spread_bps: tier.spread_bps,
+                    rate: tier.rate.to_string(),
+                })
+                .collect(),
+            ask: quote.ask.as_ref().map(|ask| Ask {
+                rate: ask.rate.to_string(),
+                fees: Some((&ask.fees).into()),
+                intermediary: ask.intermediary.to_vec(),
+            }),
+            route: quote.route.clone(),
+            indicative: quote.indicative,
+            valid_until: quote.valid_until,
+            quote_id: quote.quote_id.as_bytes().to_vec(),
+            signature: quote.signature.as_ref().map(Signature::from),
+        }
+    }
+}
+
+impl TryFrom<Quote> for super::Quote {
+    type Error = anyhow::Error;
+
+    fn try_from(quote: Quote) -> Result<Self, Self::Error> {
+        Ok(Self {
+            quote_id: Uuid::from_slice(&quote.quote_id)?,
+            request: required(quote.request)?.try_into()?,
+            rate: Decimal::from_str(&quote.rate)?,
+            source_amount: Decimal::from_str(&quote.source_amount)?,
+            target_amount: Decimal::from_str(&quote.target_amount)?,
+            intermediary: AccountId::try_from_be_slice(&quote.intermediary)?,
+            fees: quote
+                .fees
+                .map(TryInto::try_into)
+                .transpose()?
+                .unwrap_or_default(),
+            requoted: match quote.requoted {
+                Some(requoted) => Some(super::Requoted {
+                    previous_quote_id: Uuid::from_slice(&requoted.previous_quote_id)?,
+                    count: requoted.count,
+                }),
+                None => None,
+            },
+            tiers: quote
+                .tiers
+                .into_iter()
+                .map(|tier| {
+                    Ok(super::RateTier {
+                        min_amount: Decimal::from_str(&tier.min_amount)?,
+                        spread_bps: tier.spread_bps,
+                        rate: Decimal::from_str(&tier.rate)?,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?,
+            ask: match quote.ask {
+                Some(ask) => Some(super::Ask {
+                    rate: Decimal::from_str(&ask.rate)?,
+                    fees: ask
+                        .fees
+                        .map(TryInto::try_into)
+                        .transpose()?
+                        .unwrap_or_default(),
+                    intermediary: AccountId::try_from_be_slice(&ask.intermediary)?,
+                }),
+                None => None,
+            },
+            route: quote.route,
+            indicative: quote.indicative,
+            valid_until: quote.valid_until,
+            signature: quote.signature.map(Into::into),
+        })
+    }
+}
+
+impl From<&super::Receipt> for Receipt {
+    fn from(receipt: &super::Receipt) -> Self {
+        Self {
+            quote_id: receipt.quote_id.as_bytes().to_vec(),
+            source_tx_id: receipt.source_tx_id,
+            target_tx_id: receipt.target_tx_id,
+            source_amount: receipt.source_amount,
+            target_amount: receipt.target_amount,
+            effective_rate: receipt.effective_rate.to_string(),
+            signature: receipt.signature.as_ref().map(Signature::from),
+            slices: receipt.slices,
+            quoted_rate: receipt
+                .quoted_rate
+                .map(|rate| rate.to_string())
+                .unwrap_or_default(),
+            executed_rate: receipt.executed_rate.to_string(),
+        }
+    }
+}
+
+impl TryFrom<Receipt> for super::Receipt {
+    type Error = anyhow::Error;
+
+    fn try_from(receipt: Receipt) -> Result<Self, Self::Error> {
+        Ok(Self {
+            quote_id: Uuid::from_slice(&receipt.quote_id)?,
+            source_tx_id: receipt.source_tx_id,
+            target_tx_id: receipt.target_tx_id,
+            source_amount: receipt.source_amount,
+            target_amount: receipt.target_amount,
+            effective_rate: Decimal::from_str(&receipt.effective_rate)?,
+            quoted_rate: match receipt.quoted_rate.is_empty() {
+                true => None,
+                false => Some(Decimal::from_str(&receipt.quoted_rate)?),
+            },
+            executed_rate: Decimal::from_str(&receipt.executed_rate)?,
+            slices: receipt.slices,
+            signature: receipt.signature.map(Into::into),
+        })
+    }
+}
+
+impl From<&super::Signature> for Signature {
+    fn from(signature: &super::Signature) -> Self {
+        Self {
+            public_key: signature.public_key.clone(),
+            signature: signature.signature.clone(),
+        }
+    }
+}
+
+impl From<Signature> for super::Signature {
+    fn from(signature: Signature) -> Self {
+        Self {
+            public_key: signature.public_key,
+            signature: signature.signature,
+        }
+    }
+}
+
+impl From<&super::Strategy> for execute::Strategy {
+    fn from(strategy: &super::Strategy) -> Self {
+        match strategy {
+            super::Strategy::Immediate => Self::Immediate(Immediate {}),
+            super::Strategy::Limit { bounds, deadline } => Self::Limit(Limit {
+                lower: bounds.lower.to_string(),
+                upper: bounds.upper.to_string(),
+                deadline: *deadline,
+            }),
+            super::Strategy::Twap { window, slices } => Self::Twap(Twap {
+                window: *window,
+                slices: *slices,
+            }),
+        }
+    }
+}
+
+impl TryFrom<execute::Strategy> for super::Strategy {
+    type Error = anyhow::Error;
+
+    fn try_from(strategy: execute::Strategy) -> Result<Self, Self::Error> {
+        Ok(match strategy {
+            execute::Strategy::Immediate(_) => Self::Immediate,
+            execute::Strategy::Limit(limit) => Self::Limit {
+                bounds: super::Bounds {
+                    lower: Decimal::from_str(&limit.lower)?,
+                    upper: Decimal::from_str(&limit.upper)?,
+                },
+                deadline: limit.deadline,
+            },
+            execute::Strategy::Twap(twap) => Self::Twap {
+                window: twap.window,
+                slices: twap.slices,
+            },
+        })
+    }
+}
+
+impl From<&super::SwapStatus> for StatusResponse {
+    fn from(status: &super::SwapStatus) -> Self {
+        Self {
+            state: match status.state {
+                super::SwapState::Quoted => SwapState::Quoted,
+                super::SwapState::Rejected => SwapState::Rejected,
+                super::SwapState::Cancelled => SwapState::Cancelled,
+                super::SwapState::Executing => SwapState::Executing,
+                super::SwapState::Settled => SwapState::Settled,
+                super::SwapState::PartiallyFilled => SwapState::PartiallyFilled,
+                super::SwapState::Failed => SwapState::Failed,
+                super::SwapState::Refunded => SwapState::Refunded,
+                super::SwapState::PendingApproval => SwapState::PendingApproval,
+                super::SwapState::PaperTraded => SwapState::PaperTraded,
+            } as i32,
+            quote_id: status
+                .quote_id
+                .map(|quote_id| quote_id.as_bytes().to_vec())
+                .unwrap_or_default(),
+            valid_until: status.valid_until,
+            created_at: status.created_at,
+            updated_at: status.updated_at,
+        }
+    }
+}
+
+impl TryFrom<StatusResponse> for super::SwapStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(status: StatusResponse) -> Result<Self, Self::Error> {
+        Ok(Self {
+            state: match SwapState::from_i32(status.state) {
+                Some(SwapState::Quoted) => super::SwapState::Quoted,
+                Some(SwapState::Rejected) => super::SwapState::Rejected,
+                Some(SwapState::Cancelled) => super::SwapState::Cancelled,
+                Some(SwapState::Executing) => super::SwapState::Executing,
+                Some(SwapState::Settled) => super::SwapState::Settled,
+                Some(SwapState::PartiallyFilled) => super::SwapState::PartiallyFilled,
+                Some(SwapState::Failed) => super::SwapState::Failed,
+                Some(SwapState::Refunded) => super::SwapState::Refunded,
+                Some(SwapState::PendingApproval) => super::SwapState::PendingApproval,
+                Some(SwapState::PaperTraded) => super::SwapState::PaperTraded,
+                None => return Err(anyhow::anyhow!("invalid swap state {}", status.state)),
+            },
+            quote_id: match status.quote_id.is_empty() {
+                true => None,
+                false => Some(Uuid::from_slice(&status.quote_id)?),
+            },
+            valid_until: status.valid_until,
+            created_at: status.created_at,
+            updated_at: status.updated_at,
+        })
+    }
+}
+
+impl From<&super::Fees> for Fees {
+    fn from(fees: &super::Fees) -> Self {
+        Self {
+            flat_fee: fees.flat_fee.to_string(),
+            spread_bps: fees.spread_bps,
+            currency: fees.currency.clone(),
+        }
+    }
+}
+
+impl TryFrom<Fees> for super::Fees {
+    type Error = anyhow::Error;
+
+    fn try_from(fees: Fees) -> Result<Self, Self::Error> {
+        Ok(Self {
+            flat_fee: Decimal::from_str(&fees.flat_fee)?,
+            spread_bps: fees.spread_bps,
+            currency: fees.currency,
+        })
+    }
+}
+
+impl From<&super::Request> for Request {
+    fn from(request: &super::Request) -> Self {
+        Self {
+            from: request.from.to_vec(),
+            to: request.to.to_vec(),
+            amount: request.amount.to_string(),
+            side: match request.side {
+                super::Side::SellExact => Side::SellExact,
+                super::Side::BuyExact => Side::BuyExact,
+            } as i32,
+            memo: request.memo.clone().unwrap_or_default(),
+            two_way: request.two_way,
+            compliance: request.compliance.as_ref().map(ComplianceMetadata::from),
+            settle_at: request.settle_at.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&super::Compliance> for ComplianceMetadata {
+    fn from(compliance: &super::Compliance) -> Self {
+        Self {
+            originator: Some(Party::from(&compliance.originator)),
+            beneficiary: Some(Party::from(&compliance.beneficiary)),
+        }
+    }
+}
+
+impl TryFrom<ComplianceMetadata> for super::Compliance {
+    type Error = anyhow::Error;
+
+    fn try_from(compliance: ComplianceMetadata) -> Result<Self, Self::Error> {
+        Ok(Self {
+            originator: compliance
+                .originator
+                .ok_or_else(|| anyhow::anyhow!("missing originator"))?
+                .into(),
+            beneficiary: compliance
+                .beneficiary
+                .ok_or_else(|| anyhow::anyhow!("missing beneficiary"))?
+                .into(),
+        })
+    }
+}
+
+impl From<&super::Party> for Party {
+    fn from(party: &super::Party) -> Self {
+        Self {
+            name: party.name.clone(),
+            address: party.address.clone().unwrap_or_default(),
+            country: party.country.clone().unwrap_or_default(),
+            identifier: party.identifier.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Party> for super::Party {
+    fn from(party: Party) -> Self {
+        let non_empty = |field: String| Some(field).filter(|field| !field.is_empty());
+        Self {
+            name: party.name,
+            address: non_empty(party.address),
+            country: non_empty(party.country),
+            identifier: non_empty(party.identifier),
+        }
+    }
+}
+
+impl TryFrom<Request> for super::Request {
+    type Error = anyhow::Error;
+
+    fn try_from(request: Request) -> Result<Self, Self::Error> {
+        Ok(Self {
+            from: AccountId::try_from_be_slice(&request.from)?,
+            to: AccountId::try_from_be_slice(&request.to)?,
+            amount: Decimal::from_str(&request.amount)?,
+            side: match Side::from_i32(request.side) {
+                Some(Side::SellExact) => super::Side::SellExact,
+                Some(Side::BuyExact) => super::Side::BuyExact,
+                None => return Err(anyhow::anyhow!("invalid side {}", request.side)),
+            },
+            memo: Some(request.memo).filter(|memo| !memo.is_empty()),
+            two_way: request.two_way,
+            compliance: request.compliance.map(TryInto::try_into).transpose()?,
+            settle_at: Some(request.settle_at).filter(|settle_at| *settle_at > 0),
+        })
+    }
+}
+
+impl From<super::FailureCode> for FailureCode {
+    fn from(code: super::FailureCode) -> Self {
+        match code {
+            super::FailureCode::RateUnavailable => FailureCode::RateUnavailable,
+            super::FailureCode::InsufficientLiquidity => FailureCode::InsufficientLiquidity,
+            super::FailureCode::Expired => FailureCode::Expired,
+            super::FailureCode::TransferError => FailureCode::TransferError,
+            super::FailureCode::Slippage => FailureCode::Slippage,
+        }
+    }
+}
+
+impl From<FailureCode> for super::FailureCode {
+    fn from(code: FailureCode) -> Self {
+        match code {
+            FailureCode::RateUnavailable => super::FailureCode::RateUnavailable,
+            FailureCode::InsufficientLiquidity => super::FailureCode::InsufficientLiquidity,
+            FailureCode::Expired => super::FailureCode::Expired,
+            FailureCode::TransferError => super::FailureCode::TransferError,
+            FailureCode::Slippage => super::FailureCode::Slippage,
+        }
+    }
+}
+
+fn required<T>(message: Option<T>) -> anyhow::Result<T> {
+    message.ok_or_else(|| anyhow::anyhow!("missing request"))
+}
+
+/// Bound of an `AmountRange`, unbounded when empty
+fn bound(bound: &str) -> anyhow::Result<Option<Decimal>> {
+    match bound {
+        "" => Ok(None),
+        bound => Ok(Some(Decimal::from_str(bound)?)),
+    }
+}
+
+/// Decodes a protobuf encoded event
+pub fn decode(payload: &[u8]) -> anyhow::Result<super::Envelope> {
+    FxSwapMetadata::decode(payload)?.try_into()
+}
@@ -0,0 +1,90 @@
+//! Unversioned event schema, published before events were wrapped in an `Envelope`
+
+use m10_sdk::account::AccountId;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum Event {
+    Request(Request),
+    Quote(Quote),
+    Execute(Execute),
+    Completed,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Request {
+    pub from: AccountId,
+    pub to: AccountId,
+    pub amount: Decimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Quote {
+    pub request: Request,
+    pub rate: Decimal,
+    pub intermediary: AccountId,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Execute {
+    pub request: Request,
+    pub valid_until: u64,
+    pub upper_limit: Decimal,
+    pub lower_limits: Decimal,
+}
+
+impl From<Event> for super::Event {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::Request(request) => super::Event::Request(request.into()),
+            Event::Quote(quote) => super::Event::Quote(super::Quote {
+                quote_id: Uuid::nil(),
+                source_amount: quote.request.amount,
+                target_amount: quote.request.amount * quote.rate,
+                request: quote.request.into(),
+                rate: quote.rate,
+                intermediary: quote.intermediary,
+                fees: Default::default(),
+                requoted: None,
+                tiers: Vec::new(),
+                ask: None,
+                route: Vec::new(),
+                indicative: false,
+                // v1 quotes carry no expiry & are treated as expired
+                valid_until: 0,
+                signature: None,
+            }),
+            Event::Execute(execute) => super::Event::Execute(super::Execute {
+                request: execute.request.into(),
+                quote_id: Uuid::nil(),
+                quote: None,
+                strategy: super::Strategy::Limit {
+                    bounds: super::Bounds {
+                        lower: execute.lower_limits,
+                        upper: execute.upper_limit,
+                    },
+                    deadline: execute.valid_until,
+                },
+                direction: Default::default(),
+            }),
+            Event::Completed => super::Event::Completed { paper_trade: false },
+        }
+    }
+}
+
+impl From<Request> for super::Request {
+    fn from(request: Request) -> Self {
+        Self {
+            from: request.from,
+            to: request.to,
+            amount: request.amount,
+            side: super::Side::SellExact,
+            memo: None,
+            compliance: None,
+            two_way: false,
+            settle_at: None,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Windows during which pairs are quoted & settled, in the local time of their desk
+
+use crate::config::HoursConfig;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// Window open on `days` from `open` until `close`, in local time. It closes the next day when `close` isn't after `open`
+#[derive(Debug, Clone)]
+pub struct Window {
+    timezone: Tz,
+    days: Vec<Weekday>,
+    open: NaiveTime,
+    close: NaiveTime,
+}
+
+impl Window {
+    pub fn new(timezone: Tz, days: Vec<Weekday>, open: &str, close: &str) -> anyhow::Result<Self> {
+        if days.is_empty() {
+            return Err(anyhow::anyhow!("A window must open on at least one day"));
+        }
+        Ok(Self {
+            timezone,
+            days,
+            open: parse_time(open)?,
+            close: parse_time(close)?,
+        })
+    }
+
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.timezone);
+        let (day, time) = (local.weekday(), local.time());
+        match self.open < self.close {
+            true => self.days.contains(&day) && self.open <= time && time < self.close,
+            // Overnight windows belong to the day they open
+            false => {
+                (self.days.contains(&day) && time >= self.open)
+                    || (self.days.contains(&day.pred()) && time < self.close)
+            }
+        }
+    }
+
+    /// Time at which the window next opens, `at` itself when already open
+    pub fn next_open(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        if self.is_open(at) {
+            return at;
+        }
+        let today = at.with_timezone(&self.timezone).date_naive();
+        (0..=7)
+            .map(|days| today + Duration::days(days))
+            .filter(|date| self.days.contains(&date.weekday()))
+            .filter_map(|date| {
+                let open = date.and_time(self.open);
+                // Opening times skipped by a DST change open an hour later
+                self.timezone
+                    .from_local_datetime(&open)
+                    .earliest()
+                    .or_else(|| {
+                        self.timezone
+                            .from_local_datetime(&(open + Duration::hours(1)))
+                            .earliest()
+                    })
+            })
+            .map(|open| open.with_timezone(&Utc))
+            .find(|open| *open > at)
+            .unwrap_or(at)
+    }
+}
+
+/// Quoting & settlement windows of a pair
+#[derive(Debug, Clone)]
+struct PairHours {
+    base: String,
+    quote: String,
+    trading: Window,
+    settlement: Window,
+}
+
+/// Hours of the pairs with restricted trading, the others being open at all times
+#[derive(Debug, Clone, Default)]
+pub struct TradingHours {
+    pairs: Vec<PairHours>,
+}
+
+impl TradingHours {
+    pub fn new(config: &[HoursConfig]) -> anyhow::Result<Self> {
+        let pairs = config
+            .iter()
+            .map(|hours| {
+                let timezone = hours.timezone.parse::<Tz>().map_err(|err| {
+                    anyhow::anyhow!("Invalid timezone {}: {}", hours.timezone, err)
+                })?;
+                let days = hours
+                    .days
+                    .iter()
+                    .map(|day| {
+                        day.parse::<Weekday>()
+                            .map_err(|_| anyhow::anyhow!("Invalid day {}", day))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let trading = Window::new(timezone, days.clone(), &hours.open, &hours.close)?;
+                let settlement = match &hours.settlement {
+                    Some(window) => Window::new(timezone, days, &window.open, &window.close)?,
+                    None => trading.clone(),
+                };
+                Ok(PairHours {
+                    base: hours.base.to_lowercase(),
+                    quote: hours.quote.to_lowercase(),
+                    trading,
+                    settlement,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { pairs })
+    }
+
+    fn pair(&self, from: &str, to: &str) -> Option<&PairHours> {
+        let (from, to) = (from.to_lowercase(), to.to_lowercase());
+        self.pairs.iter().find(|pair| {
+            (pair.base == from && pair.quote == to) || (pair.base == to && pair.quote == from)
+        })
+    }
+
+    /// Time at which swaps from `from` to `to` are next quoted, if closed at `at`
+    pub fn closed_until(&self, from: &str, to: &str, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let window = &self.pair(from, to)?.trading;
+        (!window.is_open(at)).then(|| window.next_open(at))
+    }
+
+    /// Time at which swaps from `from` to `to` are next settled, if closed at `at`
+    pub fn settlement_closed_until(
+        &self,
+        from: &str,
+        to: &str,
+        at: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let window = &self.pair(from, to)?.settlement;
+        (!window.is_open(at)).then(|| window.next_open(at))
+    }
+}
+
+fn parse_time(time: &str) -> anyhow::Result<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| anyhow::anyhow!("Invalid time {}, expected HH:MM", time))
+}
@@ -1,93 +1,692 @@
-use crate::config::LiquidityConfig;
-use crate::event::{Event, Execute, Quote, Request};
+use crate::alerts::{Alert, AlertKind, Alerter};
+use crate::approvals::{Approvals, PendingApproval};
+use crate::breaker::CircuitBreaker;
+use crate::cursor::Cursors;
+use crate::endpoints::Endpoints;
+use crate::limits::Limits;
+use crate::netting::Netting;
+use crate::pool::{SwapPool, Ticket};
+use crate::rates::RateProvider;
+use crate::retry::Retry;
+use crate::storage::{Entry, PendingSwap, Storage};
+use crate::store::{HeldFunds, SwapStore};
+use crate::ticker::RateTicker;
 use crate::LedgerDB;
+use chrono::{DateTime, Utc};
 use futures_util::StreamExt;
 use m10_sdk::account::AccountId;
-use m10_sdk::client::Channel;
+use m10_sdk::error::M10Result;
+use m10_sdk::sdk::transaction_data::Data;
 use m10_sdk::{
-    AccountFilter, Action, ActionBuilder, Ed25519, M10Client, MetadataExt, StepBuilder, Transfer,
-    TransferBuilder, WithContext,
+    AccountFilter, Action, ActionBuilder, ActionsFilter, Ed25519, M10Client, MetadataExt, Signer,
+    StepBuilder, TransactionExt, Transfer, TransferBuilder, TransferFilter, TransferStatus, TxId,
+    TxnFilter, WithContext,
 };
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
+use service::config::{
+    Addresses, ApprovalConfig, Checkpoint, ComplianceConfig, CreditBreach, CreditConfig,
+    CurrencyCode, EngineConfig, LiquidityConfig, NegotiationConfig, NettingConfig, PairConfig,
+    PairQuoteConfig, RefundConfig, RequoteConfig, Rounding, RoutingConfig, Selection, SkewConfig,
+    TierConfig, TransportConfig,
+};
+use service::event::{
+    AmountRange, Ask, Bounds, CounterOffer, Envelope, Event, Execute, FailureCode, Fees,
+    IndicativeQuote, NettingReport, PartialFill, Quote, Receipt, Refund, Request, Requoted, Side,
+    Signature, Strategy, SwapState,
+};
+use service::hours::TradingHours;
+use service::money::Money;
+use service::pricing::{check_slippage, effective_rate, Payout, Price};
 use service::{FxSwapMetadata, FX_SWAP_ACTION};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, SystemTime};
-use tracing::{error, info, info_span, Instrument};
+use tracing::{error, info, info_span, warn, Instrument};
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct Ledger {
     currency: String,
     client: M10Client<Ed25519>,
+    /// Endpoints of the ledger `client` fails over between
+    endpoints: Endpoints,
     liquidity: AccountId,
-    base_rate: Decimal,
+    /// Accounts sharding the liquidity inventory, `liquidity` first
+    accounts: Arc<Vec<AccountId>>,
+    /// How the account receiving a swap is selected
+    selection: Selection,
+    /// Index of the next account selected round-robin
+    next_account: Arc<AtomicUsize>,
+    /// Account selected for each counterparty, when sticky
+    sticky: Arc<Mutex<HashMap<AccountId, AccountId>>>,
+    /// Accounts being rebalanced, not selected meanwhile
+    rebalancing: Arc<Mutex<HashSet<AccountId>>>,
+    /// Parameters of the quotes, swapped when the config is reloaded
+    pricing: Arc<RwLock<Arc<Pricing>>>,
+    /// Conversions between amounts of the currency & ledger units
+    money: Money,
+    /// Decimals of the currency, looked up on the ledger when unset
+    decimals: Option<u32>,
+    /// Liquidity balance below which alerts are fired
+    min_balance: Option<Decimal>,
+    /// Target share of the inventory value
+    allocation: Option<Decimal>,
+    /// Account the liquidity is rebalanced from & to
+    reserve: Option<AccountId>,
+    /// Account collecting the fees, separate from the liquidity inventory
+    fee_account: Option<AccountId>,
+    /// Currency through which other pairs are routed
+    pivot: Option<CurrencyCode>,
+    requote: RequoteConfig,
+    negotiation: NegotiationConfig,
+    compliance: ComplianceConfig,
+    refund: RefundConfig,
+    store: SwapStore,
+    /// Volume settled by each account
+    volumes: Limits,
+    /// Unsettled exposure allowed to each account
+    credit: Arc<CreditConfig>,
+    /// Notional from which requests await an operator's approval
+    approval: ApprovalConfig,
+    /// Requests waiting for an operator's decision
+    approvals: Approvals,
+    /// Durable record of swap lifecycles, if configured
+    storage: Option<Storage>,
+    /// Retries of transient ledger failures
+    retry: Retry,
+    /// Bounds on the swaps settled at once
+    pool: SwapPool,
+    /// Source of the rates quoted & settled
+    rates: Arc<dyn RateProvider>,
+    /// Pauses pairs whose rates went stale
+    breaker: CircuitBreaker,
+    /// Rates of the pairs watched by limit executions
+    ticker: RateTicker,
+    /// Largest deviation of the settlement rate from the quoted rate, in basis points
+    max_slippage_bps: Option<u32>,
+    /// Hours during which pairs are quoted & settled
+    hours: Arc<TradingHours>,
+    /// Payouts owed over the current netting window, paid out one by one when unset
+    netting: Option<Netting>,
+    /// Only simulates settlements, publishing them as paper trades
+    dry_run: bool,
+    /// Transaction the observations start from on startup, instead of their cursors
+    start_from: Option<Checkpoint>,
+    /// Key used to sign published quotes
+    signer: Arc<Ed25519>,
 }
 
 impl Ledger {
-    pub fn new(address: String, currency: String, config: LiquidityConfig) -> anyhow::Result<Self> {
-        let channel = Channel::from_shared(address)?
-            .keep_alive_while_idle(true)
-            .http2_keep_alive_interval(Duration::from_secs(30))
-            .timeout(Duration::from_secs(30))
-            .connect_lazy()?;
-        let signer = Ed25519::load_key_pair(
-            config
-                .key_pair
-                .to_str()
-                .ok_or_else(|| anyhow::anyhow!("Invalid key path"))?,
-        )?;
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        address: Addresses,
+        transport: &TransportConfig,
+        currency: String,
+        config: LiquidityConfig,
+        key_pair: &[u8],
+        requote: RequoteConfig,
+        negotiation: NegotiationConfig,
+        compliance: ComplianceConfig,
+        refund: RefundConfig,
+        routing: RoutingConfig,
+        pairs: &HashMap<String, PairQuoteConfig>,
+        engine: &EngineConfig,
+        store: SwapStore,
+        volumes: Limits,
+        credit: Arc<CreditConfig>,
+        approval: ApprovalConfig,
+        approvals: Approvals,
+        storage: Option<Storage>,
+        retry: Retry,
+        pool: SwapPool,
+        rates: Arc<dyn RateProvider>,
+        breaker: CircuitBreaker,
+        ticker: RateTicker,
+        max_slippage_bps: Option<u32>,
+        hours: Arc<TradingHours>,
+        netting: Option<NettingConfig>,
+        dry_run: bool,
+    ) -> anyhow::Result<Self> {
+        let (endpoints, channel) = Endpoints::new(address.urls(), transport, key_pair)?;
+        let signer = Ed25519::from_pkcs8(key_pair)?;
         let client = M10Client::new(signer, channel);
+        let liquidity = config.account;
+        let mut accounts = vec![liquidity];
+        for &account in &config.accounts {
+            if !accounts.contains(&account) {
+                accounts.push(account);
+            }
+        }
 
         Ok(Self {
             currency: currency.to_lowercase(),
             client,
-            liquidity: AccountId::try_from_be_slice(&hex::decode(&config.account)?)?,
-            base_rate: config.base_rate,
+            endpoints,
+            liquidity,
+            accounts: Arc::new(accounts),
+            selection: config.selection,
+            next_account: Default::default(),
+            sticky: Default::default(),
+            rebalancing: Default::default(),
+            pricing: Arc::new(RwLock::new(Arc::new(Pricing::new(
+                &currency, &config, &routing, pairs, engine,
+            )?))),
+            money: Money::new(config.decimals.unwrap_or_default(), config.rounding)?,
+            decimals: config.decimals,
+            min_balance: config.min_balance,
+            allocation: config.allocation,
+            reserve: match config.reserve {
+                Some(account) => Some(account),
+                None if config.allocation.is_some() => {
+                    return Err(anyhow::anyhow!(
+                        "A reserve account is required to rebalance {}",
+                        currency
+                    ))
+                }
+                None => None,
+            },
+            fee_account: config.fee_account,
+            pivot: routing.pivot.map(|pivot| pivot.to_lowercase()),
+            requote,
+            negotiation,
+            compliance,
+            refund,
+            store,
+            volumes,
+            credit,
+            approval,
+            approvals,
+            storage,
+            retry,
+            pool,
+            rates,
+            breaker,
+            ticker,
+            max_slippage_bps,
+            hours,
+            netting: netting.map(|_| Netting::default()),
+            dry_run,
+            start_from: config.start_from,
+            signer: Arc::new(Ed25519::from_pkcs8(key_pair)?),
         })
     }
 
+    /// Sends requests to the first reachable endpoint of the ledger
+    pub async fn connect(&self) {
+        self.endpoints.connect(self.liquidity).await
+    }
+
+    /// Looks up the decimals of the currency on the ledger, unless configured
+    pub async fn load_decimals(&mut self) -> anyhow::Result<()> {
+        let decimals = match self.decimals {
+            Some(decimals) => decimals,
+            None => {
+                self.retry
+                    .call("get_account_info", || {
+                        self.client.get_account_info(self.liquidity)
+                    })
+                    .await?
+                    .decimals
+            }
+        };
+        info!(currency = %self.currency, %decimals, rounding = ?self.money.rounding(), "Currency amounts");
+        self.money = Money::new(decimals, self.money.rounding())?;
+        Ok(())
+    }
+
+    /// Conversions between amounts of the currency & ledger units
+    pub fn money(&self) -> Money {
+        self.money
+    }
+
+    /// Spread charged on swaps to `currency`, in basis points
+    fn spread_to(&self, currency: &str) -> u32 {
+        let pricing = self.pricing();
+        pricing
+            .pair_terms
+            .get(currency)
+            .and_then(|terms| terms.spread_bps)
+            .or_else(|| pricing.pair_spread_bps.get(currency).copied())
+            .unwrap_or(pricing.spread_bps)
+    }
+
+    /// Sizes of the swaps to `currency` served, in the liquidity currency
+    fn limits_to(&self, currency: &str) -> AmountRange {
+        let pricing = self.pricing();
+        match pricing.pair_terms.get(currency) {
+            Some(terms) => AmountRange {
+                min: terms.min_amount.or(pricing.limits.min),
+                max: terms.max_amount.or(pricing.limits.max),
+            },
+            None => pricing.limits,
+        }
+    }
+
+    /// Validity of the quotes of swaps to `currency`
+    fn quote_ttl_to(&self, currency: &str) -> Duration {
+        let pricing = self.pricing();
+        pricing
+            .pair_terms
+            .get(currency)
+            .and_then(|terms| terms.quote_ttl)
+            .map(Duration::from_secs)
+            .unwrap_or(pricing.quote_ttl)
+    }
+
+    /// Whether swaps to `currency` are quoted at all
+    fn pair_enabled(&self, currency: &str) -> bool {
+        self.pricing()
+            .pair_terms
+            .get(currency)
+            .is_none_or(|terms| terms.enabled)
+    }
+
+    /// Adds `amount` of the liquidity currency settled for `request` to the volume of its account
+    fn record_volume(&self, request: &Request, amount: u64) {
+        let notional = self.money.from_units(amount) / self.base_rate();
+        if let Err(err) = self.volumes.record(&request.from, notional) {
+            warn!(%err, %notional, "Could not record settled volume");
+        }
+    }
+
+    fn quotes_directly(&self, currency: &str) -> bool {
+        self.pricing()
+            .pairs
+            .as_ref()
+            .is_none_or(|pairs| pairs.contains(currency))
+    }
+
+    /// Whether swaps from `from` to `to` are allowed by the pair allow-list
+    fn allows_pair(&self, from: &str, to: &str) -> bool {
+        let allowed = &self.pricing().allowed_pairs;
+        allowed.is_empty() || allowed.iter().any(|pair| pair.allows(from, to))
+    }
+
     async fn get_currencies(&self, request: &Request) -> anyhow::Result<(String, String)> {
-        let from = self.client.get_account_info(request.from).await?;
-        let to = self.client.get_account_info(request.to).await?;
+        let from = self
+            .retry
+            .call("get_account_info", || {
+                self.client.get_account_info(request.from)
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("Unknown account {}", request.from))?;
+        let to = self
+            .retry
+            .call("get_account_info", || {
+                self.client.get_account_info(request.to)
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("Unknown account {}", request.to))?;
         Ok((from.code.to_lowercase(), to.code.to_lowercase()))
     }
 
-    pub async fn observe_transfers(self, db: LedgerDB) -> anyhow::Result<()> {
+    /// Whether the request is answered by this liquidity provider: every provider observes it, but only that of its
+    /// source currency answers. Requests of unknown accounts are left for the validation to reject
+    async fn serves(&self, request: &Request) -> bool {
+        match self.get_currencies(request).await {
+            Ok((from_currency, _)) => from_currency == self.currency,
+            Err(_) => true,
+        }
+    }
+
+    /// Checks the request is well-formed & can be served by this liquidity provider,
+    /// returning the source & target currencies
+    async fn validate_request(
+        &self,
+        db: &LedgerDB,
+        request: &Request,
+    ) -> anyhow::Result<(String, String)> {
+        request.validate()?;
+        let (from_currency, to_currency) = self.get_currencies(request).await?;
+        if from_currency != self.currency {
+            return Err(anyhow::anyhow!(
+                "Source currency {} does not match the liquidity currency {}",
+                from_currency,
+                self.currency
+            ));
+        }
+        if !db.contains_key(&to_currency) {
+            return Err(anyhow::anyhow!(
+                "Unsupported target currency {}",
+                to_currency
+            ));
+        }
+        if !self.allows_pair(&from_currency, &to_currency) {
+            let (from, to) = (from_currency.to_uppercase(), to_currency.to_uppercase());
+            return Err(match self.allows_pair(&to_currency, &from_currency) {
+                true => anyhow::anyhow!(
+                    "Swaps from {} to {} are not quoted, only from {} to {}",
+                    from,
+                    to,
+                    to,
+                    from
+                ),
+                false => anyhow::anyhow!("Pair {}/{} is not quoted", from, to),
+            });
+        }
+        if !self.pair_enabled(&to_currency) {
+            return Err(anyhow::anyhow!(
+                "Pair {}/{} is disabled",
+                from_currency.to_uppercase(),
+                to_currency.to_uppercase()
+            ));
+        }
+        route(db, &from_currency, &to_currency)?;
+        if let Some(opens_at) = self
+            .hours
+            .closed_until(&from_currency, &to_currency, Utc::now())
+        {
+            return Err(Closed {
+                pair: format!(
+                    "{}/{}",
+                    from_currency.to_uppercase(),
+                    to_currency.to_uppercase()
+                ),
+                opens_at,
+            }
+            .into());
+        }
+        let limits = self.limits_to(&to_currency);
+        if limits.is_bounded() {
+            // The amount is in the target currency when buying
+            let amount = match request.side {
+                Side::SellExact => request.amount,
+                Side::BuyExact => {
+                    let rate = get_fx_rate(db, &from_currency, &to_currency).await?;
+                    request
+                        .amount
+                        .checked_div(rate)
+                        .ok_or_else(|| anyhow::anyhow!("Amount {} overflows", request.amount))?
+                }
+            };
+            if !limits.contains(amount) {
+                return Err(OutOfRange {
+                    amount,
+                    allowed: limits,
+                }
+                .into());
+            }
+        }
+        // The amount is in the target currency when buying
+        let base_rate = match request.side {
+            Side::SellExact => self.base_rate(),
+            Side::BuyExact => db[&to_currency].base_rate(),
+        };
+        let notional = request
+            .amount
+            .checked_div(base_rate)
+            .ok_or_else(|| anyhow::anyhow!("Amount {} overflows", request.amount))?;
+        if let Some(threshold) = self.compliance.threshold {
+            if request.compliance.is_none() && notional > threshold {
+                return Err(anyhow::anyhow!(
+                    "Originator & beneficiary information is required above a notional of {}",
+                    threshold
+                ));
+            }
+        }
+        self.volumes.check(&request.from, notional)?;
+        Ok((from_currency, to_currency))
+    }
+
+    /// Checks the execution is well-formed & funded by the requesting account
+    async fn validate_execute(
+        &self,
+        db: &LedgerDB,
+        execute: &Execute,
+        source: &SourceLeg,
+    ) -> anyhow::Result<()> {
+        execute.validate()?;
+        let request = execute.settlement_request();
+        if source.from != Some(request.from) {
+            return Err(anyhow::anyhow!(
+                "Execution was not funded by account {}",
+                request.from
+            ));
+        }
+        if source.amount == 0 {
+            return Err(anyhow::anyhow!("Execution transferred no funds"));
+        }
+        if source.pending && !matches!(execute.strategy, Strategy::Immediate) {
+            return Err(anyhow::anyhow!(
+                "Held executions can only be settled immediately"
+            ));
+        }
+        if source.pending && request.settle_at.is_some() {
+            return Err(anyhow::anyhow!("Forward swaps can't be held"));
+        }
+        self.validate_request(db, &request).await?;
+        Ok(())
+    }
+
+    /// Name of the stream of transfers observed by `account`
+    pub fn transfer_stream(&self, account: AccountId) -> String {
+        match account == self.liquidity {
+            true => format!("transfers/{}", self.currency),
+            false => format!("transfers/{}/{}", self.currency, account),
+        }
+    }
+
+    /// Name of the observation of the swap actions, as persisted in the cursors
+    fn action_stream(&self) -> String {
+        format!("actions/{}", self.currency)
+    }
+
+    /// Moves the cursors of every observation of the ledger to the configured checkpoint, if any
+    pub fn rewind(&self, cursors: &Cursors) -> anyhow::Result<()> {
+        let checkpoint = match self.start_from {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(()),
+        };
+        let streams = std::iter::once(self.action_stream()).chain(
+            self.accounts
+                .iter()
+                .map(|&account| self.transfer_stream(account)),
+        );
+        for stream in streams {
+            info!(%stream, %checkpoint, "Starting from the configured checkpoint");
+            cursors.rewind(&stream, checkpoint)?;
+        }
+        Ok(())
+    }
+
+    /// Observes the transfers of `account`, failing over to another endpoint if the ledger became unreachable
+    pub async fn observe_transfers(
+        self,
+        db: LedgerDB,
+        cursors: Cursors,
+        account: AccountId,
+    ) -> anyhow::Result<()> {
+        let observed = self.watch_transfers(db, cursors, account).await;
+        if observed.is_err() {
+            self.endpoints.fail_over(self.liquidity).await;
+        }
+        observed
+    }
+
+    async fn watch_transfers(
+        &self,
+        db: LedgerDB,
+        cursors: Cursors,
+        account: AccountId,
+    ) -> anyhow::Result<()> {
+        let stream = self.transfer_stream(account);
         // Sign the request to observe all transfer from & to the liquidity account
-        let mut transfers = self
-            .client
-            .observe_transfers(AccountFilter::default().involves(self.liquidity))
-            .await?;
+        let mut filter = AccountFilter::default().involves(account);
+        if let Some(tx_id) = cursors.resume_from(&stream) {
+            info!(%tx_id, "Resuming transfers");
+            filter = filter.starting_from(tx_id);
+        }
+        let mut transfers = self.client.observe_transfers(filter).await?;
         info!("Observing transfers");
 
-        while let Some(Ok(transfers)) = transfers.next().await {
-            for transfer in transfers {
-                if let Err(err) = self.handle_transfer(db.clone(), transfer).await {
+        while let Some(transfers) = transfers.next().await {
+            for transfer in transfers? {
+                let tx_id = transfer.tx_id;
+                if let Err(err) = self.handle_transfer(db.clone(), account, transfer).await {
                     error!(%err);
                 }
+                if let Err(err) = cursors.advance(&stream, tx_id) {
+                    error!(%err, "Could not persist cursor");
+                }
             }
         }
         Ok(())
     }
 
-    async fn handle_transfer(&self, ledger: LedgerDB, transfer: Transfer) -> anyhow::Result<()> {
+    async fn handle_transfer(
+        &self,
+        ledger: LedgerDB,
+        account: AccountId,
+        transfer: Transfer,
+    ) -> anyhow::Result<()> {
         if let Some(payload) = transfer.with_type::<FxSwapMetadata>() {
-            let event = serde_json::from_slice::<Event>(payload)?;
+            if !self
+                .store
+                .mark_processed(&transfer.context_id, account, transfer.tx_id)
+            {
+                info!(tx_id = %transfer.tx_id, "Ignoring already processed transfer");
+                return Ok(());
+            }
+            let source = SourceLeg {
+                tx_id: transfer.tx_id,
+                from: transfer
+                    .steps
+                    .iter()
+                    .find(|step| step.to == account)
+                    .map(|step| step.from),
+                amount: transfer
+                    .steps
+                    .iter()
+                    .filter(|step| step.to == account)
+                    .map(|step| step.amount)
+                    .sum(),
+                pending: matches!(transfer.status, TransferStatus::Pending),
+                account,
+            };
+            let event = match Envelope::decode(payload) {
+                Ok(envelope) => {
+                    if !self.observe(&transfer.context_id, account, &envelope) {
+                        return Ok(());
+                    }
+                    envelope.event
+                }
+                Err(err) => {
+                    let reason = err.to_string();
+                    info!(%reason, "Rejecting invalid payload");
+                    if let Some(from) = source.from {
+                        let reject = Event::Reject {
+                            reason,
+                            allowed: None,
+                            reopens_at: None,
+                        };
+                        self.publish(from, &reject, transfer.context_id).await?;
+                    }
+                    return Ok(());
+                }
+            };
             info!(?event);
             if let Event::Execute(execute) = event {
-                let from = execute.request.from;
-                let to = execute.request.to;
-                let this = self.clone();
-                tokio::spawn(
-                    async move {
-                        info!("Start");
-                        if let Err(err) =
-                            swap_task(this, ledger, execute, transfer.context_id).await
-                        {
-                            error!(%err);
-                        }
-                        info!("Done");
+                // Transfers replayed after a restart were settled or refunded already
+                if self.replayed(&transfer.context_id, transfer.tx_id).await {
+                    info!(tx_id = %transfer.tx_id, "Ignoring replayed execution");
+                    return Ok(());
+                }
+                self.persist(
+                    &transfer.context_id,
+                    &Entry::Event(Event::Execute(execute.clone())),
+                    Some(transfer.tx_id),
+                )
+                .await;
+                if self.store.is_terminal(&transfer.context_id) {
+                    info!("Ignoring execution of a terminated swap");
+                    return self
+                        .refund_source(
+                            &execute.settlement_request(),
+                            &source,
+                            "Swap was cancelled".to_string(),
+                            transfer.context_id,
+                        )
+                        .await;
+                }
+                let verified = match self.validate_execute(&ledger, &execute, &source).await {
+                    Ok(()) => self.verify_execute(&ledger, &execute).and_then(|quote| {
+                        self.verify_funding(&execute, &source)?;
+                        Ok(quote)
+                    }),
+                    Err(err) => Err(err),
+                };
+                let rejection = match verified {
+                    Ok(quote) if quote.is_expired() => Some(Event::Failed {
+                        code: FailureCode::Expired,
+                        message: "Quote expired".to_string(),
+                    }),
+                    Ok(quote)
+                        if !self
+                            .store
+                            .retire_quote(&transfer.context_id, quote.quote_id) =>
+                    {
+                        Some(Event::Reject {
+                            reason: format!("Quote {} is no longer valid", quote.quote_id),
+                            allowed: None,
+                            reopens_at: None,
+                        })
                     }
-                    .instrument(info_span!("swap", %from, %to)),
-                );
+                    Ok(_) => None,
+                    Err(err) => Some(rejection(&err)),
+                };
+                if let Some(event) = rejection {
+                    info!(?event, "Rejecting execution");
+                    let request = execute.settlement_request();
+                    self.publish(request.from, &event, transfer.context_id.clone())
+                        .await?;
+                    return self
+                        .refund_source(
+                            &request,
+                            &source,
+                            "Execution was rejected".to_string(),
+                            transfer.context_id,
+                        )
+                        .await;
+                }
+                if let Some(settle_at) = execute.request.settle_at {
+                    self.persist_accepted(&execute, &source, &transfer.context_id)
+                        .await;
+                    return self.defer_swap(
+                        ledger,
+                        execute,
+                        source,
+                        0,
+                        transfer.context_id,
+                        settle_at,
+                    );
+                }
+                let ticket = match self.pool.admit() {
+                    Some(ticket) => ticket,
+                    None => {
+                        warn!(queued = %self.pool.queued(), "Swap queue full, rejecting execution");
+                        let request = execute.settlement_request();
+                        let reject = Event::Reject {
+                            reason: "Too many swaps being settled, try again later".to_string(),
+                            allowed: None,
+                            reopens_at: None,
+                        };
+                        self.publish(request.from, &reject, transfer.context_id.clone())
+                            .await?;
+                        return self
+                            .refund_source(
+                                &request,
+                                &source,
+                                "Swap queue is full".to_string(),
+                                transfer.context_id,
+                            )
+                            .await;
+                    }
+                };
+                self.persist_accepted(&execute, &source, &transfer.context_id)
+                    .await;
+                self.spawn_swap(ledger, execute, source, 0, transfer.context_id, ticket)?;
             } else {
                 error!("invalid event type");
             }
@@ -95,129 +694,2414 @@ impl Ledger {
         Ok(())
     }
 
-    async fn handle_request(&self, db: &LedgerDB, action: Action) -> anyhow::Result<()> {
-        let event = serde_json::from_slice::<Event>(&action.payload)?;
+    /// Settles the payouts owed over each netting window of `window` seconds in a single batched transfer,
+    /// with one step per liquidity account & recipient
+    pub async fn net_payouts(self, window: u64) -> anyhow::Result<()> {
+        let netting = match &self.netting {
+            Some(netting) => netting.clone(),
+            None => return Ok(()),
+        };
+        let mut interval = tokio::time::interval(Duration::from_secs(window));
+        loop {
+            interval.tick().await;
+            let positions = netting.close_window();
+            if positions.is_empty() {
+                continue;
+            }
+            let payouts: usize = positions.iter().map(|position| position.payouts()).sum();
+            let context_id = Uuid::new_v4().as_bytes().to_vec();
+            info!(
+                positions = positions.len(),
+                %payouts,
+                context_id = %hex::encode(&context_id),
+                "Settling netted payouts"
+            );
+            let settled = self
+                .retry
+                .run(
+                    "net_payouts",
+                    || {
+                        let transfer = positions
+                            .iter()
+                            .fold(TransferBuilder::new(), |transfer, position| {
+                                transfer.step(StepBuilder::new(
+                                    position.payer,
+                                    position.recipient,
+                                    position.amount,
+                                ))
+                            })
+                            .context_id(context_id.clone());
+                        self.client.transfer(transfer)
+                    },
+                    || async {
+                        let transfers = self
+                            .client
+                            .list_transfers(TxnFilter::<TransferFilter>::by_context_id(
+                                context_id.clone(),
+                            ))
+                            .await?;
+                        Ok(transfers
+                            .into_iter()
+                            .find(|transfer| transfer.success)
+                            .map(|transfer| transfer.tx_id))
+                    },
+                )
+                .await;
+            match settled {
+                Ok(tx_id) => {
+                    info!(%tx_id, "Netted payouts settled");
+                    for position in positions {
+                        position.settled(tx_id);
+                    }
+                }
+                Err(err) => {
+                    error!(%err, "Could not settle netted payouts");
+                    let reason = err.to_string();
+                    for position in positions {
+                        position.failed(&reason);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Records the acceptance of an execution, resumed on restart until settled
+    async fn persist_accepted(&self, execute: &Execute, source: &SourceLeg, context_id: &[u8]) {
+        let accepted = Entry::Accepted {
+            execute: execute.clone(),
+            source_tx_id: source.tx_id,
+            from: execute.settlement_request().from,
+            amount: source.amount,
+            pending: source.pending,
+            currency: self.currency.clone(),
+            account: Some(source.account),
+        };
+        self.persist(context_id, &accepted, Some(source.tx_id))
+            .await;
+    }
+
+    /// Resumes the settlement of an execution interrupted by a restart
+    pub fn resume(&self, db: LedgerDB, swap: PendingSwap) -> anyhow::Result<()> {
+        info!(
+            context_id = %hex::encode(&swap.context_id),
+            settled = %swap.settled,
+            "Resuming swap"
+        );
+        self.store
+            .retire_quote(&swap.context_id, swap.execute.quote_id);
+        let source = SourceLeg {
+            tx_id: swap.source_tx_id,
+            from: Some(swap.from),
+            amount: swap.amount,
+            pending: swap.pending,
+            account: swap.account.unwrap_or(self.liquidity),
+        };
+        if let Some(settle_at) = swap.execute.request.settle_at {
+            return self.defer_swap(
+                db,
+                swap.execute,
+                source,
+                swap.settled,
+                swap.context_id,
+                settle_at,
+            );
+        }
+        let ticket = self.pool.readmit();
+        self.spawn_swap(
+            db,
+            swap.execute,
+            source,
+            swap.settled,
+            swap.context_id,
+            ticket,
+        )
+    }
+
+    /// Holds the source funds of a forward swap until `settle_at`, then queues its settlement.
+    /// The wait isn't tracked, so that shutdowns don't wait for it, the swap being resumed on restart instead
+    fn defer_swap(
+        &self,
+        ledger: LedgerDB,
+        execute: Execute,
+        source: SourceLeg,
+        settled: u64,
+        context_id: Vec<u8>,
+        settle_at: u64,
+    ) -> anyhow::Result<()> {
+        self.hold_source(&execute, &source, settled, &context_id)?;
+        let settle_at = SystemTime::UNIX_EPOCH + Duration::from_secs(settle_at);
+        let wait = settle_at
+            .duration_since(SystemTime::now())
+            .unwrap_or_default();
+        info!(?wait, "Deferring forward swap until its settlement date");
+        let Request { from, to, .. } = execute.settlement_request();
+        let quote_id = execute.quote_id;
+        let this = self.clone();
+        tokio::spawn(
+            async move {
+                tokio::time::sleep(wait).await;
+                let ticket = this.pool.readmit();
+                if let Err(err) =
+                    this.spawn_swap(ledger, execute, source, settled, context_id, ticket)
+                {
+                    error!(%err);
+                }
+            }
+            .instrument(info_span!("forward", %from, %to, %quote_id)),
+        );
+        Ok(())
+    }
+
+    /// Holds the source funds of an accepted execution, refunded if still unsettled past its deadline
+    fn hold_source(
+        &self,
+        execute: &Execute,
+        source: &SourceLeg,
+        settled: u64,
+        context_id: &[u8],
+    ) -> anyhow::Result<()> {
+        self.store.mark_executed(context_id);
+        self.store.set_state(context_id, SwapState::Executing);
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        self.store.hold(
+            context_id,
+            HeldFunds {
+                liquidity: source.account,
+                request: execute.settlement_request(),
+                tx_id: source.tx_id,
+                amount: source.amount - settled,
+                pending: source.pending,
+                expires_at: (execute.settles_by(now) + self.refund.grace) * 1000,
+            },
+        );
+        Ok(())
+    }
+
+    /// Settles an accepted execution in the background once `ticket` is given a slot,
+    /// `settled` of its source leg being already settled
+    fn spawn_swap(
+        &self,
+        ledger: LedgerDB,
+        execute: Execute,
+        source: SourceLeg,
+        settled: u64,
+        context_id: Vec<u8>,
+        ticket: Ticket,
+    ) -> anyhow::Result<()> {
+        self.hold_source(&execute, &source, settled, &context_id)?;
+        let Request { from, to, .. } = execute.settlement_request();
+        let quote_id = execute.quote_id;
+        let this = self.clone();
+        let swap = tokio::spawn(
+            async move {
+                info!(queued = %this.pool.queued(), running = %this.pool.running(), "Queued");
+                let _slot = ticket.slot().await;
+                info!("Start");
+                if let Err(err) = swap_task(
+                    this.clone(),
+                    ledger,
+                    execute,
+                    source,
+                    settled,
+                    context_id.clone(),
+                )
+                .await
+                {
+                    error!(%err);
+                    let code = err
+                        .downcast_ref::<SwapError>()
+                        .map(|err| err.code)
+                        .unwrap_or(FailureCode::TransferError);
+                    let failed = Event::Failed {
+                        code,
+                        message: err.to_string(),
+                    };
+                    if let Err(err) = this.publish(from, &failed, context_id.clone()).await {
+                        error!(%err, "Could not publish failure");
+                    }
+                    if let Some(funds) = this.store.take_held(&context_id) {
+                        let reason = "Swap failed".to_string();
+                        if let Err(err) = this.refund_funds(context_id, funds, reason).await {
+                            error!(%err, "Could not refund failed swap");
+                        }
+                    }
+                }
+                info!("Done");
+            }
+            .instrument(info_span!("swap", %from, %to, %quote_id)),
+        );
+        self.store.track_swap(swap);
+        Ok(())
+    }
+
+    /// Handles a swap action invoked by the account `sender`, if known
+    async fn handle_request(
+        &self,
+        db: &LedgerDB,
+        action: Action,
+        sender: Option<AccountId>,
+    ) -> anyhow::Result<()> {
+        if !self
+            .store
+            .mark_processed(&action.context_id, self.liquidity, action.tx_id)
+        {
+            info!(tx_id = %action.tx_id, "Ignoring already processed action");
+            return Ok(());
+        }
+        let event = match Envelope::decode(&action.payload) {
+            Ok(envelope) => {
+                if !self.observe(&action.context_id, self.liquidity, &envelope) {
+                    return Ok(());
+                }
+                envelope.event
+            }
+            Err(err) => {
+                let reason = err.to_string();
+                info!(%reason, "Rejecting invalid payload");
+                if let Some(sender) = sender {
+                    let reject = Event::Reject {
+                        reason,
+                        allowed: None,
+                        reopens_at: None,
+                    };
+                    self.publish(sender, &reject, action.context_id).await?;
+                }
+                return Ok(());
+            }
+        };
         info!(?event);
+        if !matches!(event, Event::StatusRequest) {
+            self.persist(
+                &action.context_id,
+                &Entry::Event(event.clone()),
+                Some(action.tx_id),
+            )
+            .await;
+        }
         let request = match event {
             Event::Request(request) => request,
-            Event::Quote(_) | Event::Execute(_) | Event::Completed => return Ok(()),
+            // Counter-offers are negotiated by the liquidity provider of the quote
+            Event::CounterOffer(offer) if !self.owns(offer.quote.intermediary) => return Ok(()),
+            Event::CounterOffer(offer) => {
+                return self
+                    .handle_counter_offer(db, offer, sender, action.context_id)
+                    .await;
+            }
+            Event::StatusRequest => {
+                return self.handle_status_request(&action.context_id).await;
+            }
+            Event::Cancel => {
+                let requester = self
+                    .store
+                    .status(&action.context_id)
+                    .map(|(account, _)| account);
+                if requester.is_none() || sender != requester {
+                    warn!(
+                        ?sender,
+                        ?requester,
+                        "Ignoring cancellation not sent by the requester"
+                    );
+                    return Ok(());
+                }
+                info!("Swap cancelled");
+                self.approvals.decide(&action.context_id, false);
+                self.store.mark_terminal(&action.context_id);
+                self.store
+                    .set_state(&action.context_id, SwapState::Cancelled);
+                return Ok(());
+            }
+            Event::Quote(_)
+            | Event::Execute(_)
+            | Event::Completed { .. }
+            | Event::PartialFill(_)
+            | Event::Receipt(_)
+            | Event::Netted(_)
+            | Event::StatusResponse(_)
+            | Event::Refunded(_)
+            | Event::Failed { .. }
+            | Event::Reject { .. } => return Ok(()),
         };
-        let (from_currency, to_currency) = self.get_currencies(&request).await?;
-        if from_currency != self.currency {
+        if !self.serves(&request).await {
             return Ok(());
         }
-        let rate = get_fx_rate(db, &from_currency, &to_currency).await?;
-        let intermediary = db
-            .get(&from_currency)
-            .ok_or_else(|| anyhow::anyhow!("Missing currency"))?
-            .liquidity;
-        let quote = Quote {
-            request,
-            rate,
-            intermediary,
-        };
-        info!(?quote, "Publishing quote");
-        self.client
-            .action(
-                ActionBuilder::for_account(
-                    FX_SWAP_ACTION.to_string(),
-                    self.liquidity,
-                    quote.request.from,
-                )
-                .payload(serde_json::to_vec(&Event::Quote(quote))?),
-                action.context_id,
-            )
-            .await?;
-        Ok(())
+        if self.store.status(&action.context_id).is_some() {
+            info!("Ignoring duplicate request, the context was already answered");
+            return Ok(());
+        }
+        if let Some((notional, reason)) =
+            self.approval_reason(db, &request, &action.context_id).await
+        {
+            return self.park(db, request, action.context_id, notional, reason);
+        }
+        self.answer_request(db, request, action.context_id).await
     }
 
-    pub async fn observe_actions(self, db: LedgerDB) -> anyhow::Result<()> {
-        // Sign the request to observe all actions named `FX_SWAP_ACTION`
-        let mut actions = self
-            .client
-            .observe_actions(AccountFilter::name(FX_SWAP_ACTION).involves(self.liquidity))
-            .await?;
-        info!(action = %FX_SWAP_ACTION, "Started observations");
-        while let Some(Ok(actions)) = actions.next().await {
-            for action in actions {
-                if let Err(err) = self.handle_request(&db, action).await {
+    /// Notional of `request` & why it must be approved by an operator before being quoted, if so
+    async fn approval_reason(
+        &self,
+        db: &LedgerDB,
+        request: &Request,
+        context_id: &[u8],
+    ) -> Option<(Decimal, String)> {
+        // Invalid requests are rejected straight away
+        let (_, to_currency) = self.validate_request(db, request).await.ok()?;
+        let base_rate = match request.side {
+            Side::SellExact => self.base_rate(),
+            Side::BuyExact => db.get(&to_currency)?.base_rate(),
+        };
+        let notional = request.amount.checked_div(base_rate)?;
+        if let Some(threshold) = self.approval.threshold.filter(|t| notional >= *t) {
+            return Some((
+                notional,
+                format!("Notional reaches the approval threshold of {}", threshold),
+            ));
+        }
+        if self.credit.on_breach == CreditBreach::Flag {
+            let breach = self.credit_breach(db, request.from, notional, context_id)?;
+            return Some((notional, breach));
+        }
+        None
+    }
+
+    /// Parks `request` until an operator decides on it, rejecting it if not approved in time
+    fn park(
+        &self,
+        db: &LedgerDB,
+        request: Request,
+        context_id: Vec<u8>,
+        notional: Decimal,
+        reason: String,
+    ) -> anyhow::Result<()> {
+        let timeout = Duration::from_secs(self.approval.timeout);
+        let expires_at = (SystemTime::now() + timeout)
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        info!(%notional, %reason, "Request pending approval");
+        self.store.mark_pending_approval(&context_id, request.from);
+        let decision = self.approvals.park(
+            &context_id,
+            PendingApproval {
+                context_id: hex::encode(&context_id),
+                currency: self.currency.to_uppercase(),
+                request: request.clone(),
+                notional,
+                reason,
+                expires_at,
+            },
+        );
+        let this = self.clone();
+        let db = db.clone();
+        tokio::spawn(
+            async move {
+                let rejection = match tokio::time::timeout(timeout, decision).await {
+                    Ok(Ok(true)) => None,
+                    Ok(_) => Some("Request was not approved"),
+                    Err(_) => {
+                        this.approvals.remove(&context_id);
+                        Some("Request approval timed out")
+                    }
+                };
+                if this.store.is_terminal(&context_id) {
+                    info!("Ignoring decision on a cancelled request");
+                    return;
+                }
+                let result = match rejection {
+                    None => {
+                        info!("Request approved");
+                        this.answer_request(&db, request, context_id).await
+                    }
+                    Some(reason) => {
+                        info!(%reason, "Rejecting request");
+                        let reject = Event::Reject {
+                            reason: reason.to_string(),
+                            allowed: None,
+                            reopens_at: None,
+                        };
+                        this.publish(request.from, &reject, context_id)
+                            .await
+                            .map(|_| ())
+                    }
+                };
+                if let Err(err) = result {
                     error!(%err);
                 }
             }
-        }
+            .instrument(info_span!("approval", %notional)),
+        );
         Ok(())
     }
-}
-
-async fn get_fx_rate(
-    db: &LedgerDB,
-    from_currency: &str,
-    to_currency: &str,
-) -> anyhow::Result<Decimal> {
-    info!("Getting Fx rate");
-    let from_ledger = db
-        .get(from_currency)
-        .ok_or_else(|| anyhow::anyhow!("Missing ledger for currency {}", from_currency))?;
-    let to_ledger = db
-        .get(to_currency)
-        .ok_or_else(|| anyhow::anyhow!("Missing ledger for currency {}", to_currency))?;
 
-    Ok(to_ledger.base_rate / from_ledger.base_rate)
-}
+    /// Quotes `request`, or rejects it
+    async fn answer_request(
+        &self,
+        db: &LedgerDB,
+        request: Request,
+        context_id: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let from = request.from;
+        let quote = match self.validate_request(db, &request).await {
+            Ok((from_currency, to_currency)) => self
+                .quote(db, request, &from_currency, &to_currency, None, None)
+                .await
+                .and_then(|quote| {
+                    self.check_exposure(db, &quote, &context_id)?;
+                    self.check_credit(db, &quote, &context_id)?;
+                    Ok((quote, from_currency, to_currency))
+                }),
+            Err(err) => Err(err),
+        };
+        let event = match &quote {
+            Ok((quote, _, _)) => {
+                info!(
+                    raw_rate = %quote.rate,
+                    quoted_rate = %quote.fees.net_rate(quote.rate),
+                    spread_bps = quote.fees.spread_bps,
+                    ?quote,
+                    "Publishing quote"
+                );
+                Event::Quote(quote.clone())
+            }
+            Err(err) => {
+                info!(reason = %err, "Rejecting request");
+                rejection(err)
+            }
+        };
+        self.publish(from, &event, context_id.clone()).await?;
 
-async fn swap_task(
-    ledger: Ledger,
-    db: LedgerDB,
-    execute: Execute,
-    context_id: Vec<u8>,
-) -> anyhow::Result<()> {
-    let mut interval = tokio::time::interval(Duration::from_secs(10));
-    let valid_until = SystemTime::UNIX_EPOCH + Duration::from_secs(execute.valid_until);
-    let limits = execute.lower_limits..execute.upper_limit;
-    let (from_currency, to_currency) = ledger.get_currencies(&execute.request).await?;
-    let to_ledger = db
-        .get(&to_currency)
-        .ok_or_else(|| anyhow::anyhow!("Missing currency"))?
-        .clone();
+        if let (Ok((quote, from_currency, to_currency)), true) =
+            (quote, self.requote.max_requotes > 0)
+        {
+            let this = self.clone();
+            let db = db.clone();
+            let quote_id = quote.quote_id;
+            tokio::spawn(
+                async move {
+                    if let Err(err) = this
+                        .requote_task(db, quote, from_currency, to_currency, context_id)
+                        .await
+                    {
+                        error!(%err);
+                    }
+                }
+                .instrument(info_span!("requote", %quote_id)),
+            );
+        }
+        Ok(())
+    }
 
-    loop {
-        info!("Polling");
-        if let Ok(rate) = get_fx_rate(&db, &from_currency, &to_currency).await {
-            let limits_exceeded = !limits.contains(&rate);
-            let time_exceeded = SystemTime::now() > valid_until;
-            if limits_exceeded || time_exceeded {
-                let amount = (execute.request.amount * rate).try_into()?;
-                info!("Executing swap");
-                to_ledger
-                    .client
-                    .transfer(
-                        TransferBuilder::new()
-                            .step(StepBuilder::new(
-                                to_ledger.liquidity,
-                                execute.request.to,
-                                amount,
-                            ))
-                            .context_id(context_id.clone()),
-                    )
+    /// Publishes the current status of the swap to its initiator
+    async fn handle_status_request(&self, context_id: &[u8]) -> anyhow::Result<()> {
+        match self.store.status(context_id) {
+            Some((account, status)) => {
+                info!(state = %status.state, "Publishing swap status");
+                self.publish(account, &Event::StatusResponse(status), context_id.to_vec())
                     .await?;
+            }
+            None => info!("Ignoring status request for an unknown swap"),
+        }
+        Ok(())
+    }
 
-                info!("Publishing completion");
-                to_ledger
-                    .client
-                    .action(
-                        ActionBuilder::for_account(
-                            FX_SWAP_ACTION,
-                            to_ledger.liquidity,
-                            execute.request.from,
-                        )
-                        .payload(serde_json::to_vec(&Event::Completed).unwrap()),
-                        context_id,
-                    )
-                    .await?;
-                break;
+    /// Answers a counter-offer with a quote at the offered rate if within the negotiation tolerance,
+    /// a quote at the current rate otherwise, or a rejection
+    async fn handle_counter_offer(
+        &self,
+        db: &LedgerDB,
+        offer: CounterOffer,
+        sender: Option<AccountId>,
+        context_id: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let from = offer.quote.request.from;
+        let event = match self.counter_quote(db, &offer, sender, &context_id).await {
+            Ok(quote) => {
+                info!(?quote, "Publishing negotiated quote");
+                Event::Quote(quote)
+            }
+            Err(err) => {
+                let reason = err.to_string();
+                info!(%reason, "Rejecting counter-offer");
+                Event::Reject {
+                    reason,
+                    allowed: None,
+                    reopens_at: None,
+                }
+            }
+        };
+        self.publish(from, &event, context_id).await?;
+        Ok(())
+    }
+
+    async fn counter_quote(
+        &self,
+        db: &LedgerDB,
+        offer: &CounterOffer,
+        sender: Option<AccountId>,
+        context_id: &[u8],
+    ) -> anyhow::Result<Quote> {
+        let quote = &offer.quote;
+        if quote.verify()? != self.signer.public_key() {
+            return Err(anyhow::anyhow!(
+                "Quote was not signed by this liquidity provider"
+            ));
+        }
+        if sender != Some(quote.request.from) {
+            return Err(anyhow::anyhow!(
+                "Counter-offer was not sent by the requesting account {}",
+                quote.request.from
+            ));
+        }
+        if self.store.is_terminal(context_id) || self.store.is_executed(context_id) {
+            return Err(anyhow::anyhow!("Swap can no longer be negotiated"));
+        }
+        if self.store.count_counter_offer(context_id) > self.negotiation.max_counter_offers {
+            return Err(anyhow::anyhow!(
+                "Exceeded the maximum of {} counter-offers",
+                self.negotiation.max_counter_offers
+            ));
+        }
+        let (from_currency, to_currency) = self.validate_request(db, &quote.request).await?;
+        let superseded = || anyhow::anyhow!("Quote {} is no longer valid", quote.quote_id);
+        if self.store.is_retired(quote.quote_id) {
+            return Err(superseded());
+        }
+        let requoted = Requoted {
+            previous_quote_id: quote.quote_id,
+            count: quote.requoted.as_ref().map_or(0, |requoted| requoted.count) + 1,
+        };
+        let requote = self
+            .quote(
+                db,
+                quote.request.clone(),
+                &from_currency,
+                &to_currency,
+                Some(requoted.clone()),
+                None,
+            )
+            .await?;
+        let ours = requote.fees.net_rate(requote.rate);
+        let tolerance = Decimal::from(self.negotiation.tolerance_bps) / Decimal::from(10_000);
+        let counter = match offer.rate > ours * (Decimal::ONE + tolerance) {
+            true => {
+                info!(offered = %offer.rate, %ours, "Counter-offer outside tolerance, re-quoting");
+                requote
+            }
+            false => {
+                // Spread giving the offered rate, rounded in favour of the liquidity provider
+                let spread_bps = ((Decimal::ONE - offer.rate / requote.rate)
+                    * Decimal::from(10_000))
+                .ceil()
+                .max(Decimal::ZERO)
+                .to_u32()
+                .unwrap_or_default()
+                .min(requote.fees.spread_bps);
+                self.quote(
+                    db,
+                    quote.request.clone(),
+                    &from_currency,
+                    &to_currency,
+                    Some(requoted),
+                    Some(spread_bps),
+                )
+                .await?
             }
+        };
+        // The quote countered stays valid until superseded by a new one
+        match self.store.retire_quote(context_id, quote.quote_id) {
+            true => Ok(counter),
+            false => Err(superseded()),
         }
+    }
+
+    /// Publishes updated quotes for a request until it is executed, cancelled,
+    /// or the maximum number of re-quotes is reached
+    async fn requote_task(
+        self,
+        db: LedgerDB,
+        mut quote: Quote,
+        from_currency: String,
+        to_currency: String,
+        context_id: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.requote.interval));
         interval.tick().await;
+        let tolerance = Decimal::from(self.requote.rate_tolerance_bps) / Decimal::from(10_000);
+        let mut count = 0;
+        while count < self.requote.max_requotes {
+            interval.tick().await;
+            if self.store.is_terminal(&context_id) || self.store.is_executed(&context_id) {
+                break;
+            }
+            let rate = match get_fx_rate(&db, &from_currency, &to_currency).await {
+                Ok(rate) => rate,
+                // e.g. the pair is paused, the quote expires unless the rate recovers
+                Err(err) => {
+                    info!(%err, "Could not re-evaluate quote");
+                    continue;
+                }
+            };
+            let moved = (rate - quote.rate).abs() > quote.rate * tolerance;
+            if !quote.is_expired() && !moved {
+                continue;
+            }
+            if !self.store.retire_quote(&context_id, quote.quote_id) {
+                // Executed in the meantime
+                break;
+            }
+            count += 1;
+            let requoted = Requoted {
+                previous_quote_id: quote.quote_id,
+                count,
+            };
+            quote = self
+                .quote(
+                    &db,
+                    quote.request,
+                    &from_currency,
+                    &to_currency,
+                    Some(requoted),
+                    None,
+                )
+                .await?;
+            info!(
+                raw_rate = %quote.rate,
+                quoted_rate = %quote.fees.net_rate(quote.rate),
+                spread_bps = quote.fees.spread_bps,
+                ?quote,
+                "Publishing re-quote"
+            );
+            self.publish(
+                quote.request.from,
+                &Event::Quote(quote.clone()),
+                context_id.clone(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Records the sequence number of an event observed by `account`, returns `false` if it must be ignored
+    fn observe(&self, context_id: &[u8], account: AccountId, envelope: &Envelope) -> bool {
+        let fresh = self.store.observe_sequence(
+            context_id,
+            account,
+            &envelope.public_key,
+            envelope.sequence,
+        );
+        if !fresh {
+            warn!(
+                sequence = envelope.sequence,
+                public_key = %hex::encode(&envelope.public_key),
+                "Ignoring replayed or out of order event"
+            );
+        }
+        fresh
+    }
+
+    /// Publishes an FX event from the liquidity account to `to`
+    async fn publish(
+        &self,
+        to: AccountId,
+        event: &Event,
+        context_id: Vec<u8>,
+    ) -> anyhow::Result<TxId> {
+        self.store.record(&context_id, to, event);
+        let public_key = self.signer.public_key();
+        let sequence = self.store.next_sequence(&context_id, public_key);
+        let envelope = Envelope::new(event.clone(), sequence, public_key);
+        let tx_id = self.action(to, envelope.encode(), &context_id).await?;
+        if !matches!(event, Event::StatusResponse(_)) {
+            self.persist(&context_id, &Entry::Event(event.clone()), Some(tx_id))
+                .await;
+        }
+        Ok(tx_id)
+    }
+
+    /// Whether the execution received with the transfer `tx_id` was handled before, according to the storage
+    async fn replayed(&self, context_id: &[u8], tx_id: TxId) -> bool {
+        match &self.storage {
+            Some(storage) => match storage.handled(context_id, tx_id).await {
+                Ok(handled) => handled,
+                Err(err) => {
+                    error!(%err, "Could not check whether the execution was handled");
+                    false
+                }
+            },
+            None => false,
+        }
+    }
+
+    /// Records a step of the swap lifecycle, if a storage is configured
+    async fn persist(&self, context_id: &[u8], entry: &Entry, tx_id: Option<TxId>) {
+        if let Some(storage) = &self.storage {
+            if let Err(err) = storage.record(context_id, entry, tx_id).await {
+                error!(%err, "Could not persist swap entry");
+            }
+        }
+    }
+
+    async fn quote(
+        &self,
+        db: &LedgerDB,
+        request: Request,
+        from_currency: &str,
+        to_currency: &str,
+        requoted: Option<Requoted>,
+        spread_bps: Option<u32>,
+    ) -> anyhow::Result<Quote> {
+        let mut price = self
+            .price(db, from_currency, to_currency, request.amount)
+            .await?;
+        let intermediary = db
+            .get(from_currency)
+            .ok_or_else(|| anyhow::anyhow!("Missing currency"))?
+            .select(request.from)
+            .await;
+        if let Some(spread_bps) = spread_bps {
+            // Negotiated spread
+            price.fees.spread_bps = spread_bps;
+        }
+        let rate = price.rate;
+        let two_way = request.two_way;
+        let mut quote = price.quote(request, intermediary, self.quote_ttl_to(to_currency))?;
+        quote.requoted = requoted;
+        quote.indicative = !self.covers(db, to_currency, quote.target_amount).await;
+        quote.ask = match two_way {
+            true => {
+                let to_ledger = db
+                    .get(to_currency)
+                    .ok_or_else(|| anyhow::anyhow!("Missing currency"))?;
+                let fees = Fees {
+                    flat_fee: to_ledger.pricing().flat_fee,
+                    spread_bps: self.pricing().ask_spread_bps,
+                    currency: to_currency.to_uppercase(),
+                };
+                Some(Ask {
+                    rate: Decimal::ONE / fees.net_rate(Decimal::ONE / rate),
+                    fees,
+                    intermediary: to_ledger.select(quote.request.to).await,
+                })
+            }
+            false => None,
+        };
+        quote.validate()?;
+        quote.signature = Some(self.sign(&quote.signing_payload()).await?);
+        Ok(quote)
+    }
+
+    /// Prices a swap to `to_currency` as it would be quoted, without publishing anything
+    pub async fn indicative_quote(
+        &self,
+        db: &LedgerDB,
+        to_currency: &str,
+        side: Side,
+        amount: Decimal,
+    ) -> anyhow::Result<IndicativeQuote> {
+        if !self.pair_enabled(to_currency) {
+            return Err(anyhow::anyhow!(
+                "Pair {}/{} is disabled",
+                self.currency,
+                to_currency
+            ));
+        }
+        let price = self.price(db, &self.currency, to_currency, amount).await?;
+        let (source_amount, target_amount) = price.fees.convert(side, amount, price.rate)?;
+        Ok(IndicativeQuote {
+            route: price.route,
+            rate: price.rate,
+            fees: price.fees,
+            source_amount,
+            target_amount,
+        })
+    }
+
+    /// Rate, route & fees of a swap of `amount`, its spread skewed by the inventories & set by the tier of `amount`
+    async fn price(
+        &self,
+        db: &LedgerDB,
+        from_currency: &str,
+        to_currency: &str,
+        amount: Decimal,
+    ) -> anyhow::Result<Price> {
+        let rate = get_fx_rate(db, from_currency, to_currency).await?;
+        let route = route(db, from_currency, to_currency)?;
+        // Each pivot currency charges its own spread on the next hop
+        let pivot_spread_bps: u32 = route[1..]
+            .windows(2)
+            .map(|hop| db[&hop[0]].spread_to(&hop[1]))
+            .sum();
+        // Buying more of a currency we're long is quoted wider, selling it tighter
+        let skew_bps = (self.skew_bps().await - db[to_currency].skew_bps().await)
+            .round()
+            .to_i64()
+            .unwrap_or_default();
+        if skew_bps != 0 {
+            info!(%skew_bps, "Skewing spread from inventory");
+        }
+        let skewed = |spread_bps: u32| (spread_bps as i64 + skew_bps).max(0) as u32;
+        let fees = Fees {
+            flat_fee: self.pricing().flat_fee,
+            spread_bps: skewed(self.spread_to(&route[1]) + pivot_spread_bps),
+            currency: from_currency.to_uppercase(),
+        };
+        let tiers = self
+            .pricing()
+            .tiers
+            .get(to_currency)
+            .into_iter()
+            .flatten()
+            .map(|tier| (tier.min_amount, skewed(tier.spread_bps + pivot_spread_bps)))
+            .collect::<Vec<_>>();
+        Ok(Price::new(rate, route, fees, tiers, amount))
+    }
+
+    /// Whether the liquidity in `currency` covers `amount`, quotes it doesn't cover being published as indicative only
+    async fn covers(&self, db: &LedgerDB, currency: &str, amount: Decimal) -> bool {
+        let ledger = &db[currency];
+        match ledger.payer().await {
+            Ok((_, balance)) if ledger.money.from_units(balance) >= amount => true,
+            Ok((_, balance)) => {
+                info!(available = %balance, %amount, "Insufficient liquidity, quoting as indicative");
+                false
+            }
+            Err(err) => {
+                warn!(%err, "Could not check liquidity, quoting as indicative");
+                false
+            }
+        }
+    }
+
+    async fn sign(&self, payload: &[u8]) -> anyhow::Result<Signature> {
+        Signature::sign(self.signer.as_ref(), payload).await
+    }
+
+    /// Verifies the execution refers to a quote signed by its liquidity provider
+    fn verify_execute<'a>(&self, db: &LedgerDB, execute: &'a Execute) -> anyhow::Result<&'a Quote> {
+        let quote = execute
+            .quote
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Missing quote"))?;
+        // Executions at the ask settle on the target currency ledger
+        let quoting = db
+            .values()
+            .find(|ledger| ledger.owns(quote.intermediary))
+            .ok_or_else(|| anyhow::anyhow!("Unknown intermediary {}", quote.intermediary))?;
+        if quote.verify()? != quoting.signer.public_key() {
+            return Err(anyhow::anyhow!(
+                "Quote was not signed by this liquidity provider"
+            ));
+        }
+        if !quote.covers(&execute.request) {
+            return Err(anyhow::anyhow!(
+                "Execution does not match the quoted request"
+            ));
+        }
+        Ok(quote)
+    }
+
+    /// Checks the source leg transfers exactly what the execution pays, rounded up to the ledger units as by initiators
+    fn verify_funding(&self, execute: &Execute, source: &SourceLeg) -> anyhow::Result<()> {
+        let owed =
+            Money::new(self.money.decimals(), Rounding::Up)?.to_units(execute.source_amount()?)?;
+        match source.amount.cmp(&owed) {
+            std::cmp::Ordering::Less => Err(anyhow::anyhow!(
+                "Execution transferred only {} of the {} owed",
+                source.amount,
+                owed
+            )),
+            std::cmp::Ordering::Greater => Err(anyhow::anyhow!(
+                "Execution transferred {}, more than the {} owed",
+                source.amount,
+                owed
+            )),
+            std::cmp::Ordering::Equal => Ok(()),
+        }
+    }
+
+    /// Returns the funds of a rejected source leg to its sender
+    async fn refund_source(
+        &self,
+        request: &Request,
+        source: &SourceLeg,
+        reason: String,
+        context_id: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let to = match source.from {
+            Some(from) => from,
+            None => return Ok(()),
+        };
+        if let Some(refund_tx_id) = return_source(
+            self,
+            source.account,
+            to,
+            request,
+            source.tx_id,
+            source.amount,
+            source.pending,
+            context_id.clone(),
+        )
+        .await?
+        {
+            let refund = Refund {
+                source_tx_id: source.tx_id,
+                refund_tx_id,
+                amount: source.amount,
+                reason,
+            };
+            self.publish(to, &Event::Refunded(refund), context_id)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns held funds to the initiator, holding them again to be retried if the transfer fails
+    async fn refund_funds(
+        &self,
+        context_id: Vec<u8>,
+        funds: HeldFunds,
+        reason: String,
+    ) -> anyhow::Result<()> {
+        let request = &funds.request;
+        let refund_tx_id = match return_source(
+            self,
+            funds.liquidity,
+            request.from,
+            request,
+            funds.tx_id,
+            funds.amount,
+            funds.pending,
+            context_id.clone(),
+        )
+        .await
+        {
+            Ok(Some(refund_tx_id)) => refund_tx_id,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                self.store.hold(&context_id, funds);
+                return Err(err);
+            }
+        };
+        let refund = Refund {
+            source_tx_id: funds.tx_id,
+            refund_tx_id,
+            amount: funds.amount,
+            reason,
+        };
+        self.publish(request.from, &Event::Refunded(refund), context_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Refunds executions left unsettled past their deadline, e.g. by a stuck settlement
+    pub async fn refund_expired(self) -> anyhow::Result<()> {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.refund.interval));
+        info!("Watching unsettled executions");
+        loop {
+            interval.tick().await;
+            for (context_id, funds) in self.store.take_expired(&self.accounts) {
+                let context = hex::encode(&context_id);
+                warn!(%context, amount = %funds.amount, "Execution expired without settlement");
+                let reason = "Execution expired without settlement".to_string();
+                if let Err(err) = self.refund_funds(context_id, funds, reason).await {
+                    error!(%err, %context, "Could not refund expired execution");
+                }
+            }
+        }
+    }
+
+    pub fn base_rate(&self) -> Decimal {
+        self.pricing().base_rate
+    }
+
+    /// Current parameters of the quotes
+    fn pricing(&self) -> Arc<Pricing> {
+        self.pricing.read().unwrap().clone()
+    }
+
+    /// Swaps the parameters of the quotes, e.g. once reloaded from the config
+    pub fn reprice(&self, pricing: Pricing) {
+        *self.pricing.write().unwrap() = Arc::new(pricing);
+    }
+
+    /// Spread adjustment in basis points from the inventory, positive when long & negative when short
+    async fn skew_bps(&self) -> Decimal {
+        let skew = match self.pricing().skew {
+            Some(skew) => skew,
+            None => return Decimal::ZERO,
+        };
+        match self.balance().await {
+            Ok(balance) => {
+                let deviation = (self.money.from_units(balance) - skew.target) / skew.target;
+                deviation.clamp(-Decimal::ONE, Decimal::ONE) * Decimal::from(skew.max_bps)
+            }
+            Err(err) => {
+                warn!(%err, "Could not check inventory, quoting without skew");
+                Decimal::ZERO
+            }
+        }
+    }
+
+    pub fn max_exposure(&self) -> Option<Decimal> {
+        self.pricing().max_exposure
+    }
+
+    /// Checks publishing `quote` keeps the open exposure of its currencies within their caps
+    fn check_exposure(
+        &self,
+        db: &LedgerDB,
+        quote: &Quote,
+        context_id: &[u8],
+    ) -> anyhow::Result<()> {
+        let mut exposure = self.store.exposure(context_id);
+        let (from_currency, to_currency) = match (quote.route.first(), quote.route.last()) {
+            (Some(from_currency), Some(to_currency)) => (from_currency, to_currency),
+            _ => return Ok(()),
+        };
+        *exposure.entry(from_currency.clone()).or_default() += quote.source_amount;
+        *exposure.entry(to_currency.clone()).or_default() -= quote.target_amount;
+        for currency in [from_currency, to_currency] {
+            let cap = db.get(currency).and_then(|ledger| ledger.max_exposure());
+            if let Some(cap) = cap.filter(|cap| exposure[currency].abs() > *cap) {
+                return Err(anyhow::anyhow!(
+                    "Open exposure in {} would exceed its cap of {}",
+                    currency.to_uppercase(),
+                    cap
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks publishing `quote` keeps the unsettled exposure to its requesting account within its credit limit.
+    /// Flagged requests are instead approved by an operator before being quoted
+    fn check_credit(&self, db: &LedgerDB, quote: &Quote, context_id: &[u8]) -> anyhow::Result<()> {
+        if self.credit.on_breach == CreditBreach::Flag {
+            return Ok(());
+        }
+        let notional = quote.source_amount / self.base_rate();
+        match self.credit_breach(db, quote.request.from, notional, context_id) {
+            Some(breach) => Err(anyhow::anyhow!(breach)),
+            None => Ok(()),
+        }
+    }
+
+    /// How another `notional` would exceed the credit limit of `account`, if so
+    fn credit_breach(
+        &self,
+        db: &LedgerDB,
+        account: AccountId,
+        notional: Decimal,
+        context_id: &[u8],
+    ) -> Option<String> {
+        let limit = *self
+            .credit
+            .accounts
+            .get(&account.to_string())
+            .or(self.credit.limit.as_ref())?;
+        let used: Decimal = self
+            .store
+            .credit_used(account, context_id)
+            .into_iter()
+            .filter_map(|(currency, amount)| Some(amount / db.get(&currency)?.base_rate()))
+            .sum();
+        if used + notional <= limit {
+            return None;
+        }
+        warn!(%account, %limit, %used, %notional, "Credit limit exceeded");
+        Some(format!(
+            "Credit limit of {} exceeded, {} unsettled",
+            limit, used
+        ))
+    }
+
+    /// Target share of the inventory value, if rebalanced
+    pub fn allocation(&self) -> Option<Decimal> {
+        self.allocation
+    }
+
+    /// Liquidity accounts of this currency, the primary one first
+    pub fn accounts(&self) -> &[AccountId] {
+        &self.accounts
+    }
+
+    /// Whether `account` is one of the liquidity accounts
+    fn owns(&self, account: AccountId) -> bool {
+        self.accounts.contains(&account)
+    }
+
+    /// Liquidity account receiving the swap of `counterparty`, skipping those being rebalanced
+    async fn select(&self, counterparty: AccountId) -> AccountId {
+        let rebalancing = self.rebalancing.lock().unwrap().clone();
+        let candidates = self
+            .accounts
+            .iter()
+            .copied()
+            .filter(|account| !rebalancing.contains(account))
+            .collect::<Vec<_>>();
+        if candidates.len() <= 1 {
+            return candidates.first().copied().unwrap_or(self.liquidity);
+        }
+        match self.selection {
+            Selection::RoundRobin => {
+                let next = self.next_account.fetch_add(1, Ordering::Relaxed);
+                candidates[next % candidates.len()]
+            }
+            Selection::LargestBalance => match self.largest(&candidates).await {
+                Ok((account, _)) => account,
+                Err(err) => {
+                    warn!(%err, "Could not check balances, selecting the primary account");
+                    candidates[0]
+                }
+            },
+            Selection::Sticky => {
+                let mut sticky = self.sticky.lock().unwrap();
+                match sticky.get(&counterparty) {
+                    Some(account) if candidates.contains(account) => *account,
+                    _ => {
+                        let next = self.next_account.fetch_add(1, Ordering::Relaxed);
+                        let account = candidates[next % candidates.len()];
+                        sticky.insert(counterparty, account);
+                        account
+                    }
+                }
+            }
+        }
+    }
+
+    /// Liquidity account paying out swaps & its balance, the largest one
+    async fn payer(&self) -> anyhow::Result<(AccountId, u64)> {
+        self.largest(&self.accounts).await
+    }
+
+    /// Account holding the largest balance among `accounts`, the first one on ties
+    async fn largest(&self, accounts: &[AccountId]) -> anyhow::Result<(AccountId, u64)> {
+        let mut largest = (self.liquidity, 0);
+        for (index, account) in accounts.iter().enumerate() {
+            let balance = self.account_balance(*account).await?;
+            if index == 0 || balance > largest.1 {
+                largest = (*account, balance);
+            }
+        }
+        Ok(largest)
+    }
+
+    /// Balance of a single liquidity account
+    async fn account_balance(&self, account: AccountId) -> anyhow::Result<u64> {
+        let account = self
+            .retry
+            .call("get_account", || self.client.get_account(account))
+            .await?;
+        Ok(account.balance)
+    }
+
+    /// Balance of the liquidity accounts
+    pub async fn balance(&self) -> anyhow::Result<u64> {
+        let mut balance = 0;
+        for account in self.accounts.iter() {
+            balance += self.account_balance(*account).await?;
+        }
+        Ok(balance)
+    }
+
+    /// Transfers `amount` from the liquidity account `from` to `to` within `context_id`, with the settlement metadata of `request` if any.
+    /// Transient failures are retried, unless the failed attempt was settled regardless
+    async fn transfer(
+        &self,
+        from: AccountId,
+        to: AccountId,
+        amount: u64,
+        request: Option<&Request>,
+        context_id: Vec<u8>,
+    ) -> M10Result<TxId> {
+        // Failed attempts are only looked up before being retried
+        let sent = match self.retry.enabled() {
+            true => self.sent_transfers(from, to, amount, &context_id).await?,
+            false => Vec::new(),
+        };
+        self.transfer_after(&sent, from, to, amount, request, context_id)
+            .await
+    }
+
+    /// Like `transfer`, any matching transfer settled but `sent` being taken for an earlier attempt
+    async fn transfer_after(
+        &self,
+        sent: &[TxId],
+        from: AccountId,
+        to: AccountId,
+        amount: u64,
+        request: Option<&Request>,
+        context_id: Vec<u8>,
+    ) -> M10Result<TxId> {
+        self.retry
+            .run(
+                "transfer",
+                || {
+                    let step = match request {
+                        Some(request) => settlement_step(from, to, amount, request),
+                        None => StepBuilder::new(from, to, amount),
+                    };
+                    self.client.transfer(
+                        TransferBuilder::new()
+                            .step(step)
+                            .context_id(context_id.clone()),
+                    )
+                },
+                || self.landed(sent, from, to, amount, &context_id),
+            )
+            .await
+    }
+
+    /// Transfers settled within `context_id` of `amount` from the liquidity account `from` to `to`
+    async fn sent_transfers(
+        &self,
+        from: AccountId,
+        to: AccountId,
+        amount: u64,
+        context_id: &[u8],
+    ) -> M10Result<Vec<TxId>> {
+        let transfers = self
+            .client
+            .list_transfers(TxnFilter::<TransferFilter>::by_context_id(
+                context_id.to_vec(),
+            ))
+            .await?;
+        Ok(transfers
+            .into_iter()
+            .filter(|transfer| {
+                transfer.success
+                    && transfer
+                        .steps
+                        .iter()
+                        .any(|step| step.from == from && step.to == to && step.amount == amount)
+            })
+            .map(|transfer| transfer.tx_id)
+            .collect())
+    }
+
+    /// Matching transfer settled since `sent` was listed, if any
+    async fn landed(
+        &self,
+        sent: &[TxId],
+        from: AccountId,
+        to: AccountId,
+        amount: u64,
+        context_id: &[u8],
+    ) -> M10Result<Option<TxId>> {
+        let transfers = self.sent_transfers(from, to, amount, context_id).await?;
+        Ok(transfers.into_iter().find(|tx_id| !sent.contains(tx_id)))
+    }
+
+    /// Invokes the swap action on `to` with `payload`, retrying transient failures.
+    /// Payloads are unique by their sequence, so one already recorded within `context_id` isn't invoked again
+    async fn action(&self, to: AccountId, payload: Vec<u8>, context_id: &[u8]) -> M10Result<TxId> {
+        self.retry
+            .run(
+                "action",
+                || {
+                    self.client.action(
+                        ActionBuilder::for_account(FX_SWAP_ACTION.to_string(), self.liquidity, to)
+                            .payload(payload.clone()),
+                        context_id.to_vec(),
+                    )
+                },
+                || async {
+                    let actions = self
+                        .client
+                        .list_actions(TxnFilter::<ActionsFilter>::by_context_id(
+                            FX_SWAP_ACTION.to_string(),
+                            context_id.to_vec(),
+                        ))
+                        .await?;
+                    Ok(actions
+                        .into_iter()
+                        .find(|action| action.payload == payload)
+                        .map(|action| action.tx_id))
+                },
+            )
+            .await
+    }
+
+    /// Moves `adjustment` from the reserve into the primary liquidity account, or out of it when negative.
+    /// Swaps are received by the other accounts meanwhile
+    pub async fn transfer_reserve(&self, adjustment: i64) -> anyhow::Result<TxId> {
+        let reserve = self
+            .reserve
+            .ok_or_else(|| anyhow::anyhow!("Missing reserve account"))?;
+        let (from, to) = match adjustment > 0 {
+            true => (reserve, self.liquidity),
+            false => (self.liquidity, reserve),
+        };
+        self.rebalancing.lock().unwrap().insert(self.liquidity);
+        let result = self
+            .client
+            .transfer(TransferBuilder::new().step(StepBuilder::new(
+                from,
+                to,
+                adjustment.unsigned_abs(),
+            )))
+            .await;
+        self.rebalancing.lock().unwrap().remove(&self.liquidity);
+        Ok(result?)
+    }
+
+    /// Whether alerts are fired when the liquidity runs low
+    pub fn monitors_liquidity(&self) -> bool {
+        self.min_balance.is_some()
+    }
+
+    /// Checks the liquidity balance every `interval`, alerting when it drops below its minimum & once topped up again
+    pub async fn monitor_liquidity(
+        self,
+        interval: Duration,
+        alerter: Alerter,
+    ) -> anyhow::Result<()> {
+        let min_balance = match self.min_balance {
+            Some(min_balance) => min_balance,
+            None => return Ok(()),
+        };
+        let mut interval = tokio::time::interval(interval);
+        let mut low = false;
+        info!(%min_balance, "Monitoring liquidity");
+        loop {
+            interval.tick().await;
+            let balance = self.balance().await?;
+            if (self.money.from_units(balance) < min_balance) == low {
+                continue;
+            }
+            low = !low;
+            let kind = match low {
+                true => AlertKind::LowLiquidity,
+                false => AlertKind::LiquidityRestored,
+            };
+            alerter
+                .fire(Alert {
+                    kind,
+                    currency: self.currency.to_uppercase(),
+                    account: self.liquidity.to_string(),
+                    balance,
+                    min_balance: min_balance.to_string(),
+                })
+                .await;
+        }
+    }
+
+    /// Observes the swap actions, failing over to another endpoint if the ledger became unreachable
+    pub async fn observe_actions(self, db: LedgerDB, cursors: Cursors) -> anyhow::Result<()> {
+        let observed = self.watch_actions(db, cursors).await;
+        if observed.is_err() {
+            self.endpoints.fail_over(self.liquidity).await;
+        }
+        observed
+    }
+
+    async fn watch_actions(&self, db: LedgerDB, cursors: Cursors) -> anyhow::Result<()> {
+        let stream = self.action_stream();
+        // Sign the request to observe all actions named `FX_SWAP_ACTION`
+        let mut filter = AccountFilter::name(FX_SWAP_ACTION).involves(self.liquidity);
+        if let Some(tx_id) = cursors.resume_from(&stream) {
+            info!(%tx_id, "Resuming actions");
+            filter = filter.starting_from(tx_id);
+        }
+        // Observed from the ledger client, as the SDK's actions drop the account which invoked them
+        let request = self.signer.sign_request(filter.into()).await?;
+        let mut actions = self.client.client.observe_actions(request).await?;
+        info!(action = %FX_SWAP_ACTION, "Started observations");
+        while let Some(actions) = actions.next().await {
+            for transaction in actions?.transactions {
+                let sender = match transaction.data() {
+                    Some(Data::InvokeAction(invoked)) => {
+                        AccountId::try_from_be_slice(&invoked.from_account).ok()
+                    }
+                    _ => None,
+                };
+                let action = Action::try_from(transaction)?;
+                let tx_id = action.tx_id;
+                if let Err(err) = self.handle_request(&db, action, sender).await {
+                    error!(%err);
+                }
+                if let Err(err) = cursors.advance(&stream, tx_id) {
+                    error!(%err, "Could not persist cursor");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub async fn get_fx_rate(
+    db: &LedgerDB,
+    from_currency: &str,
+    to_currency: &str,
+) -> anyhow::Result<Decimal> {
+    info!("Getting Fx rate");
+    let route = route(db, from_currency, to_currency)?;
+    // The pair is as stale as its stalest hop
+    let age = route
+        .windows(2)
+        .filter_map(|hop| db[&hop[0]].rates.age(&hop[0], &hop[1]))
+        .max();
+    db[from_currency]
+        .breaker
+        .check(from_currency, to_currency, age)?;
+    let mut rate = Decimal::ONE;
+    for hop in route.windows(2) {
+        rate *= db[&hop[0]].rates.rate(&hop[0], &hop[1]).await?;
+    }
+    Ok(rate)
+}
+
+/// Currencies a swap converts through, via the pivot currency when the pair isn't quoted directly
+fn route(
+    db: &LedgerDB,
+    from_currency: &str,
+    to_currency: &str,
+) -> anyhow::Result<Vec<CurrencyCode>> {
+    let ledger = |currency: &str| {
+        db.get(currency)
+            .ok_or_else(|| anyhow::anyhow!("Missing ledger for currency {}", currency))
+    };
+    let from_ledger = ledger(from_currency)?;
+    ledger(to_currency)?;
+    if from_ledger.quotes_directly(to_currency) {
+        return Ok(vec![from_currency.to_string(), to_currency.to_string()]);
+    }
+    match &from_ledger.pivot {
+        Some(pivot)
+            if pivot != from_currency
+                && pivot != to_currency
+                && from_ledger.quotes_directly(pivot)
+                && ledger(pivot)?.quotes_directly(to_currency) =>
+        {
+            Ok(vec![
+                from_currency.to_string(),
+                pivot.clone(),
+                to_currency.to_string(),
+            ])
+        }
+        _ => Err(anyhow::anyhow!(
+            "No route from {} to {}",
+            from_currency,
+            to_currency
+        )),
+    }
+}
+
+async fn swap_task(
+    ledger: Ledger,
+    db: LedgerDB,
+    execute: Execute,
+    source: SourceLeg,
+    settled: u64,
+    context_id: Vec<u8>,
+) -> anyhow::Result<()> {
+    let request = execute.settlement_request();
+    let (from_currency, to_currency) = ledger.get_currencies(&request).await?;
+    let to_ledger = db
+        .get(&to_currency)
+        .ok_or_else(|| SwapError::new(FailureCode::RateUnavailable, "Missing currency"))?
+        .clone();
+    let route = route(&db, &from_currency, &to_currency)
+        .map_err(|err| SwapError::new(FailureCode::RateUnavailable, err))?;
+    let settlement = Settlement {
+        from_ledger: ledger,
+        to_ledger,
+        db,
+        route,
+        fees: execute.fees(),
+        request,
+        quote_id: execute.quote_id,
+        quoted_rate: execute.quoted_rate(),
+        source,
+        settled,
+        from_currency,
+        to_currency,
+        context_id,
+    };
+    if settlement.from_ledger.dry_run {
+        return settlement.paper_trade().await;
+    }
+    if settlement.source.pending {
+        return settlement.held().await;
+    }
+    match execute.strategy {
+        Strategy::Immediate => settlement.immediate().await,
+        Strategy::Limit { bounds, deadline } => settlement.limit(bounds, deadline).await,
+        Strategy::Twap { window, slices } => settlement.twap(window, slices).await,
+    }
+}
+
+/// Parameters of the quotes of a currency, reloaded without restarting
+#[derive(Debug)]
+pub struct Pricing {
+    base_rate: Decimal,
+    flat_fee: Decimal,
+    spread_bps: u32,
+    /// Spread charged at the ask of two-way quotes
+    ask_spread_bps: u32,
+    /// Swap sizes served, in the liquidity currency
+    limits: AmountRange,
+    /// Spread adjustment from the inventory
+    skew: Option<SkewConfig>,
+    /// Net open exposure allowed
+    max_exposure: Option<Decimal>,
+    /// Spread per target currency, overriding `spread_bps`
+    pair_spread_bps: HashMap<CurrencyCode, u32>,
+    /// Spread tiers per target currency
+    tiers: HashMap<CurrencyCode, Vec<TierConfig>>,
+    /// Target currencies quoted directly, all of them when unset
+    pairs: Option<HashSet<CurrencyCode>>,
+    /// Pairs quoted & their directions, all of them when empty
+    allowed_pairs: Vec<PairConfig>,
+    /// Validity of the quotes
+    quote_ttl: Duration,
+    /// Quoting terms per target currency, overriding the above
+    pair_terms: HashMap<CurrencyCode, PairQuoteConfig>,
+}
+
+impl Pricing {
+    pub fn new(
+        currency: &str,
+        config: &LiquidityConfig,
+        routing: &RoutingConfig,
+        pairs: &HashMap<String, PairQuoteConfig>,
+        engine: &EngineConfig,
+    ) -> anyhow::Result<Self> {
+        let currency = currency.to_lowercase();
+        let mut pair_terms = HashMap::new();
+        for (pair, terms) in pairs {
+            let (from, to) = PairQuoteConfig::currencies(pair)
+                .ok_or_else(|| anyhow::anyhow!("Invalid pair {}, expected from/to", pair))?;
+            if from == currency {
+                pair_terms.insert(to, terms.clone());
+            }
+        }
+        if config.base_rate <= Decimal::ZERO {
+            return Err(anyhow::anyhow!(
+                "Base rate of {} must be positive",
+                currency
+            ));
+        }
+        Ok(Self {
+            base_rate: config.base_rate,
+            flat_fee: config.flat_fee,
+            spread_bps: config.spread_bps,
+            ask_spread_bps: config.ask_spread_bps.unwrap_or(config.spread_bps),
+            limits: AmountRange {
+                min: config.min_amount,
+                max: config.max_amount,
+            },
+            skew: match config.skew {
+                Some(skew) if skew.target <= Decimal::ZERO => {
+                    return Err(anyhow::anyhow!(
+                        "Skew target of {} must be positive",
+                        currency
+                    ))
+                }
+                skew => skew,
+            },
+            max_exposure: config.max_exposure,
+            pair_spread_bps: config
+                .pair_spread_bps
+                .iter()
+                .map(|(currency, spread_bps)| (currency.to_lowercase(), *spread_bps))
+                .collect(),
+            tiers: config
+                .tiers
+                .iter()
+                .map(|(currency, tiers)| (currency.to_lowercase(), tiers.clone()))
+                .collect(),
+            pairs: config.pairs.as_ref().map(|pairs| {
+                pairs
+                    .iter()
+                    .map(|currency| currency.to_lowercase())
+                    .collect()
+            }),
+            allowed_pairs: routing.allowed.clone(),
+            quote_ttl: Duration::from_secs(config.quote_ttl.unwrap_or(engine.quote_ttl)),
+            pair_terms,
+        })
+    }
+}
+
+/// Settlement of an accepted execution, according to its strategy
+struct Settlement {
+    from_ledger: Ledger,
+    to_ledger: Ledger,
+    db: LedgerDB,
+    /// Currencies the swap converts through
+    route: Vec<CurrencyCode>,
+    request: Request,
+    fees: Fees,
+    quote_id: Uuid,
+    /// Rate quoted for the settlement, if any
+    quoted_rate: Option<Decimal>,
+    source: SourceLeg,
+    /// Amount of the source leg settled before a restart
+    settled: u64,
+    from_currency: String,
+    to_currency: String,
+    context_id: Vec<u8>,
+}
+
+impl Settlement {
+    async fn rate(&self) -> anyhow::Result<Decimal> {
+        get_fx_rate(&self.db, &self.from_currency, &self.to_currency).await
+    }
+
+    /// Looks up the rate right before transferring, failing if it slipped from the quoted rate by more than allowed
+    async fn executed_rate(&self) -> anyhow::Result<Decimal> {
+        // Forward swaps settle at the rate agreed when executed
+        if let (Some(_), Some(quoted)) = (self.request.settle_at, self.quoted_rate) {
+            return Ok(quoted);
+        }
+        let rate = self
+            .rate()
+            .await
+            .map_err(|err| SwapError::new(FailureCode::RateUnavailable, err))?;
+        if let (Some(quoted), Some(max_bps)) = (self.quoted_rate, self.from_ledger.max_slippage_bps)
+        {
+            check_slippage(rate, quoted, max_bps)
+                .map_err(|slipped| SwapError::new(FailureCode::Slippage, slipped))?;
+        }
+        Ok(rate)
+    }
+
+    /// Waits for the settlement window of the pair to open, returns whether it was closed
+    async fn pause_until_settlement(&self) -> bool {
+        let opens_at = match self.from_ledger.hours.settlement_closed_until(
+            &self.from_currency,
+            &self.to_currency,
+            Utc::now(),
+        ) {
+            Some(opens_at) => opens_at,
+            None => return false,
+        };
+        info!(opens_at = %opens_at.to_rfc3339(), "Settlement window closed, pausing");
+        let pause = (opens_at - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(pause).await;
+        info!("Settlement window opened, resuming");
+        true
+    }
+
+    /// Amount of the source leg left to settle
+    fn unsettled(&self) -> u64 {
+        self.source.amount - self.settled
+    }
+
+    /// Settles the whole swap at the current rate
+    async fn immediate(self) -> anyhow::Result<()> {
+        if self
+            .settle(self.unsettled(), self.unsettled())
+            .await?
+            .is_some()
+        {
+            self.complete().await?;
+        }
+        Ok(())
+    }
+
+    /// Publishes what settling the rest of the swap at the current rate would deliver, without transferring anything.
+    /// The source funds are left to the refund sweeper
+    async fn paper_trade(self) -> anyhow::Result<()> {
+        let rate = self.executed_rate().await?;
+        let amount = self.unsettled();
+        let target = Payout::new(
+            &self.request,
+            &self.fees,
+            self.to_ledger.money,
+            rate,
+            self.source.amount,
+        )?
+        .share(amount);
+        let (_, available) = self.to_ledger.payer().await?;
+        if available < target {
+            warn!(%available, %target, "Liquidity would only partially fill the swap");
+        }
+        self.log_pivots();
+        info!(%rate, quoted_rate = ?self.quoted_rate, %amount, %target, "Paper trading swap");
+        let receipt = Receipt {
+            quote_id: self.quote_id,
+            source_tx_id: self.source.tx_id,
+            target_tx_id: 0,
+            source_amount: amount,
+            target_amount: target,
+            effective_rate: self.effective_rate(amount, target),
+            quoted_rate: self.quoted_rate,
+            executed_rate: rate,
+            slices: 0,
+            signature: None,
+        };
+        self.publish_receipt(receipt).await?;
+        info!("Publishing paper trade completion");
+        self.from_ledger
+            .publish(
+                self.request.from,
+                &Event::Completed { paper_trade: true },
+                self.context_id.clone(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Settles a held source leg: the destination leg is prepared as a pending transfer, then both are committed
+    async fn held(self) -> anyhow::Result<()> {
+        let rate = self.executed_rate().await?;
+        let (_, target) = self.fees.amounts(&self.request, rate)?;
+        let target = self.to_ledger.money.to_units(target)?;
+        let amount = self.source.amount;
+        let (payer, available) = self.to_ledger.payer().await?;
+        if available < target {
+            return Err(SwapError::new(
+                FailureCode::InsufficientLiquidity,
+                format!("{} available to deliver {}", available, target),
+            )
+            .into());
+        }
+        if !self.from_ledger.store.release(&self.context_id, amount) {
+            info!("Source funds were already refunded");
+            return Ok(());
+        }
+        self.log_pivots();
+        info!("Preparing destination leg");
+        let target_tx_id = self
+            .to_ledger
+            .client
+            .initiate_transfer(
+                TransferBuilder::new()
+                    .step(settlement_step(
+                        payer,
+                        self.request.to,
+                        target,
+                        &self.request,
+                    ))
+                    .context_id(self.context_id.clone()),
+            )
+            .await
+            .map_err(|err| {
+                self.from_ledger.store.restore(&self.context_id, amount);
+                SwapError::new(FailureCode::TransferError, err)
+            })?;
+
+        info!("Committing both legs");
+        let committed = self
+            .from_ledger
+            .client
+            .commit_transfer(self.source.tx_id, true, self.context_id.clone())
+            .await;
+        if let Err(err) = committed {
+            // The source leg stays held until rejected by the refund sweeper
+            self.from_ledger.store.restore(&self.context_id, amount);
+            let rejected = self
+                .to_ledger
+                .client
+                .commit_transfer(target_tx_id, false, self.context_id.clone())
+                .await;
+            if let Err(err) = rejected {
+                error!(%err, %target_tx_id, "Could not reject destination leg");
+            }
+            return Err(SwapError::new(FailureCode::TransferError, err).into());
+        }
+        let committed = self
+            .to_ledger
+            .client
+            .commit_transfer(target_tx_id, true, self.context_id.clone())
+            .await;
+        let target_tx_id = match committed {
+            Ok(_) => target_tx_id,
+            Err(err) => {
+                error!(%err, %target_tx_id, "Source leg committed but not the destination leg");
+                self.recover_destination(payer, target_tx_id, target)
+                    .await?
+            }
+        };
+
+        self.settled(rate, amount, target, target_tx_id).await?;
+        self.complete().await
+    }
+
+    /// Delivers `target` by a plain transfer once the source leg was committed but not the pending destination leg
+    /// `target_tx_id`, refunding the source leg if it can't be delivered either.
+    /// Both legs may settle on different ledgers, either of which can fail alone
+    async fn recover_destination(
+        &self,
+        payer: AccountId,
+        target_tx_id: TxId,
+        target: u64,
+    ) -> anyhow::Result<TxId> {
+        // The pending leg is rejected first, so the payout is never delivered twice
+        let rejected = self
+            .to_ledger
+            .client
+            .commit_transfer(target_tx_id, false, self.context_id.clone())
+            .await;
+        if let Err(err) = rejected {
+            error!(%err, %target_tx_id, "Could not reject destination leg, left to reconcile manually");
+            return Err(SwapError::new(FailureCode::TransferError, err).into());
+        }
+        let err = match self.pay_out(payer, target).await {
+            Ok(tx_id) => return Ok(tx_id),
+            Err(err) => err,
+        };
+        // The committed source leg is refunded by a plain transfer, or by the refund sweeper if that fails too
+        let funds = HeldFunds {
+            liquidity: self.source.account,
+            request: self.request.clone(),
+            tx_id: self.source.tx_id,
+            amount: self.source.amount,
+            pending: false,
+            expires_at: 0,
+        };
+        let reason = format!("Payout failed: {}", err);
+        let refunded = self
+            .from_ledger
+            .refund_funds(self.context_id.clone(), funds, reason)
+            .await;
+        if let Err(err) = refunded {
+            error!(%err, "Could not refund source leg, left to the refund sweeper");
+        }
+        Err(SwapError::new(FailureCode::TransferError, err).into())
+    }
+
+    /// Settles the whole swap once the rate leaves `bounds`, or at `deadline` at the latest
+    async fn limit(self, bounds: Bounds, deadline: u64) -> anyhow::Result<()> {
+        let mut watched = self
+            .from_ledger
+            .ticker
+            .watch(&self.from_currency, &self.to_currency);
+        let deadline = SystemTime::UNIX_EPOCH + Duration::from_secs(deadline);
+        let expiry = tokio::time::sleep(
+            deadline
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+        );
+        tokio::pin!(expiry);
+        let bounds = bounds.lower..bounds.upper;
+        let mut rate = self.rate().await;
+        loop {
+            if self.pause_until_settlement().await {
+                // The rate may have returned within the bounds meanwhile
+                rate = self.rate().await;
+            }
+            let time_exceeded = SystemTime::now() > deadline;
+            match rate {
+                Ok(rate) if time_exceeded || !bounds.contains(&rate) => {
+                    info!(%rate, "Rate left the bounds or deadline reached");
+                    if self
+                        .settle(self.unsettled(), self.unsettled())
+                        .await?
+                        .is_some()
+                    {
+                        self.complete().await?;
+                    }
+                    return Ok(());
+                }
+                Err(err) if time_exceeded => {
+                    return Err(SwapError::new(FailureCode::RateUnavailable, err).into());
+                }
+                _ => {}
+            }
+            // Wakes on rate changes only, settling at the deadline at a freshly looked up rate
+            rate = tokio::select! {
+                Ok(()) = watched.changed() => {
+                    let current = *watched.borrow();
+                    info!(rate = ?current, "Rate changed");
+                    current.ok_or_else(|| anyhow::anyhow!("Rate unavailable"))
+                }
+                _ = &mut expiry => self.rate().await,
+            };
+        }
+    }
+
+    /// Settles the swap in `slices` equal parts at regular intervals over `window` seconds,
+    /// then publishes a receipt summarizing them at their volume-weighted rate
+    async fn twap(self, window: u64, slices: u32) -> anyhow::Result<()> {
+        let slices = slices.max(1);
+        let period = (Duration::from_secs(window) / slices).max(Duration::from_millis(1));
+        let mut interval = tokio::time::interval(period);
+        let total = self.unsettled() as u128;
+        let mut settled = 0;
+        let mut receipts = Vec::new();
+        for slice in 1..=slices {
+            interval.tick().await;
+            self.pause_until_settlement().await;
+            let amount = (total * slice as u128 / slices as u128) as u64 - settled;
+            info!(%slice, %amount, "Settling slice");
+            match self.settle(amount, self.unsettled() - settled).await? {
+                Some(receipt) => receipts.push(receipt),
+                None => return Ok(()),
+            }
+            settled += amount;
+        }
+        if let Some(last) = receipts.last() {
+            let source_amount: u64 = receipts.iter().map(|receipt| receipt.source_amount).sum();
+            let target_amount = receipts.iter().map(|receipt| receipt.target_amount).sum();
+            let executed_rate = receipts
+                .iter()
+                .map(|receipt| receipt.executed_rate * Decimal::from(receipt.source_amount))
+                .sum::<Decimal>()
+                / Decimal::from(source_amount.max(1));
+            let summary = Receipt {
+                target_tx_id: last.target_tx_id,
+                source_amount,
+                target_amount,
+                effective_rate: self.effective_rate(source_amount, target_amount),
+                executed_rate,
+                slices: receipts.len() as u32,
+                signature: None,
+                ..last.clone()
+            };
+            info!(%source_amount, %target_amount, rate = %summary.effective_rate, "Publishing TWAP summary");
+            self.publish_receipt(summary).await?;
+        }
+        self.complete().await
+    }
+
+    /// Settles `amount` of the source leg at the rate looked up right before transferring & publishes its receipt.
+    /// Partially fills the `unsettled` source amount instead if liquidity is short, returning no receipt
+    async fn settle(&self, amount: u64, unsettled: u64) -> anyhow::Result<Option<Receipt>> {
+        let rate = self.executed_rate().await?;
+        let payout = Payout::new(
+            &self.request,
+            &self.fees,
+            self.to_ledger.money,
+            rate,
+            self.source.amount,
+        )?;
+        let target = payout.share(amount);
+        let (payer, available) = self.to_ledger.payer().await?;
+        if available < target {
+            if self.from_ledger.store.take_held(&self.context_id).is_none() {
+                info!("Source funds were already refunded");
+                return Ok(None);
+            }
+            partial_fill(
+                &self.from_ledger,
+                self.source.account,
+                &self.to_ledger,
+                payer,
+                &self.request,
+                unsettled,
+                payout.share(unsettled),
+                available,
+                self.context_id.clone(),
+            )
+            .await?;
+            return Ok(None);
+        }
+
+        if !self.from_ledger.store.release(&self.context_id, amount) {
+            info!("Source funds were already refunded");
+            return Ok(None);
+        }
+        self.log_pivots();
+        info!(%rate, quoted_rate = ?self.quoted_rate, "Executing swap");
+        let paid = match &self.to_ledger.netting {
+            Some(netting) => self.net_out(netting, payer, target).await,
+            None => self.pay_out(payer, target).await,
+        };
+        let target_tx_id = match paid {
+            Ok(target_tx_id) => target_tx_id,
+            Err(err) => {
+                // Compensate by refunding whatever is left of the source leg
+                self.from_ledger.store.restore(&self.context_id, amount);
+                if let Some(funds) = self.from_ledger.store.take_held(&self.context_id) {
+                    let reason = format!("Payout failed: {}", err);
+                    let refunded = self
+                        .from_ledger
+                        .refund_funds(self.context_id.clone(), funds, reason)
+                        .await;
+                    if let Err(err) = refunded {
+                        error!(%err, "Could not refund source leg, left to the refund sweeper");
+                    }
+                }
+                return Err(SwapError::new(FailureCode::TransferError, err).into());
+            }
+        };
+        let receipt = self.settled(rate, amount, target, target_tx_id).await?;
+        Ok(Some(receipt))
+    }
+
+    /// Delivers `target` from the `payer` account to the recipient, retrying failed transfers with exponential backoff
+    async fn pay_out(&self, payer: AccountId, target: u64) -> anyhow::Result<TxId> {
+        let mut backoff = Duration::from_secs(self.from_ledger.refund.payout_backoff);
+        let mut retries = 0;
+        let (to, context_id) = (self.request.to, &self.context_id);
+        let sent = self
+            .to_ledger
+            .sent_transfers(payer, to, target, context_id)
+            .await?;
+        loop {
+            let result = self
+                .to_ledger
+                .transfer_after(
+                    &sent,
+                    payer,
+                    to,
+                    target,
+                    Some(&self.request),
+                    context_id.clone(),
+                )
+                .await;
+            let err = match result {
+                Ok(tx_id) => return Ok(tx_id),
+                Err(err) => err,
+            };
+            // A payout which failed to respond may still settle, & must not be sent twice
+            if let Ok(Some(tx_id)) = self
+                .to_ledger
+                .landed(&sent, payer, to, target, context_id)
+                .await
+            {
+                info!(%tx_id, "Failed payout was settled regardless");
+                return Ok(tx_id);
+            }
+            if retries >= self.from_ledger.refund.payout_retries {
+                return Err(err.into());
+            }
+            retries += 1;
+            warn!(%err, %retries, ?backoff, "Payout failed, retrying");
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    /// Owes `target` to the recipient in the current netting window & reports the batched transfer settling it
+    async fn net_out(
+        &self,
+        netting: &Netting,
+        payer: AccountId,
+        target: u64,
+    ) -> anyhow::Result<TxId> {
+        info!(%target, "Netting payout");
+        let payout = netting.owe(payer, self.request.to, target).await?;
+        let report = NettingReport {
+            tx_id: payout.tx_id,
+            amount: target,
+            net_amount: payout.net_amount,
+            netted: payout.netted,
+        };
+        info!(?report, "Publishing netting report");
+        self.from_ledger
+            .publish(
+                self.request.from,
+                &Event::Netted(report),
+                self.context_id.clone(),
+            )
+            .await?;
+        Ok(payout.tx_id)
+    }
+
+    fn log_pivots(&self) {
+        // Legs through a pivot currency net out within its liquidity account,
+        // so only the source & target legs are transferred
+        for pivot in &self.route[1..self.route.len() - 1] {
+            info!(%pivot, route = %self.route.join(">"), "Routing through pivot currency");
+        }
+    }
+
+    /// Records `amount` of the source leg as settled for `target` & publishes its receipt
+    async fn settled(
+        &self,
+        rate: Decimal,
+        amount: u64,
+        target: u64,
+        target_tx_id: TxId,
+    ) -> anyhow::Result<Receipt> {
+        self.from_ledger.record_volume(&self.request, amount);
+        self.collect_fees(rate, amount, target).await;
+
+        info!("Publishing receipt");
+        let receipt = Receipt {
+            quote_id: self.quote_id,
+            source_tx_id: self.source.tx_id,
+            target_tx_id,
+            source_amount: amount,
+            target_amount: target,
+            effective_rate: self.effective_rate(amount, target),
+            quoted_rate: self.quoted_rate,
+            executed_rate: rate,
+            slices: 0,
+            signature: None,
+        };
+        self.publish_receipt(receipt).await
+    }
+
+    /// Ratio of the settled amounts, including fees
+    fn effective_rate(&self, source_amount: u64, target_amount: u64) -> Decimal {
+        effective_rate(
+            self.from_ledger.money,
+            self.to_ledger.money,
+            source_amount,
+            target_amount,
+        )
+    }
+
+    /// Signs & publishes `receipt` to the initiator, returning it signed
+    async fn publish_receipt(&self, mut receipt: Receipt) -> anyhow::Result<Receipt> {
+        receipt.signature = Some(self.to_ledger.sign(&receipt.signing_payload()).await?);
+        // The initiator observes the ledger of the source currency
+        self.from_ledger
+            .publish(
+                self.request.from,
+                &Event::Receipt(receipt.clone()),
+                self.context_id.clone(),
+            )
+            .await?;
+        Ok(receipt)
+    }
+
+    /// Moves the fees charged on `amount` of the source leg from the liquidity account to the fee account
+    async fn collect_fees(&self, rate: Decimal, amount: u64, target: u64) {
+        let fee_account = match self.from_ledger.fee_account {
+            Some(fee_account) => fee_account,
+            None => return,
+        };
+        let money = self.from_ledger.money;
+        let fee = money.from_units(amount) - self.to_ledger.money.from_units(target) / rate;
+        // No fee is left when the settlement rate moved in favour of the initiator
+        let fee = money.to_units(fee).unwrap_or_default();
+        if fee == 0 {
+            return;
+        }
+        info!(%fee, "Collecting fees");
+        let result = self
+            .from_ledger
+            .transfer(
+                self.source.account,
+                fee_account,
+                fee,
+                None,
+                self.context_id.clone(),
+            )
+            .await;
+        if let Err(err) = result {
+            // The swap is settled regardless, the fees stay in the liquidity account
+            warn!(%err, %fee, "Failed to collect fees");
+        }
+    }
+
+    async fn complete(&self) -> anyhow::Result<()> {
+        self.from_ledger.store.take_held(&self.context_id);
+        info!("Publishing completion");
+        self.from_ledger
+            .publish(
+                self.request.from,
+                &Event::Completed { paper_trade: false },
+                self.context_id.clone(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Settles the portion of the swap covered by the liquidity available to `payer` & refunds the remainder
+/// from the `source` account
+#[allow(clippy::too_many_arguments)]
+async fn partial_fill(
+    from_ledger: &Ledger,
+    source: AccountId,
+    to_ledger: &Ledger,
+    payer: AccountId,
+    request: &Request,
+    received: u64,
+    amount: u64,
+    available: u64,
+    context_id: Vec<u8>,
+) -> anyhow::Result<()> {
+    let remaining = amount - available;
+    let refunded = (received as u128 * remaining as u128 / amount as u128) as u64;
+    info!(%amount, %available, %refunded, "Insufficient liquidity, partially filling swap");
+
+    if available == 0 {
+        refund(
+            from_ledger,
+            source,
+            request.from,
+            request,
+            received,
+            context_id,
+        )
+        .await?;
+        return Err(
+            SwapError::new(FailureCode::InsufficientLiquidity, "No liquidity available").into(),
+        );
+    }
+
+    to_ledger
+        .transfer(
+            payer,
+            request.to,
+            available,
+            Some(request),
+            context_id.clone(),
+        )
+        .await
+        .map_err(|err| SwapError::new(FailureCode::TransferError, err))?;
+    from_ledger.record_volume(request, received - refunded);
+    refund(
+        from_ledger,
+        source,
+        request.from,
+        request,
+        refunded,
+        context_id.clone(),
+    )
+    .await?;
+
+    info!("Publishing partial fill");
+    from_ledger
+        .publish(
+            request.from,
+            &Event::PartialFill(PartialFill {
+                filled: available,
+                remaining,
+                refunded,
+            }),
+            context_id,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Returns `amount` of the source leg `source_tx_id` received by `from` to `to`, rejecting it instead while it is held
+#[allow(clippy::too_many_arguments)]
+async fn return_source(
+    from_ledger: &Ledger,
+    from: AccountId,
+    to: AccountId,
+    request: &Request,
+    source_tx_id: TxId,
+    amount: u64,
+    pending: bool,
+    context_id: Vec<u8>,
+) -> anyhow::Result<Option<TxId>> {
+    if !pending {
+        return refund(from_ledger, from, to, request, amount, context_id).await;
+    }
+    info!(%source_tx_id, "Rejecting held source leg");
+    let tx_id = from_ledger
+        .client
+        .commit_transfer(source_tx_id, false, context_id)
+        .await?;
+    Ok(Some(tx_id))
+}
+
+/// Returns `amount` of the source leg received by `from` to `to`, if any
+async fn refund(
+    from_ledger: &Ledger,
+    from: AccountId,
+    to: AccountId,
+    request: &Request,
+    amount: u64,
+    context_id: Vec<u8>,
+) -> anyhow::Result<Option<TxId>> {
+    if amount == 0 {
+        return Ok(None);
+    }
+    info!(%amount, "Refunding source leg");
+    let tx_id = from_ledger
+        .transfer(from, to, amount, Some(request), context_id)
+        .await
+        .map_err(|err| SwapError::new(FailureCode::TransferError, err))?;
+    Ok(Some(tx_id))
+}
+
+/// Transfer step of a swap, carrying the request's memo & compliance information if any
+fn settlement_step(from: AccountId, to: AccountId, amount: u64, request: &Request) -> StepBuilder {
+    let mut step = StepBuilder::new(from, to, amount);
+    if let Some(memo) = request.memo_metadata() {
+        step = step.metadata(memo);
+    }
+    if let Some(compliance) = request.compliance_metadata() {
+        step = step.metadata(compliance);
+    }
+    step
+}
+
+/// Incoming transfer from the initiator to the liquidity provider
+struct SourceLeg {
+    tx_id: TxId,
+    /// Account funding the swap
+    from: Option<AccountId>,
+    amount: u64,
+    /// Held by a pending transfer, committed together with the destination leg
+    pending: bool,
+    /// Liquidity account receiving the swap
+    account: AccountId,
+}
+
+/// Swap failure carrying the reason code published in `Event::Failed`
+#[derive(Debug)]
+struct SwapError {
+    code: FailureCode,
+    message: String,
+}
+
+impl SwapError {
+    fn new(code: FailureCode, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for SwapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for SwapError {}
+
+/// Requested amount outside of the swap sizes served
+#[derive(Debug)]
+struct OutOfRange {
+    amount: Decimal,
+    allowed: AmountRange,
+}
+
+impl std::fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Amount {} is outside of the allowed range {}",
+            self.amount, self.allowed
+        )
+    }
+}
+
+impl std::error::Error for OutOfRange {}
+
+/// Pair requested outside of its trading hours
+#[derive(Debug)]
+struct Closed {
+    pair: String,
+    opens_at: DateTime<Utc>,
+}
+
+impl std::fmt::Display for Closed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Pair {} is closed until {}",
+            self.pair,
+            self.opens_at.to_rfc3339()
+        )
+    }
+}
+
+impl std::error::Error for Closed {}
+
+/// Rejection of an invalid request, carrying the allowed range when the amount was outside of it
+/// & the next opening time when the pair was closed
+fn rejection(err: &anyhow::Error) -> Event {
+    Event::Reject {
+        reason: err.to_string(),
+        allowed: err.downcast_ref::<OutOfRange>().map(|err| err.allowed),
+        reopens_at: err
+            .downcast_ref::<Closed>()
+            .map(|err| err.opens_at.timestamp() as u64),
     }
-    Ok(())
 }
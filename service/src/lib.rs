@@ -1,15 +1,15 @@
 #![allow(dead_code)]
 
-use m10_sdk::MetadataType;
-
 pub mod config;
 pub mod event;
+pub mod hours;
+pub mod logging;
+pub mod money;
+pub mod pricing;
+pub mod secret;
 
 pub const FX_SWAP_ACTION: &str = "m10.fx.swap";
 pub const FX_SWAP_METADATA: &str = "m10.fx.execute";
+pub const COMPLIANCE_METADATA: &str = "m10.fx.compliance";
 
-pub struct FxSwapMetadata;
-
-impl MetadataType for FxSwapMetadata {
-    const TYPE_URL: &'static str = FX_SWAP_METADATA;
-}
+pub use event::FxSwapMetadata;
@@ -0,0 +1,93 @@
+use m10_sdk::account::AccountId;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use service::config::LimitsConfig;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Volume settled by an account at a point in time
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Settled {
+    at: u64,
+    notional: Decimal,
+}
+
+/// Volume settled by each account over a rolling window, persisted to survive restarts
+#[derive(Clone, Default)]
+pub struct Limits {
+    config: Arc<LimitsConfig>,
+    volumes: Arc<Mutex<HashMap<String, Vec<Settled>>>>,
+}
+
+impl Limits {
+    /// Loads the volumes persisted at the configured path, if any
+    pub fn load(config: LimitsConfig) -> anyhow::Result<Self> {
+        let volumes = match &config.path {
+            Some(path) if path.exists() => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+            _ => HashMap::new(),
+        };
+        Ok(Self {
+            config: Arc::new(config),
+            volumes: Arc::new(Mutex::new(volumes)),
+        })
+    }
+
+    /// Checks `account` can settle another `notional` without exceeding its cap
+    pub fn check(&self, account: &AccountId, notional: Decimal) -> anyhow::Result<()> {
+        let account = account.to_string();
+        let cap = match self
+            .config
+            .accounts
+            .get(&account)
+            .or(self.config.cap.as_ref())
+        {
+            Some(cap) => *cap,
+            None => return Ok(()),
+        };
+        let mut volumes = self.volumes.lock().unwrap();
+        let volume: Decimal = self
+            .prune(volumes.entry(account).or_default())
+            .iter()
+            .map(|settled| settled.notional)
+            .sum();
+        if volume + notional > cap {
+            return Err(anyhow::anyhow!(
+                "Volume cap of {} exceeded, {} settled within the last {}s",
+                cap,
+                volume,
+                self.config.window
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds `notional` to the volume settled by `account`
+    pub fn record(&self, account: &AccountId, notional: Decimal) -> anyhow::Result<()> {
+        let mut volumes = self.volumes.lock().unwrap();
+        let settled = volumes.entry(account.to_string()).or_default();
+        self.prune(settled).push(Settled {
+            at: now()?,
+            notional,
+        });
+        volumes.retain(|_, settled| !settled.is_empty());
+        if let Some(path) = &self.config.path {
+            // Replace the file atomically, so a crash never leaves it truncated
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec(&*volumes)?)?;
+            std::fs::rename(tmp, path)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the volumes settled before the window
+    fn prune<'a>(&self, settled: &'a mut Vec<Settled>) -> &'a mut Vec<Settled> {
+        let since = now().unwrap_or_default().saturating_sub(self.config.window);
+        settled.retain(|settled| settled.at >= since);
+        settled
+    }
+}
+
+fn now() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
@@ -0,0 +1,56 @@
+//! Output of the logs of both binaries, as set in the `log` section of the config
+
+use crate::config::{LogConfig, LogFormat, LogRotation};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+/// Installs the global subscriber, `RUST_LOG` overriding the configured levels.
+/// Targets are only written with `targets`, except in JSON
+pub fn init(config: &LogConfig, targets: bool) -> anyhow::Result<()> {
+    install(config, targets, BoxMakeWriter::new(std::io::stdout))
+}
+
+/// Installs the global subscriber like `init`, but on the standard error unless logging to files,
+/// leaving the standard output to the results of a command
+pub fn init_stderr(config: &LogConfig, targets: bool) -> anyhow::Result<()> {
+    install(config, targets, BoxMakeWriter::new(std::io::stderr))
+}
+
+fn install(config: &LogConfig, targets: bool, console: BoxMakeWriter) -> anyhow::Result<()> {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(directives) => EnvFilter::try_new(directives)?,
+        Err(_) => EnvFilter::try_new(config.directives())?,
+    };
+    let writer = match &config.file {
+        Some(file) => {
+            let rotation = match file.rotation {
+                LogRotation::Minutely => Rotation::MINUTELY,
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                LogRotation::Never => Rotation::NEVER,
+            };
+            BoxMakeWriter::new(RollingFileAppender::new(
+                rotation,
+                &file.directory,
+                &file.prefix,
+            ))
+        }
+        None => console,
+    };
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(config.file.is_none())
+        .with_target(targets);
+    let layer = match config.format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer.json().with_target(true).boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(layer)
+        .with(filter)
+        .try_init()?;
+    Ok(())
+}
@@ -1,56 +1,418 @@
-mod config;
-mod event;
+mod admin;
+mod alerts;
+mod approvals;
+mod breaker;
+mod cursor;
+mod endpoints;
 mod ledger;
+mod limits;
+mod netting;
+mod pool;
+mod rates;
+mod rebalance;
+mod reload;
+mod retry;
+mod storage;
+mod store;
+mod supervisor;
+mod ticker;
 
-use crate::config::CurrencyCode;
+use crate::alerts::Alerter;
+use crate::approvals::Approvals;
+use crate::breaker::CircuitBreaker;
+use crate::cursor::Cursors;
 use crate::ledger::Ledger;
+use crate::limits::Limits;
+use crate::pool::SwapPool;
+use crate::rates::{HttpRates, MedianRates, RateProvider, StaticRates, StreamRates};
+use crate::retry::Retry;
+use crate::storage::Storage;
+use crate::store::SwapStore;
+use crate::supervisor::Supervisor;
+use crate::ticker::RateTicker;
+use clap::Parser;
 use futures_util::future::select_all;
+use service::config::{self, CurrencyCode};
+use service::hours::TradingHours;
+use service::logging;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::{info_span, Instrument};
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info, info_span, warn, Instrument};
 
 pub type LedgerDB = Arc<HashMap<CurrencyCode, Ledger>>;
 
+/// Interval at which the health of the observers is logged
+const HEALTH_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Liquidity provider quoting & settling FX swaps
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Config file, `./config.toml` if it exists when unset
+    #[clap(short, long, env = "APP_CONFIG")]
+    config: Option<PathBuf>,
+    /// Profile of the config overlaid onto the rest of it, e.g. `prod` for `[profiles.prod]`
+    #[clap(short, long, env = "APP_PROFILE")]
+    profile: Option<String>,
+    /// Validates the config & exits
+    #[clap(long)]
+    check_config: bool,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
-
-    let config = config::parse()?;
+    let args = Args::parse();
+    let config = config::parse(args.config.as_deref(), args.profile.as_deref())?;
+    logging::init(&config.log, true)?;
+    config.validate()?;
+    if args.check_config {
+        info!("Config is valid");
+        return Ok(());
+    }
     let address = config.address;
+    let transport = config.transport;
+    let requote = config.requote;
+    let negotiation = config.negotiation;
+    let compliance = config.compliance;
+    let refund = config.refund;
+    let shutdown = config.shutdown;
+    let routing = config.routing;
+    let pairs = config.pairs;
+    let max_slippage_bps = config.max_slippage_bps;
+    let dry_run = config.dry_run;
+    let engine = config.engine;
+    let poll_interval = Duration::from_secs(engine.poll_interval);
+    let store = SwapStore::new(config.retention);
+    let cursors = Cursors::load(config.cursors)?;
+    let limits = Limits::load(config.limits)?;
+    let credit = Arc::new(config.credit);
+    let approval = config.approval;
+    let approvals = Approvals::default();
+    let hours = Arc::new(TradingHours::new(&config.hours)?);
+    let netting = config.netting;
+    let reload = config.reload;
+    let alerter = Alerter::new(&config.alerts);
+    let alert_interval = Duration::from_secs(config.alerts.interval);
+    let mut rebalance = config.rebalance;
+    // Rebalancing transfers are only reported too while paper trading
+    rebalance.dry_run |= dry_run;
+    let retry = Retry::new(&config.retry)?;
+    let pool = SwapPool::new(config.concurrency);
+    let breaker = CircuitBreaker::new(config.breaker);
+    let ticker = RateTicker::default();
+    let (feeds, streams) = match (config.rates, config.rate_stream, &config.aggregate) {
+        (feed, stream, None) => (Vec::from_iter(feed), Vec::from_iter(stream)),
+        (None, None, Some(aggregate)) => (aggregate.feeds.clone(), aggregate.streams.clone()),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Only one of rates, rate_stream & aggregate can be set"
+            ))
+        }
+    };
+    let feeds: Vec<_> = feeds
+        .into_iter()
+        .map(|config| Arc::new(HttpRates::new(config)))
+        .collect();
+    let streams: Vec<_> = streams
+        .into_iter()
+        .map(|config| Arc::new(StreamRates::new(config)))
+        .collect();
+    let base_rates = Arc::new(StaticRates::new(&config.liquidity));
+    let rates: Arc<dyn RateProvider> = match &config.aggregate {
+        Some(aggregate) => {
+            let mut sources: Vec<(String, Arc<dyn RateProvider>)> = Vec::new();
+            if aggregate.base_rates {
+                sources.push(("base_rates".to_string(), base_rates.clone()));
+            }
+            for feed in &feeds {
+                sources.push((feed.name().to_string(), feed.clone()));
+            }
+            for stream in &streams {
+                sources.push((stream.name().to_string(), stream.clone()));
+            }
+            Arc::new(MedianRates::new(
+                sources,
+                aggregate.tolerance_bps,
+                aggregate.min_sources,
+            ))
+        }
+        None => match (feeds.first(), streams.first()) {
+            (Some(feed), None) => feed.clone(),
+            (None, Some(stream)) => stream.clone(),
+            (None, None) => base_rates.clone(),
+            _ => return Err(anyhow::anyhow!("Rates can't be both polled & streamed")),
+        },
+    };
+    let storage = match &config.database {
+        Some(url) => Some(Storage::connect(url).await?),
+        None => None,
+    };
 
-    let ledgers = config
+    // Key pairs are resolved up front, as they may be fetched from a secret store
+    let mut key_pairs = HashMap::new();
+    for (currency, liquidity) in &config.liquidity {
+        let key_pair = liquidity.key_pair.resolve().await.map_err(|err| {
+            anyhow::anyhow!("Could not resolve the key pair of {}: {}", currency, err)
+        })?;
+        key_pairs.insert(currency.clone(), key_pair);
+    }
+
+    let mut ledgers = config
         .liquidity
         .into_iter()
         .map(|(currency, config)| {
+            let key_pair = &key_pairs[&currency];
+            let address = config.address.clone().unwrap_or_else(|| address.clone());
+            info!(%currency, %address, "Connecting to ledger");
             Ok((
                 currency.to_lowercase(),
-                Ledger::new(address.clone(), currency, config)?,
+                Ledger::new(
+                    address,
+                    &transport,
+                    currency,
+                    config,
+                    key_pair,
+                    requote,
+                    negotiation,
+                    compliance,
+                    refund,
+                    routing.clone(),
+                    &pairs,
+                    &engine,
+                    store.clone(),
+                    limits.clone(),
+                    credit.clone(),
+                    approval,
+                    approvals.clone(),
+                    storage.clone(),
+                    retry.clone(),
+                    pool.clone(),
+                    rates.clone(),
+                    breaker.clone(),
+                    ticker.clone(),
+                    max_slippage_bps,
+                    hours.clone(),
+                    netting,
+                    dry_run,
+                )?,
             ))
         })
         .collect::<anyhow::Result<HashMap<CurrencyCode, Ledger>>>()?;
+    for ledger in ledgers.values_mut() {
+        ledger.connect().await;
+        ledger.load_decimals().await?;
+        ledger.rewind(&cursors)?;
+    }
 
     let ledger_db = Arc::new(ledgers);
 
-    let mut futures = vec![];
+    // Resume swaps interrupted by a restart
+    if let Some(storage) = &storage {
+        for swap in storage.pending().await? {
+            match ledger_db.get(&swap.currency) {
+                Some(ledger) => ledger.resume(ledger_db.clone(), swap)?,
+                None => error!(currency = %swap.currency, "Can't resume swap, missing currency"),
+            }
+        }
+    }
+
+    // Observers are restarted when they fail, only exiting on unrecoverable errors
+    let supervisor = Supervisor::default();
+    let mut futures = vec![tokio::spawn(
+        supervisor.clone().report_health(HEALTH_REPORT_INTERVAL),
+    )];
     for (currency, ledger) in ledger_db.iter() {
         // Observe actions
-        futures.push(tokio::spawn(
-            ledger
-                .clone()
-                .observe_actions(ledger_db.clone())
-                .instrument(info_span!("actions",%currency)),
-        ));
+        futures.push(supervisor.spawn(format!("actions/{}", currency), {
+            let (ledger, db, cursors) = (ledger.clone(), ledger_db.clone(), cursors.clone());
+            let span = info_span!("actions", %currency);
+            move || {
+                ledger
+                    .clone()
+                    .observe_actions(db.clone(), cursors.clone())
+                    .instrument(span.clone())
+            }
+        }));
 
-        // Observe transfers
-        futures.push(tokio::spawn(
-            ledger
+        // Observe transfers of each liquidity account
+        for &account in ledger.accounts() {
+            futures.push(supervisor.spawn(ledger.transfer_stream(account), {
+                let (ledger, db, cursors) = (ledger.clone(), ledger_db.clone(), cursors.clone());
+                let span = info_span!("transfers", %currency, %account);
+                move || {
+                    ledger
+                        .clone()
+                        .observe_transfers(db.clone(), cursors.clone(), account)
+                        .instrument(span.clone())
+                }
+            }));
+        }
+
+        // Refund unsettled executions
+        futures.push(supervisor.spawn(format!("refunds/{}", currency), {
+            let ledger = ledger.clone();
+            let span = info_span!("refunds", %currency);
+            move || ledger.clone().refund_expired().instrument(span.clone())
+        }));
+
+        // Settle the payouts netted over each window
+        if let Some(netting) = netting {
+            futures.push(supervisor.spawn(format!("netting/{}", currency), {
+                let ledger = ledger.clone();
+                let span = info_span!("netting", %currency);
+                move || {
+                    ledger
+                        .clone()
+                        .net_payouts(netting.window)
+                        .instrument(span.clone())
+                }
+            }));
+        }
+
+        // Alert on low liquidity
+        if ledger.monitors_liquidity() {
+            futures.push(supervisor.spawn(format!("alerts/{}", currency), {
+                let (ledger, alerter) = (ledger.clone(), alerter.clone());
+                let span = info_span!("alerts", %currency);
+                move || {
+                    ledger
+                        .clone()
+                        .monitor_liquidity(alert_interval, alerter.clone())
+                        .instrument(span.clone())
+                }
+            }));
+        }
+    }
+
+    // Reprice the ledgers when the config changes
+    futures.push(supervisor.spawn("reload".to_string(), {
+        let (db, base_rates) = (ledger_db.clone(), base_rates.clone());
+        let path = args.config.clone();
+        let profile = args.profile.clone();
+        move || {
+            reload::watch(
+                db.clone(),
+                base_rates.clone(),
+                path.clone(),
+                profile.clone(),
+                reload,
+            )
+            .instrument(info_span!("reload"))
+        }
+    }));
+
+    // Forget stale swap contexts
+    futures.push(supervisor.spawn("gc".to_string(), {
+        let store = store.clone();
+        move || store.clone().collect_garbage().instrument(info_span!("gc"))
+    }));
+
+    // Publish the rates watched by limit executions
+    futures.push(supervisor.spawn("ticker".to_string(), {
+        let (db, rates) = (ledger_db.clone(), rates.clone());
+        move || {
+            ticker
                 .clone()
-                .observe_transfers(ledger_db.clone())
-                .instrument(info_span!("transfers",%currency)),
-        ));
+                .run(db.clone(), rates.clone(), poll_interval)
+                .instrument(info_span!("ticker"))
+        }
+    }));
+
+    // Poll the rate feeds
+    for feed in feeds {
+        futures.push(supervisor.spawn(format!("rates/{}", feed.name()), {
+            let span = info_span!("rates", source = %feed.name());
+            move || feed.clone().poll().instrument(span.clone())
+        }));
+    }
+
+    // Receive the rate streams, reconnecting when they close
+    for stream in streams {
+        futures.push(supervisor.spawn(format!("rate_stream/{}", stream.name()), {
+            let span = info_span!("rate_stream", source = %stream.name());
+            move || stream.clone().stream().instrument(span.clone())
+        }));
+    }
+
+    if let Some(address) = &config.admin {
+        let address = address.parse()?;
+        let token = match &config.admin_token {
+            Some(token) => {
+                let token = token
+                    .resolve()
+                    .await
+                    .map_err(|err| anyhow::anyhow!("Could not resolve the admin token: {}", err))?;
+                Some(String::from_utf8(token)?.trim().to_string())
+            }
+            None => None,
+        };
+        futures.push(supervisor.spawn("admin".to_string(), {
+            let (db, store, pool) = (ledger_db.clone(), store.clone(), pool.clone());
+            let (rates, approvals) = (rates.clone(), approvals.clone());
+            move || {
+                admin::serve(
+                    address,
+                    db.clone(),
+                    store.clone(),
+                    pool.clone(),
+                    rates.clone(),
+                    approvals.clone(),
+                    token.clone(),
+                )
+                .instrument(info_span!("admin"))
+            }
+        }));
+    }
+
+    // Keep the inventory at its target allocation
+    if ledger_db
+        .values()
+        .any(|ledger| ledger.allocation().is_some())
+    {
+        futures.push(supervisor.spawn("rebalance".to_string(), {
+            let db = ledger_db.clone();
+            move || rebalance::rebalance(db.clone(), rebalance).instrument(info_span!("rebalance"))
+        }));
+    }
+
+    // A failed task shuts the service down too, once the swaps being settled are drained
+    let failed = tokio::select! {
+        (result, _, _) = select_all(futures.iter_mut()) => match result {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(err),
+            Err(err) => Some(err.into()),
+        },
+        result = shutdown_signal() => result.err(),
+    };
+    if let Some(err) = &failed {
+        error!(%err, "Task failed");
     }
 
-    select_all(futures).await.0??;
+    // Stop accepting swaps, any transaction not handled yet is replayed from the cursors on restart
+    info!("Shutting down");
+    for future in &futures {
+        future.abort();
+    }
+    let running = store.drain(Duration::from_secs(shutdown.deadline)).await;
+    if running > 0 {
+        warn!(%running, "Exiting with unsettled swaps, resumed on restart if a database is configured");
+    }
 
+    match failed {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Completes on SIGINT or SIGTERM
+async fn shutdown_signal() -> anyhow::Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => {}
+    }
     Ok(())
 }
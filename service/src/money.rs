@@ -0,0 +1,208 @@
+//! Conversions between the decimal amounts quoted in a currency & the integer amounts transferred on its ledger
+
+use crate::config::Rounding;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::fmt::{Display, Formatter};
+
+/// Largest scale of a `Decimal`, beyond which the ledger units of a currency can't be represented
+pub const MAX_DECIMALS: u32 = 28;
+
+/// Amounts of a currency with `decimals` digits after the decimal point on the ledger
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Money {
+    decimals: u32,
+    rounding: Rounding,
+}
+
+impl Money {
+    pub fn new(decimals: u32, rounding: Rounding) -> Result<Self, MoneyError> {
+        if decimals > MAX_DECIMALS {
+            return Err(MoneyError::Decimals(decimals));
+        }
+        Ok(Self { decimals, rounding })
+    }
+
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    /// Ledger units of `amount`, rounded to the smallest unit of the currency
+    pub fn to_units(&self, amount: Decimal) -> Result<u64, MoneyError> {
+        if amount.is_sign_negative() && !amount.is_zero() {
+            return Err(MoneyError::Negative(amount));
+        }
+        let scaled = (0..self.decimals)
+            .try_fold(amount, |scaled, _| scaled.checked_mul(Decimal::TEN))
+            .ok_or(MoneyError::Overflow(amount))?;
+        let units = match self.rounding {
+            Rounding::Down => scaled.round_dp_with_strategy(0, RoundingStrategy::ToZero),
+            Rounding::Up => scaled.round_dp_with_strategy(0, RoundingStrategy::AwayFromZero),
+            Rounding::HalfUp => {
+                scaled.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+            }
+            Rounding::HalfEven => {
+                scaled.round_dp_with_strategy(0, RoundingStrategy::MidpointNearestEven)
+            }
+            Rounding::Exact if !scaled.fract().is_zero() => {
+                return Err(MoneyError::SubUnit {
+                    amount,
+                    decimals: self.decimals,
+                })
+            }
+            Rounding::Exact => scaled,
+        };
+        units.to_u64().ok_or(MoneyError::Overflow(amount))
+    }
+
+    /// Amount of the currency transferred as `units` on the ledger
+    pub fn from_units(&self, units: u64) -> Decimal {
+        Decimal::from_i128_with_scale(units as i128, self.decimals)
+    }
+}
+
+/// Amount which can't be transferred on the ledger
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoneyError {
+    /// The currency has more decimals than a `Decimal` can hold
+    Decimals(u32),
+    Negative(Decimal),
+    /// The amount doesn't fit in ledger units
+    Overflow(Decimal),
+    /// The amount is finer than the smallest unit of the currency, with exact rounding
+    SubUnit {
+        amount: Decimal,
+        decimals: u32,
+    },
+}
+
+impl Display for MoneyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoneyError::Decimals(decimals) => write!(
+                f,
+                "Currency has {} decimals, more than the {} supported",
+                decimals, MAX_DECIMALS
+            ),
+            MoneyError::Negative(amount) => write!(f, "Amount {} is negative", amount),
+            MoneyError::Overflow(amount) => {
+                write!(f, "Amount {} overflows the ledger units", amount)
+            }
+            MoneyError::SubUnit { amount, decimals } => write!(
+                f,
+                "Amount {} has a remainder below the smallest unit of a currency with {} decimals",
+                amount, decimals
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(amount: &str) -> Decimal {
+        amount.parse().unwrap()
+    }
+
+    fn units(amount: &str, rounding: Rounding) -> Result<u64, MoneyError> {
+        Money::new(2, rounding)
+            .unwrap()
+            .to_units(self::amount(amount))
+    }
+
+    #[test]
+    fn rounds_sub_units_per_mode() {
+        let cases = [
+            ("1.234", [123, 124, 123, 123]),
+            ("1.235", [123, 124, 124, 124]),
+            ("1.245", [124, 125, 125, 124]),
+            ("1.236", [123, 124, 124, 124]),
+        ];
+        let modes = [
+            Rounding::Down,
+            Rounding::Up,
+            Rounding::HalfUp,
+            Rounding::HalfEven,
+        ];
+        for (value, expected) in cases {
+            for (rounding, expected) in modes.into_iter().zip(expected) {
+                assert_eq!(
+                    units(value, rounding),
+                    Ok(expected),
+                    "{} rounded {:?}",
+                    value,
+                    rounding
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exact_rejects_sub_units() {
+        assert_eq!(units("1.23", Rounding::Exact), Ok(123));
+        assert_eq!(units("1.230", Rounding::Exact), Ok(123));
+        assert_eq!(
+            units("1.234", Rounding::Exact),
+            Err(MoneyError::SubUnit {
+                amount: amount("1.234"),
+                decimals: 2
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_negative_amounts() {
+        assert_eq!(
+            units("-0.01", Rounding::Down),
+            Err(MoneyError::Negative(amount("-0.01")))
+        );
+        // Negative zero is still zero
+        assert_eq!(units("-0", Rounding::Exact), Ok(0));
+    }
+
+    #[test]
+    fn rejects_overflowing_amounts() {
+        let max = Decimal::from(u64::MAX);
+        assert_eq!(
+            Money::new(0, Rounding::Exact).unwrap().to_units(max),
+            Ok(u64::MAX)
+        );
+        assert_eq!(
+            Money::new(2, Rounding::Exact).unwrap().to_units(max),
+            Err(MoneyError::Overflow(max))
+        );
+        // Scaling past the range of a decimal overflows as well
+        assert_eq!(
+            Money::new(28, Rounding::Down)
+                .unwrap()
+                .to_units(Decimal::MAX),
+            Err(MoneyError::Overflow(Decimal::MAX))
+        );
+    }
+
+    #[test]
+    fn rejects_more_decimals_than_supported() {
+        assert!(Money::new(MAX_DECIMALS, Rounding::Exact).is_ok());
+        assert_eq!(
+            Money::new(MAX_DECIMALS + 1, Rounding::Exact).unwrap_err(),
+            MoneyError::Decimals(MAX_DECIMALS + 1)
+        );
+    }
+
+    #[test]
+    fn units_round_trip() {
+        for decimals in [0, 2, 6, 18, MAX_DECIMALS] {
+            let money = Money::new(decimals, Rounding::Exact).unwrap();
+            for units in [0, 1, 12_345, u64::MAX] {
+                assert_eq!(money.to_units(money.from_units(units)), Ok(units));
+            }
+        }
+    }
+}
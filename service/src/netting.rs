@@ -0,0 +1,94 @@
+use m10_sdk::account::AccountId;
+use m10_sdk::TxId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Batched transfer settling a payout, along with the others owed to the same recipient
+#[derive(Debug, Clone, Copy)]
+pub struct NettedPayout {
+    pub tx_id: TxId,
+    /// Net amount transferred to the recipient
+    pub net_amount: u64,
+    /// Payouts netted together
+    pub netted: u32,
+}
+
+/// Sum of the payouts owed by a liquidity account to a recipient over a netting window
+pub struct NetPosition {
+    pub payer: AccountId,
+    pub recipient: AccountId,
+    pub amount: u64,
+    owed: Vec<oneshot::Sender<Result<NettedPayout, String>>>,
+}
+
+impl NetPosition {
+    /// Number of payouts netted into the position
+    pub fn payouts(&self) -> usize {
+        self.owed.len()
+    }
+
+    /// Notifies each payout of the batched transfer `tx_id` settling it
+    pub fn settled(self, tx_id: TxId) {
+        let payout = NettedPayout {
+            tx_id,
+            net_amount: self.amount,
+            netted: self.owed.len() as u32,
+        };
+        for owed in self.owed {
+            let _ = owed.send(Ok(payout));
+        }
+    }
+
+    /// Notifies each payout of the failure to settle them
+    pub fn failed(self, reason: &str) {
+        for owed in self.owed {
+            let _ = owed.send(Err(reason.to_string()));
+        }
+    }
+}
+
+/// Payouts owed over the current netting window of a ledger
+#[derive(Clone, Default)]
+pub struct Netting {
+    positions: Arc<Mutex<HashMap<(AccountId, AccountId), NetPosition>>>,
+}
+
+impl Netting {
+    /// Owes `amount` to `recipient` from `payer`, waiting for the batched transfer settling it at the end of the window
+    pub async fn owe(
+        &self,
+        payer: AccountId,
+        recipient: AccountId,
+        amount: u64,
+    ) -> anyhow::Result<NettedPayout> {
+        let (owed, settled) = oneshot::channel();
+        {
+            let mut positions = self.positions.lock().unwrap();
+            let position = positions
+                .entry((payer, recipient))
+                .or_insert_with(|| NetPosition {
+                    payer,
+                    recipient,
+                    amount: 0,
+                    owed: Vec::new(),
+                });
+            position.amount += amount;
+            position.owed.push(owed);
+        }
+        settled
+            .await
+            .map_err(|_| anyhow::anyhow!("Netting window closed without settling the payout"))?
+            .map_err(|reason| anyhow::anyhow!(reason))
+    }
+
+    /// Takes the net positions of the window, starting the next one
+    pub fn close_window(&self) -> Vec<NetPosition> {
+        self.positions
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, position)| position)
+            .collect()
+    }
+}
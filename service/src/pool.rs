@@ -0,0 +1,76 @@
+use service::config::ConcurrencyConfig;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bounds the swaps settled at once, queueing the executions accepted beyond it
+#[derive(Clone)]
+pub struct SwapPool {
+    slots: Arc<Semaphore>,
+    max_swaps: usize,
+    max_queued: usize,
+    /// Accepted executions waiting for a slot
+    queued: Arc<AtomicUsize>,
+}
+
+/// Place of an accepted execution in the queue, until it is given a slot
+pub struct Ticket {
+    pool: SwapPool,
+}
+
+impl SwapPool {
+    pub fn new(config: ConcurrencyConfig) -> Self {
+        let max_swaps = config.max_swaps.max(1);
+        Self {
+            slots: Arc::new(Semaphore::new(max_swaps)),
+            max_swaps,
+            max_queued: config.max_queued,
+            queued: Default::default(),
+        }
+    }
+
+    /// Queues an execution, `None` when the queue is full
+    pub fn admit(&self) -> Option<Ticket> {
+        let capacity = self.max_queued + self.slots.available_permits();
+        self.queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (queued < capacity).then(|| queued + 1)
+            })
+            .ok()
+            .map(|_| Ticket { pool: self.clone() })
+    }
+
+    /// Queues an execution regardless of the queue's capacity, e.g. one resumed after a restart
+    pub fn readmit(&self) -> Ticket {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        Ticket { pool: self.clone() }
+    }
+
+    /// Executions waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Swaps being settled
+    pub fn running(&self) -> usize {
+        self.max_swaps - self.slots.available_permits()
+    }
+}
+
+impl Ticket {
+    /// Waits for a slot, held until the swap is settled
+    pub async fn slot(self) -> OwnedSemaphorePermit {
+        self.pool
+            .slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Swap slots are never closed")
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.pool.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
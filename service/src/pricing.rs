@@ -0,0 +1,210 @@
+//! Pricing of the quotes & of their settlement, shared by the service & the `simulate` command of the cli
+
+use crate::config::CurrencyCode;
+use crate::event::{Fees, Quote, RateTier, Request};
+use crate::money::Money;
+use async_trait::async_trait;
+use m10_sdk::account::AccountId;
+use rust_decimal::Decimal;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Source of the FX rates quoted & settled
+#[async_trait]
+pub trait RateProvider: Send + Sync {
+    /// Amount of `quote` per unit of `base`
+    async fn rate(&self, base: &str, quote: &str) -> anyhow::Result<Decimal>;
+
+    /// Notified whenever rates are pushed, `None` for providers which are polled
+    fn changes(&self) -> Option<watch::Receiver<()>> {
+        None
+    }
+
+    /// Time since the rate of `base`/`quote` was last updated, `None` for rates which don't age
+    fn age(&self, _base: &str, _quote: &str) -> Option<Duration> {
+        None
+    }
+
+    /// Health of each underlying source, for providers aggregating several of them
+    fn health(&self) -> Vec<(String, SourceHealth)> {
+        Vec::new()
+    }
+}
+
+/// Health of a rate source
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SourceHealth {
+    /// Whether its last lookup succeeded
+    pub up: bool,
+    /// Failed lookups
+    pub errors: u64,
+    /// Rates discarded for deviating from the other sources
+    pub outliers: u64,
+}
+
+/// Rate, route & fees a swap is quoted at
+#[derive(Debug, Clone)]
+pub struct Price {
+    pub rate: Decimal,
+    /// Currencies the swap converts through
+    pub route: Vec<CurrencyCode>,
+    pub fees: Fees,
+    pub tiers: Vec<RateTier>,
+}
+
+impl Price {
+    /// Price at `rate` through `route`, the spread of `fees` being that of the tier of `amount` among the
+    /// `(min_amount, spread_bps)` of `tiers`, if any
+    pub fn new(
+        rate: Decimal,
+        route: Vec<CurrencyCode>,
+        mut fees: Fees,
+        tiers: impl IntoIterator<Item = (Decimal, u32)>,
+        amount: Decimal,
+    ) -> Self {
+        let mut tiers = tiers
+            .into_iter()
+            .map(|(min_amount, spread_bps)| RateTier {
+                min_amount,
+                spread_bps,
+                rate: Fees {
+                    spread_bps,
+                    ..fees.clone()
+                }
+                .net_rate(rate),
+            })
+            .collect::<Vec<_>>();
+        tiers.sort_by_key(|tier| tier.min_amount);
+        if let Some(tier) = tiers.iter().rev().find(|tier| tier.min_amount <= amount) {
+            fees.spread_bps = tier.spread_bps;
+        }
+        Self {
+            rate,
+            route,
+            fees,
+            tiers,
+        }
+    }
+
+    /// Unsigned quote of `request` at this price, paid to `intermediary` & valid for `ttl`
+    pub fn quote(
+        self,
+        request: Request,
+        intermediary: AccountId,
+        ttl: Duration,
+    ) -> anyhow::Result<Quote> {
+        let (source_amount, target_amount) = self.fees.amounts(&request, self.rate)?;
+        let valid_until = (SystemTime::now() + ttl)
+            .duration_since(UNIX_EPOCH)?
+            .as_secs();
+        Ok(Quote {
+            quote_id: Uuid::new_v4(),
+            request,
+            rate: self.rate,
+            source_amount,
+            target_amount,
+            intermediary,
+            fees: self.fees,
+            requoted: None,
+            tiers: self.tiers,
+            ask: None,
+            route: self.route,
+            indicative: false,
+            valid_until,
+            signature: None,
+        })
+    }
+}
+
+/// Rate looked up at settlement which moved too far from the quoted rate
+#[derive(Debug, Clone)]
+pub struct Slipped {
+    pub rate: Decimal,
+    pub quoted: Decimal,
+    pub slippage_bps: Decimal,
+    pub max_bps: u32,
+}
+
+impl Display for Slipped {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Rate {} slipped {} bps from the quoted rate {}, above the maximum of {} bps",
+            self.rate,
+            self.slippage_bps.round_dp(2),
+            self.quoted,
+            self.max_bps
+        )
+    }
+}
+
+impl std::error::Error for Slipped {}
+
+/// Deviation of `rate` from the `quoted` rate, in basis points
+pub fn slippage_bps(rate: Decimal, quoted: Decimal) -> Decimal {
+    ((rate - quoted) / quoted).abs() * Decimal::from(10_000)
+}
+
+/// Checks that `rate` deviates from the `quoted` rate by at most `max_bps`, unchecked without a quoted rate
+pub fn check_slippage(rate: Decimal, quoted: Decimal, max_bps: u32) -> Result<(), Slipped> {
+    if quoted.is_zero() {
+        return Ok(());
+    }
+    let slippage_bps = slippage_bps(rate, quoted);
+    match slippage_bps > Decimal::from(max_bps) {
+        true => Err(Slipped {
+            rate,
+            quoted,
+            slippage_bps,
+            max_bps,
+        }),
+        false => Ok(()),
+    }
+}
+
+/// Target amount owed for the source leg of a swap, in the ledger units of both currencies
+#[derive(Debug, Clone, Copy)]
+pub struct Payout {
+    /// Target units owed for the whole source leg
+    total: u64,
+    /// Source units paid
+    paid: u64,
+}
+
+impl Payout {
+    /// Payout of the `paid` units of the source leg of `request` settled at `rate`, net of `fees`
+    pub fn new(
+        request: &Request,
+        fees: &Fees,
+        target: Money,
+        rate: Decimal,
+        paid: u64,
+    ) -> anyhow::Result<Self> {
+        let (_, amount) = fees.amounts(request, rate)?;
+        Ok(Self {
+            total: target.to_units(amount)?,
+            paid,
+        })
+    }
+
+    /// Target units owed for `amount` units of the source leg, e.g. for a slice of it
+    pub fn share(&self, amount: u64) -> u64 {
+        (self.total as u128 * amount as u128 / self.paid.max(1) as u128) as u64
+    }
+}
+
+/// Ratio of the settled amounts, including fees
+pub fn effective_rate(
+    source: Money,
+    target: Money,
+    source_units: u64,
+    target_units: u64,
+) -> Decimal {
+    let source_amount = source.from_units(source_units);
+    match source_amount.is_zero() {
+        true => Decimal::ZERO,
+        false => target.from_units(target_units) / source_amount,
+    }
+}
@@ -0,0 +1,446 @@
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use futures_util::{SinkExt, StreamExt};
+use rust_decimal::prelude::FromStr;
+use rust_decimal::Decimal;
+use service::config::{CurrencyCode, LiquidityConfig, RateFeedConfig, RateStreamConfig};
+pub use service::pricing::{RateProvider, SourceHealth};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+
+/// Rates derived from the base rate of each currency, updated when the config is reloaded
+pub struct StaticRates {
+    base_rates: RwLock<HashMap<CurrencyCode, Decimal>>,
+}
+
+impl StaticRates {
+    pub fn new(liquidity: &HashMap<CurrencyCode, LiquidityConfig>) -> Self {
+        Self {
+            base_rates: RwLock::new(base_rates(liquidity)),
+        }
+    }
+
+    /// Replaces the base rates with those of `liquidity`
+    pub fn update(&self, liquidity: &HashMap<CurrencyCode, LiquidityConfig>) {
+        *self.base_rates.write().unwrap() = base_rates(liquidity);
+    }
+
+    fn base_rate(&self, currency: &str) -> anyhow::Result<Decimal> {
+        self.base_rates
+            .read()
+            .unwrap()
+            .get(currency)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("No base rate for currency {}", currency))
+    }
+}
+
+fn base_rates(
+    liquidity: &HashMap<CurrencyCode, LiquidityConfig>,
+) -> HashMap<CurrencyCode, Decimal> {
+    liquidity
+        .iter()
+        .map(|(currency, config)| (currency.to_lowercase(), config.base_rate))
+        .collect()
+}
+
+#[async_trait]
+impl RateProvider for StaticRates {
+    async fn rate(&self, base: &str, quote: &str) -> anyhow::Result<Decimal> {
+        Ok(self.base_rate(quote)? / self.base_rate(base)?)
+    }
+}
+
+/// Last rates received from a feed, by symbol
+struct FeedCache {
+    /// Symbol the rates are quoted against
+    base: String,
+    /// Symbol of each currency, its code in uppercase by default
+    symbols: HashMap<CurrencyCode, String>,
+    max_age: Option<u64>,
+    last: Mutex<Option<Snapshot>>,
+}
+
+/// Rates by symbol & when they were last updated
+struct Snapshot {
+    rates: HashMap<String, Decimal>,
+    updated_at: Instant,
+    /// When the rate of each symbol was last updated
+    updated: HashMap<String, Instant>,
+}
+
+impl FeedCache {
+    fn new(base: String, symbols: HashMap<CurrencyCode, String>, max_age: Option<u64>) -> Self {
+        Self {
+            base,
+            symbols: symbols
+                .into_iter()
+                .map(|(currency, symbol)| (currency.to_lowercase(), symbol))
+                .collect(),
+            max_age,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Replaces the cached rates
+    fn replace(&self, rates: HashMap<String, Decimal>) {
+        let now = Instant::now();
+        *self.last.lock().unwrap() = Some(Snapshot {
+            updated: rates.keys().map(|symbol| (symbol.clone(), now)).collect(),
+            rates,
+            updated_at: now,
+        });
+    }
+
+    /// Updates the cached rates of the symbols in `rates`, keeping the others
+    fn merge(&self, rates: HashMap<String, Decimal>) {
+        let mut last = self.last.lock().unwrap();
+        let now = Instant::now();
+        let snapshot = last.get_or_insert_with(|| Snapshot {
+            rates: HashMap::new(),
+            updated_at: now,
+            updated: HashMap::new(),
+        });
+        snapshot
+            .updated
+            .extend(rates.keys().map(|symbol| (symbol.clone(), now)));
+        snapshot.rates.extend(rates);
+        snapshot.updated_at = now;
+    }
+
+    /// Time since the rates were last updated, if ever
+    fn age(&self) -> Option<Duration> {
+        self.last
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|snapshot| snapshot.updated_at.elapsed())
+    }
+
+    /// Time since the rates of both currencies were updated, if ever
+    fn pair_age(&self, base: &str, quote: &str) -> Option<Duration> {
+        let last = self.last.lock().unwrap();
+        let snapshot = last.as_ref()?;
+        let mut age = Duration::ZERO;
+        for currency in [base, quote] {
+            let symbol = self.symbol(currency);
+            if !symbol.eq_ignore_ascii_case(&self.base) {
+                age = age.max(snapshot.updated.get(&symbol)?.elapsed());
+            }
+        }
+        Some(age)
+    }
+
+    fn symbol(&self, currency: &str) -> String {
+        self.symbols
+            .get(currency)
+            .cloned()
+            .unwrap_or_else(|| currency.to_string())
+            .to_uppercase()
+    }
+
+    fn rate(&self, base: &str, quote: &str) -> anyhow::Result<Decimal> {
+        let last = self.last.lock().unwrap();
+        let snapshot = last
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No rates were received from the feed yet"))?;
+        let age = snapshot.updated_at.elapsed();
+        if let Some(max_age) = self.max_age {
+            if age > Duration::from_secs(max_age) {
+                return Err(anyhow::anyhow!(
+                    "Rates are stale, last updated {}s ago",
+                    age.as_secs()
+                ));
+            }
+        }
+        let price = |currency: &str| {
+            let symbol = self.symbol(currency);
+            match symbol.eq_ignore_ascii_case(&self.base) {
+                true => Ok(Decimal::ONE),
+                false => snapshot
+                    .rates
+                    .get(&symbol)
+                    .copied()
+                    .filter(|rate| !rate.is_zero())
+                    .ok_or_else(|| anyhow::anyhow!("No rate for {} in the feed", symbol)),
+            }
+        };
+        Ok(price(quote)? / price(base)?)
+    }
+}
+
+/// Rates by symbol in the object at `pointer` of a feed message
+fn parse_rates(
+    body: &serde_json::Value,
+    pointer: &str,
+) -> anyhow::Result<HashMap<String, Decimal>> {
+    let rates = body
+        .pointer(pointer)
+        .and_then(|rates| rates.as_object())
+        .ok_or_else(|| anyhow::anyhow!("No rates at {}", pointer))?;
+    rates
+        .iter()
+        .map(|(symbol, rate)| {
+            let rate = match rate {
+                serde_json::Value::Number(rate) => rate.to_string(),
+                serde_json::Value::String(rate) => rate.clone(),
+                _ => return Err(anyhow::anyhow!("Invalid rate for {}", symbol)),
+            };
+            Ok((symbol.to_uppercase(), Decimal::from_str(&rate)?))
+        })
+        .collect()
+}
+
+/// Rates polled from an HTTP JSON feed, the last good rates being served when a poll fails
+pub struct HttpRates {
+    config: RateFeedConfig,
+    client: reqwest::Client,
+    cache: FeedCache,
+}
+
+impl HttpRates {
+    pub fn new(config: RateFeedConfig) -> Self {
+        Self {
+            cache: FeedCache::new(config.base.clone(), config.symbols.clone(), config.max_age),
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Name of the source in logs & metrics
+    pub fn name(&self) -> &str {
+        self.config.name.as_deref().unwrap_or(&self.config.url)
+    }
+
+    /// Polls the feed every `interval`, keeping the last good rates when it fails
+    pub async fn poll(self: Arc<Self>) -> anyhow::Result<()> {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval));
+        info!(url = %self.config.url, "Polling rate feed");
+        loop {
+            interval.tick().await;
+            match self.fetch().await {
+                Ok(rates) => {
+                    info!(symbols = %rates.len(), "Refreshed rates");
+                    self.cache.replace(rates);
+                }
+                Err(err) => {
+                    let age = self.cache.age().map(|age| age.as_secs());
+                    warn!(%err, ?age, "Could not poll rate feed, serving the last good rates");
+                }
+            }
+        }
+    }
+
+    async fn fetch(&self) -> anyhow::Result<HashMap<String, Decimal>> {
+        let body: serde_json::Value = self
+            .client
+            .get(&self.config.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        parse_rates(&body, &self.config.pointer)
+    }
+}
+
+#[async_trait]
+impl RateProvider for HttpRates {
+    async fn rate(&self, base: &str, quote: &str) -> anyhow::Result<Decimal> {
+        self.cache.rate(base, quote)
+    }
+
+    fn age(&self, base: &str, quote: &str) -> Option<Duration> {
+        self.cache.pair_age(base, quote)
+    }
+}
+
+/// Rates pushed over a WebSocket stream, each message updating the rates it carries
+pub struct StreamRates {
+    config: RateStreamConfig,
+    cache: FeedCache,
+    changes: watch::Sender<()>,
+}
+
+impl StreamRates {
+    pub fn new(config: RateStreamConfig) -> Self {
+        Self {
+            cache: FeedCache::new(config.base.clone(), config.symbols.clone(), config.max_age),
+            config,
+            changes: watch::channel(()).0,
+        }
+    }
+
+    /// Name of the source in logs & metrics
+    pub fn name(&self) -> &str {
+        self.config.name.as_deref().unwrap_or(&self.config.url)
+    }
+
+    /// Receives rates until the stream closes, the last rates received being served meanwhile
+    pub async fn stream(self: Arc<Self>) -> anyhow::Result<()> {
+        let (mut socket, _) = connect_async(self.config.url.as_str()).await?;
+        info!(url = %self.config.url, "Streaming rates");
+        if let Some(subscribe) = &self.config.subscribe {
+            socket.send(Message::Text(subscribe.clone())).await?;
+        }
+        while let Some(message) = socket.next().await {
+            let text = match message? {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            let rates = serde_json::from_str(&text)
+                .map_err(anyhow::Error::from)
+                .and_then(|body| parse_rates(&body, &self.config.pointer));
+            match rates {
+                Ok(rates) => {
+                    debug!(symbols = %rates.len(), "Received rates");
+                    self.cache.merge(rates);
+                    self.changes.send_replace(());
+                }
+                // e.g. subscription acknowledgements
+                Err(err) => debug!(%err, "Ignoring stream message"),
+            }
+        }
+        Err(anyhow::anyhow!("Rate stream closed"))
+    }
+}
+
+#[async_trait]
+impl RateProvider for StreamRates {
+    async fn rate(&self, base: &str, quote: &str) -> anyhow::Result<Decimal> {
+        self.cache.rate(base, quote)
+    }
+
+    fn changes(&self) -> Option<watch::Receiver<()>> {
+        Some(self.changes.subscribe())
+    }
+
+    fn age(&self, base: &str, quote: &str) -> Option<Duration> {
+        self.cache.pair_age(base, quote)
+    }
+}
+
+/// Rates aggregated from several sources, as the median of the sources within `tolerance` of the median of all of them
+pub struct MedianRates {
+    sources: Vec<(String, Arc<dyn RateProvider>)>,
+    /// Relative deviation from the median beyond which a source is discarded
+    tolerance: Decimal,
+    min_sources: usize,
+    health: Mutex<HashMap<String, SourceHealth>>,
+    changes: Arc<watch::Sender<()>>,
+}
+
+impl MedianRates {
+    pub fn new(
+        sources: Vec<(String, Arc<dyn RateProvider>)>,
+        tolerance_bps: u32,
+        min_sources: usize,
+    ) -> Self {
+        let changes = Arc::new(watch::channel(()).0);
+        // Forward the rates pushed by any source
+        for (_, source) in &sources {
+            if let Some(mut source_changes) = source.changes() {
+                let changes = changes.clone();
+                tokio::spawn(async move {
+                    while source_changes.changed().await.is_ok() {
+                        changes.send_replace(());
+                    }
+                });
+            }
+        }
+        Self {
+            health: Mutex::new(
+                sources
+                    .iter()
+                    .map(|(name, _)| (name.clone(), SourceHealth::default()))
+                    .collect(),
+            ),
+            sources,
+            tolerance: Decimal::from(tolerance_bps) / Decimal::from(10_000),
+            min_sources: min_sources.max(1),
+            changes,
+        }
+    }
+}
+
+#[async_trait]
+impl RateProvider for MedianRates {
+    async fn rate(&self, base: &str, quote: &str) -> anyhow::Result<Decimal> {
+        let results = join_all(
+            self.sources
+                .iter()
+                .map(|(_, source)| source.rate(base, quote)),
+        )
+        .await;
+        let mut health = self.health.lock().unwrap();
+        let mut rates = Vec::new();
+        for ((name, _), result) in self.sources.iter().zip(results) {
+            let source = health.entry(name.clone()).or_default();
+            source.up = result.is_ok();
+            match result {
+                Ok(rate) => rates.push((name, rate)),
+                Err(err) => {
+                    source.errors += 1;
+                    debug!(source = %name, %err, "Rate source failed");
+                }
+            }
+        }
+        let center = median(rates.iter().map(|(_, rate)| *rate).collect())
+            .ok_or_else(|| anyhow::anyhow!("No rate source available for {}/{}", base, quote))?;
+        let mut agreed = Vec::new();
+        for (name, rate) in rates {
+            if (rate - center).abs() > center * self.tolerance {
+                warn!(source = %name, %rate, median = %center, "Discarding outlier rate");
+                health.entry(name.clone()).or_default().outliers += 1;
+            } else {
+                agreed.push(rate);
+            }
+        }
+        if agreed.len() < self.min_sources {
+            return Err(anyhow::anyhow!(
+                "Only {} rate sources agree on {}/{}, {} required",
+                agreed.len(),
+                base,
+                quote,
+                self.min_sources
+            ));
+        }
+        median(agreed).ok_or_else(|| anyhow::anyhow!("No rate source agrees on {}/{}", base, quote))
+    }
+
+    fn changes(&self) -> Option<watch::Receiver<()>> {
+        Some(self.changes.subscribe())
+    }
+
+    /// Age of the freshest source
+    fn age(&self, base: &str, quote: &str) -> Option<Duration> {
+        self.sources
+            .iter()
+            .filter_map(|(_, source)| source.age(base, quote))
+            .min()
+    }
+
+    fn health(&self) -> Vec<(String, SourceHealth)> {
+        let health = self.health.lock().unwrap();
+        self.sources
+            .iter()
+            .map(|(name, _)| (name.clone(), health.get(name).copied().unwrap_or_default()))
+            .collect()
+    }
+}
+
+fn median(mut rates: Vec<Decimal>) -> Option<Decimal> {
+    rates.sort();
+    let mid = rates.len() / 2;
+    match rates.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((rates[mid - 1] + rates[mid]) / Decimal::TWO),
+        _ => Some(rates[mid]),
+    }
+}
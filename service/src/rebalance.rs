@@ -0,0 +1,97 @@
+use crate::LedgerDB;
+use rust_decimal::Decimal;
+use service::config::{CurrencyCode, RebalanceConfig};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Position of a currency against its target allocation
+struct Leg {
+    currency: CurrencyCode,
+    balance: u64,
+    /// Share of the inventory value, in percent
+    share: Decimal,
+    /// Target share of the inventory value, in percent
+    target: Decimal,
+    /// Amount moved into the liquidity account from the reserve, out of it when negative
+    adjustment: i64,
+}
+
+/// Keeps the liquidity inventory at its target allocation, moving funds between the liquidity accounts & their reserves
+pub async fn rebalance(db: LedgerDB, config: RebalanceConfig) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval));
+    info!(dry_run = config.dry_run, "Rebalancing liquidity");
+    loop {
+        interval.tick().await;
+        for leg in plan(&db, config.tolerance).await? {
+            info!(
+                currency = %leg.currency,
+                balance = %leg.balance,
+                share = %leg.share.round_dp(2),
+                target = %leg.target.round_dp(2),
+                adjustment = %leg.adjustment,
+                dry_run = config.dry_run,
+                "Rebalance report"
+            );
+            if config.dry_run || leg.adjustment == 0 {
+                continue;
+            }
+            match db[&leg.currency].transfer_reserve(leg.adjustment).await {
+                Ok(tx_id) => info!(currency = %leg.currency, %tx_id, "Rebalanced"),
+                Err(err) => error!(%err, currency = %leg.currency, "Could not rebalance"),
+            }
+        }
+    }
+}
+
+/// Adjustments bringing each allocated currency back to its target share, valued at base rates
+async fn plan(db: &LedgerDB, tolerance: Decimal) -> anyhow::Result<Vec<Leg>> {
+    let mut positions = Vec::new();
+    for (currency, ledger) in db.iter() {
+        if let Some(allocation) = ledger.allocation() {
+            let balance = ledger.balance().await?;
+            let value = ledger.money().from_units(balance) / ledger.base_rate();
+            positions.push((currency, allocation, balance, value));
+        }
+    }
+    let total_value: Decimal = positions.iter().map(|(_, _, _, value)| *value).sum();
+    let total_allocation: Decimal = positions
+        .iter()
+        .map(|(_, allocation, _, _)| *allocation)
+        .sum();
+    if total_value.is_zero() || total_allocation.is_zero() {
+        return Ok(Vec::new());
+    }
+
+    Ok(positions
+        .into_iter()
+        .map(|(currency, allocation, balance, value)| {
+            let share = value / total_value * Decimal::ONE_HUNDRED;
+            let target = allocation / total_allocation * Decimal::ONE_HUNDRED;
+            let adjustment = match (target - share).abs() > tolerance {
+                true => {
+                    let amount = (target - share) / Decimal::ONE_HUNDRED
+                        * total_value
+                        * db[currency].base_rate();
+                    let units = db[currency]
+                        .money()
+                        .to_units(amount.abs())
+                        .ok()
+                        .and_then(|units| i64::try_from(units).ok())
+                        .unwrap_or_default();
+                    match amount.is_sign_negative() {
+                        true => -units,
+                        false => units,
+                    }
+                }
+                false => 0,
+            };
+            Leg {
+                currency: currency.clone(),
+                balance,
+                share,
+                target,
+                adjustment,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,96 @@
+use crate::ledger::Pricing;
+use crate::rates::StaticRates;
+use crate::LedgerDB;
+use service::config::{self, ReloadConfig};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info, warn};
+
+/// Reloads the quoting parameters when the config file changes or on SIGHUP, also picking up the environment.
+/// The ledgers keep observing their streams, other settings only take effect on restart
+pub async fn watch(
+    db: LedgerDB,
+    base_rates: Arc<StaticRates>,
+    path: Option<PathBuf>,
+    profile: Option<String>,
+    config: ReloadConfig,
+) -> anyhow::Result<()> {
+    let (path, profile) = (path.as_deref(), profile.as_deref());
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval));
+    let mut modified = modified_at(config::file(path));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let current = modified_at(config::file(path));
+                if current == modified {
+                    continue;
+                }
+                modified = current;
+                info!("Config file changed, reloading");
+            }
+            _ = hangup.recv() => info!("Reloading config on SIGHUP"),
+        }
+        match reload(&db, &base_rates, path, profile) {
+            Ok(()) => info!("Config reloaded"),
+            Err(err) => error!(%err, "Invalid config, keeping the current one"),
+        }
+    }
+}
+
+fn modified_at(file: &Path) -> Option<SystemTime> {
+    std::fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Validates the quoting parameters of every currency before repricing any of them
+fn reload(
+    db: &LedgerDB,
+    base_rates: &StaticRates,
+    path: Option<&Path>,
+    profile: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = config::parse(path, profile)?;
+    config.validate()?;
+    let liquidity = config
+        .liquidity
+        .iter()
+        .map(|(currency, liquidity)| (currency.to_lowercase(), liquidity))
+        .collect::<HashMap<_, _>>();
+    let mut repriced = Vec::new();
+    for (currency, ledger) in db.iter() {
+        let liquidity = liquidity.get(currency).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Currency {} can only be removed on restart",
+                currency.to_uppercase()
+            )
+        })?;
+        repriced.push((
+            currency,
+            ledger,
+            Pricing::new(
+                currency,
+                liquidity,
+                &config.routing,
+                &config.pairs,
+                &config.engine,
+            )?,
+        ));
+    }
+    for currency in liquidity
+        .keys()
+        .filter(|currency| !db.contains_key(*currency))
+    {
+        warn!(%currency, "Currency only served after a restart");
+    }
+    for (currency, ledger, pricing) in repriced {
+        info!(%currency, ?pricing, "Repricing");
+        ledger.reprice(pricing);
+    }
+    base_rates.update(&config.liquidity);
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use m10_sdk::error::{M10Error, M10Result};
+use service::config::RetryConfig;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::Code;
+use tracing::{info, warn};
+
+/// Retries ledger RPCs failing with a transient status, backing off between attempts
+#[derive(Clone)]
+pub struct Retry {
+    max_attempts: u32,
+    backoff: Duration,
+    codes: Arc<Vec<Code>>,
+}
+
+impl Retry {
+    pub fn new(config: &RetryConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            max_attempts: config.max_attempts.max(1),
+            backoff: Duration::from_millis(config.backoff),
+            codes: Arc::new(
+                config
+                    .codes
+                    .iter()
+                    .map(|code| parse_code(code))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+        })
+    }
+
+    /// Whether failed calls are attempted again
+    pub fn enabled(&self) -> bool {
+        self.max_attempts > 1
+    }
+
+    /// Runs an idempotent `call`, retrying transient failures
+    pub async fn call<T, C, F>(&self, operation: &str, call: C) -> M10Result<T>
+    where
+        C: FnMut() -> F,
+        F: Future<Output = M10Result<T>>,
+    {
+        self.run(operation, call, || async { Ok(None) }).await
+    }
+
+    /// Runs `call`, retrying transient failures.
+    /// Before each retry, `landed` looks up the outcome of a failed attempt which reached the ledger regardless,
+    /// which is returned instead of repeating it
+    pub async fn run<T, C, F, L, G>(
+        &self,
+        operation: &str,
+        mut call: C,
+        mut landed: L,
+    ) -> M10Result<T>
+    where
+        C: FnMut() -> F,
+        F: Future<Output = M10Result<T>>,
+        L: FnMut() -> G,
+        G: Future<Output = M10Result<Option<T>>>,
+    {
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        loop {
+            let err = match call().await {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt >= self.max_attempts || !self.retryable(&err) => {
+                    return Err(err)
+                }
+                Err(err) => err,
+            };
+            warn!(operation, %err, attempt, ?backoff, "Retrying ledger call");
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+            if let Some(value) = landed().await? {
+                info!(operation, "Failed attempt reached the ledger, not retrying");
+                return Ok(value);
+            }
+        }
+    }
+
+    fn retryable(&self, err: &M10Error) -> bool {
+        matches!(err, M10Error::Status(status) if self.codes.contains(&status.code()))
+    }
+}
+
+fn parse_code(code: &str) -> anyhow::Result<Code> {
+    Ok(match code {
+        "cancelled" => Code::Cancelled,
+        "unknown" => Code::Unknown,
+        "deadline_exceeded" => Code::DeadlineExceeded,
+        "resource_exhausted" => Code::ResourceExhausted,
+        "aborted" => Code::Aborted,
+        "internal" => Code::Internal,
+        "unavailable" => Code::Unavailable,
+        _ => return Err(anyhow::anyhow!("Unsupported retry status code {}", code)),
+    })
+}
@@ -0,0 +1,131 @@
+//! Key material referenced from the config, resolved when the service starts
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Field of a Vault secret read when its reference doesn't name one
+const DEFAULT_VAULT_FIELD: &str = "key_pair";
+
+/// Reference to a secret: `file://<path>` or a plain path for the raw file, `env://<VAR>` for a base64 environment
+/// variable, or `vault://<path>#<field>` for a base64 field of a HashiCorp Vault secret, using `VAULT_ADDR` & `VAULT_TOKEN`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SecretSource {
+    File(PathBuf),
+    Env(String),
+    Vault { path: String, field: String },
+}
+
+impl SecretSource {
+    /// Whether the secret is held by a remote store, & can only be read asynchronously
+    pub fn is_remote(&self) -> bool {
+        matches!(self, SecretSource::Vault { .. })
+    }
+
+    /// Reads a secret held locally, i.e. in a file or an environment variable
+    pub fn read(&self) -> anyhow::Result<Vec<u8>> {
+        match self {
+            SecretSource::File(path) => std::fs::read(path)
+                .map_err(|err| anyhow::anyhow!("Could not read {}: {}", path.display(), err)),
+            SecretSource::Env(var) => {
+                let value = std::env::var(var)
+                    .map_err(|_| anyhow::anyhow!("Environment variable {} is not set", var))?;
+                base64::decode(value.trim())
+                    .map_err(|err| anyhow::anyhow!("{} is not base64: {}", var, err))
+            }
+            SecretSource::Vault { .. } => Err(anyhow::anyhow!("{} is held remotely", self)),
+        }
+    }
+
+    /// Resolves the secret, fetching it from Vault if needed
+    pub async fn resolve(&self) -> anyhow::Result<Vec<u8>> {
+        let (path, field) = match self {
+            SecretSource::Vault { path, field } => (path, field),
+            _ => return self.read(),
+        };
+        let (address, token) = vault_env()?;
+        let url = format!("{}/v1/{}", address.trim_end_matches('/'), path);
+        let secret: serde_json::Value = reqwest::Client::new()
+            .get(&url)
+            .header("X-Vault-Token", token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        // KV version 2 nests the fields of the secret under its metadata
+        let value = secret
+            .pointer(&format!("/data/data/{}", field))
+            .or_else(|| secret.pointer(&format!("/data/{}", field)))
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} has no string field {}", path, field))?;
+        base64::decode(value.trim())
+            .map_err(|err| anyhow::anyhow!("{} is not base64: {}", self, err))
+    }
+}
+
+/// Address of the Vault server & the token it is accessed with
+pub fn vault_env() -> anyhow::Result<(String, String)> {
+    let var = |name: &str| {
+        std::env::var(name)
+            .map_err(|_| anyhow::anyhow!("{} must be set to read secrets from Vault", name))
+    };
+    Ok((var("VAULT_ADDR")?, var("VAULT_TOKEN")?))
+}
+
+impl FromStr for SecretSource {
+    type Err = anyhow::Error;
+
+    fn from_str(reference: &str) -> Result<Self, Self::Err> {
+        let (scheme, location) = match reference.split_once("://") {
+            Some(parts) => parts,
+            None => return Ok(SecretSource::File(PathBuf::from(reference))),
+        };
+        if location.is_empty() {
+            return Err(anyhow::anyhow!("Secret reference {} is empty", reference));
+        }
+        match scheme {
+            "file" => Ok(SecretSource::File(PathBuf::from(location))),
+            "env" => Ok(SecretSource::Env(location.to_string())),
+            "vault" => {
+                let (path, field) = location
+                    .split_once('#')
+                    .unwrap_or((location, DEFAULT_VAULT_FIELD));
+                Ok(SecretSource::Vault {
+                    path: path.trim_start_matches('/').to_string(),
+                    field: field.to_string(),
+                })
+            }
+            _ => Err(anyhow::anyhow!(
+                "Unsupported secret scheme {}, expected file, env or vault",
+                scheme
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for SecretSource {
+    type Error = anyhow::Error;
+
+    fn try_from(reference: String) -> Result<Self, Self::Error> {
+        reference.parse()
+    }
+}
+
+impl From<SecretSource> for String {
+    fn from(source: SecretSource) -> Self {
+        source.to_string()
+    }
+}
+
+impl Display for SecretSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretSource::File(path) => write!(f, "{}", path.display()),
+            SecretSource::Env(var) => write!(f, "env://{}", var),
+            SecretSource::Vault { path, field } => write!(f, "vault://{}#{}", path, field),
+        }
+    }
+}
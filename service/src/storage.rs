@@ -0,0 +1,246 @@
+use m10_sdk::account::AccountId;
+use serde::{Deserialize, Serialize};
+use service::event::{Event, Execute};
+use sqlx::postgres::PgPool;
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SQLITE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS swap_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    context_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    entry TEXT NOT NULL,
+    tx_id BIGINT,
+    recorded_at BIGINT NOT NULL
+)";
+
+const POSTGRES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS swap_entries (
+    id BIGSERIAL PRIMARY KEY,
+    context_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    entry TEXT NOT NULL,
+    tx_id BIGINT,
+    recorded_at BIGINT NOT NULL
+)";
+
+const INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS swap_entries_context_id ON swap_entries (context_id)";
+
+const INSERT: &str = "INSERT INTO swap_entries (context_id, kind, entry, tx_id, recorded_at)
+    VALUES ($1, $2, $3, $4, $5)";
+
+/// Entries of contexts with an accepted execution which didn't reach a final state
+const PENDING: &str = "SELECT context_id, entry FROM swap_entries
+    WHERE context_id IN (SELECT context_id FROM swap_entries WHERE kind = 'accepted')
+    AND context_id NOT IN (
+        SELECT context_id FROM swap_entries
+        WHERE kind IN ('completed', 'partial_fill', 'failed', 'refunded')
+    )
+    ORDER BY id";
+
+/// Executions received with a transfer of the context, whether accepted or rejected
+const HANDLED: &str = "SELECT COUNT(*) FROM swap_entries
+    WHERE context_id = $1 AND tx_id = $2 AND kind IN ('execute', 'accepted')";
+
+/// Step in the lifecycle of a swap context
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Entry {
+    /// Event received or published in the context
+    Event(Event),
+    /// Execution accepted for settlement
+    Accepted {
+        execute: Execute,
+        /// Transfer from the initiator to the liquidity provider
+        source_tx_id: u64,
+        from: AccountId,
+        amount: u64,
+        /// Source leg held by a pending transfer
+        #[serde(default)]
+        pending: bool,
+        /// Currency of the liquidity provider settling the swap
+        currency: String,
+        /// Liquidity account receiving the swap, the primary one when unset
+        #[serde(default)]
+        account: Option<AccountId>,
+    },
+}
+
+impl Entry {
+    fn kind(&self) -> &'static str {
+        match self {
+            Entry::Accepted { .. } => "accepted",
+            Entry::Event(event) => match event {
+                Event::Request(_) => "request",
+                Event::Quote(_) => "quote",
+                Event::Execute(_) => "execute",
+                Event::Completed { .. } => "completed",
+                Event::PartialFill(_) => "partial_fill",
+                Event::Reject { .. } => "reject",
+                Event::Cancel => "cancel",
+                Event::Receipt(_) => "receipt",
+                Event::Failed { .. } => "failed",
+                Event::CounterOffer(_) => "counter_offer",
+                Event::StatusRequest => "status_request",
+                Event::StatusResponse(_) => "status_response",
+                Event::Refunded(_) => "refunded",
+                Event::Netted(_) => "netted",
+            },
+        }
+    }
+}
+
+/// Accepted execution which was interrupted before reaching a final state
+pub struct PendingSwap {
+    pub context_id: Vec<u8>,
+    pub execute: Execute,
+    pub source_tx_id: u64,
+    pub from: AccountId,
+    pub amount: u64,
+    /// Source amount already settled, according to the published receipts
+    pub settled: u64,
+    pub pending: bool,
+    pub currency: String,
+    pub account: Option<AccountId>,
+}
+
+/// Durable record of the lifecycle of every swap context
+#[derive(Clone)]
+pub enum Storage {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+impl Storage {
+    /// Connects to the database at `url`, e.g. `sqlite://swaps.db` or `postgres://...`, & creates its schema
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let storage = if url.starts_with("postgres") {
+            let pool = PgPool::connect(url).await?;
+            sqlx::query(POSTGRES_SCHEMA).execute(&pool).await?;
+            sqlx::query(INDEX).execute(&pool).await?;
+            Storage::Postgres(pool)
+        } else if url.starts_with("sqlite") {
+            let pool = SqlitePool::connect(url).await?;
+            sqlx::query(SQLITE_SCHEMA).execute(&pool).await?;
+            sqlx::query(INDEX).execute(&pool).await?;
+            Storage::Sqlite(pool)
+        } else {
+            return Err(anyhow::anyhow!("Unsupported database {}", url));
+        };
+        Ok(storage)
+    }
+
+    pub async fn record(
+        &self,
+        context_id: &[u8],
+        entry: &Entry,
+        tx_id: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let context_id = hex::encode(context_id);
+        let kind = entry.kind();
+        let entry = serde_json::to_string(entry)?;
+        let tx_id = tx_id.map(|tx_id| tx_id as i64);
+        let recorded_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        match self {
+            Storage::Sqlite(pool) => {
+                sqlx::query(INSERT)
+                    .bind(context_id)
+                    .bind(kind)
+                    .bind(entry)
+                    .bind(tx_id)
+                    .bind(recorded_at)
+                    .execute(pool)
+                    .await?;
+            }
+            Storage::Postgres(pool) => {
+                sqlx::query(INSERT)
+                    .bind(context_id)
+                    .bind(kind)
+                    .bind(entry)
+                    .bind(tx_id)
+                    .bind(recorded_at)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the execution received with the transfer `tx_id` was already handled, e.g. before a restart
+    pub async fn handled(&self, context_id: &[u8], tx_id: u64) -> anyhow::Result<bool> {
+        let context_id = hex::encode(context_id);
+        let tx_id = tx_id as i64;
+        let count: i64 = match self {
+            Storage::Sqlite(pool) => sqlx::query(HANDLED)
+                .bind(context_id)
+                .bind(tx_id)
+                .fetch_one(pool)
+                .await?
+                .get(0),
+            Storage::Postgres(pool) => sqlx::query(HANDLED)
+                .bind(context_id)
+                .bind(tx_id)
+                .fetch_one(pool)
+                .await?
+                .get(0),
+        };
+        Ok(count > 0)
+    }
+
+    /// Accepted executions which were neither settled, refunded nor failed
+    pub async fn pending(&self) -> anyhow::Result<Vec<PendingSwap>> {
+        let rows = match self {
+            Storage::Sqlite(pool) => sqlx::query(PENDING)
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|row| (row.get(0), row.get(1)))
+                .collect::<Vec<(String, String)>>(),
+            Storage::Postgres(pool) => sqlx::query(PENDING)
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|row| (row.get(0), row.get(1)))
+                .collect(),
+        };
+
+        let mut pending = HashMap::<String, PendingSwap>::new();
+        for (context_id, entry) in rows {
+            match serde_json::from_str(&entry)? {
+                Entry::Accepted {
+                    execute,
+                    source_tx_id,
+                    from,
+                    amount,
+                    pending: held,
+                    currency,
+                    account,
+                } => {
+                    pending.insert(
+                        context_id.clone(),
+                        PendingSwap {
+                            context_id: hex::decode(&context_id)?,
+                            execute,
+                            source_tx_id,
+                            from,
+                            amount,
+                            settled: 0,
+                            pending: held,
+                            currency,
+                            account,
+                        },
+                    );
+                }
+                // TWAP summaries repeat the slices already settled
+                Entry::Event(Event::Receipt(receipt)) if receipt.slices == 0 => {
+                    if let Some(swap) = pending.get_mut(&context_id) {
+                        swap.settled += receipt.source_amount;
+                    }
+                }
+                Entry::Event(_) => {}
+            }
+        }
+        Ok(pending.into_values().collect())
+    }
+}
@@ -0,0 +1,541 @@
+use futures_util::future::join_all;
+use m10_sdk::account::AccountId;
+use rust_decimal::Decimal;
+use service::config::{CurrencyCode, RetentionConfig};
+use service::event::{Event, Request, SwapState, SwapStatus};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub type ContextId = Vec<u8>;
+
+/// Sequence numbers per context & publisher public key
+type Sequences = HashMap<(ContextId, Vec<u8>), u64>;
+
+/// Sequence numbers per context, publisher public key & liquidity account observing them
+type Observed = HashMap<(ContextId, Vec<u8>, AccountId), u64>;
+
+/// Source funds received by a liquidity provider, until settled or refunded
+#[derive(Clone)]
+pub struct HeldFunds {
+    /// Liquidity account holding the funds
+    pub liquidity: AccountId,
+    pub request: Request,
+    /// Transfer from the initiator to the liquidity provider
+    pub tx_id: u64,
+    /// Amount not settled yet, in the source currency
+    pub amount: u64,
+    /// Held by a pending transfer, rejected rather than refunded
+    pub pending: bool,
+    /// [EPOCH TIME] Unsettled funds are refunded after this time, in milliseconds
+    pub expires_at: u64,
+}
+
+/// Amounts a swap quoted or being settled is to receive & deliver
+struct OpenPosition {
+    /// Account requesting the swap
+    account: AccountId,
+    from_currency: CurrencyCode,
+    source_amount: Decimal,
+    to_currency: CurrencyCode,
+    target_amount: Decimal,
+    /// [EPOCH TIME] Quotes which weren't executed stop counting after this time
+    valid_until: u64,
+    executed: bool,
+}
+
+/// Shared record of swap contexts observed by the service
+#[derive(Clone, Default)]
+pub struct SwapStore {
+    /// Contexts which can no longer be executed, e.g. cancelled by the initiator
+    terminal: Arc<Mutex<HashSet<ContextId>>>,
+    /// Quotes which can no longer be executed, i.e. already executed or superseded, & their context
+    retired: Arc<Mutex<HashMap<Uuid, ContextId>>>,
+    /// Contexts for which an execution has been accepted
+    executed: Arc<Mutex<HashSet<ContextId>>>,
+    /// Status of each swap & the account its events are published to
+    statuses: Arc<Mutex<HashMap<ContextId, (AccountId, SwapStatus)>>>,
+    /// Counter-offers received per context
+    counter_offers: Arc<Mutex<HashMap<ContextId, u32>>>,
+    /// Last sequence number published per context & public key
+    published: Arc<Mutex<Sequences>>,
+    /// Last sequence number observed per context & public key, by each liquidity account as every ledger observes
+    /// the same actions
+    observed: Arc<Mutex<Observed>>,
+    /// Source funds of executions which are being settled
+    held: Arc<Mutex<HashMap<ContextId, HeldFunds>>>,
+    /// Transactions already handled per context & liquidity account
+    processed: Arc<Mutex<HashSet<(ContextId, AccountId, u64)>>>,
+    /// Tasks settling accepted executions
+    swaps: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    /// Positions of the swaps quoted or being settled
+    positions: Arc<Mutex<HashMap<ContextId, OpenPosition>>>,
+    /// Contexts by recency of their last activity
+    recent: Arc<Mutex<Recent>>,
+    retention: RetentionConfig,
+}
+
+impl SwapStore {
+    pub fn new(retention: RetentionConfig) -> Self {
+        Self {
+            retention,
+            ..Default::default()
+        }
+    }
+
+    /// Records activity in a context, forgetting the least recently active contexts beyond `max_contexts`
+    /// which can be collected, as by `collect_stale`
+    fn touch(&self, context_id: &[u8]) {
+        let now = now_millis();
+        let evicted = {
+            let mut recent = self.recent.lock().unwrap();
+            recent.touch(context_id, now);
+            let capacity = self.retention.max_contexts;
+            if recent.len() <= capacity {
+                return;
+            }
+            // Down to 90% of the capacity, so that contexts aren't evicted on every activity
+            let excess = recent.len() - capacity * 9 / 10;
+            let statuses = self.statuses.lock().unwrap();
+            let held = self.held.lock().unwrap();
+            recent
+                .oldest()
+                .filter(|evicted| evicted.as_slice() != context_id)
+                .filter(|evicted| forgettable(evicted, &statuses, &held, now))
+                .take(excess)
+                .cloned()
+                .collect::<HashSet<_>>()
+        };
+        if !evicted.is_empty() {
+            warn!(evicted = %evicted.len(), "Too many contexts tracked, forgetting the least recently active");
+            self.forget(&evicted);
+        }
+    }
+
+    /// Contexts tracked in memory
+    pub fn tracked(&self) -> usize {
+        self.recent.lock().unwrap().len()
+    }
+
+    /// Forgets the state of `contexts`, but the funds they hold
+    fn forget(&self, contexts: &HashSet<ContextId>) {
+        self.terminal
+            .lock()
+            .unwrap()
+            .retain(|context_id| !contexts.contains(context_id));
+        self.retired
+            .lock()
+            .unwrap()
+            .retain(|_, context_id| !contexts.contains(context_id));
+        self.executed
+            .lock()
+            .unwrap()
+            .retain(|context_id| !contexts.contains(context_id));
+        self.statuses
+            .lock()
+            .unwrap()
+            .retain(|context_id, _| !contexts.contains(context_id));
+        self.counter_offers
+            .lock()
+            .unwrap()
+            .retain(|context_id, _| !contexts.contains(context_id));
+        self.published
+            .lock()
+            .unwrap()
+            .retain(|(context_id, _), _| !contexts.contains(context_id));
+        self.observed
+            .lock()
+            .unwrap()
+            .retain(|(context_id, _, _), _| !contexts.contains(context_id));
+        self.processed
+            .lock()
+            .unwrap()
+            .retain(|(context_id, _, _)| !contexts.contains(context_id));
+        self.positions
+            .lock()
+            .unwrap()
+            .retain(|context_id, _| !contexts.contains(context_id));
+        let mut recent = self.recent.lock().unwrap();
+        for context_id in contexts {
+            recent.remove(context_id);
+        }
+    }
+
+    /// Forgets the contexts idle for longer than the retention `ttl`, unless settling, holding funds or still executable
+    pub fn collect_stale(&self) -> usize {
+        let now = now_millis();
+        let idle = self
+            .recent
+            .lock()
+            .unwrap()
+            .idle_since(now.saturating_sub(self.retention.ttl * 1000));
+        let stale = {
+            let statuses = self.statuses.lock().unwrap();
+            let held = self.held.lock().unwrap();
+            idle.into_iter()
+                .filter(|context_id| forgettable(context_id, &statuses, &held, now))
+                .collect::<HashSet<_>>()
+        };
+        self.forget(&stale);
+        stale.len()
+    }
+
+    /// Collects the stale contexts every retention `interval`
+    pub async fn collect_garbage(self) -> anyhow::Result<()> {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.retention.interval));
+        info!(ttl = %self.retention.ttl, max_contexts = %self.retention.max_contexts, "Collecting stale contexts");
+        loop {
+            interval.tick().await;
+            let collected = self.collect_stale();
+            if collected > 0 {
+                info!(%collected, tracked = %self.tracked(), "Forgot stale contexts");
+            }
+        }
+    }
+
+    pub fn mark_terminal(&self, context_id: &[u8]) {
+        self.terminal.lock().unwrap().insert(context_id.to_vec());
+        self.positions.lock().unwrap().remove(context_id);
+    }
+
+    pub fn is_terminal(&self, context_id: &[u8]) -> bool {
+        self.terminal.lock().unwrap().contains(context_id)
+    }
+
+    pub fn is_retired(&self, quote_id: Uuid) -> bool {
+        self.retired.lock().unwrap().contains_key(&quote_id)
+    }
+
+    /// Retires a quote executed or superseded within `context_id`, returns `false` if it was already retired
+    pub fn retire_quote(&self, context_id: &[u8], quote_id: Uuid) -> bool {
+        let mut retired = self.retired.lock().unwrap();
+        if retired.contains_key(&quote_id) {
+            return false;
+        }
+        retired.insert(quote_id, context_id.to_vec());
+        true
+    }
+
+    pub fn mark_executed(&self, context_id: &[u8]) {
+        if let Some(position) = self.positions.lock().unwrap().get_mut(context_id) {
+            position.executed = true;
+        }
+        self.executed.lock().unwrap().insert(context_id.to_vec());
+    }
+
+    pub fn is_executed(&self, context_id: &[u8]) -> bool {
+        self.executed.lock().unwrap().contains(context_id)
+    }
+
+    /// Updates the status of a swap from an event published to `account`
+    pub fn record(&self, context_id: &[u8], account: AccountId, event: &Event) {
+        let state = match event {
+            Event::Quote(_) => SwapState::Quoted,
+            Event::Reject { .. } => SwapState::Rejected,
+            Event::Completed { paper_trade: false } => SwapState::Settled,
+            Event::Completed { paper_trade: true } => SwapState::PaperTraded,
+            Event::PartialFill(_) => SwapState::PartiallyFilled,
+            Event::Failed { .. } => SwapState::Failed,
+            Event::Refunded(_) => SwapState::Refunded,
+            _ => return,
+        };
+        self.touch(context_id);
+        let now = now_millis();
+        let mut statuses = self.statuses.lock().unwrap();
+        let (_, status) = statuses.entry(context_id.to_vec()).or_insert_with(|| {
+            (
+                account,
+                SwapStatus {
+                    state,
+                    quote_id: None,
+                    valid_until: 0,
+                    created_at: now,
+                    updated_at: now,
+                },
+            )
+        });
+        status.state = state;
+        status.updated_at = now;
+        if let Event::Quote(quote) = event {
+            status.quote_id = Some(quote.quote_id);
+            status.valid_until = quote.valid_until;
+        }
+
+        let mut positions = self.positions.lock().unwrap();
+        match event {
+            Event::Quote(quote) => {
+                if let (Some(from_currency), Some(to_currency)) =
+                    (quote.route.first(), quote.route.last())
+                {
+                    positions.insert(
+                        context_id.to_vec(),
+                        OpenPosition {
+                            account: quote.request.from,
+                            from_currency: from_currency.clone(),
+                            source_amount: quote.source_amount,
+                            to_currency: to_currency.clone(),
+                            target_amount: quote.target_amount,
+                            valid_until: quote.valid_until,
+                            executed: false,
+                        },
+                    );
+                }
+            }
+            _ => {
+                positions.remove(context_id);
+            }
+        }
+    }
+
+    /// Net open exposure per currency of the swaps quoted or being settled, other than `excluding`.
+    /// Amounts to receive count positively & amounts to deliver negatively
+    pub fn exposure(&self, excluding: &[u8]) -> HashMap<CurrencyCode, Decimal> {
+        let mut positions = self.positions.lock().unwrap();
+        prune_positions(&mut positions);
+        let mut exposure = HashMap::<CurrencyCode, Decimal>::new();
+        for (_, position) in positions
+            .iter()
+            .filter(|(context_id, _)| context_id.as_slice() != excluding)
+        {
+            *exposure.entry(position.from_currency.clone()).or_default() += position.source_amount;
+            *exposure.entry(position.to_currency.clone()).or_default() -= position.target_amount;
+        }
+        exposure
+    }
+
+    /// Source amounts per currency of the swaps quoted to or being settled for `account`, other than `excluding`
+    pub fn credit_used(
+        &self,
+        account: AccountId,
+        excluding: &[u8],
+    ) -> Vec<(CurrencyCode, Decimal)> {
+        let mut positions = self.positions.lock().unwrap();
+        prune_positions(&mut positions);
+        positions
+            .iter()
+            .filter(|(context_id, position)| {
+                position.account == account && context_id.as_slice() != excluding
+            })
+            .map(|(_, position)| (position.from_currency.clone(), position.source_amount))
+            .collect()
+    }
+
+    /// Updates the state of a known swap
+    pub fn set_state(&self, context_id: &[u8], state: SwapState) {
+        if let Some((_, status)) = self.statuses.lock().unwrap().get_mut(context_id) {
+            status.state = state;
+            status.updated_at = now_millis();
+        }
+    }
+
+    /// Records a request parked until an operator approves it, its events being published to `account`
+    pub fn mark_pending_approval(&self, context_id: &[u8], account: AccountId) {
+        self.touch(context_id);
+        let now = now_millis();
+        self.statuses.lock().unwrap().insert(
+            context_id.to_vec(),
+            (
+                account,
+                SwapStatus {
+                    state: SwapState::PendingApproval,
+                    quote_id: None,
+                    valid_until: 0,
+                    created_at: now,
+                    updated_at: now,
+                },
+            ),
+        );
+    }
+
+    /// Status of a swap & the account its events are published to
+    pub fn status(&self, context_id: &[u8]) -> Option<(AccountId, SwapStatus)> {
+        self.statuses.lock().unwrap().get(context_id).cloned()
+    }
+
+    /// Records a counter-offer, returns the number received for the context
+    pub fn count_counter_offer(&self, context_id: &[u8]) -> u32 {
+        let mut counter_offers = self.counter_offers.lock().unwrap();
+        let count = counter_offers.entry(context_id.to_vec()).or_default();
+        *count += 1;
+        *count
+    }
+
+    /// Allocates the sequence number of the next event published with `public_key`
+    pub fn next_sequence(&self, context_id: &[u8], public_key: &[u8]) -> u64 {
+        let mut published = self.published.lock().unwrap();
+        let sequence = published
+            .entry((context_id.to_vec(), public_key.to_vec()))
+            .or_default();
+        *sequence += 1;
+        *sequence
+    }
+
+    /// Records a sequence number observed by `liquidity`, returns `false` if it was replayed or out of order
+    pub fn observe_sequence(
+        &self,
+        context_id: &[u8],
+        liquidity: AccountId,
+        public_key: &[u8],
+        sequence: u64,
+    ) -> bool {
+        if sequence == 0 {
+            // Published before sequence numbers were introduced
+            return true;
+        }
+        let mut observed = self.observed.lock().unwrap();
+        let last = observed
+            .entry((context_id.to_vec(), public_key.to_vec(), liquidity))
+            .or_default();
+        if sequence <= *last {
+            return false;
+        }
+        *last = sequence;
+        true
+    }
+
+    /// Records a transaction observed by `liquidity` as handled, returns `false` if it already was
+    pub fn mark_processed(&self, context_id: &[u8], liquidity: AccountId, tx_id: u64) -> bool {
+        self.touch(context_id);
+        self.processed
+            .lock()
+            .unwrap()
+            .insert((context_id.to_vec(), liquidity, tx_id))
+    }
+
+    pub fn track_swap(&self, swap: JoinHandle<()>) {
+        let mut swaps = self.swaps.lock().unwrap();
+        swaps.retain(|swap| !swap.is_finished());
+        swaps.push(swap);
+    }
+
+    /// Waits up to `deadline` for the swaps being settled, returns how many are still running
+    pub async fn drain(&self, deadline: Duration) -> usize {
+        let mut swaps = std::mem::take(&mut *self.swaps.lock().unwrap());
+        let _ = tokio::time::timeout(deadline, join_all(swaps.iter_mut())).await;
+        swaps.iter().filter(|swap| !swap.is_finished()).count()
+    }
+
+    pub fn hold(&self, context_id: &[u8], funds: HeldFunds) {
+        self.held.lock().unwrap().insert(context_id.to_vec(), funds);
+    }
+
+    /// Claims `amount` of the held funds for settlement, returns `false` if they were refunded
+    pub fn release(&self, context_id: &[u8], amount: u64) -> bool {
+        match self.held.lock().unwrap().get_mut(context_id) {
+            Some(funds) if funds.amount >= amount => {
+                funds.amount -= amount;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `amount` to the held funds, e.g. after a failed settlement
+    pub fn restore(&self, context_id: &[u8], amount: u64) {
+        if let Some(funds) = self.held.lock().unwrap().get_mut(context_id) {
+            funds.amount += amount;
+        }
+    }
+
+    /// Removes the funds held for a context, e.g. to refund them
+    pub fn take_held(&self, context_id: &[u8]) -> Option<HeldFunds> {
+        self.held.lock().unwrap().remove(context_id)
+    }
+
+    /// Removes the funds held by any of the `liquidity` accounts past their expiry
+    pub fn take_expired(&self, liquidity: &[AccountId]) -> Vec<(ContextId, HeldFunds)> {
+        let now = now_millis();
+        let mut held = self.held.lock().unwrap();
+        let expired = held
+            .iter()
+            .filter(|(_, funds)| liquidity.contains(&funds.liquidity) && funds.expires_at < now)
+            .map(|(context_id, _)| context_id.clone())
+            .collect::<Vec<_>>();
+        expired
+            .into_iter()
+            .filter_map(|context_id| {
+                let funds = held.remove(&context_id)?;
+                Some((context_id, funds))
+            })
+            .collect()
+    }
+}
+
+/// Whether a context can be forgotten at `now`, in milliseconds: it holds no funds & its swap is final, or was quoted
+/// but the quote expired
+fn forgettable(
+    context_id: &[u8],
+    statuses: &HashMap<ContextId, (AccountId, SwapStatus)>,
+    held: &HashMap<ContextId, HeldFunds>,
+    now: u64,
+) -> bool {
+    if held.contains_key(context_id) {
+        return false;
+    }
+    match statuses.get(context_id) {
+        Some((_, status)) if status.state == SwapState::Quoted => status.valid_until < now / 1000,
+        Some((_, status)) => status.state.is_final(),
+        None => true,
+    }
+}
+
+/// Drops the positions of quotes which expired without being executed
+fn prune_positions(positions: &mut HashMap<ContextId, OpenPosition>) {
+    let now = now_millis() / 1000;
+    positions.retain(|_, position| position.executed || position.valid_until >= now);
+}
+
+/// Contexts by recency of their last activity
+#[derive(Default)]
+struct Recent {
+    /// Order & time in milliseconds of the last activity of each context
+    seen: HashMap<ContextId, (u64, u64)>,
+    /// Contexts by order of their last activity
+    order: BTreeMap<u64, ContextId>,
+    next: u64,
+}
+
+impl Recent {
+    fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Records activity at `now`
+    fn touch(&mut self, context_id: &[u8], now: u64) {
+        if let Some((order, _)) = self.seen.remove(context_id) {
+            self.order.remove(&order);
+        }
+        self.next += 1;
+        self.seen.insert(context_id.to_vec(), (self.next, now));
+        self.order.insert(self.next, context_id.to_vec());
+    }
+
+    /// Contexts from the least recently active
+    fn oldest(&self) -> impl Iterator<Item = &ContextId> {
+        self.order.values()
+    }
+
+    fn remove(&mut self, context_id: &[u8]) {
+        if let Some((order, _)) = self.seen.remove(context_id) {
+            self.order.remove(&order);
+        }
+    }
+
+    /// Contexts last active before `cutoff`
+    fn idle_since(&self, cutoff: u64) -> Vec<ContextId> {
+        self.order
+            .values()
+            .take_while(|context_id| self.seen[*context_id].1 < cutoff)
+            .cloned()
+            .collect()
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
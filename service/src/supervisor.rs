@@ -0,0 +1,110 @@
+use m10_sdk::error::M10Error;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tonic::Code;
+use tracing::{error, info, warn};
+
+/// Delay before the first restart of a failed task, doubled on each consecutive failure
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Tasks running for this long before failing are restarted without backoff
+const STABLE_AFTER: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Debug)]
+pub enum Health {
+    Running,
+    /// Waiting to be restarted after `failures` consecutive failures
+    Restarting {
+        failures: u32,
+        error: String,
+    },
+}
+
+/// Restarts crashed observers with exponential backoff & keeps track of their health
+#[derive(Clone, Default)]
+pub struct Supervisor {
+    health: Arc<Mutex<BTreeMap<String, Health>>>,
+}
+
+impl Supervisor {
+    /// Runs the task created by `start`, restarting it whenever it stops, until it fails with an unrecoverable error
+    pub fn spawn<F, T>(&self, name: String, start: F) -> JoinHandle<anyhow::Result<()>>
+    where
+        F: Fn() -> T + Send + 'static,
+        T: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut failures = 0;
+            loop {
+                this.set_health(&name, Health::Running);
+                let started = Instant::now();
+                let err = match start().await {
+                    Ok(()) => anyhow::anyhow!("Observation ended"),
+                    Err(err) if is_unrecoverable(&err) => {
+                        error!(%err, task = %name, "Unrecoverable failure");
+                        return Err(err);
+                    }
+                    Err(err) => err,
+                };
+                if started.elapsed() > STABLE_AFTER {
+                    failures = 0;
+                }
+                let backoff = MIN_BACKOFF
+                    .saturating_mul(1 << failures.min(6))
+                    .min(MAX_BACKOFF);
+                failures += 1;
+                warn!(%err, task = %name, %failures, ?backoff, "Restarting");
+                this.set_health(
+                    &name,
+                    Health::Restarting {
+                        failures,
+                        error: err.to_string(),
+                    },
+                );
+                tokio::time::sleep(backoff).await;
+            }
+        })
+    }
+
+    /// Health of every supervised task, by name
+    pub fn health(&self) -> BTreeMap<String, Health> {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Logs the health of the supervised tasks every `interval`
+    pub async fn report_health(self, interval: Duration) -> anyhow::Result<()> {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            for (task, health) in self.health() {
+                match health {
+                    Health::Running => info!(%task, "Running"),
+                    Health::Restarting { failures, error } => {
+                        warn!(%task, %failures, %error, "Restarting")
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_health(&self, name: &str, health: Health) {
+        self.health.lock().unwrap().insert(name.to_string(), health);
+    }
+}
+
+/// Failures which restarting can't fix, e.g. a liquidity key rejected by the ledger
+fn is_unrecoverable(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<M10Error>() {
+        Some(M10Error::Signing(_) | M10Error::InvalidAccountId(_)) => true,
+        Some(M10Error::Status(status)) => matches!(
+            status.code(),
+            Code::Unauthenticated | Code::PermissionDenied | Code::InvalidArgument
+        ),
+        _ => false,
+    }
+}
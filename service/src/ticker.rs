@@ -0,0 +1,85 @@
+use crate::ledger::get_fx_rate;
+use crate::rates::RateProvider;
+use crate::LedgerDB;
+use rust_decimal::Decimal;
+use service::config::CurrencyCode;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, info};
+
+/// Source & target currencies of a pair
+type Pair = (CurrencyCode, CurrencyCode);
+
+/// Current rate of each pair watched by a swap, `None` while it's unavailable
+#[derive(Clone, Default)]
+pub struct RateTicker {
+    pairs: Arc<Mutex<HashMap<Pair, watch::Sender<Option<Decimal>>>>>,
+}
+
+impl RateTicker {
+    /// Watches the rate of `from`/`to`, notified whenever it changes
+    pub fn watch(&self, from: &str, to: &str) -> watch::Receiver<Option<Decimal>> {
+        self.pairs
+            .lock()
+            .unwrap()
+            .entry((from.to_string(), to.to_string()))
+            .or_insert_with(|| watch::channel(None).0)
+            .subscribe()
+    }
+
+    /// Pairs still watched by a swap, forgetting the others
+    fn watched(&self) -> Vec<Pair> {
+        let mut pairs = self.pairs.lock().unwrap();
+        pairs.retain(|_, rate| rate.receiver_count() > 0);
+        pairs.keys().cloned().collect()
+    }
+
+    /// Publishes the rate of a pair, watchers only being notified when it changed
+    fn publish(&self, pair: &Pair, rate: Option<Decimal>) {
+        if let Some(sender) = self.pairs.lock().unwrap().get(pair) {
+            if *sender.borrow() != rate {
+                sender.send_replace(rate);
+            }
+        }
+    }
+
+    /// Looks up the rates of the watched pairs whenever rates are pushed, & every `interval` for rates which are polled
+    pub async fn run(
+        self,
+        db: LedgerDB,
+        rates: Arc<dyn RateProvider>,
+        interval: Duration,
+    ) -> anyhow::Result<()> {
+        let mut changes = rates.changes();
+        let mut interval = tokio::time::interval(interval);
+        info!("Publishing rates");
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = rate_changed(&mut changes) => {}
+            }
+            for pair in self.watched() {
+                let rate = match get_fx_rate(&db, &pair.0, &pair.1).await {
+                    Ok(rate) => Some(rate),
+                    Err(err) => {
+                        debug!(from = %pair.0, to = %pair.1, %err, "Rate unavailable");
+                        None
+                    }
+                };
+                self.publish(&pair, rate);
+            }
+        }
+    }
+}
+
+/// Resolves on the next rate pushed by the provider, never for providers which are polled
+async fn rate_changed(changes: &mut Option<watch::Receiver<()>>) {
+    if let Some(changes) = changes {
+        if changes.changed().await.is_ok() {
+            return;
+        }
+    }
+    std::future::pending().await
+}